@@ -818,18 +818,22 @@ fn generate_bindings(config: &Config) {
         "hpke.h",
         "hmac.h",
         "hrss.h",
+        "kdf.h",
         "md4.h",
         "md5.h",
+        "mlkem.h",
         "obj_mac.h",
         "objects.h",
         "opensslv.h",
         "ossl_typ.h",
+        "pkcs7.h",
         "pkcs12.h",
         "poly1305.h",
         "rand.h",
         "rc4.h",
         "ripemd.h",
         "siphash.h",
+        "slhdsa.h",
         "srtp.h",
         "trust_token.h",
         "x509v3.h",
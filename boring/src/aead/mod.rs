@@ -0,0 +1,950 @@
+//! Authenticated encryption with associated data, using BoringSSL's `EVP_AEAD` interface.
+//!
+//! Unlike the AEAD support in [`symm`](crate::symm), which drives AEADs through the streaming
+//! `EVP_CIPHER` interface, this module exposes them as single-shot operations. The general
+//! purpose algorithms BoringSSL implements are available, including ChaCha20-Poly1305,
+//! XChaCha20-Poly1305 and AES-GCM-SIV. The TLS record constructions, including the TLS 1.2 and
+//! TLS 1.3 variants of AES-GCM, keep state across seal calls and are not.
+//!
+//! ```
+//! use rama_boring::aead::{Algorithm, NonceSequence, OpeningKey, SealingKey};
+//!
+//! let algorithm = Algorithm::chacha20_poly1305();
+//! let key = [42; 32];
+//! let sealing_key = SealingKey::new(algorithm, &key).unwrap();
+//! let opening_key = OpeningKey::new(algorithm, &key).unwrap();
+//!
+//! let mut nonces = NonceSequence::new(algorithm, &[0; 4]).unwrap();
+//! let nonce = nonces.advance().unwrap();
+//!
+//! let mut in_out = b"hello".to_vec();
+//! sealing_key.seal_in_place(&nonce, b"aad", &mut in_out).unwrap();
+//! let plaintext = opening_key.open_in_place(&nonce, b"aad", &mut in_out).unwrap();
+//! assert_eq!(plaintext, b"hello");
+//! ```
+//!
+//! # Nonces
+//!
+//! Reusing a nonce with the same key breaks the security of most AEADs. [`NonceSequence`] builds
+//! nonces from a fixed prefix and a counter, which is the safest option when a single party
+//! encrypts with a key. Random nonces from [`Algorithm::random_nonce`] are only safe when the
+//! nonce is large enough, as with XChaCha20-Poly1305, or for nonce misuse-resistant algorithms
+//! such as AES-GCM-SIV.
+
+use std::fmt;
+
+use crate::error::ErrorStack;
+use crate::rand::rand_bytes;
+use crate::{cvt, cvt_p, ffi};
+
+use foreign_types::{ForeignType, ForeignTypeRef};
+use openssl_macros::corresponds;
+
+pub mod stream;
+
+/// An AEAD algorithm.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Algorithm(*const ffi::EVP_AEAD);
+
+impl Algorithm {
+    /// AES-128 in Galois Counter Mode.
+    #[corresponds(EVP_aead_aes_128_gcm)]
+    #[must_use]
+    pub fn aes_128_gcm() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_128_gcm()) }
+    }
+
+    /// AES-192 in Galois Counter Mode.
+    #[corresponds(EVP_aead_aes_192_gcm)]
+    #[must_use]
+    pub fn aes_192_gcm() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_192_gcm()) }
+    }
+
+    /// AES-256 in Galois Counter Mode.
+    #[corresponds(EVP_aead_aes_256_gcm)]
+    #[must_use]
+    pub fn aes_256_gcm() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_256_gcm()) }
+    }
+
+    /// AES-128-GCM with an internally generated random nonce.
+    ///
+    /// The nonce is included in the sealed output, so callers pass an empty nonce.
+    #[corresponds(EVP_aead_aes_128_gcm_randnonce)]
+    #[must_use]
+    pub fn aes_128_gcm_randnonce() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_128_gcm_randnonce()) }
+    }
+
+    /// AES-256-GCM with an internally generated random nonce.
+    ///
+    /// The nonce is included in the sealed output, so callers pass an empty nonce.
+    #[corresponds(EVP_aead_aes_256_gcm_randnonce)]
+    #[must_use]
+    pub fn aes_256_gcm_randnonce() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_256_gcm_randnonce()) }
+    }
+
+    /// AES-128 in GCM-SIV mode ([RFC 8452]), which tolerates nonce reuse.
+    ///
+    /// [RFC 8452]: https://www.rfc-editor.org/rfc/rfc8452.html
+    #[corresponds(EVP_aead_aes_128_gcm_siv)]
+    #[must_use]
+    pub fn aes_128_gcm_siv() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_128_gcm_siv()) }
+    }
+
+    /// AES-256 in GCM-SIV mode ([RFC 8452]), which tolerates nonce reuse.
+    ///
+    /// [RFC 8452]: https://www.rfc-editor.org/rfc/rfc8452.html
+    #[corresponds(EVP_aead_aes_256_gcm_siv)]
+    #[must_use]
+    pub fn aes_256_gcm_siv() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_256_gcm_siv()) }
+    }
+
+    /// AES-128 in CTR mode with HMAC-SHA256 truncated to 128 bits.
+    #[corresponds(EVP_aead_aes_128_ctr_hmac_sha256)]
+    #[must_use]
+    pub fn aes_128_ctr_hmac_sha256() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_128_ctr_hmac_sha256()) }
+    }
+
+    /// AES-256 in CTR mode with HMAC-SHA256 truncated to 128 bits.
+    #[corresponds(EVP_aead_aes_256_ctr_hmac_sha256)]
+    #[must_use]
+    pub fn aes_256_ctr_hmac_sha256() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_256_ctr_hmac_sha256()) }
+    }
+
+    /// AES-128-CCM with the parameters used by Bluetooth LE: a 13 byte nonce and a 4 byte tag.
+    #[corresponds(EVP_aead_aes_128_ccm_bluetooth)]
+    #[must_use]
+    pub fn aes_128_ccm_bluetooth() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_128_ccm_bluetooth()) }
+    }
+
+    /// AES-128-CCM with the parameters used by Bluetooth Mesh: a 13 byte nonce and an 8 byte tag.
+    #[corresponds(EVP_aead_aes_128_ccm_bluetooth_8)]
+    #[must_use]
+    pub fn aes_128_ccm_bluetooth_8() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_128_ccm_bluetooth_8()) }
+    }
+
+    /// AES-128-CCM with the parameters used by Matter: a 13 byte nonce and a 16 byte tag.
+    #[corresponds(EVP_aead_aes_128_ccm_matter)]
+    #[must_use]
+    pub fn aes_128_ccm_matter() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_128_ccm_matter()) }
+    }
+
+    /// AES-128 in EAX mode.
+    #[corresponds(EVP_aead_aes_128_eax)]
+    #[must_use]
+    pub fn aes_128_eax() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_128_eax()) }
+    }
+
+    /// AES-256 in EAX mode.
+    #[corresponds(EVP_aead_aes_256_eax)]
+    #[must_use]
+    pub fn aes_256_eax() -> Self {
+        unsafe { Self(ffi::EVP_aead_aes_256_eax()) }
+    }
+
+    /// ChaCha20-Poly1305 as described in [RFC 8439].
+    ///
+    /// [RFC 8439]: https://www.rfc-editor.org/rfc/rfc8439.html
+    #[corresponds(EVP_aead_chacha20_poly1305)]
+    #[must_use]
+    pub fn chacha20_poly1305() -> Self {
+        unsafe { Self(ffi::EVP_aead_chacha20_poly1305()) }
+    }
+
+    /// XChaCha20-Poly1305, a ChaCha20-Poly1305 variant with a 24 byte nonce.
+    ///
+    /// The nonce is large enough to be chosen at random.
+    #[corresponds(EVP_aead_xchacha20_poly1305)]
+    #[must_use]
+    pub fn xchacha20_poly1305() -> Self {
+        unsafe { Self(ffi::EVP_aead_xchacha20_poly1305()) }
+    }
+
+    /// The length of keys for this algorithm, in bytes.
+    #[corresponds(EVP_AEAD_key_length)]
+    #[must_use]
+    pub fn key_len(&self) -> usize {
+        unsafe { ffi::EVP_AEAD_key_length(self.0) }
+    }
+
+    /// The length of nonces for this algorithm, in bytes.
+    #[corresponds(EVP_AEAD_nonce_length)]
+    #[must_use]
+    pub fn nonce_len(&self) -> usize {
+        unsafe { ffi::EVP_AEAD_nonce_length(self.0) }
+    }
+
+    /// The maximum number of bytes sealing adds to a plaintext.
+    #[corresponds(EVP_AEAD_max_overhead)]
+    #[must_use]
+    pub fn max_overhead(&self) -> usize {
+        unsafe { ffi::EVP_AEAD_max_overhead(self.0) }
+    }
+
+    /// The maximum tag length supported by this algorithm.
+    #[corresponds(EVP_AEAD_max_tag_len)]
+    #[must_use]
+    pub fn max_tag_len(&self) -> usize {
+        unsafe { ffi::EVP_AEAD_max_tag_len(self.0) }
+    }
+
+    /// Generates a random nonce of the correct length for this algorithm.
+    ///
+    /// See the [module documentation](crate::aead#nonces) for when random nonces are safe to use.
+    pub fn random_nonce(&self) -> Result<Vec<u8>, ErrorStack> {
+        let mut nonce = vec![0; self.nonce_len()];
+        rand_bytes(&mut nonce)?;
+        Ok(nonce)
+    }
+
+    #[must_use]
+    pub fn as_ptr(&self) -> *const ffi::EVP_AEAD {
+        self.0
+    }
+}
+
+// SAFETY: `EVP_AEAD`s are immutable static values
+unsafe impl Sync for Algorithm {}
+unsafe impl Send for Algorithm {}
+
+impl fmt::Debug for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Algorithm")
+            .field("key_len", &self.key_len())
+            .field("nonce_len", &self.nonce_len())
+            .field("max_overhead", &self.max_overhead())
+            .finish()
+    }
+}
+
+/// Produces unique nonces from a fixed prefix followed by a big-endian 64-bit counter.
+///
+/// The prefix makes up the bytes of the nonce not used by the counter, so it is empty for
+/// algorithms with 8 byte nonces. Using a distinct prefix per sender allows several parties to
+/// share a key.
+#[derive(Clone, Debug)]
+pub struct NonceSequence {
+    prefix: Vec<u8>,
+    counter: Option<u64>,
+}
+
+impl NonceSequence {
+    /// Creates a sequence for `algorithm`, starting at a counter of zero.
+    ///
+    /// `prefix` must be exactly 8 bytes shorter than the algorithm's nonce.
+    pub fn new(algorithm: Algorithm, prefix: &[u8]) -> Result<Self, ErrorStack> {
+        Self::with_counter(algorithm, prefix, 0)
+    }
+
+    /// Like [`NonceSequence::new`], but starts at `counter`.
+    pub fn with_counter(
+        algorithm: Algorithm,
+        prefix: &[u8],
+        counter: u64,
+    ) -> Result<Self, ErrorStack> {
+        if algorithm.nonce_len() != prefix.len() + 8 {
+            return Err(ErrorStack::internal_error_str(
+                "nonce prefix length does not match the algorithm",
+            ));
+        }
+        Ok(Self {
+            prefix: prefix.to_vec(),
+            counter: Some(counter),
+        })
+    }
+
+    /// Returns the next nonce.
+    ///
+    /// Fails once the counter is exhausted instead of wrapping around.
+    pub fn advance(&mut self) -> Result<Vec<u8>, ErrorStack> {
+        let counter = self
+            .counter
+            .ok_or_else(|| ErrorStack::internal_error_str("nonce sequence exhausted"))?;
+        self.counter = counter.checked_add(1);
+
+        let mut nonce = Vec::with_capacity(self.prefix.len() + 8);
+        nonce.extend_from_slice(&self.prefix);
+        nonce.extend_from_slice(&counter.to_be_bytes());
+        Ok(nonce)
+    }
+}
+
+fn new_ctx(
+    algorithm: Algorithm,
+    key: &[u8],
+    tag_len: usize,
+) -> Result<*mut ffi::EVP_AEAD_CTX, ErrorStack> {
+    unsafe {
+        ffi::init();
+        cvt_p(ffi::EVP_AEAD_CTX_new(
+            algorithm.as_ptr(),
+            key.as_ptr(),
+            key.len(),
+            tag_len,
+        ))
+    }
+}
+
+fn ctx_algorithm(ctx: *const ffi::EVP_AEAD_CTX) -> Algorithm {
+    unsafe { Algorithm(ffi::EVP_AEAD_CTX_aead(ctx)) }
+}
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::EVP_AEAD_CTX;
+    fn drop = ffi::EVP_AEAD_CTX_free;
+
+    /// A key used to encrypt and authenticate messages.
+    pub struct SealingKey;
+}
+
+impl SealingKey {
+    /// Creates a sealing key using the algorithm's default tag length.
+    #[corresponds(EVP_AEAD_CTX_new)]
+    pub fn new(algorithm: Algorithm, key: &[u8]) -> Result<Self, ErrorStack> {
+        Self::with_tag_len(algorithm, key, ffi::EVP_AEAD_DEFAULT_TAG_LENGTH as usize)
+    }
+
+    /// Creates a sealing key producing truncated tags of `tag_len` bytes.
+    ///
+    /// Not all algorithms support truncated tags.
+    #[corresponds(EVP_AEAD_CTX_new)]
+    pub fn with_tag_len(
+        algorithm: Algorithm,
+        key: &[u8],
+        tag_len: usize,
+    ) -> Result<Self, ErrorStack> {
+        new_ctx(algorithm, key, tag_len).map(|ptr| unsafe { Self::from_ptr(ptr) })
+    }
+}
+
+impl SealingKeyRef {
+    /// Returns the algorithm of this key.
+    #[corresponds(EVP_AEAD_CTX_aead)]
+    #[must_use]
+    pub fn algorithm(&self) -> Algorithm {
+        ctx_algorithm(self.as_ptr())
+    }
+
+    /// Encrypts `plaintext`, returning the ciphertext with the tag appended.
+    #[corresponds(EVP_AEAD_CTX_seal)]
+    pub fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+        let mut out = plaintext.to_vec();
+        self.seal_in_place(nonce, aad, &mut out)?;
+        Ok(out)
+    }
+
+    /// Encrypts `in_out` in place and appends the tag.
+    ///
+    /// On error, `in_out` is truncated to its original length but its contents are unspecified.
+    #[corresponds(EVP_AEAD_CTX_seal)]
+    pub fn seal_in_place(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &mut Vec<u8>,
+    ) -> Result<(), ErrorStack> {
+        let in_len = in_out.len();
+        let max_out_len = in_len + self.algorithm().max_overhead();
+        in_out.resize(max_out_len, 0);
+
+        let mut out_len = 0;
+        let ptr = in_out.as_mut_ptr();
+        // SAFETY: BoringSSL allows the input and output to alias exactly. The buffer holds
+        // `max_out_len` bytes, of which the first `in_len` are the plaintext.
+        let res = unsafe {
+            cvt(ffi::EVP_AEAD_CTX_seal(
+                self.as_ptr(),
+                ptr,
+                &mut out_len,
+                max_out_len,
+                nonce.as_ptr(),
+                nonce.len(),
+                ptr,
+                in_len,
+                aad.as_ptr(),
+                aad.len(),
+            ))
+        };
+        match res {
+            Ok(()) => in_out.truncate(out_len),
+            Err(_) => in_out.truncate(in_len),
+        }
+        res
+    }
+
+    /// Encrypts `in_out` in place, returning the tag separately.
+    #[corresponds(EVP_AEAD_CTX_seal_scatter)]
+    pub fn seal_in_place_separate_tag(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &mut [u8],
+    ) -> Result<Vec<u8>, ErrorStack> {
+        self.seal_scatter(nonce, aad, in_out, &[])
+    }
+
+    /// Encrypts `in_out` in place, and also encrypts `extra_in` into the returned tag buffer.
+    ///
+    /// The returned buffer holds the ciphertext of `extra_in` followed by the tag. This allows
+    /// the ciphertext to be written into a fixed size buffer, with the remainder of the plaintext
+    /// encrypted along with the tag. Only some algorithms support a non-empty `extra_in`.
+    #[corresponds(EVP_AEAD_CTX_seal_scatter)]
+    pub fn seal_scatter(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &mut [u8],
+        extra_in: &[u8],
+    ) -> Result<Vec<u8>, ErrorStack> {
+        let mut tag = vec![0; extra_in.len() + self.algorithm().max_overhead()];
+        let mut tag_len = 0;
+        let ptr = in_out.as_mut_ptr();
+        // SAFETY: the input and output alias exactly, which BoringSSL allows. The tag buffer is
+        // sized for the extra input and the maximum overhead.
+        unsafe {
+            cvt(ffi::EVP_AEAD_CTX_seal_scatter(
+                self.as_ptr(),
+                ptr,
+                tag.as_mut_ptr(),
+                &mut tag_len,
+                tag.len(),
+                nonce.as_ptr(),
+                nonce.len(),
+                ptr,
+                in_out.len(),
+                extra_in.as_ptr(),
+                extra_in.len(),
+                aad.as_ptr(),
+                aad.len(),
+            ))?;
+        }
+        tag.truncate(tag_len);
+        Ok(tag)
+    }
+}
+
+impl fmt::Debug for SealingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SealingKey")
+            .field("algorithm", &self.algorithm())
+            .finish_non_exhaustive()
+    }
+}
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::EVP_AEAD_CTX;
+    fn drop = ffi::EVP_AEAD_CTX_free;
+
+    /// A key used to authenticate and decrypt messages.
+    pub struct OpeningKey;
+}
+
+impl OpeningKey {
+    /// Creates an opening key using the algorithm's default tag length.
+    #[corresponds(EVP_AEAD_CTX_new)]
+    pub fn new(algorithm: Algorithm, key: &[u8]) -> Result<Self, ErrorStack> {
+        Self::with_tag_len(algorithm, key, ffi::EVP_AEAD_DEFAULT_TAG_LENGTH as usize)
+    }
+
+    /// Creates an opening key expecting truncated tags of `tag_len` bytes.
+    #[corresponds(EVP_AEAD_CTX_new)]
+    pub fn with_tag_len(
+        algorithm: Algorithm,
+        key: &[u8],
+        tag_len: usize,
+    ) -> Result<Self, ErrorStack> {
+        new_ctx(algorithm, key, tag_len).map(|ptr| unsafe { Self::from_ptr(ptr) })
+    }
+}
+
+impl OpeningKeyRef {
+    /// Returns the algorithm of this key.
+    #[corresponds(EVP_AEAD_CTX_aead)]
+    #[must_use]
+    pub fn algorithm(&self) -> Algorithm {
+        ctx_algorithm(self.as_ptr())
+    }
+
+    /// Authenticates and decrypts `ciphertext`, which must have the tag appended.
+    #[corresponds(EVP_AEAD_CTX_open)]
+    pub fn open(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+        let mut out = ciphertext.to_vec();
+        let len = self.open_in_place(nonce, aad, &mut out)?.len();
+        out.truncate(len);
+        Ok(out)
+    }
+
+    /// Authenticates and decrypts `in_out` in place, returning the plaintext.
+    ///
+    /// `in_out` must hold the ciphertext with the tag appended. The plaintext is a prefix of
+    /// `in_out`. On error, the contents of `in_out` are unspecified.
+    #[corresponds(EVP_AEAD_CTX_open)]
+    pub fn open_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a mut [u8], ErrorStack> {
+        let mut out_len = 0;
+        let ptr = in_out.as_mut_ptr();
+        // SAFETY: the input and output alias exactly, which BoringSSL allows. The plaintext is
+        // never longer than the ciphertext.
+        unsafe {
+            cvt(ffi::EVP_AEAD_CTX_open(
+                self.as_ptr(),
+                ptr,
+                &mut out_len,
+                in_out.len(),
+                nonce.as_ptr(),
+                nonce.len(),
+                ptr,
+                in_out.len(),
+                aad.as_ptr(),
+                aad.len(),
+            ))?;
+        }
+        Ok(&mut in_out[..out_len])
+    }
+
+    /// Authenticates and decrypts `in_out` in place, using a separately transmitted tag.
+    ///
+    /// This is the counterpart of [`SealingKeyRef::seal_in_place_separate_tag`]. On error, the
+    /// contents of `in_out` are unspecified.
+    #[corresponds(EVP_AEAD_CTX_open_gather)]
+    pub fn open_in_place_separate_tag(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), ErrorStack> {
+        let ptr = in_out.as_mut_ptr();
+        // SAFETY: the input and output alias exactly, which BoringSSL allows
+        unsafe {
+            cvt(ffi::EVP_AEAD_CTX_open_gather(
+                self.as_ptr(),
+                ptr,
+                nonce.as_ptr(),
+                nonce.len(),
+                ptr,
+                in_out.len(),
+                tag.as_ptr(),
+                tag.len(),
+                aad.as_ptr(),
+                aad.len(),
+            ))
+        }
+    }
+}
+
+impl fmt::Debug for OpeningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpeningKey")
+            .field("algorithm", &self.algorithm())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex::FromHex;
+
+    struct TestVector {
+        algorithm: fn() -> Algorithm,
+        key: &'static str,
+        nonce: &'static str,
+        aad: &'static str,
+        msg: &'static str,
+        ct: &'static str,
+        tag: &'static str,
+    }
+
+    const LADIES_AND_GENTLEMEN: &str = "4c616469657320616e642047656e746c656d656e206f662074686520636c617373206f66202739393a204966204920636f756c64206f6666657220796f75206f6e6c79206f6e652074697020666f7220746865206675747572652c2073756e73637265656e20776f756c642062652069742e";
+
+    // Valid test cases from Wycheproof and the specifications the algorithms come from.
+    const VECTORS: &[TestVector] = &[
+        // aes_gcm_test.json
+        TestVector {
+            algorithm: Algorithm::aes_128_gcm,
+            key: "5b9604fe14eadba931b0ccf34843dab9",
+            nonce: "028318abc1824029138141a2",
+            aad: "",
+            msg: "001d0c231287c1182784554ca3a21908",
+            ct: "26073cc1d851beff176384dc9896d5ff",
+            tag: "0a3ea7a5487cb5f7d70fb6c58d038554",
+        },
+        // aes_gcm_test.json
+        TestVector {
+            algorithm: Algorithm::aes_128_gcm,
+            key: "5b9604fe14eadba931b0ccf34843dab9",
+            nonce: "921d2507fa8007b7bd067d34",
+            aad: "00112233445566778899aabbccddeeff",
+            msg: "001d0c231287c1182784554ca3a21908",
+            ct: "49d8b9783e911913d87094d1f63cc765",
+            tag: "1e348ba07cca2cf04c618cb4d43a5b92",
+        },
+        // aes_gcm_test.json, the valid case of the ModifiedTag group
+        TestVector {
+            algorithm: Algorithm::aes_128_gcm,
+            key: "000102030405060708090a0b0c0d0e0f",
+            nonce: "505152535455565758595a5b",
+            aad: "",
+            msg: "202122232425262728292a2b2c2d2e2f",
+            ct: "eb156d081ed6b6b55f4612f021d87b39",
+            tag: "d8847dbc326a06e988c77ad3863e6083",
+        },
+        // chacha20_poly1305_test.json tcId 1, RFC 8439 section 2.8.2
+        TestVector {
+            algorithm: Algorithm::chacha20_poly1305,
+            key: "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f",
+            nonce: "070000004041424344454647",
+            aad: "50515253c0c1c2c3c4c5c6c7",
+            msg: LADIES_AND_GENTLEMEN,
+            ct: "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d63dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b3692ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc3ff4def08e4b7a9de576d26586cec64b6116",
+            tag: "1ae10b594f09e26a7e902ecbd0600691",
+        },
+        // draft-irtf-cfrg-xchacha section A.3.1
+        TestVector {
+            algorithm: Algorithm::xchacha20_poly1305,
+            key: "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f",
+            nonce: "404142434445464748494a4b4c4d4e4f5051525354555657",
+            aad: "50515253c0c1c2c3c4c5c6c7",
+            msg: LADIES_AND_GENTLEMEN,
+            ct: "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b4522f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff921f9664c97637da9768812f615c68b13b52e",
+            tag: "c0875924c1c7987947deafd8780acf49",
+        },
+        // GCM specification test case 2
+        TestVector {
+            algorithm: Algorithm::aes_128_gcm,
+            key: "00000000000000000000000000000000",
+            nonce: "000000000000000000000000",
+            aad: "",
+            msg: "00000000000000000000000000000000",
+            ct: "0388dace60b6a392f328c2b971b2fe78",
+            tag: "ab6e47d42cec13bdf53a67b21257bddf",
+        },
+        // GCM specification test case 3
+        TestVector {
+            algorithm: Algorithm::aes_128_gcm,
+            key: "feffe9928665731c6d6a8f9467308308",
+            nonce: "cafebabefacedbaddecaf888",
+            aad: "",
+            msg: "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255",
+            ct: "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985",
+            tag: "4d5c2af327cd64a62cf35abd2ba6fab4",
+        },
+        // RFC 8452 appendix C.1, first vector
+        TestVector {
+            algorithm: Algorithm::aes_128_gcm_siv,
+            key: "01000000000000000000000000000000",
+            nonce: "030000000000000000000000",
+            aad: "",
+            msg: "",
+            ct: "",
+            tag: "dc20e2d83f25705bb49e439eca56de25",
+        },
+    ];
+
+    /// The tag modifications of Wycheproof's ModifiedTag test groups, as bit indexes to flip.
+    /// Bit `i` is bit `i % 8` of byte `i / 8`, starting from the least significant bit.
+    const MODIFIED_TAGS: &[&[usize]] = &[
+        &[0],
+        &[1],
+        &[7],
+        &[8],
+        &[31],
+        &[32],
+        &[33],
+        &[63],
+        &[64],
+        &[71],
+        &[77],
+        &[80],
+        &[96],
+        &[97],
+        &[103],
+        &[120],
+        &[121],
+        &[126],
+        &[127],
+        &[0, 64],
+        &[31, 63],
+        &[63, 127],
+        &[
+            7, 15, 23, 31, 39, 47, 55, 63, 71, 79, 87, 95, 103, 111, 119, 127,
+        ],
+        &[
+            0, 8, 16, 24, 32, 40, 48, 56, 64, 72, 80, 88, 96, 104, 112, 120,
+        ],
+    ];
+
+    fn all_algorithms() -> Vec<Algorithm> {
+        vec![
+            Algorithm::aes_128_gcm(),
+            Algorithm::aes_192_gcm(),
+            Algorithm::aes_256_gcm(),
+            Algorithm::aes_128_gcm_randnonce(),
+            Algorithm::aes_256_gcm_randnonce(),
+            Algorithm::aes_128_gcm_siv(),
+            Algorithm::aes_256_gcm_siv(),
+            Algorithm::aes_128_ctr_hmac_sha256(),
+            Algorithm::aes_256_ctr_hmac_sha256(),
+            Algorithm::aes_128_ccm_bluetooth(),
+            Algorithm::aes_128_ccm_bluetooth_8(),
+            Algorithm::aes_128_ccm_matter(),
+            Algorithm::aes_128_eax(),
+            Algorithm::aes_256_eax(),
+            Algorithm::chacha20_poly1305(),
+            Algorithm::xchacha20_poly1305(),
+        ]
+    }
+
+    fn keys(algorithm: Algorithm) -> (SealingKey, OpeningKey) {
+        let key = vec![7; algorithm.key_len()];
+        (
+            SealingKey::new(algorithm, &key).unwrap(),
+            OpeningKey::new(algorithm, &key).unwrap(),
+        )
+    }
+
+    #[test]
+    fn vectors() {
+        for v in VECTORS {
+            let algorithm = (v.algorithm)();
+            let key = Vec::from_hex(v.key).unwrap();
+            let nonce = Vec::from_hex(v.nonce).unwrap();
+            let aad = Vec::from_hex(v.aad).unwrap();
+            let msg = Vec::from_hex(v.msg).unwrap();
+            let mut expected = Vec::from_hex(v.ct).unwrap();
+            expected.extend(Vec::from_hex(v.tag).unwrap());
+
+            let sealing_key = SealingKey::new(algorithm, &key).unwrap();
+            assert_eq!(sealing_key.seal(&nonce, &aad, &msg).unwrap(), expected);
+
+            let opening_key = OpeningKey::new(algorithm, &key).unwrap();
+            assert_eq!(opening_key.open(&nonce, &aad, &expected).unwrap(), msg);
+
+            let tag_start = expected.len() - 16;
+            for bits in MODIFIED_TAGS {
+                let mut bad_tag = expected.clone();
+                for bit in *bits {
+                    bad_tag[tag_start + bit / 8] ^= 1 << (bit % 8);
+                }
+                assert!(opening_key.open(&nonce, &aad, &bad_tag).is_err());
+            }
+            for tag in [[0; 16], [0xff; 16]] {
+                let mut bad_tag = expected.clone();
+                bad_tag[tag_start..].copy_from_slice(&tag);
+                assert!(opening_key.open(&nonce, &aad, &bad_tag).is_err());
+            }
+
+            assert!(opening_key.open(&nonce, b"x", &expected).is_err());
+            assert!(opening_key
+                .open(&nonce, &aad, &expected[..expected.len() - 1])
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn wycheproof() {
+        let files: &[(fn() -> Algorithm, &str)] = &[
+            (
+                Algorithm::aes_128_gcm,
+                include_str!("../../test/wycheproof-aes-128-gcm.txt"),
+            ),
+            (
+                Algorithm::aes_256_gcm,
+                include_str!("../../test/wycheproof-aes-256-gcm.txt"),
+            ),
+            (
+                Algorithm::aes_128_gcm_siv,
+                include_str!("../../test/wycheproof-aes-128-gcm-siv.txt"),
+            ),
+            (
+                Algorithm::aes_256_gcm_siv,
+                include_str!("../../test/wycheproof-aes-256-gcm-siv.txt"),
+            ),
+            (
+                Algorithm::chacha20_poly1305,
+                include_str!("../../test/wycheproof-chacha20-poly1305.txt"),
+            ),
+            (
+                Algorithm::xchacha20_poly1305,
+                include_str!("../../test/wycheproof-xchacha20-poly1305.txt"),
+            ),
+        ];
+
+        for (algorithm, file) in files {
+            let algorithm = algorithm();
+            for line in file.lines().filter(|line| !line.starts_with('#')) {
+                let fields: Vec<&str> = line.split(':').collect();
+                let [key, nonce, aad, msg, ct, tag] =
+                    [0, 1, 2, 3, 4, 5].map(|i| Vec::from_hex(fields[i]).unwrap());
+                let mut sealed = ct;
+                sealed.extend(tag);
+
+                let opening_key = OpeningKey::new(algorithm, &key).unwrap();
+                let opened = opening_key.open(&nonce, &aad, &sealed);
+                match fields[6] {
+                    "valid" => {
+                        assert_eq!(opened.unwrap(), msg, "{line}");
+                        let sealing_key = SealingKey::new(algorithm, &key).unwrap();
+                        assert_eq!(sealing_key.seal(&nonce, &aad, &msg).unwrap(), sealed);
+                    }
+                    "invalid" => assert!(opened.is_err(), "{line}"),
+                    result => panic!("unexpected result {result}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn roundtrip_all_algorithms() {
+        for algorithm in all_algorithms() {
+            let (sealing_key, opening_key) = keys(algorithm);
+            let nonce = algorithm.random_nonce().unwrap();
+
+            let mut in_out = b"the quick brown fox".to_vec();
+            sealing_key
+                .seal_in_place(&nonce, b"aad", &mut in_out)
+                .unwrap();
+            assert!(in_out.len() > 19 && in_out.len() <= 19 + algorithm.max_overhead());
+
+            let plaintext = opening_key
+                .open_in_place(&nonce, b"aad", &mut in_out)
+                .unwrap();
+            assert_eq!(plaintext, b"the quick brown fox");
+            assert!(opening_key.open(&nonce, b"other", &in_out).is_err());
+        }
+    }
+
+    #[test]
+    fn separate_tag() {
+        let algorithm = Algorithm::aes_256_gcm();
+        let (sealing_key, opening_key) = keys(algorithm);
+        let nonce = algorithm.random_nonce().unwrap();
+
+        let mut in_out = *b"separate tag";
+        let tag = sealing_key
+            .seal_in_place_separate_tag(&nonce, b"", &mut in_out)
+            .unwrap();
+        assert_eq!(tag.len(), algorithm.max_tag_len());
+        assert_ne!(&in_out, b"separate tag");
+
+        let sealed = sealing_key.seal(&nonce, b"", b"separate tag").unwrap();
+        assert_eq!(&sealed[..12], &in_out);
+        assert_eq!(&sealed[12..], &tag[..]);
+
+        let mut bad_tag = tag.clone();
+        bad_tag[0] ^= 1;
+        let mut copy = in_out;
+        assert!(opening_key
+            .open_in_place_separate_tag(&nonce, b"", &mut copy, &bad_tag)
+            .is_err());
+
+        opening_key
+            .open_in_place_separate_tag(&nonce, b"", &mut in_out, &tag)
+            .unwrap();
+        assert_eq!(&in_out, b"separate tag");
+    }
+
+    #[test]
+    fn scatter_extra_in() {
+        let algorithm = Algorithm::chacha20_poly1305();
+        let (sealing_key, opening_key) = keys(algorithm);
+        let nonce = algorithm.random_nonce().unwrap();
+
+        let mut head = *b"head";
+        let tail = sealing_key
+            .seal_scatter(&nonce, b"aad", &mut head, b"tail")
+            .unwrap();
+        assert_eq!(tail.len(), 4 + algorithm.max_overhead());
+
+        let mut sealed = head.to_vec();
+        sealed.extend_from_slice(&tail);
+        assert_eq!(
+            opening_key.open(&nonce, b"aad", &sealed).unwrap(),
+            b"headtail"
+        );
+    }
+
+    #[test]
+    fn truncated_tag() {
+        let algorithm = Algorithm::aes_128_gcm();
+        let key = [1; 16];
+        let sealing_key = SealingKey::with_tag_len(algorithm, &key, 12).unwrap();
+        let opening_key = OpeningKey::with_tag_len(algorithm, &key, 12).unwrap();
+        let nonce = [0; 12];
+
+        let sealed = sealing_key.seal(&nonce, b"", b"msg").unwrap();
+        assert_eq!(sealed.len(), 3 + 12);
+        assert_eq!(opening_key.open(&nonce, b"", &sealed).unwrap(), b"msg");
+        assert!(OpeningKey::new(algorithm, &key)
+            .unwrap()
+            .open(&nonce, b"", &sealed)
+            .is_err());
+    }
+
+    #[test]
+    fn bad_key_and_nonce() {
+        let algorithm = Algorithm::aes_128_gcm();
+        assert!(SealingKey::new(algorithm, &[0; 15]).is_err());
+        assert!(OpeningKey::new(algorithm, &[0; 32]).is_err());
+
+        let (sealing_key, _) = keys(Algorithm::chacha20_poly1305());
+        let mut in_out = b"msg".to_vec();
+        assert!(sealing_key
+            .seal_in_place(&[0; 8], b"", &mut in_out)
+            .is_err());
+        assert_eq!(in_out.len(), 3);
+    }
+
+    #[test]
+    fn nonce_sequence() {
+        let algorithm = Algorithm::chacha20_poly1305();
+        assert!(NonceSequence::new(algorithm, &[0; 12]).is_err());
+
+        let mut nonces = NonceSequence::new(algorithm, &[1, 2, 3, 4]).unwrap();
+        assert_eq!(
+            nonces.advance().unwrap(),
+            [1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            nonces.advance().unwrap(),
+            [1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 1]
+        );
+
+        let mut nonces = NonceSequence::with_counter(algorithm, &[0; 4], u64::MAX).unwrap();
+        assert!(nonces.advance().is_ok());
+        assert!(nonces.advance().is_err());
+    }
+
+    #[test]
+    fn empty_gcm_nonce() {
+        let (sealing_key, opening_key) = keys(Algorithm::aes_128_gcm());
+        assert!(sealing_key.seal(&[], b"", b"hello").is_err());
+        assert!(opening_key.open(&[], b"", &[0; 21]).is_err());
+    }
+
+    #[test]
+    fn algorithm_params() {
+        assert_eq!(Algorithm::aes_128_gcm().key_len(), 16);
+        assert_eq!(Algorithm::aes_256_gcm().nonce_len(), 12);
+        assert_eq!(Algorithm::chacha20_poly1305().max_tag_len(), 16);
+        assert_eq!(Algorithm::xchacha20_poly1305().nonce_len(), 24);
+        assert_eq!(Algorithm::aes_128_ccm_bluetooth().max_overhead(), 4);
+        assert_eq!(Algorithm::aes_128_gcm_randnonce().nonce_len(), 0);
+    }
+}
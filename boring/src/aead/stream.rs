@@ -0,0 +1,550 @@
+//! Online authenticated encryption of large payloads.
+//!
+//! [`Encryptor`] and [`Decryptor`] split a payload into fixed size chunks and seal each chunk
+//! separately, following the STREAM construction of Hoang, Reyhanitabar, Rogaway and Vizár.
+//! Neither side needs to hold more than a single chunk in memory, and the decryptor only releases
+//! plaintext once the chunk it belongs to has been authenticated.
+//!
+//! ```
+//! use std::io::{Read, Write};
+//! use rama_boring::aead::stream::{Decryptor, Encryptor};
+//! use rama_boring::aead::Algorithm;
+//!
+//! let algorithm = Algorithm::aes_256_gcm();
+//! let key = [42; 32];
+//!
+//! let mut encryptor = Encryptor::new(algorithm, &key, Vec::new()).unwrap();
+//! encryptor.write_all(b"a very large payload").unwrap();
+//! let ciphertext = encryptor.finish().unwrap();
+//!
+//! let mut decryptor = Decryptor::new(algorithm, &key, &ciphertext[..]).unwrap();
+//! let mut plaintext = Vec::new();
+//! decryptor.read_to_end(&mut plaintext).unwrap();
+//! assert_eq!(plaintext, b"a very large payload");
+//! ```
+//!
+//! # Wire format
+//!
+//! The output starts with an 11 byte header, followed by the sealed chunks:
+//!
+//! ```text
+//! header = nonce_prefix (7 bytes) || chunk_size (4 bytes, big-endian)
+//! chunk  = ciphertext || tag
+//! ```
+//!
+//! The nonce prefix is chosen at random for every stream. Every chunk holds exactly `chunk_size`
+//! bytes of plaintext, except for the last one which holds between 0 and `chunk_size` bytes. The
+//! last chunk is only empty if the whole payload is, so every stream has at least one chunk.
+//!
+//! The nonce of chunk `i` (counting from zero) is
+//!
+//! ```text
+//! nonce = nonce_prefix (7 bytes) || i (4 bytes, big-endian) || last (1 byte)
+//! ```
+//!
+//! where `last` is `1` for the final chunk and `0` otherwise. The header is passed as associated
+//! data for every chunk. Reordered or dropped chunks fail to authenticate because of the counter,
+//! and a stream truncated at a chunk boundary fails because its new final chunk was not sealed
+//! with the last chunk flag.
+//!
+//! The algorithm is not part of the wire format and must be agreed on out of band. Only
+//! algorithms with 12 byte nonces, such as AES-GCM and ChaCha20-Poly1305, are supported.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::aead::{Algorithm, OpeningKey, SealingKey};
+use crate::error::ErrorStack;
+use crate::rand::rand_bytes;
+
+/// The length of the stream header.
+pub const HEADER_LEN: usize = NONCE_PREFIX_LEN + 4;
+
+/// The chunk size used by [`Encryptor::new`].
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The largest chunk size accepted by [`Encryptor`] and [`Decryptor`].
+///
+/// This bounds the memory a [`Decryptor`] allocates for an untrusted stream.
+pub const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+const NONCE_PREFIX_LEN: usize = 7;
+const NONCE_LEN: usize = NONCE_PREFIX_LEN + 5;
+
+fn check_algorithm(algorithm: Algorithm) -> Result<(), ErrorStack> {
+    if algorithm.nonce_len() != NONCE_LEN {
+        return Err(ErrorStack::internal_error_str(
+            "streaming encryption requires an algorithm with 12 byte nonces",
+        ));
+    }
+    Ok(())
+}
+
+fn chunk_nonce(header: &[u8; HEADER_LEN], counter: u32, last: bool) -> [u8; NONCE_LEN] {
+    let mut nonce = [0; NONCE_LEN];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(&header[..NONCE_PREFIX_LEN]);
+    nonce[NONCE_PREFIX_LEN..NONCE_LEN - 1].copy_from_slice(&counter.to_be_bytes());
+    nonce[NONCE_LEN - 1] = u8::from(last);
+    nonce
+}
+
+fn next_counter(counter: u32) -> io::Result<u32> {
+    counter
+        .checked_add(1)
+        .ok_or_else(|| io::Error::other("stream chunk counter exhausted"))
+}
+
+/// Encrypts a stream, writing the sealed chunks to an underlying writer.
+///
+/// [`Encryptor::finish`] must be called once all data has been written, to seal the last chunk.
+/// Dropping the encryptor without calling it produces a stream that fails to decrypt.
+pub struct Encryptor<W: Write> {
+    key: SealingKey,
+    writer: W,
+    header: [u8; HEADER_LEN],
+    header_written: bool,
+    counter: u32,
+    chunk_size: usize,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> Encryptor<W> {
+    /// Creates an encryptor using chunks of [`DEFAULT_CHUNK_SIZE`] bytes.
+    pub fn new(algorithm: Algorithm, key: &[u8], writer: W) -> Result<Self, ErrorStack> {
+        Self::with_chunk_size(algorithm, key, DEFAULT_CHUNK_SIZE, writer)
+    }
+
+    /// Creates an encryptor using chunks of `chunk_size` bytes.
+    ///
+    /// `chunk_size` must be between 1 and [`MAX_CHUNK_SIZE`].
+    pub fn with_chunk_size(
+        algorithm: Algorithm,
+        key: &[u8],
+        chunk_size: usize,
+        writer: W,
+    ) -> Result<Self, ErrorStack> {
+        check_algorithm(algorithm)?;
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(ErrorStack::internal_error_str("invalid chunk size"));
+        }
+
+        let mut header = [0; HEADER_LEN];
+        rand_bytes(&mut header[..NONCE_PREFIX_LEN])?;
+        header[NONCE_PREFIX_LEN..].copy_from_slice(&(chunk_size as u32).to_be_bytes());
+
+        Ok(Self {
+            key: SealingKey::new(algorithm, key)?,
+            writer,
+            header,
+            header_written: false,
+            counter: 0,
+            chunk_size,
+            buf: Vec::with_capacity(chunk_size + algorithm.max_overhead()),
+        })
+    }
+
+    /// Returns a shared reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Seals the last chunk and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_chunk(true)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_chunk(&mut self, last: bool) -> io::Result<()> {
+        if !self.header_written {
+            self.writer.write_all(&self.header)?;
+            self.header_written = true;
+        }
+
+        let nonce = chunk_nonce(&self.header, self.counter, last);
+        self.key
+            .seal_in_place(&nonce, &self.header, &mut self.buf)?;
+        self.writer.write_all(&self.buf)?;
+        self.buf.clear();
+
+        if !last {
+            self.counter = next_counter(self.counter)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Encryptor<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+        // A full chunk is only sealed once more data arrives, as it might be the last one
+        if self.buf.len() == self.chunk_size {
+            self.write_chunk(false)?;
+        }
+        let len = data.len().min(self.chunk_size - self.buf.len());
+        self.buf.extend_from_slice(&data[..len]);
+        Ok(len)
+    }
+
+    /// Flushes the underlying writer.
+    ///
+    /// Plaintext is only sealed in complete chunks, so this does not force out buffered data.
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> fmt::Debug for Encryptor<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Encryptor")
+            .field("chunk_size", &self.chunk_size)
+            .field("counter", &self.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Decrypts a stream produced by an [`Encryptor`], reading it from an underlying reader.
+///
+/// Reads fail with [`io::ErrorKind::InvalidData`] if the stream has been tampered with or
+/// truncated. Data returned before such an error has been authenticated, but the stream as a
+/// whole is only known to be complete once a read returns `0`. Once a read has failed this way,
+/// every later read fails too, so a bad chunk can't be skipped or retried.
+pub struct Decryptor<R: Read> {
+    key: OpeningKey,
+    reader: R,
+    header: Option<[u8; HEADER_LEN]>,
+    counter: u32,
+    chunk_size: usize,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    pos: usize,
+    done: bool,
+    failed: bool,
+}
+
+impl<R: Read> Decryptor<R> {
+    /// Creates a decryptor. The header is read along with the first chunk.
+    pub fn new(algorithm: Algorithm, key: &[u8], reader: R) -> Result<Self, ErrorStack> {
+        check_algorithm(algorithm)?;
+        Ok(Self {
+            key: OpeningKey::new(algorithm, key)?,
+            reader,
+            header: None,
+            counter: 0,
+            chunk_size: 0,
+            input: Vec::new(),
+            output: Vec::new(),
+            pos: 0,
+            done: false,
+            failed: false,
+        })
+    }
+
+    /// Returns a shared reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads until `buf` is full or the underlying reader is exhausted.
+    fn fill(&mut self, buf_len: usize) -> io::Result<()> {
+        while self.input.len() < buf_len {
+            let start = self.input.len();
+            self.input.resize(buf_len, 0);
+            match self.reader.read(&mut self.input[start..]) {
+                Ok(0) => {
+                    self.input.truncate(start);
+                    break;
+                }
+                Ok(n) => self.input.truncate(start + n),
+                Err(e) => {
+                    self.input.truncate(start);
+                    if e.kind() != io::ErrorKind::Interrupted {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_header(&mut self) -> io::Result<[u8; HEADER_LEN]> {
+        if let Some(header) = self.header {
+            return Ok(header);
+        }
+
+        self.fill(HEADER_LEN)?;
+        let header: [u8; HEADER_LEN] = self.input[..]
+            .try_into()
+            .map_err(|_| invalid_data("truncated stream header"))?;
+        self.input.clear();
+
+        let chunk_size = u32::from_be_bytes(header[NONCE_PREFIX_LEN..].try_into().unwrap());
+        let chunk_size = chunk_size as usize;
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(invalid_data("invalid chunk size"));
+        }
+
+        self.chunk_size = chunk_size;
+        self.header = Some(header);
+        Ok(header)
+    }
+
+    fn read_chunk(&mut self) -> io::Result<()> {
+        let header = self.read_header()?;
+        let sealed_len = self.chunk_size + self.key.algorithm().max_overhead();
+
+        // Read one byte past the chunk to tell whether it is the last one
+        self.fill(sealed_len + 1)?;
+        let last = self.input.len() <= sealed_len;
+        let chunk_len = self.input.len().min(sealed_len);
+
+        let nonce = chunk_nonce(&header, self.counter, last);
+        let mut chunk = std::mem::take(&mut self.output);
+        chunk.clear();
+        chunk.extend_from_slice(&self.input[..chunk_len]);
+        self.input.drain(..chunk_len);
+
+        let len = self
+            .key
+            .open_in_place(&nonce, &header, &mut chunk)
+            .map_err(|_| invalid_data("stream chunk failed to authenticate"))?
+            .len();
+        chunk.truncate(len);
+        self.output = chunk;
+        self.pos = 0;
+
+        if last {
+            self.done = true;
+        } else {
+            self.counter = next_counter(self.counter)?;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Decryptor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.failed {
+            return Err(invalid_data("stream previously failed to decrypt"));
+        }
+
+        while self.pos == self.output.len() {
+            if self.done || buf.is_empty() {
+                return Ok(0);
+            }
+            if let Err(e) = self.read_chunk() {
+                // Errors of the underlying reader can be retried, but not invalid streams
+                self.failed = e.kind() == io::ErrorKind::InvalidData;
+                return Err(e);
+            }
+        }
+
+        let len = buf.len().min(self.output.len() - self.pos);
+        buf[..len].copy_from_slice(&self.output[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+impl<R: Read> fmt::Debug for Decryptor<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Decryptor")
+            .field("chunk_size", &self.chunk_size)
+            .field("counter", &self.counter)
+            .field("done", &self.done)
+            .field("failed", &self.failed)
+            .finish_non_exhaustive()
+    }
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [7; 32];
+
+    fn encrypt(algorithm: Algorithm, chunk_size: usize, data: &[u8]) -> Vec<u8> {
+        let key = &KEY[..algorithm.key_len()];
+        let mut encryptor =
+            Encryptor::with_chunk_size(algorithm, key, chunk_size, Vec::new()).unwrap();
+        // Feed the data in awkward pieces to exercise the chunk buffering
+        for piece in data.chunks(7) {
+            encryptor.write_all(piece).unwrap();
+        }
+        encryptor.finish().unwrap()
+    }
+
+    fn decrypt(algorithm: Algorithm, ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+        let key = &KEY[..algorithm.key_len()];
+        let mut decryptor = Decryptor::new(algorithm, key, ciphertext).unwrap();
+        let mut plaintext = Vec::new();
+        decryptor.read_to_end(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn roundtrip() {
+        let data: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+        for algorithm in [
+            Algorithm::aes_128_gcm(),
+            Algorithm::aes_256_gcm(),
+            Algorithm::chacha20_poly1305(),
+        ] {
+            let key = &KEY[..algorithm.key_len()];
+            for len in [0, 1, 99, 100, 101, 200, 1000] {
+                let mut encryptor =
+                    Encryptor::with_chunk_size(algorithm, key, 100, Vec::new()).unwrap();
+                encryptor.write_all(&data[..len]).unwrap();
+                let ciphertext = encryptor.finish().unwrap();
+
+                let chunks = len.div_ceil(100).max(1);
+                assert_eq!(ciphertext.len(), HEADER_LEN + len + chunks * 16);
+
+                let mut decryptor = Decryptor::new(algorithm, key, &ciphertext[..]).unwrap();
+                let mut plaintext = Vec::new();
+                decryptor.read_to_end(&mut plaintext).unwrap();
+                assert_eq!(plaintext, &data[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn header() {
+        let ciphertext = encrypt(Algorithm::chacha20_poly1305(), 100, b"hello");
+        assert_eq!(&ciphertext[NONCE_PREFIX_LEN..HEADER_LEN], &[0, 0, 0, 100]);
+
+        // Every stream gets a fresh nonce prefix
+        let other = encrypt(Algorithm::chacha20_poly1305(), 100, b"hello");
+        assert_ne!(ciphertext[..NONCE_PREFIX_LEN], other[..NONCE_PREFIX_LEN]);
+    }
+
+    #[test]
+    fn small_reads() {
+        let algorithm = Algorithm::aes_256_gcm();
+        let data = [3; 250];
+        let ciphertext = encrypt(algorithm, 16, &data);
+
+        let mut decryptor = Decryptor::new(algorithm, &KEY, &ciphertext[..]).unwrap();
+        let mut plaintext = Vec::new();
+        let mut buf = [0; 5];
+        loop {
+            let n = decryptor.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            plaintext.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(plaintext, data);
+    }
+
+    #[test]
+    fn truncation_detected() {
+        let algorithm = Algorithm::aes_256_gcm();
+        let ciphertext = encrypt(algorithm, 10, &[1; 30]);
+        let sealed_len = 10 + 16;
+
+        // Truncated at a chunk boundary
+        let err = decrypt(algorithm, &ciphertext[..HEADER_LEN + 2 * sealed_len]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Truncated in the middle of a chunk
+        assert!(decrypt(algorithm, &ciphertext[..ciphertext.len() - 1]).is_err());
+
+        // Truncated header or no chunks at all
+        assert!(decrypt(algorithm, &ciphertext[..5]).is_err());
+        assert!(decrypt(algorithm, &ciphertext[..HEADER_LEN]).is_err());
+    }
+
+    #[test]
+    fn extension_detected() {
+        let algorithm = Algorithm::chacha20_poly1305();
+        let mut ciphertext = encrypt(algorithm, 10, &[1; 30]);
+        let last_chunk = ciphertext[ciphertext.len() - 26..].to_vec();
+        ciphertext.extend_from_slice(&last_chunk);
+        assert!(decrypt(algorithm, &ciphertext).is_err());
+    }
+
+    #[test]
+    fn reordering_detected() {
+        let algorithm = Algorithm::aes_128_gcm();
+        let ciphertext = encrypt(algorithm, 10, &[1; 35]);
+        let sealed_len = 10 + 16;
+
+        let mut reordered = ciphertext[..HEADER_LEN].to_vec();
+        reordered
+            .extend_from_slice(&ciphertext[HEADER_LEN + sealed_len..HEADER_LEN + 2 * sealed_len]);
+        reordered.extend_from_slice(&ciphertext[HEADER_LEN..HEADER_LEN + sealed_len]);
+        reordered.extend_from_slice(&ciphertext[HEADER_LEN + 2 * sealed_len..]);
+        assert!(decrypt(algorithm, &reordered).is_err());
+    }
+
+    #[test]
+    fn tampering_detected() {
+        let algorithm = Algorithm::aes_256_gcm();
+        let ciphertext = encrypt(algorithm, 10, &[1; 30]);
+
+        for i in [0, NONCE_PREFIX_LEN + 3, HEADER_LEN, ciphertext.len() - 1] {
+            let mut tampered = ciphertext.clone();
+            tampered[i] ^= 1;
+            assert!(decrypt(algorithm, &tampered).is_err());
+        }
+
+        let mut oversized = ciphertext.clone();
+        oversized[NONCE_PREFIX_LEN..HEADER_LEN].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(decrypt(algorithm, &oversized).is_err());
+    }
+
+    #[test]
+    fn failure_is_permanent() {
+        let algorithm = Algorithm::aes_256_gcm();
+        let ciphertext = encrypt(algorithm, 10, &[1; 30]);
+        let second = HEADER_LEN + 10 + 16;
+
+        // A forged second chunk, followed by the genuine one
+        let mut forged = ciphertext[..second].to_vec();
+        forged.extend_from_slice(&ciphertext[second..second + 10 + 16]);
+        *forged.last_mut().unwrap() ^= 1;
+        forged.extend_from_slice(&ciphertext[second..]);
+
+        let mut decryptor = Decryptor::new(algorithm, &KEY, &forged[..]).unwrap();
+        let mut buf = [0; 10];
+        decryptor.read_exact(&mut buf).unwrap();
+        let err = decryptor.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Retrying would otherwise decrypt the genuine chunk that follows the forged one
+        let err = decryptor.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(decryptor.read_to_end(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn wrong_key() {
+        let algorithm = Algorithm::aes_256_gcm();
+        let ciphertext = encrypt(algorithm, 10, b"secret");
+        let mut decryptor = Decryptor::new(algorithm, &[8; 32], &ciphertext[..]).unwrap();
+        assert!(decryptor.read_to_end(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let algorithm = Algorithm::aes_256_gcm();
+        assert!(Encryptor::with_chunk_size(algorithm, &KEY, 0, Vec::new()).is_err());
+        assert!(
+            Encryptor::with_chunk_size(algorithm, &KEY, MAX_CHUNK_SIZE + 1, Vec::new()).is_err()
+        );
+        assert!(Encryptor::new(Algorithm::xchacha20_poly1305(), &KEY, Vec::new()).is_err());
+        assert!(Decryptor::new(Algorithm::xchacha20_poly1305(), &KEY, &[][..]).is_err());
+    }
+}
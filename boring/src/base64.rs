@@ -73,6 +73,49 @@ pub fn decode_block(src: &str) -> Result<Vec<u8>, ErrorStack> {
     Ok(out)
 }
 
+/// Encodes a slice of bytes to an unpadded base64url string, as used by JOSE.
+///
+/// # Panics
+///
+/// Panics if the input length or computed output length overflow a signed C integer.
+pub fn encode_block_url(src: &[u8]) -> String {
+    encode_block(src)
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '+' => '-',
+            '/' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Decodes an unpadded base64url string to bytes.
+///
+/// Unlike [`decode_block`], padding and whitespace are rejected.
+///
+/// # Panics
+///
+/// Panics if the input length or computed output length overflow a signed C integer.
+pub fn decode_block_url(src: &str) -> Result<Vec<u8>, ErrorStack> {
+    if src.len() % 4 == 1 || src.contains(['+', '/', '=']) || src.contains(char::is_whitespace) {
+        return Err(ErrorStack::internal_error_str("invalid base64url"));
+    }
+
+    let mut padded = src
+        .chars()
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            c => c,
+        })
+        .collect::<String>();
+    while padded.len() % 4 != 0 {
+        padded.push('=');
+    }
+    decode_block(&padded)
+}
+
 fn encoded_len(src_len: usize) -> Option<usize> {
     let mut len = (src_len / 3).checked_mul(4)?;
 
@@ -126,4 +169,19 @@ mod tests {
         assert_eq!(b"foobar".to_vec(), decode_block(" Zm9vYmFy\n").unwrap());
         assert_eq!(b"foob".to_vec(), decode_block(" Zm9vYg==\n").unwrap());
     }
+
+    #[test]
+    fn test_block_url() {
+        assert_eq!("", encode_block_url(b""));
+        assert_eq!("Zg", encode_block_url(b"f"));
+        assert_eq!("-_8", encode_block_url(&[0xfb, 0xff]));
+        assert_eq!(b"f".to_vec(), decode_block_url("Zg").unwrap());
+        assert_eq!(vec![0xfb, 0xff], decode_block_url("-_8").unwrap());
+        assert_eq!(b"foobar".to_vec(), decode_block_url("Zm9vYmFy").unwrap());
+
+        assert!(decode_block_url("Zg==").is_err());
+        assert!(decode_block_url("+/8").is_err());
+        assert!(decode_block_url(" Zm9v").is_err());
+        assert!(decode_block_url("Zm9vY").is_err());
+    }
 }
@@ -0,0 +1,419 @@
+//! RSA blind signatures ([RFC 9474]).
+//!
+//! A client blinds a message with the public key of a signer, which signs it without learning the
+//! message. The client then unblinds the signature, giving a standard RSASSA-PSS signature of the
+//! message that anyone with the public key can verify, and that the signer cannot link to the
+//! signing request.
+//!
+//! ```
+//! use rama_boring::blind_rsa::{self, BlindRsaVariant};
+//! use rama_boring::rsa::Rsa;
+//!
+//! let key = Rsa::generate(2048).unwrap();
+//! let public_key = Rsa::public_key_from_der(&key.public_key_to_der().unwrap()).unwrap();
+//! let variant = BlindRsaVariant::SHA384_PSS_RANDOMIZED;
+//!
+//! // Client
+//! let message = variant.prepare(b"hello").unwrap();
+//! let blinded = variant.blind(&public_key, &message).unwrap();
+//!
+//! // Signer
+//! let blind_signature = blind_rsa::blind_sign(&key, blinded.blinded_message()).unwrap();
+//!
+//! // Client
+//! let signature = blinded
+//!     .finalize(&public_key, &message, &blind_signature)
+//!     .unwrap();
+//! assert!(variant.verify(&public_key, &message, &signature).unwrap());
+//! ```
+//!
+//! # Variants
+//!
+//! The four RSABSSA-SHA384 variants of the RFC are supported. The randomized variants prefix the
+//! message with 32 random bytes in [`BlindRsaVariant::prepare`], so that the signer cannot choose
+//! the encoded message. The PSSZERO variants use an empty salt, so that signatures of a prepared
+//! message are deterministic.
+//!
+//! The blinding factor is secret. Its inverse is computed with the blinded modular inversion of
+//! BoringSSL, and the products with it use Montgomery multiplication. The signer uses the blinded
+//! RSA private key operation of BoringSSL, and checks its result before returning it.
+//!
+//! [RFC 9474]: https://www.rfc-editor.org/rfc/rfc9474.html
+
+use std::fmt;
+
+use crate::bn::{BigNum, BigNumContext, BigNumRef, MontContext};
+use crate::error::ErrorStack;
+use crate::hash::{hash, Hasher, MessageDigest};
+use crate::memcmp;
+use crate::pkey::{HasPrivate, HasPublic, PKey};
+use crate::rand::rand_bytes;
+use crate::rsa::{Padding, RsaRef};
+use crate::sign::{RsaPssSaltlen, Verifier};
+
+// The length of the random prefix of the randomized variants
+const PREFIX_LEN: usize = 32;
+
+/// An RSABSSA variant.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlindRsaVariant {
+    salt_len: usize,
+    randomized: bool,
+}
+
+impl BlindRsaVariant {
+    /// RSABSSA-SHA384-PSS-Randomized, the variant recommended by the RFC.
+    pub const SHA384_PSS_RANDOMIZED: BlindRsaVariant = BlindRsaVariant {
+        salt_len: 48,
+        randomized: true,
+    };
+    /// RSABSSA-SHA384-PSSZERO-Randomized.
+    pub const SHA384_PSSZERO_RANDOMIZED: BlindRsaVariant = BlindRsaVariant {
+        salt_len: 0,
+        randomized: true,
+    };
+    /// RSABSSA-SHA384-PSS-Deterministic.
+    pub const SHA384_PSS_DETERMINISTIC: BlindRsaVariant = BlindRsaVariant {
+        salt_len: 48,
+        randomized: false,
+    };
+    /// RSABSSA-SHA384-PSSZERO-Deterministic.
+    pub const SHA384_PSSZERO_DETERMINISTIC: BlindRsaVariant = BlindRsaVariant {
+        salt_len: 0,
+        randomized: false,
+    };
+
+    /// Returns the name of the variant, such as `RSABSSA-SHA384-PSS-Randomized`.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match (self.salt_len, self.randomized) {
+            (0, true) => "RSABSSA-SHA384-PSSZERO-Randomized",
+            (0, false) => "RSABSSA-SHA384-PSSZERO-Deterministic",
+            (_, true) => "RSABSSA-SHA384-PSS-Randomized",
+            (_, false) => "RSABSSA-SHA384-PSS-Deterministic",
+        }
+    }
+
+    /// Returns the length of the PSS salt.
+    #[must_use]
+    pub fn salt_len(&self) -> usize {
+        self.salt_len
+    }
+
+    /// Returns true if messages are prefixed with random bytes.
+    #[must_use]
+    pub fn is_randomized(&self) -> bool {
+        self.randomized
+    }
+
+    /// Prepares `message` for signing, prefixing it with random bytes for randomized variants.
+    ///
+    /// The prepared message is what is blinded, finalized and verified.
+    pub fn prepare(&self, message: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+        if !self.randomized {
+            return Ok(message.to_vec());
+        }
+        let mut prepared = vec![0; PREFIX_LEN];
+        rand_bytes(&mut prepared)?;
+        prepared.extend_from_slice(message);
+        Ok(prepared)
+    }
+
+    /// Blinds the prepared `message` for the signer with public key `key`.
+    pub fn blind<T>(&self, key: &RsaRef<T>, message: &[u8]) -> Result<BlindedMessage, ErrorStack>
+    where
+        T: HasPublic,
+    {
+        let mut salt = vec![0; self.salt_len];
+        rand_bytes(&mut salt)?;
+        let mut r = BigNum::new()?;
+        while r.num_bits() == 0 {
+            key.n().rand_range(&mut r)?;
+        }
+        self.blind_with(key, message, &salt, &r)
+    }
+
+    /// Verifies `signature` of the prepared `message`.
+    ///
+    /// This is RSASSA-PSS verification with SHA-384, so other implementations of RSASSA-PSS can
+    /// verify the signatures as well.
+    pub fn verify<T>(
+        &self,
+        key: &RsaRef<T>,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, ErrorStack>
+    where
+        T: HasPublic,
+    {
+        let key = PKey::from_rsa(key.to_owned())?;
+        let mut verifier = Verifier::new(MessageDigest::sha384(), &key)?;
+        verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
+        verifier.set_rsa_pss_saltlen(RsaPssSaltlen::custom(self.salt_len as i32))?;
+        verifier.set_rsa_mgf1_md(MessageDigest::sha384())?;
+        verifier.verify_oneshot(signature, message)
+    }
+
+    /// Blinds `message` with the PSS salt `salt` and the blinding factor `r`.
+    fn blind_with<T>(
+        &self,
+        key: &RsaRef<T>,
+        message: &[u8],
+        salt: &[u8],
+        r: &BigNumRef,
+    ) -> Result<BlindedMessage, ErrorStack>
+    where
+        T: HasPublic,
+    {
+        let n = key.n();
+        let encoded = pss_encode(message, n.num_bits() as usize - 1, salt)?;
+        let m = BigNum::from_slice(&encoded)?;
+
+        let mut ctx = BigNumContext::new()?;
+        let mut gcd = BigNum::new()?;
+        gcd.gcd(&m, n, &mut ctx)?;
+        if gcd.num_bits() != 1 {
+            return Err(ErrorStack::internal_error_str(
+                "blind RSA message not coprime to the modulus",
+            ));
+        }
+
+        let mont = MontContext::new(n, &mut ctx)?;
+        let inverse = mont.inverse(r, &mut ctx)?;
+        let x = mont.exp(r, key.e(), &mut ctx)?;
+        let z = mont.mul(&m, &x, &mut ctx)?;
+        Ok(BlindedMessage {
+            variant: *self,
+            blinded_message: z.to_vec_padded(key.size() as usize)?,
+            inverse,
+        })
+    }
+}
+
+impl fmt::Debug for BlindRsaVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A blinded message, and the secret needed to unblind its signature.
+pub struct BlindedMessage {
+    variant: BlindRsaVariant,
+    blinded_message: Vec<u8>,
+    inverse: BigNum,
+}
+
+impl BlindedMessage {
+    /// Returns the blinded message to send to the signer.
+    #[must_use]
+    pub fn blinded_message(&self) -> &[u8] {
+        &self.blinded_message
+    }
+
+    /// Unblinds the signature of the signer, returning the signature of the prepared `message`.
+    ///
+    /// `key` and `message` must be the ones the message was blinded with. The signature is
+    /// verified before it is returned.
+    pub fn finalize<T>(
+        self,
+        key: &RsaRef<T>,
+        message: &[u8],
+        blind_signature: &[u8],
+    ) -> Result<Vec<u8>, ErrorStack>
+    where
+        T: HasPublic,
+    {
+        let len = key.size() as usize;
+        let z = BigNum::from_slice(blind_signature)?;
+        if blind_signature.len() != len || z >= *key.n() {
+            return Err(ErrorStack::internal_error_str(
+                "invalid blind RSA signature",
+            ));
+        }
+
+        let mut ctx = BigNumContext::new()?;
+        let mont = MontContext::new(key.n(), &mut ctx)?;
+        let signature = mont.mul(&z, &self.inverse, &mut ctx)?.to_vec_padded(len)?;
+        if !self.variant.verify(key, message, &signature)? {
+            return Err(ErrorStack::internal_error_str(
+                "invalid blind RSA signature",
+            ));
+        }
+        Ok(signature)
+    }
+}
+
+impl fmt::Debug for BlindedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlindedMessage")
+            .field("variant", &self.variant)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Signs a blinded message with the private key `key`.
+///
+/// The signature is checked with the public key before it is returned, so that a faulty private
+/// key operation cannot leak the key.
+pub fn blind_sign<T>(key: &RsaRef<T>, blinded_message: &[u8]) -> Result<Vec<u8>, ErrorStack>
+where
+    T: HasPrivate,
+{
+    let len = key.size() as usize;
+    if blinded_message.len() != len || BigNum::from_slice(blinded_message)? >= *key.n() {
+        return Err(ErrorStack::internal_error_str(
+            "invalid blinded RSA message",
+        ));
+    }
+
+    let mut signature = vec![0; len];
+    key.private_decrypt(blinded_message, &mut signature, Padding::NONE)?;
+    let mut check = vec![0; len];
+    key.public_encrypt(&signature, &mut check, Padding::NONE)?;
+    if !memcmp::eq(&check, blinded_message) {
+        return Err(ErrorStack::internal_error_str(
+            "blind RSA signature check failed",
+        ));
+    }
+    Ok(signature)
+}
+
+/// EMSA-PSS-ENCODE of RFC 8017 with SHA-384, for a given salt.
+fn pss_encode(message: &[u8], em_bits: usize, salt: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+    let md = MessageDigest::sha384();
+    let hash_len = md.size();
+    let em_len = em_bits.div_ceil(8);
+    if em_len < hash_len + salt.len() + 2 {
+        return Err(ErrorStack::internal_error_str(
+            "RSA key too small for blind signatures",
+        ));
+    }
+
+    let message_hash = hash(md, message)?;
+    let mut hasher = Hasher::new(md)?;
+    hasher.update(&[0; 8])?;
+    hasher.update(&message_hash)?;
+    hasher.update(salt)?;
+    let h = hasher.finish()?;
+
+    // EM = maskedDB || H || 0xbc, with DB = PS || 0x01 || salt
+    let db_len = em_len - hash_len - 1;
+    let mut em = vec![0; em_len];
+    em[db_len - salt.len() - 1] = 1;
+    em[db_len - salt.len()..db_len].copy_from_slice(salt);
+    for (counter, chunk) in (0u32..).zip(em[..db_len].chunks_mut(hash_len)) {
+        let mut hasher = Hasher::new(md)?;
+        hasher.update(&h)?;
+        hasher.update(&counter.to_be_bytes())?;
+        for (byte, mask) in chunk.iter_mut().zip(hasher.finish()?.iter()) {
+            *byte ^= mask;
+        }
+    }
+    em[0] &= 0xff >> (8 * em_len - em_bits);
+    em[db_len..em_len - 1].copy_from_slice(&h);
+    em[em_len - 1] = 0xbc;
+    Ok(em)
+}
+
+#[cfg(test)]
+mod test {
+    use hex::FromHex;
+
+    use super::*;
+    use crate::pkey::Private;
+    use crate::rsa::Rsa;
+
+    fn key() -> Rsa<Private> {
+        Rsa::private_key_from_pem(include_bytes!("../test/rsa.pem")).unwrap()
+    }
+
+    #[test]
+    fn blind_sign_finalize() {
+        let key = key();
+        let public_key = Rsa::public_key_from_der(&key.public_key_to_der().unwrap()).unwrap();
+        for variant in [
+            BlindRsaVariant::SHA384_PSS_RANDOMIZED,
+            BlindRsaVariant::SHA384_PSSZERO_RANDOMIZED,
+            BlindRsaVariant::SHA384_PSS_DETERMINISTIC,
+            BlindRsaVariant::SHA384_PSSZERO_DETERMINISTIC,
+        ] {
+            let message = variant.prepare(b"message").unwrap();
+            assert_eq!(message.len() > 7, variant.is_randomized());
+
+            let blinded = variant.blind(&public_key, &message).unwrap();
+            let blind_signature = blind_sign(&key, blinded.blinded_message()).unwrap();
+            let signature = blinded
+                .finalize(&public_key, &message, &blind_signature)
+                .unwrap();
+            assert!(variant.verify(&public_key, &message, &signature).unwrap());
+            assert!(!variant.verify(&public_key, b"other", &signature).unwrap());
+
+            // A signature for another message fails to finalize
+            let blinded = variant.blind(&public_key, &message).unwrap();
+            assert!(blinded
+                .finalize(&public_key, b"other", &blind_signature)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn invalid_blinded_message() {
+        let key = key();
+        assert!(blind_sign(&key, &[0xff; 256]).is_err());
+        assert!(blind_sign(&key, &[1; 255]).is_err());
+    }
+
+    #[test]
+    fn fixed_inverse_regression() {
+        // Regression vectors for the test key, with a blinding inverse derived from a fixed string
+        // so that the output is fixed. The blind signatures are the blinded messages raised to the
+        // private exponent, and the signatures verify with `openssl dgst -sha384 -sigopt
+        // rsa_padding_mode:pss` using the salt length of each variant.
+        let key = key();
+        let message = Vec::from_hex(
+            "8f3dc6fb8c4a02f4d6352edf0907822c1210a9b32f9bdda4c45a698c80023aa6b59f8cfec5fdbb36331372ebefedae7d",
+        )
+        .unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+        let seed = hash(MessageDigest::sha512(), b"blind-rsa inv")
+            .unwrap()
+            .repeat(4);
+        let mut inverse = BigNum::new().unwrap();
+        inverse
+            .nnmod(&BigNum::from_slice(&seed).unwrap(), key.n(), &mut ctx)
+            .unwrap();
+        let mut r = BigNum::new().unwrap();
+        r.mod_inverse(&inverse, key.n(), &mut ctx).unwrap();
+
+        let prefix = (1..=32).collect::<Vec<u8>>();
+        let salt = (0x40..0x70).collect::<Vec<u8>>();
+        let vectors = [
+            (
+                BlindRsaVariant::SHA384_PSS_RANDOMIZED,
+                [prefix, message.clone()].concat(),
+                salt,
+                concat!("2d1b3fa885916ab3ea76a40607936e019a3a4c2433a890e14d2984dce96d887d1433ce8eb0b3b1886013d996c0f5ba2a","973bb11851907adce7b818770347cdae380483007e9801d75532aeea56d4e9e1832ef6b718d75e8ec108616e327223cc","5bf2153dda363c6523731f70dca846406045b4f84378c1a42891166cb7a319f7dc24a4ffc07c177908b0f82a79a9985a","e3ef29d0c3fb39a7f01945ec569bd3b427c2212692c8e33cdb0fa5d64a66c9cb5cb960dca01ba61b03f50ed506f0640b","1d93f5512393d8b86b8cefbbf11f27854732a6318ea69044ccafe0ef3706276a487064fe5c556c7877bac733b7d5df41","0bc7090fd38b0775e2b779ee3bdd3b1a",),
+                concat!("6974e507f50bd3c9bebaa1824ccab7d3eda8cba92b7b324d758c31e1764d45f865a3139723e902480796da6d583a01f1","e532ada6f95831e57f5cb68e1a51b8bddb5f82373840b97904f7acd539a2210878ed60ce724f48a91a8782dcc5d24872","9f80ee2630e9b02e70673c62340aff0cec8c5dc9a423811bab5649ee962eb02571469c51ff64040157f973947514d629","958baabf8e7cb16c5536d509dad6b5437a0372962bf3f87e2e28ff9e8b1b96382e96583894a8f0bfb297da90e91f10e4","58e11955f8076f3f6cd4e42568816ca6555a6082993f13a69046bd19f248e5ed3166fb3229280f8a0d554c5869609e51","746a30871c8fec5cfc4a66bbf4b58f56",),
+                concat!("924e98f2239b035796f00f8cc64727e6fd802feea1f1fa9b35835267369bc4c55a49a33f861828f15bd8c9f8e430f890","c9487c94e6b18695e606040bf50baa4f196381a7743814ce18b6d21a4a29d705c45e6047b0700fe7038003c116abc109","15ef407962328f26a1b91c7e4a89a711a3396461ac9746752b4b1262ef2e6a37f56d5b3ac5d804405670ef32bd0d7692","51a960175cec3bf571e2eb755f01064cb3c4aa266ca14a890aa63d2be4512c893f0b998d9271248754a4ea561be6fca6","066d0db2bbdb4f13c67cc1036f4447b54ad0bcfb4005aa3e3418bb157eed0abd6ae187016e777f066d493f2dcfdbbc3c","300ba791a16e08484b177b1dbbdad1fb",),
+            ),
+            (
+                BlindRsaVariant::SHA384_PSSZERO_DETERMINISTIC,
+                message,
+                vec![],
+                concat!("3f1785fab212d3955db9f3303ba887efcc359ef07ea2562af562cfeed547eaaf411af26e4ca7fa7ea0dce3489601368f","7bc07e47e3770d59acd30f42ccc509521b70a72089d80206da6cf79b96ab1ba65c206810cdac306f83469923bdffd65c","03f2d6dcff412e79ffce19a94dec86d762b60907f201a22609b7451b25f4a3fc060cb84c8ab6b27121fe2f5ece77d10c","e1aa4e5cc53d429b72f8214895074ebc2e4d7a5f8a520826ce4b4653c56b4ea11ab4836cd8eca584e0749ad6e904fe2d","4dc12d81488ffb5e758a8135f70a790aecd5f8d850167fd1f270076de8e9e9ba8079146e63afe52fc5a3ad3dbc018a92","11a1aa380fb76703198e38ca97b36fe4",),
+                concat!("76744abb889bdd05ca1022d561d6c8b8926e621efe6d2d6016f77611654a1f4388b9d1d774574b502c69000b61128ea9","0aafab8aaf810c8df33dd420d517f84ca45d1d9c2660da38549929c77df4287ef9fac9fc196ff71fe3fb818054d11ba9","bc2138b37c14098748323375692dc7e57291db316935bea104ac5f858c8f12aa812c33b54bce15c21ace9a463754c6f3","b82df305e77a8511803d747771afd2e8e3669b57c5c447014e3c8486a85e40d8423a3f7dd89939ac7c5332035aa5222e","858605d201a26f0c93c77963405f4674a254e2872a8ed6755fdbb1e6e6de13ffe8ba982314b08f728ddd748ba02ea378","c6196d2d36ecfdb197e07634cebaf1fc",),
+                concat!("0031a18e2828bbdf12da55a2121325e722149540312c15bf8655645e93851e4368107838617ef414f27127869d3aa974","a1ec8e81c9b2d8b16bc2c8ac7fe6d0e5d6b5b605cd4aeaea03bd33adc9a17074b9f6ce9164b957fdfa2c4ae9d13967d1","143872e58db5a60768d666e02243b7d518617685e36a59d5548ffb20e4973b12315c0e9aad20e99a92cbf737f2d69401","9560fd09274261aa934705bc37c9ab1002a0eb190a7bb313ee2f63b68a35771678fdf05cf4288e5fd89857baf5b97fcf","3493e11abef21b627d8ef1412e0a9708ec604eb1d0125fb2058e86883fc208402c567f4da31848ca71afb74c4fd46646","a12ffbac367700abb980b0f7dafc1977",),
+            ),
+        ];
+        for (variant, message, salt, blinded_message, blind_signature, signature) in vectors {
+            let blinded = variant.blind_with(&key, &message, &salt, &r).unwrap();
+            assert_eq!(hex::encode(blinded.blinded_message()), blinded_message);
+            let blind_signature_bytes = blind_sign(&key, blinded.blinded_message()).unwrap();
+            assert_eq!(hex::encode(&blind_signature_bytes), blind_signature);
+            let finalized = blinded
+                .finalize(&key, &message, &blind_signature_bytes)
+                .unwrap();
+            assert_eq!(hex::encode(finalized), signature);
+        }
+    }
+}
@@ -33,6 +33,7 @@ use std::{fmt, ptr};
 use crate::asn1::Asn1Integer;
 use crate::error::ErrorStack;
 use crate::ffi::BN_is_negative;
+use crate::secret::SecretBytes;
 use crate::string::OpensslString;
 use crate::{cvt, cvt_n, cvt_p};
 use openssl_macros::corresponds;
@@ -763,6 +764,29 @@ impl BigNumRef {
         Ok(v)
     }
 
+    /// Like [`to_vec`](Self::to_vec), but returns the bytes in a buffer that is wiped when
+    /// dropped, for private values such as RSA private components.
+    #[corresponds(BN_bn2bin)]
+    #[must_use]
+    pub fn to_vec_secret(&self) -> SecretBytes {
+        let mut v = SecretBytes::zeroed(self.num_bytes() as usize);
+        unsafe {
+            ffi::BN_bn2bin(self.as_ptr(), v.as_mut_ptr());
+        }
+        v
+    }
+
+    /// Like [`to_vec_padded`](Self::to_vec_padded), but returns the bytes in a buffer that is
+    /// wiped when dropped.
+    #[corresponds(BN_bn2bin_padded)]
+    pub fn to_vec_padded_secret(&self, pad_to: usize) -> Result<SecretBytes, ErrorStack> {
+        let mut v = SecretBytes::zeroed(pad_to);
+        unsafe {
+            cvt(ffi::BN_bn2bin_padded(v.as_mut_ptr(), pad_to, self.as_ptr()))?;
+        }
+        Ok(v)
+    }
+
     /// Returns a decimal string representation of `self`.
     ///
     /// ```
@@ -870,6 +894,119 @@ impl BigNum {
     }
 }
 
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::BN_MONT_CTX;
+    fn drop = ffi::BN_MONT_CTX_free;
+
+    /// Montgomery arithmetic modulo an odd modulus, such as an RSA modulus or the order of an
+    /// elliptic curve group.
+    pub(crate) struct MontContext;
+}
+
+impl MontContext {
+    pub(crate) fn new(
+        modulus: &BigNumRef,
+        ctx: &mut BigNumContextRef,
+    ) -> Result<MontContext, ErrorStack> {
+        unsafe {
+            cvt_p(ffi::BN_MONT_CTX_new_for_modulus(
+                modulus.as_ptr(),
+                ctx.as_ptr(),
+            ))
+            .map(|p| MontContext::from_ptr(p))
+        }
+    }
+}
+
+impl MontContextRef {
+    /// Returns the modulus of the context.
+    pub(crate) fn modulus(&self) -> &BigNumRef {
+        unsafe { BigNumRef::from_ptr(ptr::addr_of_mut!((*self.as_ptr()).N)) }
+    }
+
+    /// Returns `a * b` modulo the modulus, for `a` and `b` smaller than the modulus.
+    pub(crate) fn mul(
+        &self,
+        a: &BigNumRef,
+        b: &BigNumRef,
+        ctx: &mut BigNumContextRef,
+    ) -> Result<BigNum, ErrorStack> {
+        let b_mont = BigNum::new()?;
+        let out = BigNum::new()?;
+        unsafe {
+            cvt(ffi::BN_to_montgomery(
+                b_mont.as_ptr(),
+                b.as_ptr(),
+                self.as_ptr(),
+                ctx.as_ptr(),
+            ))?;
+            cvt(ffi::BN_mod_mul_montgomery(
+                out.as_ptr(),
+                a.as_ptr(),
+                b_mont.as_ptr(),
+                self.as_ptr(),
+                ctx.as_ptr(),
+            ))?;
+        }
+        Ok(out)
+    }
+
+    /// Returns `a + b` modulo the modulus, for `a` and `b` smaller than the modulus.
+    pub(crate) fn add(&self, a: &BigNumRef, b: &BigNumRef) -> Result<BigNum, ErrorStack> {
+        let out = BigNum::new()?;
+        unsafe {
+            cvt(ffi::BN_mod_add_quick(
+                out.as_ptr(),
+                a.as_ptr(),
+                b.as_ptr(),
+                self.modulus().as_ptr(),
+            ))?;
+        }
+        Ok(out)
+    }
+
+    /// Returns `a` to the power `p` modulo the modulus.
+    pub(crate) fn exp(
+        &self,
+        a: &BigNumRef,
+        p: &BigNumRef,
+        ctx: &mut BigNumContextRef,
+    ) -> Result<BigNum, ErrorStack> {
+        let out = BigNum::new()?;
+        unsafe {
+            cvt(ffi::BN_mod_exp_mont_consttime(
+                out.as_ptr(),
+                a.as_ptr(),
+                p.as_ptr(),
+                self.modulus().as_ptr(),
+                ctx.as_ptr(),
+                self.as_ptr(),
+            ))?;
+        }
+        Ok(out)
+    }
+
+    /// Returns the inverse of `a` modulo the modulus, blinding the computation.
+    pub(crate) fn inverse(
+        &self,
+        a: &BigNumRef,
+        ctx: &mut BigNumContextRef,
+    ) -> Result<BigNum, ErrorStack> {
+        let out = BigNum::new()?;
+        let mut no_inverse = 0;
+        unsafe {
+            cvt(ffi::BN_mod_inverse_blinded(
+                out.as_ptr(),
+                &mut no_inverse,
+                a.as_ptr(),
+                self.as_ptr(),
+                ctx.as_ptr(),
+            ))?;
+        }
+        Ok(out)
+    }
+}
+
 impl fmt::Debug for BigNumRef {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.to_dec_str() {
@@ -1136,6 +1273,14 @@ mod tests {
         assert_eq!(v0, v1);
     }
 
+    #[test]
+    fn test_to_vec_secret() {
+        let v0 = BigNum::from_u32(0x4543).unwrap();
+        assert_eq!(*v0.to_vec_secret(), *v0.to_vec());
+        assert_eq!(*v0.to_vec_padded_secret(4).unwrap(), [0, 0, 0x45, 0x43]);
+        assert!(v0.to_vec_padded_secret(1).is_err());
+    }
+
     #[test]
     fn test_negation() {
         let a = BigNum::from_u32(909_829_283).unwrap();
@@ -0,0 +1,355 @@
+//! Minimal DER encoding and decoding for structures that are handled through their ASN.1
+//! definition, such as extensions, PKCS #7 messages, PKCS #12 archives and ML-KEM keys.
+//!
+//! Only what those structures need is supported: definite lengths, single byte tags and
+//! non-negative integers.
+
+use foreign_types::ForeignType;
+use std::fmt::Write;
+use std::mem::{self, MaybeUninit};
+use std::{ptr, slice};
+
+use crate::asn1::Asn1Object;
+use crate::error::ErrorStack;
+use crate::ffi;
+
+pub(crate) const BOOLEAN: u8 = 0x01;
+pub(crate) const INTEGER: u8 = 0x02;
+pub(crate) const BIT_STRING: u8 = 0x03;
+pub(crate) const OCTET_STRING: u8 = 0x04;
+pub(crate) const NULL: u8 = 0x05;
+pub(crate) const OBJECT_IDENTIFIER: u8 = 0x06;
+pub(crate) const UTF8_STRING: u8 = 0x0c;
+pub(crate) const IA5_STRING: u8 = 0x16;
+pub(crate) const VISIBLE_STRING: u8 = 0x1a;
+pub(crate) const BMP_STRING: u8 = 0x1e;
+pub(crate) const SEQUENCE: u8 = 0x30;
+pub(crate) const SET: u8 = 0x31;
+
+/// The tag of a primitive `[n] IMPLICIT` field.
+pub(crate) const fn context(n: u8) -> u8 {
+    0x80 | n
+}
+
+/// The tag of a constructed `[n]` field, either `EXPLICIT` or `IMPLICIT` over a constructed
+/// type.
+pub(crate) const fn context_constructed(n: u8) -> u8 {
+    0xa0 | n
+}
+
+/// Encodes a value with tag `tag` and contents `contents`.
+pub(crate) fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let len = contents.len();
+    let mut out = Vec::with_capacity(len + 10);
+    out.push(tag);
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|&&b| b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+    out.extend_from_slice(contents);
+    out
+}
+
+/// Encodes a `SEQUENCE` of already encoded elements.
+pub(crate) fn sequence(elements: &[Vec<u8>]) -> Vec<u8> {
+    tlv(SEQUENCE, &elements.concat())
+}
+
+/// Encodes a `SET OF` already encoded elements, sorting them as DER requires.
+pub(crate) fn set_of(elements: &[Vec<u8>]) -> Vec<u8> {
+    let mut elements = elements.to_vec();
+    elements.sort();
+    tlv(SET, &elements.concat())
+}
+
+/// Encodes a non-negative integer with tag `tag`.
+pub(crate) fn integer(tag: u8, value: u64) -> Vec<u8> {
+    unsigned_integer(tag, &value.to_be_bytes())
+}
+
+/// Encodes the non-negative integer with big-endian bytes `bytes` with tag `tag`.
+pub(crate) fn unsigned_integer(tag: u8, bytes: &[u8]) -> Vec<u8> {
+    let skip = bytes.iter().take_while(|&&b| b == 0).count();
+    let mut contents = bytes[skip..].to_vec();
+    if contents.is_empty() {
+        contents.push(0);
+    }
+    // A leading one bit would make the integer negative
+    if contents[0] & 0x80 != 0 {
+        contents.insert(0, 0);
+    }
+    tlv(tag, &contents)
+}
+
+/// Encodes an `OBJECT IDENTIFIER` given in dotted form or as a short or long name.
+pub(crate) fn oid(oid: &str) -> Result<Vec<u8>, ErrorStack> {
+    let object = Asn1Object::from_str(oid)?;
+    unsafe {
+        let data = ffi::OBJ_get0_data(object.as_ptr());
+        let len = ffi::OBJ_length(object.as_ptr());
+        if data.is_null() || len == 0 {
+            return Err(ErrorStack::internal_error_str("object has no OID"));
+        }
+        Ok(tlv(
+            OBJECT_IDENTIFIER,
+            std::slice::from_raw_parts(data, len),
+        ))
+    }
+}
+
+/// Converts a BER encoding to DER, so that it can be read with a [`Reader`].
+///
+/// Indefinite lengths are resolved and constructed universal strings are flattened, but
+/// implicitly tagged constructed strings are left as they are.
+pub(crate) fn from_ber(ber: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+    unsafe {
+        let mut input = ffi::CBS {
+            data: ber.as_ptr(),
+            len: ber.len(),
+        };
+        let mut output: MaybeUninit<ffi::CBS> = MaybeUninit::uninit();
+        let mut storage = ptr::null_mut();
+        if ffi::CBS_asn1_ber_to_der(&mut input, output.as_mut_ptr(), &mut storage) != 1 {
+            return Err(malformed());
+        }
+        let output = output.assume_init();
+        let der = slice::from_raw_parts(output.data, output.len).to_vec();
+        ffi::OPENSSL_free(storage.cast());
+        if input.len != 0 {
+            return Err(malformed());
+        }
+        Ok(der)
+    }
+}
+
+pub(crate) fn malformed() -> ErrorStack {
+    ErrorStack::internal_error_str("malformed DER")
+}
+
+/// Reads consecutive DER values.
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Fails unless every value was read.
+    pub(crate) fn finish(&self) -> Result<(), ErrorStack> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(malformed())
+        }
+    }
+
+    pub(crate) fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Reads the next value, returning its tag and contents.
+    pub(crate) fn read_any(&mut self) -> Result<(u8, &'a [u8]), ErrorStack> {
+        let [tag, first, rest @ ..] = self.data else {
+            return Err(malformed());
+        };
+        // High tag numbers are not supported
+        if tag & 0x1f == 0x1f {
+            return Err(malformed());
+        }
+        let (len, rest) = if first & 0x80 == 0 {
+            (usize::from(*first), rest)
+        } else {
+            let count = usize::from(first & 0x7f);
+            if count == 0 || count > mem::size_of::<usize>() || rest.len() < count {
+                return Err(malformed());
+            }
+            let (bytes, rest) = rest.split_at(count);
+            let len = bytes
+                .iter()
+                .fold(0, |len, &byte| (len << 8) | usize::from(byte));
+            // DER requires the shortest length encoding
+            if bytes[0] == 0 || len < 0x80 {
+                return Err(malformed());
+            }
+            (len, rest)
+        };
+        if rest.len() < len {
+            return Err(malformed());
+        }
+        let (contents, rest) = rest.split_at(len);
+        self.data = rest;
+        Ok((*tag, contents))
+    }
+
+    /// Reads the next value, returning its whole encoding.
+    pub(crate) fn read_element(&mut self) -> Result<&'a [u8], ErrorStack> {
+        let data = self.data;
+        self.read_any()?;
+        Ok(&data[..data.len() - self.data.len()])
+    }
+
+    /// Reads the next value, which must have tag `tag`.
+    pub(crate) fn read(&mut self, tag: u8) -> Result<&'a [u8], ErrorStack> {
+        match self.read_any()? {
+            (actual, contents) if actual == tag => Ok(contents),
+            _ => Err(malformed()),
+        }
+    }
+
+    /// Reads the next value if it has tag `tag`.
+    pub(crate) fn read_optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>, ErrorStack> {
+        if self.peek_tag() == Some(tag) {
+            self.read(tag).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Reads the next value as an `[n] IMPLICIT OCTET STRING`, returning its contents.
+    ///
+    /// The constructed form is accepted too, as [`from_ber`] leaves it as it is.
+    pub(crate) fn read_implicit_octet_string(&mut self, n: u8) -> Result<Vec<u8>, ErrorStack> {
+        match self.read_any()? {
+            (tag, contents) if tag == context(n) => Ok(contents.to_vec()),
+            (tag, contents) if tag == context_constructed(n) => {
+                let mut chunks = Reader::new(contents);
+                let mut octets = vec![];
+                while !chunks.is_empty() {
+                    octets.extend_from_slice(chunks.read(OCTET_STRING)?);
+                }
+                Ok(octets)
+            }
+            _ => Err(malformed()),
+        }
+    }
+
+    /// Reads the next value as a `SEQUENCE`, returning a reader over its elements.
+    pub(crate) fn read_sequence(&mut self) -> Result<Reader<'a>, ErrorStack> {
+        self.read(SEQUENCE).map(Reader::new)
+    }
+}
+
+/// Decodes the contents of a non-negative integer that fits in a `u64`.
+pub(crate) fn parse_integer(contents: &[u8]) -> Result<u64, ErrorStack> {
+    match contents {
+        [] => Err(malformed()),
+        [byte, ..] if byte & 0x80 != 0 => Err(malformed()),
+        [0, next, ..] if next & 0x80 == 0 => Err(malformed()),
+        _ => {
+            let contents = contents.strip_prefix(&[0]).unwrap_or(contents);
+            if contents.len() > 8 {
+                return Err(ErrorStack::internal_error_str("integer too large"));
+            }
+            Ok(contents
+                .iter()
+                .fold(0, |value, &byte| (value << 8) | u64::from(byte)))
+        }
+    }
+}
+
+/// Decodes the contents of an `OBJECT IDENTIFIER` to its dotted form.
+pub(crate) fn parse_oid(contents: &[u8]) -> Result<String, ErrorStack> {
+    let mut arcs = vec![];
+    let mut arc = 0u64;
+    let mut start = true;
+    for &byte in contents {
+        // Arcs are minimally encoded in base 128
+        if start && byte == 0x80 {
+            return Err(malformed());
+        }
+        arc = arc
+            .checked_mul(128)
+            .ok_or_else(|| ErrorStack::internal_error_str("OID arc too large"))?
+            | u64::from(byte & 0x7f);
+        start = byte & 0x80 == 0;
+        if start {
+            arcs.push(arc);
+            arc = 0;
+        }
+    }
+    if !start || arcs.is_empty() {
+        return Err(malformed());
+    }
+
+    let (first, second) = match arcs[0] {
+        arc @ 0..=39 => (0, arc),
+        arc @ 40..=79 => (1, arc - 40),
+        arc => (2, arc - 80),
+    };
+    let mut oid = format!("{first}.{second}");
+    for arc in &arcs[1..] {
+        write!(oid, ".{arc}").unwrap();
+    }
+    Ok(oid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        assert_eq!(tlv(0x04, &[1, 2]), [0x04, 2, 1, 2]);
+        assert_eq!(tlv(0x04, &[0; 200])[..3], [0x04, 0x81, 200]);
+        assert_eq!(tlv(0x04, &[0; 300])[..4], [0x04, 0x82, 0x01, 0x2c]);
+        assert_eq!(integer(INTEGER, 0), [0x02, 1, 0]);
+        assert_eq!(integer(INTEGER, 127), [0x02, 1, 127]);
+        assert_eq!(integer(INTEGER, 128), [0x02, 2, 0, 128]);
+        assert_eq!(integer(context(0), 256), [0x80, 2, 1, 0]);
+        assert_eq!(unsigned_integer(INTEGER, &[0, 0, 0xff]), [0x02, 2, 0, 0xff]);
+        assert_eq!(unsigned_integer(INTEGER, &[]), [0x02, 1, 0]);
+        assert_eq!(
+            set_of(&[vec![0x04, 1, 2], vec![0x02, 1, 5], vec![0x04, 0]]),
+            [0x31, 7, 0x02, 1, 5, 0x04, 0, 0x04, 1, 2]
+        );
+        assert_eq!(
+            oid("1.3.6.1.5.5.7.48.1").unwrap(),
+            [0x06, 8, 0x2b, 6, 1, 5, 5, 7, 0x30, 1]
+        );
+        assert_eq!(oid("OCSP").unwrap(), oid("1.3.6.1.5.5.7.48.1").unwrap());
+    }
+
+    #[test]
+    fn decoding() {
+        let mut reader = Reader::new(&[0x30, 3, 0x02, 1, 5, 0x04, 0x81, 0x80]);
+        let mut sequence = reader.read_sequence().unwrap();
+        assert_eq!(sequence.read_optional(BOOLEAN).unwrap(), None);
+        assert_eq!(parse_integer(sequence.read(INTEGER).unwrap()).unwrap(), 5);
+        sequence.finish().unwrap();
+        assert_eq!(
+            Reader::new(&[0x05, 0, 0x01]).read_element().unwrap(),
+            [0x05, 0]
+        );
+        // Truncated contents
+        assert!(reader.read(0x04).is_err());
+
+        // Non-minimal lengths
+        assert!(Reader::new(&[0x04, 0x81, 1, 0]).read_any().is_err());
+        assert!(Reader::new(&[0x04, 0x82, 0, 0x80]).read_any().is_err());
+        assert!(Reader::new(&[0x04, 0x80]).read_any().is_err());
+
+        assert_eq!(parse_integer(&[0, 0x80]).unwrap(), 128);
+        assert!(parse_integer(&[0, 1]).is_err());
+        assert!(parse_integer(&[0xff]).is_err());
+        assert!(parse_integer(&[]).is_err());
+        assert!(parse_integer(&[1; 9]).is_err());
+
+        for dotted in ["1.3.6.1.5.5.7.48.1", "2.23.140.1.2.1", "2.999.3", "0.9"] {
+            let der = oid(dotted).unwrap();
+            assert_eq!(parse_oid(&der[2..]).unwrap(), dotted);
+        }
+        assert!(parse_oid(&[]).is_err());
+        assert!(parse_oid(&[0x2b, 0x86]).is_err());
+        assert!(parse_oid(&[0x2b, 0x80, 1]).is_err());
+    }
+}
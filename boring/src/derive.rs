@@ -7,6 +7,7 @@ use std::ptr;
 
 use crate::error::ErrorStack;
 use crate::pkey::{HasPrivate, HasPublic, PKeyRef};
+use crate::secret::SecretBytes;
 use crate::{cvt, cvt_p};
 
 /// A type used to derive a shared secret between two keys.
@@ -79,6 +80,16 @@ impl<'a> Deriver<'a> {
         buf.truncate(len);
         Ok(buf)
     }
+
+    /// Like [`Deriver::derive_to_vec`], but returns the shared secret in a buffer that is wiped
+    /// when dropped.
+    pub fn derive_secret(&mut self) -> Result<SecretBytes, ErrorStack> {
+        let len = self.len()?;
+        let mut buf = SecretBytes::zeroed(len);
+        let len = self.derive(&mut buf)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
 }
 
 #[cfg(test)]
@@ -109,5 +120,6 @@ mod test {
         deriver.set_peer(&pkey2).unwrap();
         let shared = deriver.derive_to_vec().unwrap();
         assert!(!shared.is_empty());
+        assert!(deriver.derive_secret().unwrap().ct_eq(&shared));
     }
 }
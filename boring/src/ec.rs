@@ -489,6 +489,22 @@ where
         ffi::i2d_ECPrivateKey
     }
 
+    private_key_to_pem_secret! {
+        /// Like [`private_key_to_pem`](Self::private_key_to_pem), but returns the key in a buffer
+        /// that is wiped when dropped.
+        #[corresponds(PEM_write_bio_ECPrivateKey)]
+        private_key_to_pem_secret,
+        ffi::PEM_write_bio_ECPrivateKey
+    }
+
+    to_der_secret! {
+        /// Like [`private_key_to_der`](Self::private_key_to_der), but returns the key in a buffer
+        /// that is wiped when dropped.
+        #[corresponds(i2d_ECPrivateKey)]
+        private_key_to_der_secret,
+        ffi::i2d_ECPrivateKey
+    }
+
     /// Return [`EcPoint`] associated with the private key
     #[corresponds(EC_KEY_get0_private_key)]
     #[must_use]
@@ -782,6 +798,20 @@ mod test {
         EcKey::generate(&group).unwrap();
     }
 
+    #[test]
+    fn private_key_secret() {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = EcKey::generate(&group).unwrap();
+        assert!(key
+            .private_key_to_der_secret()
+            .unwrap()
+            .ct_eq(&key.private_key_to_der().unwrap()));
+        assert!(key
+            .private_key_to_pem_secret()
+            .unwrap()
+            .ct_eq(&key.private_key_to_pem().unwrap()));
+    }
+
     #[test]
     fn cofactor() {
         let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
@@ -7,10 +7,13 @@ use openssl_macros::corresponds;
 use std::mem;
 use std::ptr;
 
-use crate::bn::{BigNum, BigNumRef};
-use crate::ec::EcKeyRef;
+use crate::bn::{BigNum, BigNumContext, BigNumContextRef, BigNumRef, MontContext};
+use crate::ec::{EcGroupRef, EcKeyRef, EcPoint};
 use crate::error::ErrorStack;
+use crate::hash::MessageDigest;
+use crate::hmac::Hmac;
 use crate::pkey::{HasPrivate, HasPublic};
+use crate::secret::SecretBytes;
 use crate::{cvt_n, cvt_p};
 
 foreign_type_and_impl_send_sync! {
@@ -43,6 +46,115 @@ impl EcdsaSig {
         }
     }
 
+    /// Computes a digital signature of the hash value `data` with a deterministic nonce, as
+    /// specified in [RFC 6979].
+    ///
+    /// `md` must be the digest `data` was computed with. The same key and hash value always give
+    /// the same signature, which makes it possible to reproduce published test vectors.
+    ///
+    /// This is intended for reproducing test vectors only. The nonce is derived and checked with
+    /// variable-time arithmetic on minimal-width numbers, which can leak information about the
+    /// nonce and the private key through timing. Use [`EcdsaSig::sign`] to sign with a secret key.
+    ///
+    /// [RFC 6979]: https://www.rfc-editor.org/rfc/rfc6979.html
+    pub fn sign_deterministic<T>(
+        data: &[u8],
+        eckey: &EcKeyRef<T>,
+        md: MessageDigest,
+    ) -> Result<EcdsaSig, ErrorStack>
+    where
+        T: HasPrivate,
+    {
+        let group = eckey.group();
+        let mut ctx = BigNumContext::new()?;
+        let mut order = BigNum::new()?;
+        group.order(&mut order, &mut ctx)?;
+        let order_bits = group.order_bits();
+        let len = scalar_len(group);
+
+        // The HMAC_DRBG of section 3.2 is seeded with the private key and the reduced hash
+        let e = bits_to_int(data, order_bits)?;
+        let mut reduced = BigNum::new()?;
+        reduced.nnmod(&e, &order, &mut ctx)?;
+        let mut seed = eckey.private_key().to_vec_padded(len)?;
+        seed.extend_from_slice(&reduced.to_vec_padded(len)?);
+        let seed = SecretBytes::from(seed);
+
+        let hmac = |key: &[u8], parts: &[&[u8]]| -> Result<Vec<u8>, ErrorStack> {
+            let mut hmac = Hmac::init(key, &md)?;
+            for part in parts {
+                hmac.update(part)?;
+            }
+            hmac.finalize()
+        };
+        let mut v = vec![1; md.size()];
+        let mut k = hmac(&vec![0; md.size()], &[&v, &[0], &seed])?;
+        v = hmac(&k, &[&v])?;
+        k = hmac(&k, &[&v, &[1], &seed])?;
+        v = hmac(&k, &[&v])?;
+        loop {
+            let mut t = Vec::with_capacity(len);
+            while t.len() < len {
+                v = hmac(&k, &[&v])?;
+                t.extend_from_slice(&v);
+            }
+            let nonce = bits_to_int(&t, order_bits)?;
+            if nonce.num_bits() != 0 && nonce < order {
+                let private_key = eckey.private_key();
+                if let Some(sig) =
+                    sign_with_nonce(group, &order, &reduced, private_key, &nonce, &mut ctx)?
+                {
+                    return Ok(sig);
+                }
+            }
+            k = hmac(&k, &[&v, &[0]])?;
+            v = hmac(&k, &[&v])?;
+        }
+    }
+
+    /// Computes a digital signature of the hash value `data`, returning it in the fixed-width
+    /// IEEE P1363 encoding.
+    ///
+    /// See [`EcdsaSigRef::to_p1363`].
+    pub fn sign_p1363<T>(data: &[u8], eckey: &EcKeyRef<T>) -> Result<Vec<u8>, ErrorStack>
+    where
+        T: HasPrivate,
+    {
+        EcdsaSig::sign(data, eckey)?.to_p1363(eckey.group())
+    }
+
+    /// Verifies a signature of the hash value `data` in the fixed-width IEEE P1363 encoding.
+    ///
+    /// Signatures of the wrong length are reported as invalid.
+    pub fn verify_p1363<T>(
+        data: &[u8],
+        signature: &[u8],
+        eckey: &EcKeyRef<T>,
+    ) -> Result<bool, ErrorStack>
+    where
+        T: HasPublic,
+    {
+        if signature.len() != 2 * scalar_len(eckey.group()) {
+            return Ok(false);
+        }
+        EcdsaSig::from_p1363(signature, eckey.group())?.verify(data, eckey)
+    }
+
+    /// Decodes a signature in the fixed-width IEEE P1363 encoding for curve `group`.
+    ///
+    /// See [`EcdsaSigRef::to_p1363`]. Fails if the signature is not twice as long as the
+    /// order of the group.
+    pub fn from_p1363(signature: &[u8], group: &EcGroupRef) -> Result<EcdsaSig, ErrorStack> {
+        let len = scalar_len(group);
+        if signature.len() != 2 * len {
+            return Err(ErrorStack::internal_error_str(
+                "invalid P1363 ECDSA signature length",
+            ));
+        }
+        let (r, s) = signature.split_at(len);
+        EcdsaSig::from_private_components(BigNum::from_slice(r)?, BigNum::from_slice(s)?)
+    }
+
     /// Returns a new `EcdsaSig` by setting the `r` and `s` values associated with a
     /// ECDSA signature.
     #[corresponds(ECDSA_SIG_set0)]
@@ -73,6 +185,17 @@ impl EcdsaSigRef {
         ffi::i2d_ECDSA_SIG
     }
 
+    /// Serializes the signature in the fixed-width IEEE P1363 encoding for curve `group`.
+    ///
+    /// This is the concatenation of `r` and `s`, each padded to the byte length of the order of
+    /// the group, as used by JOSE, COSE and WebAuthn.
+    pub fn to_p1363(&self, group: &EcGroupRef) -> Result<Vec<u8>, ErrorStack> {
+        let len = scalar_len(group);
+        let mut signature = self.r().to_vec_padded(len)?;
+        signature.extend_from_slice(&self.s().to_vec_padded(len)?);
+        Ok(signature)
+    }
+
     /// Verifies if the signature is a valid ECDSA signature using the given public key.
     #[corresponds(ECDSA_do_verify)]
     pub fn verify<T>(&self, data: &[u8], eckey: &EcKeyRef<T>) -> Result<bool, ErrorStack>
@@ -114,4 +237,120 @@ impl EcdsaSigRef {
     }
 }
 
+/// Returns the byte length of scalars modulo the order of `group`.
+fn scalar_len(group: &EcGroupRef) -> usize {
+    group.order_bits().div_ceil(8) as usize
+}
+
+/// Converts a bit string to an integer of at most `bits` bits by keeping its leftmost bits, as
+/// `bits2int` of RFC 6979 does.
+fn bits_to_int(bytes: &[u8], bits: u32) -> Result<BigNum, ErrorStack> {
+    let n = BigNum::from_slice(bytes)?;
+    let len = bytes.len() * 8;
+    if len <= bits as usize {
+        return Ok(n);
+    }
+    let mut truncated = BigNum::new()?;
+    truncated.rshift(&n, (len - bits as usize) as i32)?;
+    Ok(truncated)
+}
+
+/// Computes the signature `(r, s)` of `e`, reduced modulo `order`, with the nonce `k`, or `None`
+/// if either is zero.
+fn sign_with_nonce(
+    group: &EcGroupRef,
+    order: &BigNumRef,
+    e: &BigNumRef,
+    private_key: &BigNumRef,
+    k: &BigNumRef,
+    ctx: &mut BigNumContextRef,
+) -> Result<Option<EcdsaSig>, ErrorStack> {
+    let mut point = EcPoint::new(group)?;
+    point.mul_generator(group, k, ctx)?;
+    let mut x = BigNum::new()?;
+    let mut y = BigNum::new()?;
+    point.affine_coordinates_gfp(group, &mut x, &mut y, ctx)?;
+    let mut r = BigNum::new()?;
+    r.nnmod(&x, order, ctx)?;
+
+    // s = k^-1 * (e + r * d) mod n
+    let mont = MontContext::new(order, ctx)?;
+    let rd = mont.mul(&r, private_key, ctx)?;
+    let sum = mont.add(e, &rd)?;
+    let k_inv = mont.inverse(k, ctx)?;
+    let s = mont.mul(&k_inv, &sum, ctx)?;
+
+    if r.num_bits() == 0 || s.num_bits() == 0 {
+        return Ok(None);
+    }
+    EcdsaSig::from_private_components(r, s).map(Some)
+}
+
 use crate::ffi::{ECDSA_SIG_get0, ECDSA_SIG_set0};
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ec::EcKey;
+    use crate::hash::hash;
+    use crate::nid::Nid;
+    use crate::pkey::Private;
+
+    fn key(nid: Nid, private_key: &str) -> EcKey<Private> {
+        let group = crate::ec::EcGroup::from_curve_name(nid).unwrap();
+        let private_key = BigNum::from_hex_str(private_key).unwrap();
+        let mut public_key = EcPoint::new(&group).unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+        public_key
+            .mul_generator(&group, &private_key, &mut ctx)
+            .unwrap();
+        EcKey::from_private_components(&group, &private_key, &public_key).unwrap()
+    }
+
+    #[test]
+    fn p1363() {
+        let key = key(
+            Nid::SECP521R1,
+            "00FAD06DAA62BA3B25D2FB40133DA757205DE67F5BB0018FEE8C86E1B68C7E75CAA896EB32F1F47C70855836A6D16FCC1466F6D8FBEC67DB89EC0C08B0E996B83538",
+        );
+        let digest = hash(MessageDigest::sha512(), b"sample").unwrap();
+        let signature = EcdsaSig::sign_p1363(&digest, &key).unwrap();
+        assert_eq!(signature.len(), 132);
+        assert!(EcdsaSig::verify_p1363(&digest, &signature, &key).unwrap());
+        assert!(!EcdsaSig::verify_p1363(&digest, &signature[1..], &key).unwrap());
+
+        let decoded = EcdsaSig::from_p1363(&signature, key.group()).unwrap();
+        let der = EcdsaSig::from_der(&decoded.to_der().unwrap()).unwrap();
+        assert_eq!(der.to_p1363(key.group()).unwrap(), signature);
+        assert!(EcdsaSig::from_p1363(&signature[..131], key.group()).is_err());
+    }
+
+    #[test]
+    fn rfc6979() {
+        // RFC 6979, appendix A.2.5 and A.2.7, with the message "sample"
+        let vectors = [
+            (
+                Nid::X9_62_PRIME256V1,
+                MessageDigest::sha256(),
+                "C9AFA9D845BA75166B5C215767B1D6934E50C3DB36E89B127B8A622B120F6721",
+                "EFD48B2AACB6A8FD1140DD9CD45E81D69D2C877B56AAF991C34D0EA84EAF3716",
+                "F7CB1C942D657C41D436C7A1B6E29F65F3E900DBB9AFF4064DC4AB2F843ACDA8",
+            ),
+            (
+                Nid::SECP521R1,
+                MessageDigest::sha512(),
+                "0FAD06DAA62BA3B25D2FB40133DA757205DE67F5BB0018FEE8C86E1B68C7E75CAA896EB32F1F47C70855836A6D16FCC1466F6D8FBEC67DB89EC0C08B0E996B83538",
+                "00C328FAFCBD79DD77850370C46325D987CB525569FB63C5D3BC53950E6D4C5F174E25A1EE9017B5D450606ADD152B534931D7D4E8455CC91F9B15BF05EC36E377FA",
+                "00617CCE7CF5064806C467F678D3B4080D6F1CC50AF26CA209417308281B68AF282623EAA63E5B5C0723D8B8C37FF0777B1A20F8CCB1DCCC43997F1EE0E44DA4A67A",
+            ),
+        ];
+        for (nid, md, private_key, r, s) in vectors {
+            let key = key(nid, private_key);
+            let digest = hash(md, b"sample").unwrap();
+            let signature = EcdsaSig::sign_deterministic(&digest, &key, md).unwrap();
+            assert_eq!(signature.r(), &BigNum::from_hex_str(r).unwrap());
+            assert_eq!(signature.s(), &BigNum::from_hex_str(s).unwrap());
+            assert!(signature.verify(&digest, &key).unwrap());
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use openssl_macros::corresponds;
+use std::fmt;
 
 use crate::cvt;
 use crate::error::ErrorStack;
@@ -33,8 +34,15 @@ impl HmacCtxRef {
 }
 
 /// Provides an init-update-finalize API for HMAC.
+///
+/// This also implements the [`Mac`](crate::mac::Mac) trait, which adds constant-time tag
+/// verification. Cloning an HMAC in progress copies its state.
 pub struct Hmac(*mut ffi::HMAC_CTX);
 
+// SAFETY: the context is only accessed through `&mut self`
+unsafe impl Send for Hmac {}
+unsafe impl Sync for Hmac {}
+
 impl Hmac {
     /// Creates a new HMAC object with the given key and hash algorithm.
     pub fn init(key: &[u8], md: &MessageDigest) -> Result<Hmac, ErrorStack> {
@@ -75,6 +83,26 @@ impl Hmac {
         }
         Ok(out)
     }
+
+    /// The digest this HMAC is computed with.
+    #[corresponds(HMAC_CTX_get_md)]
+    #[must_use]
+    pub fn digest(&self) -> MessageDigest {
+        unsafe { MessageDigest::from_ptr(ffi::HMAC_CTX_get_md(self.0)) }
+    }
+}
+
+impl Clone for Hmac {
+    #[corresponds(HMAC_CTX_copy_ex)]
+    fn clone(&self) -> Hmac {
+        unsafe {
+            let ctx = ffi::HMAC_CTX_new();
+            assert!(!ctx.is_null());
+            let clone = Hmac(ctx);
+            assert_eq!(ffi::HMAC_CTX_copy_ex(clone.0, self.0), 1);
+            clone
+        }
+    }
 }
 
 impl Drop for Hmac {
@@ -83,6 +111,14 @@ impl Drop for Hmac {
     }
 }
 
+impl fmt::Debug for Hmac {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hmac")
+            .field("digest", &self.digest().type_())
+            .finish_non_exhaustive()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::hash;
@@ -1,31 +1,1014 @@
+//! Hybrid Public Key Encryption (HPKE, [RFC 9180]).
+//!
+//! HPKE encrypts messages to a recipient's public key. A sender context is set up against the
+//! recipient's public key, producing an encapsulated key (`enc`) that must be sent along with the
+//! ciphertexts. The recipient uses `enc` and their private key to set up the matching context.
+//!
+//! ```
+//! use rama_boring::hpke::{HpkeKey, HpkeRecipientContext, HpkeSenderContext, HpkeSuite, Kem};
+//!
+//! let suite = HpkeSuite::default();
+//! let key = HpkeKey::generate(Kem::x25519_hkdf_sha256()).unwrap();
+//! let public_key = key.public_key().unwrap();
+//!
+//! let (mut sender, enc) = HpkeSenderContext::new(suite, &public_key, b"info").unwrap();
+//! let ciphertext = sender.seal(b"hello", b"aad").unwrap();
+//!
+//! let mut recipient = HpkeRecipientContext::new(suite, &key, &enc, b"info").unwrap();
+//! assert_eq!(recipient.open(&ciphertext, b"aad").unwrap(), b"hello");
+//! ```
+//!
+//! # Modes
+//!
+//! The base mode and the authenticated mode (where the sender proves possession of a private key)
+//! are supported. The authenticated mode needs a Diffie-Hellman based KEM, so it is available with
+//! X25519 and P-256, but not with X-Wing. The PSK and auth-PSK modes are not implemented by
+//! BoringSSL.
+//!
+//! Post-quantum security is available through [`Kem::xwing`], which combines ML-KEM-768 with
+//! X25519.
+//!
+//! [RFC 9180]: https://www.rfc-editor.org/rfc/rfc9180.html
+
+use std::fmt;
+
 use crate::error::ErrorStack;
+use crate::secret::SecretBytes;
 use crate::{cvt_0i, cvt_p, ffi};
 
-use foreign_types::ForeignType;
+use foreign_types::{ForeignType, ForeignTypeRef};
+use openssl_macros::corresponds;
+
+/// An HPKE key encapsulation mechanism.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Kem(*const ffi::EVP_HPKE_KEM);
+
+impl Kem {
+    /// DHKEM(X25519, HKDF-SHA256).
+    #[corresponds(EVP_hpke_x25519_hkdf_sha256)]
+    #[must_use]
+    pub fn x25519_hkdf_sha256() -> Kem {
+        unsafe { Kem(ffi::EVP_hpke_x25519_hkdf_sha256()) }
+    }
+
+    /// DHKEM(P-256, HKDF-SHA256).
+    #[corresponds(EVP_hpke_p256_hkdf_sha256)]
+    #[must_use]
+    pub fn p256_hkdf_sha256() -> Kem {
+        unsafe { Kem(ffi::EVP_hpke_p256_hkdf_sha256()) }
+    }
+
+    /// X-Wing, the hybrid ML-KEM-768 and X25519 KEM.
+    ///
+    /// This KEM does not support the authenticated mode.
+    #[corresponds(EVP_hpke_xwing)]
+    #[must_use]
+    pub fn xwing() -> Kem {
+        unsafe { Kem(ffi::EVP_hpke_xwing()) }
+    }
+
+    /// Returns the KEM identifier as registered with IANA.
+    #[corresponds(EVP_HPKE_KEM_id)]
+    #[must_use]
+    pub fn id(&self) -> u16 {
+        unsafe { ffi::EVP_HPKE_KEM_id(self.0) }
+    }
+
+    /// Returns the length of public keys of this KEM.
+    #[corresponds(EVP_HPKE_KEM_public_key_len)]
+    #[must_use]
+    pub fn public_key_len(&self) -> usize {
+        unsafe { ffi::EVP_HPKE_KEM_public_key_len(self.0) }
+    }
+
+    /// Returns the length of private keys of this KEM.
+    #[corresponds(EVP_HPKE_KEM_private_key_len)]
+    #[must_use]
+    pub fn private_key_len(&self) -> usize {
+        unsafe { ffi::EVP_HPKE_KEM_private_key_len(self.0) }
+    }
+
+    /// Returns the length of the encapsulated key of this KEM.
+    #[corresponds(EVP_HPKE_KEM_enc_len)]
+    #[must_use]
+    pub fn enc_len(&self) -> usize {
+        unsafe { ffi::EVP_HPKE_KEM_enc_len(self.0) }
+    }
+
+    #[must_use]
+    pub fn as_ptr(&self) -> *const ffi::EVP_HPKE_KEM {
+        self.0
+    }
+}
+
+impl fmt::Debug for Kem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Kem")
+            .field(&format_args!("{:#06x}", self.id()))
+            .finish()
+    }
+}
+
+unsafe impl Sync for Kem {}
+unsafe impl Send for Kem {}
+
+/// An HPKE key derivation function.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Kdf(*const ffi::EVP_HPKE_KDF);
+
+impl Kdf {
+    /// HKDF-SHA256.
+    #[corresponds(EVP_hpke_hkdf_sha256)]
+    #[must_use]
+    pub fn hkdf_sha256() -> Kdf {
+        unsafe { Kdf(ffi::EVP_hpke_hkdf_sha256()) }
+    }
+
+    /// Returns the KDF identifier as registered with IANA.
+    #[corresponds(EVP_HPKE_KDF_id)]
+    #[must_use]
+    pub fn id(&self) -> u16 {
+        unsafe { ffi::EVP_HPKE_KDF_id(self.0) }
+    }
+
+    #[must_use]
+    pub fn as_ptr(&self) -> *const ffi::EVP_HPKE_KDF {
+        self.0
+    }
+}
+
+impl fmt::Debug for Kdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Kdf")
+            .field(&format_args!("{:#06x}", self.id()))
+            .finish()
+    }
+}
+
+unsafe impl Sync for Kdf {}
+unsafe impl Send for Kdf {}
+
+/// An HPKE AEAD.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Aead(*const ffi::EVP_HPKE_AEAD);
+
+impl Aead {
+    /// AES-128-GCM.
+    #[corresponds(EVP_hpke_aes_128_gcm)]
+    #[must_use]
+    pub fn aes_128_gcm() -> Aead {
+        unsafe { Aead(ffi::EVP_hpke_aes_128_gcm()) }
+    }
+
+    /// AES-256-GCM.
+    #[corresponds(EVP_hpke_aes_256_gcm)]
+    #[must_use]
+    pub fn aes_256_gcm() -> Aead {
+        unsafe { Aead(ffi::EVP_hpke_aes_256_gcm()) }
+    }
+
+    /// ChaCha20-Poly1305.
+    #[corresponds(EVP_hpke_chacha20_poly1305)]
+    #[must_use]
+    pub fn chacha20_poly1305() -> Aead {
+        unsafe { Aead(ffi::EVP_hpke_chacha20_poly1305()) }
+    }
+
+    /// Returns the AEAD identifier as registered with IANA.
+    #[corresponds(EVP_HPKE_AEAD_id)]
+    #[must_use]
+    pub fn id(&self) -> u16 {
+        unsafe { ffi::EVP_HPKE_AEAD_id(self.0) }
+    }
+
+    #[must_use]
+    pub fn as_ptr(&self) -> *const ffi::EVP_HPKE_AEAD {
+        self.0
+    }
+}
+
+impl fmt::Debug for Aead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Aead")
+            .field(&format_args!("{:#06x}", self.id()))
+            .finish()
+    }
+}
+
+unsafe impl Sync for Aead {}
+unsafe impl Send for Aead {}
+
+/// A complete HPKE cipher suite.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HpkeSuite {
+    pub kem: Kem,
+    pub kdf: Kdf,
+    pub aead: Aead,
+}
+
+impl HpkeSuite {
+    #[must_use]
+    pub fn new(kem: Kem, kdf: Kdf, aead: Aead) -> Self {
+        Self { kem, kdf, aead }
+    }
+}
+
+impl Default for HpkeSuite {
+    /// DHKEM(X25519, HKDF-SHA256), HKDF-SHA256 and AES-128-GCM.
+    fn default() -> Self {
+        Self::new(
+            Kem::x25519_hkdf_sha256(),
+            Kdf::hkdf_sha256(),
+            Aead::aes_128_gcm(),
+        )
+    }
+}
 
 foreign_type_and_impl_send_sync! {
     type CType = ffi::EVP_HPKE_KEY;
     fn drop = ffi::EVP_HPKE_KEY_free;
+    fn clone = clone_hpke_key;
 
+    /// An HPKE private key, along with its public key.
     pub struct HpkeKey;
 }
 
+unsafe fn clone_hpke_key(key: *mut ffi::EVP_HPKE_KEY) -> *mut ffi::EVP_HPKE_KEY {
+    let copy = ffi::EVP_HPKE_KEY_new();
+    assert!(!copy.is_null(), "EVP_HPKE_KEY_new failed");
+    assert_eq!(
+        ffi::EVP_HPKE_KEY_copy(copy, key),
+        1,
+        "EVP_HPKE_KEY_copy failed"
+    );
+    copy
+}
+
 impl HpkeKey {
     /// Allocates and initializes a key with the `EVP_HPKE_KEY` type using the
     /// `EVP_hpke_x25519_hkdf_sha256` KEM algorithm.
+    #[deprecated(
+        note = "this uses X25519 rather than P-256, use `HpkeKey::new(Kem::x25519_hkdf_sha256(), ..)`"
+    )]
     pub fn dhkem_p256_sha256(pkey: &[u8]) -> Result<HpkeKey, ErrorStack> {
+        Self::new(Kem::x25519_hkdf_sha256(), pkey)
+    }
+
+    /// Creates a key for `kem` from its serialized private key.
+    #[corresponds(EVP_HPKE_KEY_init)]
+    pub fn new(kem: Kem, private_key: &[u8]) -> Result<HpkeKey, ErrorStack> {
         unsafe {
             ffi::init();
             let hpke = cvt_p(ffi::EVP_HPKE_KEY_new()).map(|p| HpkeKey::from_ptr(p))?;
 
             cvt_0i(ffi::EVP_HPKE_KEY_init(
                 hpke.as_ptr(),
-                ffi::EVP_hpke_x25519_hkdf_sha256(),
-                pkey.as_ptr(),
-                pkey.len(),
+                kem.as_ptr(),
+                private_key.as_ptr(),
+                private_key.len(),
             ))?;
 
             Ok(hpke)
         }
     }
+
+    /// Generates a new random key for `kem`.
+    #[corresponds(EVP_HPKE_KEY_generate)]
+    pub fn generate(kem: Kem) -> Result<HpkeKey, ErrorStack> {
+        unsafe {
+            ffi::init();
+            let hpke = cvt_p(ffi::EVP_HPKE_KEY_new()).map(|p| HpkeKey::from_ptr(p))?;
+            cvt_0i(ffi::EVP_HPKE_KEY_generate(hpke.as_ptr(), kem.as_ptr()))?;
+            Ok(hpke)
+        }
+    }
+}
+
+impl HpkeKeyRef {
+    /// Returns the KEM this key is used with.
+    #[corresponds(EVP_HPKE_KEY_kem)]
+    #[must_use]
+    pub fn kem(&self) -> Kem {
+        unsafe { Kem(ffi::EVP_HPKE_KEY_kem(self.as_ptr())) }
+    }
+
+    /// Returns the serialized public key.
+    #[corresponds(EVP_HPKE_KEY_public_key)]
+    pub fn public_key(&self) -> Result<Vec<u8>, ErrorStack> {
+        let mut out = vec![0; ffi::EVP_HPKE_MAX_PUBLIC_KEY_LENGTH as usize];
+        let mut out_len = 0;
+        unsafe {
+            cvt_0i(ffi::EVP_HPKE_KEY_public_key(
+                self.as_ptr(),
+                out.as_mut_ptr(),
+                &mut out_len,
+                out.len(),
+            ))?;
+        }
+        out.truncate(out_len);
+        Ok(out)
+    }
+
+    /// Returns the serialized private key, in a buffer that is wiped when dropped.
+    #[corresponds(EVP_HPKE_KEY_private_key)]
+    pub fn private_key(&self) -> Result<SecretBytes, ErrorStack> {
+        let mut out = SecretBytes::zeroed(ffi::EVP_HPKE_MAX_PRIVATE_KEY_LENGTH as usize);
+        let mut out_len = 0;
+        unsafe {
+            cvt_0i(ffi::EVP_HPKE_KEY_private_key(
+                self.as_ptr(),
+                out.as_mut_ptr(),
+                &mut out_len,
+                out.len(),
+            ))?;
+        }
+        out.truncate(out_len);
+        Ok(out)
+    }
+}
+
+impl fmt::Debug for HpkeKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HpkeKey")
+            .field("kem", &self.kem())
+            .field("key", &"[redacted]")
+            .finish()
+    }
+}
+
+fn new_ctx() -> Result<*mut ffi::EVP_HPKE_CTX, ErrorStack> {
+    unsafe {
+        ffi::init();
+        cvt_p(ffi::EVP_HPKE_CTX_new())
+    }
+}
+
+unsafe fn ctx_export(
+    ctx: *mut ffi::EVP_HPKE_CTX,
+    out: &mut [u8],
+    exporter_context: &[u8],
+) -> Result<(), ErrorStack> {
+    cvt_0i(ffi::EVP_HPKE_CTX_export(
+        ctx,
+        out.as_mut_ptr(),
+        out.len(),
+        exporter_context.as_ptr(),
+        exporter_context.len(),
+    ))
+    .map(|_| ())
+}
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::EVP_HPKE_CTX;
+    fn drop = ffi::EVP_HPKE_CTX_free;
+
+    /// The sending side of an HPKE context.
+    ///
+    /// Each call to [`seal`](HpkeSenderContextRef::seal) advances the message sequence number, so
+    /// ciphertexts must be opened in the order they were sealed.
+    pub struct HpkeSenderContext;
+}
+
+impl HpkeSenderContext {
+    /// Sets up a base mode context encrypting to `peer_public_key`.
+    ///
+    /// Returns the context and the encapsulated key, which must be sent to the recipient.
+    #[corresponds(EVP_HPKE_CTX_setup_sender)]
+    pub fn new(
+        suite: HpkeSuite,
+        peer_public_key: &[u8],
+        info: &[u8],
+    ) -> Result<(Self, Vec<u8>), ErrorStack> {
+        let ctx = unsafe { HpkeSenderContext::from_ptr(new_ctx()?) };
+        let mut enc = vec![0; ffi::EVP_HPKE_MAX_ENC_LENGTH as usize];
+        let mut enc_len = 0;
+        unsafe {
+            cvt_0i(ffi::EVP_HPKE_CTX_setup_sender(
+                ctx.as_ptr(),
+                enc.as_mut_ptr(),
+                &mut enc_len,
+                enc.len(),
+                suite.kem.as_ptr(),
+                suite.kdf.as_ptr(),
+                suite.aead.as_ptr(),
+                peer_public_key.as_ptr(),
+                peer_public_key.len(),
+                info.as_ptr(),
+                info.len(),
+            ))?;
+        }
+        enc.truncate(enc_len);
+        Ok((ctx, enc))
+    }
+
+    /// Sets up an authenticated mode context encrypting to `peer_public_key`.
+    ///
+    /// The recipient can check the message came from the holder of `sender_key`. The KEM of the
+    /// suite must match the KEM of `sender_key`.
+    #[corresponds(EVP_HPKE_CTX_setup_auth_sender)]
+    pub fn new_auth(
+        suite: HpkeSuite,
+        sender_key: &HpkeKeyRef,
+        peer_public_key: &[u8],
+        info: &[u8],
+    ) -> Result<(Self, Vec<u8>), ErrorStack> {
+        if sender_key.kem() != suite.kem {
+            return Err(ErrorStack::internal_error_str(
+                "sender key does not match the suite KEM",
+            ));
+        }
+        let ctx = unsafe { HpkeSenderContext::from_ptr(new_ctx()?) };
+        let mut enc = vec![0; ffi::EVP_HPKE_MAX_ENC_LENGTH as usize];
+        let mut enc_len = 0;
+        unsafe {
+            cvt_0i(ffi::EVP_HPKE_CTX_setup_auth_sender(
+                ctx.as_ptr(),
+                enc.as_mut_ptr(),
+                &mut enc_len,
+                enc.len(),
+                sender_key.as_ptr(),
+                suite.kdf.as_ptr(),
+                suite.aead.as_ptr(),
+                peer_public_key.as_ptr(),
+                peer_public_key.len(),
+                info.as_ptr(),
+                info.len(),
+            ))?;
+        }
+        enc.truncate(enc_len);
+        Ok((ctx, enc))
+    }
+
+    /// Like [`Self::new`], but with fixed ephemeral key material. Only useful for test vectors.
+    #[cfg(test)]
+    fn new_with_seed(
+        suite: HpkeSuite,
+        peer_public_key: &[u8],
+        info: &[u8],
+        seed: &[u8],
+    ) -> Result<(Self, Vec<u8>), ErrorStack> {
+        let ctx = unsafe { HpkeSenderContext::from_ptr(new_ctx()?) };
+        let mut enc = vec![0; ffi::EVP_HPKE_MAX_ENC_LENGTH as usize];
+        let mut enc_len = 0;
+        unsafe {
+            cvt_0i(ffi::EVP_HPKE_CTX_setup_sender_with_seed_for_testing(
+                ctx.as_ptr(),
+                enc.as_mut_ptr(),
+                &mut enc_len,
+                enc.len(),
+                suite.kem.as_ptr(),
+                suite.kdf.as_ptr(),
+                suite.aead.as_ptr(),
+                peer_public_key.as_ptr(),
+                peer_public_key.len(),
+                info.as_ptr(),
+                info.len(),
+                seed.as_ptr(),
+                seed.len(),
+            ))?;
+        }
+        enc.truncate(enc_len);
+        Ok((ctx, enc))
+    }
+
+    /// Like [`Self::new_auth`], but with fixed ephemeral key material. Only useful for test
+    /// vectors.
+    #[cfg(test)]
+    fn new_auth_with_seed(
+        suite: HpkeSuite,
+        sender_key: &HpkeKeyRef,
+        peer_public_key: &[u8],
+        info: &[u8],
+        seed: &[u8],
+    ) -> Result<(Self, Vec<u8>), ErrorStack> {
+        let ctx = unsafe { HpkeSenderContext::from_ptr(new_ctx()?) };
+        let mut enc = vec![0; ffi::EVP_HPKE_MAX_ENC_LENGTH as usize];
+        let mut enc_len = 0;
+        unsafe {
+            cvt_0i(ffi::EVP_HPKE_CTX_setup_auth_sender_with_seed_for_testing(
+                ctx.as_ptr(),
+                enc.as_mut_ptr(),
+                &mut enc_len,
+                enc.len(),
+                sender_key.as_ptr(),
+                suite.kdf.as_ptr(),
+                suite.aead.as_ptr(),
+                peer_public_key.as_ptr(),
+                peer_public_key.len(),
+                info.as_ptr(),
+                info.len(),
+                seed.as_ptr(),
+                seed.len(),
+            ))?;
+        }
+        enc.truncate(enc_len);
+        Ok((ctx, enc))
+    }
+}
+
+impl HpkeSenderContextRef {
+    /// Encrypts and authenticates `plaintext`, also authenticating `aad`.
+    #[corresponds(EVP_HPKE_CTX_seal)]
+    pub fn seal(&mut self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+        let mut out = vec![0; plaintext.len() + self.max_overhead()];
+        let mut out_len = 0;
+        unsafe {
+            cvt_0i(ffi::EVP_HPKE_CTX_seal(
+                self.as_ptr(),
+                out.as_mut_ptr(),
+                &mut out_len,
+                out.len(),
+                plaintext.as_ptr(),
+                plaintext.len(),
+                aad.as_ptr(),
+                aad.len(),
+            ))?;
+        }
+        out.truncate(out_len);
+        Ok(out)
+    }
+
+    /// Fills `out` with a secret derived from the context and `exporter_context`.
+    #[corresponds(EVP_HPKE_CTX_export)]
+    pub fn export(&self, out: &mut [u8], exporter_context: &[u8]) -> Result<(), ErrorStack> {
+        unsafe { ctx_export(self.as_ptr(), out, exporter_context) }
+    }
+
+    /// Returns the maximum number of bytes [`seal`](Self::seal) adds to the plaintext.
+    #[corresponds(EVP_HPKE_CTX_max_overhead)]
+    #[must_use]
+    pub fn max_overhead(&self) -> usize {
+        unsafe { ffi::EVP_HPKE_CTX_max_overhead(self.as_ptr()) }
+    }
+}
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::EVP_HPKE_CTX;
+    fn drop = ffi::EVP_HPKE_CTX_free;
+
+    /// The receiving side of an HPKE context.
+    pub struct HpkeRecipientContext;
+}
+
+impl HpkeRecipientContext {
+    /// Sets up a base mode context for the encapsulated key `enc` sent by the sender.
+    #[corresponds(EVP_HPKE_CTX_setup_recipient)]
+    pub fn new(
+        suite: HpkeSuite,
+        key: &HpkeKeyRef,
+        enc: &[u8],
+        info: &[u8],
+    ) -> Result<Self, ErrorStack> {
+        if key.kem() != suite.kem {
+            return Err(ErrorStack::internal_error_str(
+                "recipient key does not match the suite KEM",
+            ));
+        }
+        let ctx = unsafe { HpkeRecipientContext::from_ptr(new_ctx()?) };
+        unsafe {
+            cvt_0i(ffi::EVP_HPKE_CTX_setup_recipient(
+                ctx.as_ptr(),
+                key.as_ptr(),
+                suite.kdf.as_ptr(),
+                suite.aead.as_ptr(),
+                enc.as_ptr(),
+                enc.len(),
+                info.as_ptr(),
+                info.len(),
+            ))?;
+        }
+        Ok(ctx)
+    }
+
+    /// Sets up an authenticated mode context, checking the sender holds the private key for
+    /// `sender_public_key`.
+    #[corresponds(EVP_HPKE_CTX_setup_auth_recipient)]
+    pub fn new_auth(
+        suite: HpkeSuite,
+        key: &HpkeKeyRef,
+        enc: &[u8],
+        info: &[u8],
+        sender_public_key: &[u8],
+    ) -> Result<Self, ErrorStack> {
+        if key.kem() != suite.kem {
+            return Err(ErrorStack::internal_error_str(
+                "recipient key does not match the suite KEM",
+            ));
+        }
+        let ctx = unsafe { HpkeRecipientContext::from_ptr(new_ctx()?) };
+        unsafe {
+            cvt_0i(ffi::EVP_HPKE_CTX_setup_auth_recipient(
+                ctx.as_ptr(),
+                key.as_ptr(),
+                suite.kdf.as_ptr(),
+                suite.aead.as_ptr(),
+                enc.as_ptr(),
+                enc.len(),
+                info.as_ptr(),
+                info.len(),
+                sender_public_key.as_ptr(),
+                sender_public_key.len(),
+            ))?;
+        }
+        Ok(ctx)
+    }
+}
+
+impl HpkeRecipientContextRef {
+    /// Decrypts and authenticates `ciphertext`, also authenticating `aad`.
+    #[corresponds(EVP_HPKE_CTX_open)]
+    pub fn open(&mut self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+        let mut out = vec![0; ciphertext.len()];
+        let mut out_len = 0;
+        unsafe {
+            cvt_0i(ffi::EVP_HPKE_CTX_open(
+                self.as_ptr(),
+                out.as_mut_ptr(),
+                &mut out_len,
+                out.len(),
+                ciphertext.as_ptr(),
+                ciphertext.len(),
+                aad.as_ptr(),
+                aad.len(),
+            ))?;
+        }
+        out.truncate(out_len);
+        Ok(out)
+    }
+
+    /// Fills `out` with a secret derived from the context and `exporter_context`.
+    #[corresponds(EVP_HPKE_CTX_export)]
+    pub fn export(&self, out: &mut [u8], exporter_context: &[u8]) -> Result<(), ErrorStack> {
+        unsafe { ctx_export(self.as_ptr(), out, exporter_context) }
+    }
+}
+
+/// Encrypts a single message to `peer_public_key` in base mode.
+///
+/// Returns `(enc, ciphertext)`.
+pub fn seal(
+    suite: HpkeSuite,
+    peer_public_key: &[u8],
+    info: &[u8],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), ErrorStack> {
+    let (mut ctx, enc) = HpkeSenderContext::new(suite, peer_public_key, info)?;
+    let ciphertext = ctx.seal(plaintext, aad)?;
+    Ok((enc, ciphertext))
+}
+
+/// Decrypts a single message produced by [`seal`].
+pub fn open(
+    suite: HpkeSuite,
+    key: &HpkeKeyRef,
+    enc: &[u8],
+    info: &[u8],
+    ciphertext: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>, ErrorStack> {
+    HpkeRecipientContext::new(suite, key, enc, info)?.open(ciphertext, aad)
+}
+
+/// Encrypts a single message to `peer_public_key` in authenticated mode.
+///
+/// Returns `(enc, ciphertext)`.
+pub fn seal_auth(
+    suite: HpkeSuite,
+    sender_key: &HpkeKeyRef,
+    peer_public_key: &[u8],
+    info: &[u8],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), ErrorStack> {
+    let (mut ctx, enc) = HpkeSenderContext::new_auth(suite, sender_key, peer_public_key, info)?;
+    let ciphertext = ctx.seal(plaintext, aad)?;
+    Ok((enc, ciphertext))
+}
+
+/// Decrypts a single message produced by [`seal_auth`].
+pub fn open_auth(
+    suite: HpkeSuite,
+    key: &HpkeKeyRef,
+    enc: &[u8],
+    info: &[u8],
+    sender_public_key: &[u8],
+    ciphertext: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>, ErrorStack> {
+    HpkeRecipientContext::new_auth(suite, key, enc, info, sender_public_key)?.open(ciphertext, aad)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hex::FromHex;
+
+    // RFC 9180, appendix A.1.1: DHKEM(X25519, HKDF-SHA256), HKDF-SHA256, AES-128-GCM, base mode
+    const INFO: &str = "4f6465206f6e2061204772656369616e2055726e";
+    const IKM_E: &str = "7268600d403fce431561aef583ee1613527cff655c1343f29812e66706df3234";
+    const PK_EM: &str = "37fda3567bdbd628e88668c3c8d7e97d1d1253b6d4ea6d44c150f741f1bf4431";
+    const PK_RM: &str = "3948cfe0ad1ddb695d780e59077195da6c56506b027329794ab02bca80815c4d";
+    const SK_RM: &str = "4612c550263fc8ad58375df3f557aac531d26850903e55a9f23f21d8534e8ac8";
+    const PT: &str = "4265617574792069732074727574682c20747275746820626561757479";
+    const CT0: &str = "f938558b5d72f1a23810b4be2ab4f84331acc02fc97babc53a52ae8218a355a96d8770ac83d07bea87e13c512a";
+    const CT1: &str = "af2d7e9ac9ae7e270f46ba1f975be53c09f8d875bdc8535458c2494e8a6eab251c03d0c22a56b8ca42c2063b84";
+    const EXPORTS: [(&str, &str); 3] = [
+        (
+            "",
+            "3853fe2b4035195a573ffc53856e77058e15d9ea064de3e59f4961d0095250ee",
+        ),
+        (
+            "00",
+            "2e8f0b54673c7029649d4eb9d5e33bf1872cf76d623ff164ac185da9e88c21a5",
+        ),
+        (
+            "54657374436f6e74657874",
+            "e9e43065102c3836401bed8c3c3c75ae46be1639869391d62c61f1ec7af54931",
+        ),
+    ];
+
+    struct AuthVector {
+        kem: fn() -> Kem,
+        ikm_e: &'static str,
+        pk_em: &'static str,
+        sk_rm: &'static str,
+        sk_sm: &'static str,
+        ct0: &'static str,
+        ct1: &'static str,
+        exports: [(&'static str, &'static str); 3],
+    }
+
+    // RFC 9180, appendix A.1.3: DHKEM(X25519, HKDF-SHA256), HKDF-SHA256, AES-128-GCM, auth mode
+    const X25519_AUTH: AuthVector = AuthVector {
+        kem: Kem::x25519_hkdf_sha256,
+        ikm_e: "6e6d8f200ea2fb20c30b003a8b4f433d2f4ed4c2658d5bc8ce2fef718059c9f7",
+        pk_em: "23fb952571a14a25e3d678140cd0e5eb47a0961bb18afcf85896e5453c312e76",
+        sk_rm: "fdea67cf831f1ca98d8e27b1f6abeb5b7745e9d35348b80fa407ff6958f9137e",
+        sk_sm: "dc4a146313cce60a278a5323d321f051c5707e9c45ba21a3479fecdf76fc69dd",
+        ct0: "5fd92cc9d46dbf8943e72a07e42f363ed5f721212cd90bcfd072bfd9f44e06b80fd17824947496e21b680c141b",
+        ct1: "d3736bb256c19bfa93d79e8f80b7971262cb7c887e35c26370cfed62254369a1b52e3d505b79dd699f002bc8ed",
+        exports: [
+            (
+                "",
+                "28c70088017d70c896a8420f04702c5a321d9cbf0279fba899b59e51bac72c85",
+            ),
+            (
+                "00",
+                "25dfc004b0892be1888c3914977aa9c9bbaf2c7471708a49e1195af48a6f29ce",
+            ),
+            (
+                "54657374436f6e74657874",
+                "5a0131813abc9a522cad678eb6bafaabc43389934adb8097d23c5ff68059eb64",
+            ),
+        ],
+    };
+
+    // DHKEM(P-256, HKDF-SHA256), HKDF-SHA256, AES-128-GCM, auth mode. The ephemeral and recipient
+    // keys are the ones of RFC 9180 appendix A.3.1, and the sender key is derived from the seed
+    // 000102..1f. The expected values are the output of OpenSSL 3.5's HPKE implementation
+    // (`OSSL_HPKE_encap` with `OSSL_HPKE_CTX_set1_ikme`) for these inputs.
+    const P256_AUTH: AuthVector = AuthVector {
+        kem: Kem::p256_hkdf_sha256,
+        ikm_e: "4270e54ffd08d79d5928020af4686d8f6b7d35dbe470265f1f5aa22816ce860e",
+        pk_em: concat!(
+            "04a92719c6195d5085104f469a8b9814d5838ff72b60501e2c4466e5e67b325ac9",
+            "8536d7b61a1af4b78e5b7f951c0900be863c403ce65c9bfcb9382657222d18c4",
+        ),
+        sk_rm: "f3ce7fdae57e1a310d87f1ebbde6f328be0a99cdbcadf4d6589cf29de4b8ffd2",
+        sk_sm: "c4a9b2ed5595907ca64a481ea78cf93a047ef7153f7d70121b2552b9b6f07cee",
+        ct0: "47258016e8e5f399a6a8a961535a82b58aa8c090a348f04145e28c73e402b96706f7e46ceca68b1449f4dc15b1",
+        ct1: "59b4e9f6e439cffd365fff7d783979595e3edf647330f4d1255e2bdf3d4c430bf019921a9e30d4c90d0388c74e",
+        exports: [
+            (
+                "",
+                "7cb46e8efb31cd17373869e533d7ab96efbe361296efd11c12ee6c28a11011b8",
+            ),
+            (
+                "00",
+                "36198f9a3ae9de6a82bc25d712b00466a0e6b69d647c088cede404074f17d41f",
+            ),
+            (
+                "54657374436f6e74657874",
+                "f9b790c2909f63dcd454306c20a0b22e13b5d3e5275fd46901142eb4189b4973",
+            ),
+        ],
+    };
+
+    fn h(s: &str) -> Vec<u8> {
+        Vec::from_hex(s).unwrap()
+    }
+
+    #[test]
+    fn rfc9180_base_sender() {
+        let suite = HpkeSuite::default();
+        let (mut ctx, enc) =
+            HpkeSenderContext::new_with_seed(suite, &h(PK_RM), &h(INFO), &h(IKM_E)).unwrap();
+        assert_eq!(enc, h(PK_EM));
+        assert_eq!(ctx.seal(&h(PT), b"Count-0").unwrap(), h(CT0));
+        assert_eq!(ctx.seal(&h(PT), b"Count-1").unwrap(), h(CT1));
+
+        for (context, expected) in EXPORTS {
+            let mut out = [0; 32];
+            ctx.export(&mut out, &h(context)).unwrap();
+            assert_eq!(out[..], h(expected)[..]);
+        }
+    }
+
+    #[test]
+    fn rfc9180_base_recipient() {
+        let suite = HpkeSuite::default();
+        let key = HpkeKey::new(suite.kem, &h(SK_RM)).unwrap();
+        assert_eq!(key.public_key().unwrap(), h(PK_RM));
+
+        let mut ctx = HpkeRecipientContext::new(suite, &key, &h(PK_EM), &h(INFO)).unwrap();
+        assert_eq!(ctx.open(&h(CT0), b"Count-0").unwrap(), h(PT));
+        assert_eq!(ctx.open(&h(CT1), b"Count-1").unwrap(), h(PT));
+
+        for (context, expected) in EXPORTS {
+            let mut out = [0; 32];
+            ctx.export(&mut out, &h(context)).unwrap();
+            assert_eq!(out[..], h(expected)[..]);
+        }
+    }
+
+    fn check_auth_vector(v: &AuthVector) {
+        let suite = HpkeSuite::new((v.kem)(), Kdf::hkdf_sha256(), Aead::aes_128_gcm());
+        let sender = HpkeKey::new(suite.kem, &h(v.sk_sm)).unwrap();
+        let recipient = HpkeKey::new(suite.kem, &h(v.sk_rm)).unwrap();
+        let sender_public_key = sender.public_key().unwrap();
+        let recipient_public_key = recipient.public_key().unwrap();
+
+        let (mut ctx, enc) = HpkeSenderContext::new_auth_with_seed(
+            suite,
+            &sender,
+            &recipient_public_key,
+            &h(INFO),
+            &h(v.ikm_e),
+        )
+        .unwrap();
+        assert_eq!(enc, h(v.pk_em));
+        assert_eq!(ctx.seal(&h(PT), b"Count-0").unwrap(), h(v.ct0));
+        assert_eq!(ctx.seal(&h(PT), b"Count-1").unwrap(), h(v.ct1));
+        for (context, expected) in v.exports {
+            let mut out = [0; 32];
+            ctx.export(&mut out, &h(context)).unwrap();
+            assert_eq!(out[..], h(expected)[..]);
+        }
+
+        let mut ctx = HpkeRecipientContext::new_auth(
+            suite,
+            &recipient,
+            &h(v.pk_em),
+            &h(INFO),
+            &sender_public_key,
+        )
+        .unwrap();
+        assert_eq!(ctx.open(&h(v.ct0), b"Count-0").unwrap(), h(PT));
+        assert_eq!(ctx.open(&h(v.ct1), b"Count-1").unwrap(), h(PT));
+        for (context, expected) in v.exports {
+            let mut out = [0; 32];
+            ctx.export(&mut out, &h(context)).unwrap();
+            assert_eq!(out[..], h(expected)[..]);
+        }
+
+        // The base mode derives different keys
+        let mut ctx = HpkeRecipientContext::new(suite, &recipient, &h(v.pk_em), &h(INFO)).unwrap();
+        assert!(ctx.open(&h(v.ct0), b"Count-0").is_err());
+    }
+
+    #[test]
+    fn rfc9180_auth_x25519() {
+        check_auth_vector(&X25519_AUTH);
+    }
+
+    #[test]
+    fn auth_p256() {
+        check_auth_vector(&P256_AUTH);
+    }
+
+    #[test]
+    fn auth_unsupported_for_xwing() {
+        let suite = HpkeSuite::new(Kem::xwing(), Kdf::hkdf_sha256(), Aead::aes_128_gcm());
+        let sender = HpkeKey::generate(suite.kem).unwrap();
+        let recipient = HpkeKey::generate(suite.kem).unwrap();
+        let recipient_public_key = recipient.public_key().unwrap();
+        assert!(HpkeSenderContext::new_auth(suite, &sender, &recipient_public_key, b"").is_err());
+    }
+
+    #[test]
+    fn out_of_order_open_fails() {
+        let suite = HpkeSuite::default();
+        let key = HpkeKey::new(suite.kem, &h(SK_RM)).unwrap();
+        let mut ctx = HpkeRecipientContext::new(suite, &key, &h(PK_EM), &h(INFO)).unwrap();
+        assert!(ctx.open(&h(CT1), b"Count-1").is_err());
+    }
+
+    #[test]
+    fn single_shot_all_suites() {
+        for kem in [
+            Kem::x25519_hkdf_sha256(),
+            Kem::p256_hkdf_sha256(),
+            Kem::xwing(),
+        ] {
+            for aead in [
+                Aead::aes_128_gcm(),
+                Aead::aes_256_gcm(),
+                Aead::chacha20_poly1305(),
+            ] {
+                let suite = HpkeSuite::new(kem, Kdf::hkdf_sha256(), aead);
+                let key = HpkeKey::generate(kem).unwrap();
+                let public_key = key.public_key().unwrap();
+                assert_eq!(public_key.len(), kem.public_key_len());
+
+                let (enc, ct) = seal(suite, &public_key, b"info", b"hello", b"aad").unwrap();
+                assert_eq!(enc.len(), kem.enc_len());
+                let pt = open(suite, &key, &enc, b"info", &ct, b"aad").unwrap();
+                assert_eq!(pt, b"hello");
+
+                assert!(open(suite, &key, &enc, b"other", &ct, b"aad").is_err());
+                assert!(open(suite, &key, &enc, b"info", &ct, b"other").is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn auth_mode_roundtrip() {
+        for kem in [Kem::x25519_hkdf_sha256(), Kem::p256_hkdf_sha256()] {
+            auth_mode_roundtrip_with(HpkeSuite::new(kem, Kdf::hkdf_sha256(), Aead::aes_128_gcm()));
+        }
+    }
+
+    fn auth_mode_roundtrip_with(suite: HpkeSuite) {
+        let sender = HpkeKey::generate(suite.kem).unwrap();
+        let recipient = HpkeKey::generate(suite.kem).unwrap();
+        let other = HpkeKey::generate(suite.kem).unwrap();
+
+        let (enc, ct) = seal_auth(
+            suite,
+            &sender,
+            &recipient.public_key().unwrap(),
+            b"info",
+            b"hello",
+            b"",
+        )
+        .unwrap();
+
+        let sender_public_key = sender.public_key().unwrap();
+        let pt = open_auth(
+            suite,
+            &recipient,
+            &enc,
+            b"info",
+            &sender_public_key,
+            &ct,
+            b"",
+        )
+        .unwrap();
+        assert_eq!(pt, b"hello");
+
+        let other_public_key = other.public_key().unwrap();
+        assert!(open_auth(
+            suite,
+            &recipient,
+            &enc,
+            b"info",
+            &other_public_key,
+            &ct,
+            b""
+        )
+        .is_err());
+        assert!(open(suite, &recipient, &enc, b"info", &ct, b"").is_err());
+    }
+
+    #[test]
+    fn key_roundtrip() {
+        let kem = Kem::p256_hkdf_sha256();
+        let key = HpkeKey::generate(kem).unwrap();
+        let private_key = key.private_key().unwrap();
+        assert_eq!(private_key.len(), kem.private_key_len());
+
+        let restored = HpkeKey::new(kem, &private_key).unwrap();
+        assert_eq!(restored.public_key().unwrap(), key.public_key().unwrap());
+        assert_eq!(key.clone().public_key().unwrap(), key.public_key().unwrap());
+    }
+
+    #[test]
+    fn suite_mismatch() {
+        let suite = HpkeSuite::default();
+        let key = HpkeKey::generate(Kem::p256_hkdf_sha256()).unwrap();
+        assert!(HpkeRecipientContext::new(suite, &key, &[0; 32], b"").is_err());
+    }
+
+    #[test]
+    fn ids() {
+        assert_eq!(Kem::x25519_hkdf_sha256().id(), 0x0020);
+        assert_eq!(Kem::p256_hkdf_sha256().id(), 0x0010);
+        assert_eq!(Kem::xwing().id(), 0x647a);
+        assert_eq!(Kdf::hkdf_sha256().id(), 0x0001);
+        assert_eq!(Aead::aes_128_gcm().id(), 0x0001);
+        assert_eq!(Aead::aes_256_gcm().id(), 0x0002);
+        assert_eq!(Aead::chacha20_poly1305().id(), 0x0003);
+    }
 }
@@ -0,0 +1,514 @@
+//! A small JSON reader and writer for the JOSE formats.
+
+use std::collections::HashSet;
+
+use crate::error::ErrorStack;
+use crate::secret::SecretBytes;
+
+// Deeper values are rejected rather than risking the stack
+const MAX_DEPTH: usize = 64;
+
+/// A JSON value.
+///
+/// Numbers keep their text, so that integers of any size survive a round trip.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    /// A string holding key material, wiped when dropped. It is written as is, so it must not
+    /// need escaping.
+    Secret(SecretBytes),
+    Array(Vec<Value>),
+    Object(Object),
+}
+
+impl Value {
+    pub(crate) fn parse(json: &str) -> Result<Value, ErrorStack> {
+        let mut parser = Parser {
+            bytes: json.as_bytes(),
+            pos: 0,
+        };
+        let value = parser.value(0)?;
+        parser.whitespace();
+        if parser.pos != parser.bytes.len() {
+            return Err(malformed());
+        }
+        Ok(value)
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the number as an integer, rounding fractions down.
+    pub(crate) fn as_i64(&self) -> Option<i64> {
+        let Value::Number(number) = self else {
+            return None;
+        };
+        number.parse().ok().or_else(|| {
+            let number = number.parse::<f64>().ok()?;
+            (number.is_finite() && number.abs() < 2f64.powi(63)).then(|| number.floor() as i64)
+        })
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_object(&self) -> Option<&Object> {
+        match self {
+            Value::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn write(&self, out: &mut String) {
+        self.write_with(out, false);
+    }
+
+    fn write_with(&self, out: &mut String, redact: bool) {
+        match self {
+            Value::Null => out.push_str("null"),
+            Value::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            Value::Number(value) => out.push_str(value),
+            Value::String(value) => write_string(out, value),
+            Value::Secret(value) => {
+                debug_assert!(value.is_ascii());
+                out.push('"');
+                out.extend(
+                    value
+                        .iter()
+                        .map(|&b| if redact { '*' } else { char::from(b) }),
+                );
+                out.push('"');
+            }
+            Value::Array(values) => {
+                out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    value.write_with(out, redact);
+                }
+                out.push(']');
+            }
+            Value::Object(object) => object.write_with(out, redact),
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out);
+        out
+    }
+
+    /// Serializes a value holding [`Value::Secret`]s into a buffer that is wiped when dropped.
+    pub(crate) fn to_json_secret(&self) -> SecretBytes {
+        // A redacted pass sizes the buffer, so that writing the secrets never reallocates and
+        // leaves copies of them behind
+        let mut redacted = String::new();
+        self.write_with(&mut redacted, true);
+        let mut out = String::with_capacity(redacted.len());
+        self.write_with(&mut out, false);
+        SecretBytes::from(out.into_bytes())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Value {
+        Value::String(value.to_owned())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Value {
+        Value::Number(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Value {
+        Value::String(value)
+    }
+}
+
+/// A JSON object, keeping the order of its members.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Object(Vec<(String, Value)>);
+
+impl Object {
+    pub(crate) fn new() -> Object {
+        Object::default()
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Value> {
+        self.0
+            .iter()
+            .find(|(member, _)| member == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the string member `name`, failing if it has another type.
+    pub(crate) fn get_str(&self, name: &str) -> Result<Option<&str>, ErrorStack> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(value) => value.as_str().map(Some).ok_or_else(malformed),
+        }
+    }
+
+    /// Sets the member `name`, replacing any previous value.
+    pub(crate) fn insert(&mut self, name: &str, value: impl Into<Value>) {
+        let value = value.into();
+        match self.0.iter_mut().find(|(member, _)| member == name) {
+            Some((_, previous)) => *previous = value,
+            None => self.0.push((name.to_owned(), value)),
+        }
+    }
+
+    pub(crate) fn write(&self, out: &mut String) {
+        self.write_with(out, false);
+    }
+
+    fn write_with(&self, out: &mut String, redact: bool) {
+        out.push('{');
+        for (i, (name, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_string(out, name);
+            out.push(':');
+            value.write_with(out, redact);
+        }
+        out.push('}');
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out);
+        out
+    }
+}
+
+impl From<SecretBytes> for Value {
+    fn from(value: SecretBytes) -> Value {
+        Value::Secret(value)
+    }
+}
+
+impl From<Object> for Value {
+    fn from(object: Object) -> Value {
+        Value::Object(object)
+    }
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn advance(&mut self) -> Result<u8, ErrorStack> {
+        let byte = self.peek().ok_or_else(malformed)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), ErrorStack> {
+        if self.advance()? == expected {
+            Ok(())
+        } else {
+            Err(malformed())
+        }
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, ErrorStack> {
+        if depth > MAX_DEPTH {
+            return Err(malformed());
+        }
+        self.whitespace();
+        match self.peek().ok_or_else(malformed)? {
+            b'{' => self.object(depth),
+            b'[' => self.array(depth),
+            b'"' => self.string().map(Value::String),
+            b't' => self.literal("true", Value::Bool(true)),
+            b'f' => self.literal("false", Value::Bool(false)),
+            b'n' => self.literal("null", Value::Null),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => Err(malformed()),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Value, ErrorStack> {
+        self.expect(b'{')?;
+        let mut members = Vec::<(String, Value)>::new();
+        let mut names = HashSet::new();
+        self.whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Value::Object(Object(members)));
+        }
+        loop {
+            self.whitespace();
+            let name = self.string()?;
+            // JOSE requires duplicate members to be rejected
+            if !names.insert(name.clone()) {
+                return Err(malformed());
+            }
+            self.whitespace();
+            self.expect(b':')?;
+            let value = self.value(depth + 1)?;
+            members.push((name, value));
+            self.whitespace();
+            match self.advance()? {
+                b',' => {}
+                b'}' => return Ok(Value::Object(Object(members))),
+                _ => return Err(malformed()),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value, ErrorStack> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+        self.whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Value::Array(values));
+        }
+        loop {
+            values.push(self.value(depth + 1)?);
+            self.whitespace();
+            match self.advance()? {
+                b',' => {}
+                b']' => return Ok(Value::Array(values)),
+                _ => return Err(malformed()),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ErrorStack> {
+        self.expect(b'"')?;
+        let mut out = Vec::new();
+        loop {
+            match self.advance()? {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = match self.advance()? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(malformed()),
+                    };
+                    out.extend_from_slice(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                byte if byte < b' ' => return Err(malformed()),
+                byte => out.push(byte),
+            }
+        }
+        // The input is a `str` and escapes are whole characters, so this cannot fail
+        String::from_utf8(out).map_err(ErrorStack::internal_error)
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, ErrorStack> {
+        let high = self.hex4()?;
+        let code_point = match high {
+            0xd800..=0xdbff => {
+                self.expect(b'\\')?;
+                self.expect(b'u')?;
+                let low = self.hex4()?;
+                if !(0xdc00..=0xdfff).contains(&low) {
+                    return Err(malformed());
+                }
+                0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+            }
+            code_point => code_point,
+        };
+        char::from_u32(code_point).ok_or_else(malformed)
+    }
+
+    fn hex4(&mut self) -> Result<u32, ErrorStack> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = char::from(self.advance()?)
+                .to_digit(16)
+                .ok_or_else(malformed)?;
+            value = (value << 4) | digit;
+        }
+        Ok(value)
+    }
+
+    fn literal(&mut self, literal: &str, value: Value) -> Result<Value, ErrorStack> {
+        let end = self.pos + literal.len();
+        if self.bytes.get(self.pos..end) != Some(literal.as_bytes()) {
+            return Err(malformed());
+        }
+        self.pos = end;
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Value, ErrorStack> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        match self.advance()? {
+            b'0' => {}
+            b'1'..=b'9' => self.digits(),
+            _ => return Err(malformed()),
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            self.digits1()?;
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            self.digits1()?;
+        }
+        // The number is ASCII
+        let number = String::from_utf8_lossy(&self.bytes[start..self.pos]).into_owned();
+        Ok(Value::Number(number))
+    }
+
+    fn digits(&mut self) {
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn digits1(&mut self) -> Result<(), ErrorStack> {
+        if !matches!(self.peek(), Some(b'0'..=b'9')) {
+            return Err(malformed());
+        }
+        self.digits();
+        Ok(())
+    }
+}
+
+fn malformed() -> ErrorStack {
+    ErrorStack::internal_error_str("malformed JSON")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let json = r#"{"a":[1,-2.5e10,true,false,null],"b":"\"\\\n\u0001é😀","c":{}}"#;
+        let value = Value::parse(json).unwrap();
+        assert_eq!(value.to_json(), json);
+
+        let value = Value::parse(" { \"x\" : \"\\u00e9\\ud83d\\ude00\\/\" } ").unwrap();
+        let object = value.as_object().unwrap();
+        assert_eq!(object.get_str("x").unwrap(), Some("é😀/"));
+        assert_eq!(object.get_str("y").unwrap(), None);
+
+        let array = Value::parse("[1, -2.5, 1e3, \"1\", 1e400]").unwrap();
+        let numbers = array.as_array().unwrap();
+        assert_eq!(numbers[0].as_i64(), Some(1));
+        assert_eq!(numbers[1].as_i64(), Some(-3));
+        assert_eq!(numbers[2].as_i64(), Some(1000));
+        assert_eq!(numbers[3].as_i64(), None);
+        assert_eq!(numbers[4].as_i64(), None);
+        assert_eq!(Value::from(-7).to_json(), "-7");
+        assert_eq!(value.to_json(), "{\"x\":\"é😀/\"}");
+    }
+
+    #[test]
+    fn secret() {
+        let mut object = Object::new();
+        object.insert("kty", "OKP");
+        object.insert("d", SecretBytes::from(&b"c2VjcmV0"[..]));
+        let value = Value::from(object);
+        let json = value.to_json_secret();
+        assert_eq!(&*json, br#"{"kty":"OKP","d":"c2VjcmV0"}"#);
+        assert_eq!(value.to_json(), r#"{"kty":"OKP","d":"c2VjcmV0"}"#);
+        assert!(!format!("{value:?}").contains("c2VjcmV0"));
+    }
+
+    #[test]
+    fn many_members() {
+        let members = (0..100_000)
+            .map(|i| format!("\"{i}\":{i}"))
+            .collect::<Vec<_>>()
+            .join(",");
+        let value = Value::parse(&format!("{{{members}}}")).unwrap();
+        let object = value.as_object().unwrap();
+        assert_eq!(object.get("99999").and_then(Value::as_i64), Some(99999));
+
+        let duplicate = format!("{{{members},\"0\":0}}");
+        assert!(Value::parse(&duplicate).is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        for json in [
+            "",
+            "{",
+            "[1,]",
+            "{\"a\":1,}",
+            "{\"a\":1,\"a\":2}",
+            "01",
+            "1.",
+            "-",
+            "tru",
+            "\"\\ud83d\"",
+            "\"\u{1}\"",
+            "\"a\" 1",
+            "[".repeat(100).as_str(),
+        ] {
+            assert!(Value::parse(json).is_err(), "{json}");
+        }
+        assert!(Value::parse("{\"a\":1}")
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .get_str("a")
+            .is_err());
+    }
+}
@@ -0,0 +1,753 @@
+//! JSON Web Keys.
+//!
+//! [`Jwk`] converts keys to and from the JSON Web Key format of [RFC 7517]: RSA keys, EC keys
+//! over P-256, P-384 and P-521, and the Ed25519 and X25519 OKP keys of [RFC 8037]. It also
+//! computes [RFC 7638] thumbprints. [`JwkSet`] reads and writes JWK Sets, such as the ones
+//! OpenID providers publish.
+//!
+//! ```
+//! use rama_boring::ec::{EcGroup, EcKey};
+//! use rama_boring::hash::MessageDigest;
+//! use rama_boring::jwk::Jwk;
+//! use rama_boring::nid::Nid;
+//! use rama_boring::pkey::{PKey, Public};
+//!
+//! let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+//! let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+//! let mut jwk = Jwk::new(key).unwrap();
+//! jwk.set_key_id("2024-01");
+//!
+//! let json = jwk.public_key_to_json().unwrap();
+//! let public = Jwk::<Public>::public_key_from_json(&json).unwrap();
+//! assert_eq!(public.key_id(), Some("2024-01"));
+//! assert_eq!(
+//!     *public.thumbprint(MessageDigest::sha256()).unwrap(),
+//!     *jwk.thumbprint(MessageDigest::sha256()).unwrap(),
+//! );
+//! ```
+//!
+//! [RFC 7517]: https://www.rfc-editor.org/rfc/rfc7517.html
+//! [RFC 8037]: https://www.rfc-editor.org/rfc/rfc8037.html
+//! [RFC 7638]: https://www.rfc-editor.org/rfc/rfc7638.html
+
+use std::fmt;
+
+use crate::base64;
+use crate::bn::{BigNum, BigNumContext};
+use crate::ec::{EcGroup, EcKey};
+use crate::error::ErrorStack;
+use crate::hash::{hash, DigestBytes, MessageDigest};
+use crate::json::{Object, Value};
+use crate::nid::Nid;
+use crate::pkey::{HasPublic, Id, PKey, PKeyRef, Private, Public};
+use crate::rsa::Rsa;
+use crate::secret::SecretBytes;
+
+const CURVES: [(&str, Nid); 3] = [
+    ("P-256", Nid::X9_62_PRIME256V1),
+    ("P-384", Nid::SECP384R1),
+    ("P-521", Nid::SECP521R1),
+];
+
+/// A key with the optional parameters of a JSON Web Key.
+pub struct Jwk<T> {
+    key: PKey<T>,
+    key_id: Option<String>,
+    key_use: Option<String>,
+    algorithm: Option<String>,
+}
+
+impl<T> Jwk<T> {
+    /// Returns the key.
+    #[must_use]
+    pub fn key(&self) -> &PKeyRef<T> {
+        &self.key
+    }
+
+    /// Consumes `self`, returning the key.
+    #[must_use]
+    pub fn into_key(self) -> PKey<T> {
+        self.key
+    }
+
+    /// Returns the `kid` parameter.
+    #[must_use]
+    pub fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    /// Sets the `kid` parameter.
+    pub fn set_key_id(&mut self, key_id: &str) {
+        self.key_id = Some(key_id.to_owned());
+    }
+
+    /// Returns the `use` parameter, `sig` or `enc`.
+    #[must_use]
+    pub fn key_use(&self) -> Option<&str> {
+        self.key_use.as_deref()
+    }
+
+    /// Sets the `use` parameter.
+    pub fn set_key_use(&mut self, key_use: &str) {
+        self.key_use = Some(key_use.to_owned());
+    }
+
+    /// Returns the `alg` parameter, such as `ES256`.
+    #[must_use]
+    pub fn algorithm(&self) -> Option<&str> {
+        self.algorithm.as_deref()
+    }
+
+    /// Sets the `alg` parameter.
+    pub fn set_algorithm(&mut self, algorithm: &str) {
+        self.algorithm = Some(algorithm.to_owned());
+    }
+
+    fn with_parameters(key: PKey<T>, object: &Object) -> Result<Jwk<T>, ErrorStack> {
+        Ok(Jwk {
+            key,
+            key_id: object.get_str("kid")?.map(str::to_owned),
+            key_use: object.get_str("use")?.map(str::to_owned),
+            algorithm: object.get_str("alg")?.map(str::to_owned),
+        })
+    }
+}
+
+impl<T> Jwk<T>
+where
+    T: HasPublic,
+{
+    /// Creates a JWK from `key`, without parameters.
+    ///
+    /// Fails if the key type is not supported.
+    pub fn new(key: PKey<T>) -> Result<Jwk<T>, ErrorStack> {
+        KeyType::of(&key)?;
+        Ok(Jwk {
+            key,
+            key_id: None,
+            key_use: None,
+            algorithm: None,
+        })
+    }
+
+    /// Serializes the public key and the parameters as a JWK.
+    pub fn public_key_to_json(&self) -> Result<String, ErrorStack> {
+        Ok(self.to_object()?.to_json())
+    }
+
+    /// Returns the public key, with the same parameters.
+    pub fn to_public(&self) -> Result<Jwk<Public>, ErrorStack> {
+        let object = self.to_object()?;
+        let key = read_public(&object, KeyType::of(&self.key)?)?;
+        Jwk::with_parameters(key, &object)
+    }
+
+    /// Computes the RFC 7638 thumbprint of the key with `digest`.
+    ///
+    /// The thumbprint covers the required public members only, so it is the same for the private
+    /// and the public key, whatever the parameters.
+    pub fn thumbprint(&self, digest: MessageDigest) -> Result<DigestBytes, ErrorStack> {
+        let mut object = Object::new();
+        for (name, value) in public_members(&self.key)? {
+            object.insert(name, value);
+        }
+        hash(digest, Value::from(object).to_json().as_bytes())
+    }
+
+    fn to_object(&self) -> Result<Object, ErrorStack> {
+        let members = public_members(&self.key)?;
+        let mut object = Object::new();
+        // Lead with `kty`, as is customary
+        if let Some((name, value)) = members.iter().find(|(name, _)| *name == "kty") {
+            object.insert(name, value.as_str());
+        }
+        for (name, value) in members {
+            object.insert(name, value);
+        }
+        for (name, value) in [
+            ("kid", &self.key_id),
+            ("use", &self.key_use),
+            ("alg", &self.algorithm),
+        ] {
+            if let Some(value) = value {
+                object.insert(name, value.as_str());
+            }
+        }
+        Ok(object)
+    }
+}
+
+impl Jwk<Public> {
+    /// Deserializes a public key from a JWK.
+    ///
+    /// The private members of a private JWK are ignored.
+    pub fn public_key_from_json(json: &str) -> Result<Jwk<Public>, ErrorStack> {
+        let object = parse_object(json)?;
+        let key_type = KeyType::from_object(&object)?.ok_or_else(unsupported)?;
+        Jwk::with_parameters(read_public(&object, key_type)?, &object)
+    }
+}
+
+impl Jwk<Private> {
+    /// Deserializes a private key from a JWK.
+    pub fn private_key_from_json(json: &str) -> Result<Jwk<Private>, ErrorStack> {
+        let object = parse_object(json)?;
+        let key_type = KeyType::from_object(&object)?.ok_or_else(unsupported)?;
+        Jwk::with_parameters(read_private(&object, key_type)?, &object)
+    }
+
+    /// Serializes the private key and the parameters as a JWK.
+    ///
+    /// The UTF-8 encoded JSON is returned in a buffer that is wiped when dropped.
+    pub fn private_key_to_json(&self) -> Result<SecretBytes, ErrorStack> {
+        Ok(Value::from(self.to_private_object()?).to_json_secret())
+    }
+
+    fn to_private_object(&self) -> Result<Object, ErrorStack> {
+        let mut object = self.to_object()?;
+        for (name, value) in private_members(&self.key)? {
+            object.insert(name, value);
+        }
+        Ok(object)
+    }
+}
+
+impl<T> Clone for Jwk<T> {
+    fn clone(&self) -> Jwk<T> {
+        Jwk {
+            key: self.key.clone(),
+            key_id: self.key_id.clone(),
+            key_use: self.key_use.clone(),
+            algorithm: self.algorithm.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Jwk<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Jwk")
+            .field("key_id", &self.key_id)
+            .field("key_use", &self.key_use)
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A JWK Set.
+pub struct JwkSet<T> {
+    keys: Vec<Jwk<T>>,
+}
+
+impl<T> JwkSet<T> {
+    /// Creates an empty set.
+    #[must_use]
+    pub fn new() -> JwkSet<T> {
+        JwkSet { keys: Vec::new() }
+    }
+
+    /// Adds a key to the set.
+    pub fn push(&mut self, key: Jwk<T>) {
+        self.keys.push(key);
+    }
+
+    /// Returns the keys of the set.
+    #[must_use]
+    pub fn keys(&self) -> &[Jwk<T>] {
+        &self.keys
+    }
+
+    /// Returns the first key with the `kid` parameter `key_id`.
+    #[must_use]
+    pub fn find(&self, key_id: &str) -> Option<&Jwk<T>> {
+        self.keys.iter().find(|key| key.key_id() == Some(key_id))
+    }
+
+    /// Parses the `keys` of a JWK Set, skipping the keys of unsupported types as RFC 7517
+    /// recommends.
+    fn parse(
+        json: &str,
+        read: impl Fn(&Object, KeyType) -> Result<PKey<T>, ErrorStack>,
+    ) -> Result<JwkSet<T>, ErrorStack> {
+        let keys = parse_object(json)?
+            .get("keys")
+            .and_then(Value::as_array)
+            .ok_or_else(malformed)?
+            .iter()
+            .map(|key| {
+                let object = key.as_object().ok_or_else(malformed)?;
+                match KeyType::from_object(object)? {
+                    Some(key_type) => {
+                        Jwk::with_parameters(read(object, key_type)?, object).map(Some)
+                    }
+                    None => Ok(None),
+                }
+            })
+            .filter_map(Result::transpose)
+            .collect::<Result<_, _>>()?;
+        Ok(JwkSet { keys })
+    }
+}
+
+impl<T> JwkSet<T>
+where
+    T: HasPublic,
+{
+    /// Serializes the public keys of the set as a JWK Set.
+    pub fn public_keys_to_json(&self) -> Result<String, ErrorStack> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| key.to_object().map(Value::from))
+            .collect::<Result<_, _>>()?;
+        let mut object = Object::new();
+        object.insert("keys", Value::Array(keys));
+        Ok(object.to_json())
+    }
+}
+
+impl JwkSet<Public> {
+    /// Deserializes the public keys of a JWK Set.
+    ///
+    /// Keys of unsupported types are skipped.
+    pub fn public_keys_from_json(json: &str) -> Result<JwkSet<Public>, ErrorStack> {
+        JwkSet::parse(json, read_public)
+    }
+}
+
+impl JwkSet<Private> {
+    /// Deserializes the private keys of a JWK Set.
+    ///
+    /// Keys of unsupported types are skipped.
+    pub fn private_keys_from_json(json: &str) -> Result<JwkSet<Private>, ErrorStack> {
+        JwkSet::parse(json, read_private)
+    }
+
+    /// Serializes the private keys of the set as a JWK Set.
+    ///
+    /// The UTF-8 encoded JSON is returned in a buffer that is wiped when dropped.
+    pub fn private_keys_to_json(&self) -> Result<SecretBytes, ErrorStack> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| key.to_private_object().map(Value::from))
+            .collect::<Result<_, _>>()?;
+        let mut object = Object::new();
+        object.insert("keys", Value::Array(keys));
+        Ok(Value::from(object).to_json_secret())
+    }
+}
+
+impl<T> Default for JwkSet<T> {
+    fn default() -> JwkSet<T> {
+        JwkSet::new()
+    }
+}
+
+impl<T> Clone for JwkSet<T> {
+    fn clone(&self) -> JwkSet<T> {
+        JwkSet {
+            keys: self.keys.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for JwkSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwkSet").field("keys", &self.keys).finish()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum KeyType {
+    Rsa,
+    Ec(Nid),
+    Ed25519,
+    X25519,
+}
+
+impl KeyType {
+    fn of<T>(key: &PKeyRef<T>) -> Result<KeyType, ErrorStack> {
+        match key.id() {
+            Id::RSA => Ok(KeyType::Rsa),
+            Id::EC => key
+                .ec_key()?
+                .group()
+                .curve_name()
+                .filter(|nid| CURVES.iter().any(|(_, curve)| curve == nid))
+                .map(KeyType::Ec)
+                .ok_or_else(unsupported),
+            Id::ED25519 => Ok(KeyType::Ed25519),
+            Id::X25519 => Ok(KeyType::X25519),
+            _ => Err(unsupported()),
+        }
+    }
+
+    /// Returns the type of a JWK, or `None` if it is not supported.
+    fn from_object(object: &Object) -> Result<Option<KeyType>, ErrorStack> {
+        let kty = object.get_str("kty")?.ok_or_else(malformed)?;
+        let crv = object.get_str("crv")?;
+        Ok(match (kty, crv) {
+            ("RSA", _) => Some(KeyType::Rsa),
+            ("EC", Some(crv)) => CURVES
+                .iter()
+                .find(|(name, _)| *name == crv)
+                .map(|&(_, nid)| KeyType::Ec(nid)),
+            ("OKP", Some("Ed25519")) => Some(KeyType::Ed25519),
+            ("OKP", Some("X25519")) => Some(KeyType::X25519),
+            ("EC" | "OKP", None) => return Err(malformed()),
+            _ => None,
+        })
+    }
+}
+
+/// Returns the required public members of the key, sorted by name as thumbprints require.
+fn public_members<T>(key: &PKeyRef<T>) -> Result<Vec<(&'static str, String)>, ErrorStack>
+where
+    T: HasPublic,
+{
+    let key_type = KeyType::of(key)?;
+    let members = match key_type {
+        KeyType::Rsa => {
+            let rsa = key.rsa()?;
+            vec![
+                ("e", base64::encode_block_url(&rsa.e().to_vec())),
+                ("kty", "RSA".to_owned()),
+                ("n", base64::encode_block_url(&rsa.n().to_vec())),
+            ]
+        }
+        KeyType::Ec(nid) => {
+            let ec_key = key.ec_key()?;
+            let group = ec_key.group();
+            let len = coordinate_len(group.degree());
+            let mut x = BigNum::new()?;
+            let mut y = BigNum::new()?;
+            let mut ctx = BigNumContext::new()?;
+            ec_key
+                .public_key()
+                .affine_coordinates_gfp(group, &mut x, &mut y, &mut ctx)?;
+            vec![
+                ("crv", curve_name(nid).to_owned()),
+                ("kty", "EC".to_owned()),
+                ("x", base64::encode_block_url(&x.to_vec_padded(len)?)),
+                ("y", base64::encode_block_url(&y.to_vec_padded(len)?)),
+            ]
+        }
+        KeyType::Ed25519 | KeyType::X25519 => {
+            let crv = if key_type == KeyType::Ed25519 {
+                "Ed25519"
+            } else {
+                "X25519"
+            };
+            let mut x = [0; 32];
+            vec![
+                ("crv", crv.to_owned()),
+                ("kty", "OKP".to_owned()),
+                ("x", base64::encode_block_url(key.raw_public_key(&mut x)?)),
+            ]
+        }
+    };
+    Ok(members)
+}
+
+fn private_members(key: &PKeyRef<Private>) -> Result<Vec<(&'static str, SecretBytes)>, ErrorStack> {
+    let members = match KeyType::of(key)? {
+        KeyType::Rsa => {
+            let rsa = key.rsa()?;
+            let (Some(p), Some(q), Some(dp), Some(dq), Some(qi)) =
+                (rsa.p(), rsa.q(), rsa.dmp1(), rsa.dmq1(), rsa.iqmp())
+            else {
+                return Err(ErrorStack::internal_error_str(
+                    "RSA key without its CRT parameters",
+                ));
+            };
+            [
+                ("d", rsa.d()),
+                ("p", p),
+                ("q", q),
+                ("dp", dp),
+                ("dq", dq),
+                ("qi", qi),
+            ]
+            .into_iter()
+            .map(|(name, value)| (name, encode_secret_url(&value.to_vec_secret())))
+            .collect()
+        }
+        KeyType::Ec(_) => {
+            let ec_key = key.ec_key()?;
+            let len = coordinate_len(ec_key.group().order_bits());
+            let d = ec_key.private_key().to_vec_padded_secret(len)?;
+            vec![("d", encode_secret_url(&d))]
+        }
+        KeyType::Ed25519 | KeyType::X25519 => {
+            let d = key.raw_private_key_secret()?;
+            vec![("d", encode_secret_url(&d))]
+        }
+    };
+    Ok(members)
+}
+
+// Like `base64::encode_block_url`, without leaving unwiped copies behind
+fn encode_secret_url(bytes: &[u8]) -> SecretBytes {
+    let mut out = SecretBytes::from(base64::encode_block(bytes).into_bytes());
+    let len = out.iter().position(|&b| b == b'=').unwrap_or(out.len());
+    out.truncate(len);
+    for b in out.iter_mut() {
+        match *b {
+            b'+' => *b = b'-',
+            b'/' => *b = b'_',
+            _ => {}
+        }
+    }
+    out
+}
+
+fn read_public(object: &Object, key_type: KeyType) -> Result<PKey<Public>, ErrorStack> {
+    match key_type {
+        KeyType::Rsa => {
+            let n = get_bignum(object, "n")?;
+            let e = get_bignum(object, "e")?;
+            PKey::from_rsa(Rsa::from_public_components(n, e)?)
+        }
+        KeyType::Ec(nid) => {
+            let group = EcGroup::from_curve_name(nid)?;
+            let len = coordinate_len(group.degree());
+            let x = BigNum::from_slice(&get_bytes_len(object, "x", len)?)?;
+            let y = BigNum::from_slice(&get_bytes_len(object, "y", len)?)?;
+            PKey::from_ec_key(EcKey::from_public_key_affine_coordinates(&group, &x, &y)?)
+        }
+        KeyType::Ed25519 => PKey::from_ed25519_public_key(&get_bytes(object, "x")?),
+        KeyType::X25519 => PKey::from_x25519_public_key(&get_bytes(object, "x")?),
+    }
+}
+
+fn read_private(object: &Object, key_type: KeyType) -> Result<PKey<Private>, ErrorStack> {
+    let key = match key_type {
+        KeyType::Rsa => {
+            if object.get("oth").is_some() {
+                return Err(ErrorStack::internal_error_str(
+                    "multi-prime RSA keys are not supported",
+                ));
+            }
+            let rsa = Rsa::from_private_components(
+                get_bignum(object, "n")?,
+                get_bignum(object, "e")?,
+                get_bignum(object, "d")?,
+                get_bignum(object, "p")?,
+                get_bignum(object, "q")?,
+                get_bignum(object, "dp")?,
+                get_bignum(object, "dq")?,
+                get_bignum(object, "qi")?,
+            )?;
+            if !rsa.check_key()? {
+                return Err(malformed());
+            }
+            return PKey::from_rsa(rsa);
+        }
+        KeyType::Ec(nid) => {
+            let public = read_public(object, key_type)?.ec_key()?;
+            let len = coordinate_len(public.group().order_bits());
+            let d = BigNum::from_slice(&SecretBytes::from(get_bytes_len(object, "d", len)?))?;
+            let ec_key = EcKey::from_private_components(
+                &EcGroup::from_curve_name(nid)?,
+                &d,
+                public.public_key(),
+            )?;
+            ec_key.check_key()?;
+            return PKey::from_ec_key(ec_key);
+        }
+        KeyType::Ed25519 => {
+            PKey::from_ed25519_private_key(&SecretBytes::from(get_bytes(object, "d")?))?
+        }
+        KeyType::X25519 => {
+            PKey::from_x25519_private_key(&SecretBytes::from(get_bytes(object, "d")?))?
+        }
+    };
+
+    // The public key is redundant for OKP keys, but must match
+    let mut public = [0; 32];
+    if key.raw_public_key(&mut public)? != get_bytes(object, "x")? {
+        return Err(malformed());
+    }
+    Ok(key)
+}
+
+fn parse_object(json: &str) -> Result<Object, ErrorStack> {
+    match Value::parse(json)? {
+        Value::Object(object) => Ok(object),
+        _ => Err(malformed()),
+    }
+}
+
+fn get_bytes(object: &Object, name: &str) -> Result<Vec<u8>, ErrorStack> {
+    let value = object.get_str(name)?.ok_or_else(malformed)?;
+    base64::decode_block_url(value)
+}
+
+/// Returns the fixed-length member `name`, as EC coordinates and private keys are.
+fn get_bytes_len(object: &Object, name: &str, len: usize) -> Result<Vec<u8>, ErrorStack> {
+    let bytes = get_bytes(object, name)?;
+    if bytes.len() != len {
+        return Err(malformed());
+    }
+    Ok(bytes)
+}
+
+fn get_bignum(object: &Object, name: &str) -> Result<BigNum, ErrorStack> {
+    BigNum::from_slice(&get_bytes(object, name)?)
+}
+
+fn coordinate_len(bits: u32) -> usize {
+    bits.div_ceil(8) as usize
+}
+
+fn curve_name(nid: Nid) -> &'static str {
+    CURVES
+        .iter()
+        .find(|(_, curve)| *curve == nid)
+        .map_or("", |(name, _)| name)
+}
+
+fn malformed() -> ErrorStack {
+    ErrorStack::internal_error_str("malformed JWK")
+}
+
+fn unsupported() -> ErrorStack {
+    ErrorStack::internal_error_str("unsupported JWK key type")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // RFC 7638, section 3.1
+    const RSA_N: &str = "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw";
+
+    #[test]
+    fn rfc7638_thumbprint() {
+        let json =
+            format!(r#"{{"kty":"RSA","n":"{RSA_N}","e":"AQAB","alg":"RS256","kid":"2011-04-29"}}"#);
+        let jwk = Jwk::<Public>::public_key_from_json(&json).unwrap();
+        assert_eq!(jwk.key_id(), Some("2011-04-29"));
+        assert_eq!(jwk.algorithm(), Some("RS256"));
+        assert_eq!(
+            base64::encode_block_url(&jwk.thumbprint(MessageDigest::sha256()).unwrap()),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+        assert_eq!(
+            jwk.public_key_to_json().unwrap(),
+            format!(r#"{{"kty":"RSA","e":"AQAB","n":"{RSA_N}","kid":"2011-04-29","alg":"RS256"}}"#)
+        );
+    }
+
+    #[test]
+    fn rfc8037_ed25519() {
+        let json = r#"{"kty":"OKP","crv":"Ed25519","d":"nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}"#;
+        let jwk = Jwk::<Private>::private_key_from_json(json).unwrap();
+        assert_eq!(
+            base64::encode_block_url(&jwk.thumbprint(MessageDigest::sha256()).unwrap()),
+            "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k"
+        );
+        assert_eq!(
+            *jwk.private_key_to_json().unwrap(),
+            *br#"{"kty":"OKP","crv":"Ed25519","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo","d":"nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A"}"#
+        );
+
+        // A mismatched public key
+        let json = json.replace("11qY", "21qY");
+        assert!(Jwk::<Private>::private_key_from_json(&json).is_err());
+    }
+
+    #[test]
+    fn rfc7517_ec() {
+        let json = r#"{"kty":"EC","crv":"P-256","x":"MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4","y":"4Etl6SRW2YiLUrN5vfvVHuhp7x8PxltmWWlbbM4IFyM","d":"870MB6gfuTJ4HtUnUvYMyJpr5eUZNP4Bk43bVdj3eAE","use":"enc","kid":"1"}"#;
+        let jwk = Jwk::<Private>::private_key_from_json(json).unwrap();
+        assert_eq!(jwk.key_use(), Some("enc"));
+        assert_eq!(
+            Jwk::<Private>::private_key_from_json(
+                std::str::from_utf8(&jwk.private_key_to_json().unwrap()).unwrap()
+            )
+            .unwrap()
+            .key()
+            .private_key_to_der()
+            .unwrap(),
+            jwk.key().private_key_to_der().unwrap()
+        );
+
+        let public = Jwk::<Public>::public_key_from_json(json).unwrap();
+        assert_eq!(
+            public.public_key_to_json().unwrap(),
+            jwk.to_public().unwrap().public_key_to_json().unwrap()
+        );
+        assert!(!public.public_key_to_json().unwrap().contains("\"d\""));
+        assert!(
+            Jwk::<Private>::private_key_from_json(&public.public_key_to_json().unwrap()).is_err()
+        );
+
+        // Coordinates must have the full length
+        let json = json.replace("MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4", "AQ");
+        assert!(Jwk::<Public>::public_key_from_json(&json).is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let p384 = EcGroup::from_curve_name(Nid::SECP384R1).unwrap();
+        let p521 = EcGroup::from_curve_name(Nid::SECP521R1).unwrap();
+        for key in [
+            PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap(),
+            PKey::from_ec_key(EcKey::generate(&p384).unwrap()).unwrap(),
+            PKey::from_ec_key(EcKey::generate(&p521).unwrap()).unwrap(),
+            PKey::from_x25519_private_key(&[7; 32]).unwrap(),
+        ] {
+            let jwk = Jwk::new(key).unwrap();
+            let json = jwk.private_key_to_json().unwrap();
+            let parsed =
+                Jwk::<Private>::private_key_from_json(std::str::from_utf8(&json).unwrap()).unwrap();
+            assert_eq!(parsed.private_key_to_json().unwrap(), json);
+
+            let json = jwk.public_key_to_json().unwrap();
+            let parsed = Jwk::<Public>::public_key_from_json(&json).unwrap();
+            assert_eq!(parsed.public_key_to_json().unwrap(), json);
+            assert_eq!(
+                *parsed.thumbprint(MessageDigest::sha256()).unwrap(),
+                *jwk.thumbprint(MessageDigest::sha256()).unwrap()
+            );
+        }
+
+        let secp256k1 = EcGroup::from_curve_name(Nid::SECP256K1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&secp256k1).unwrap()).unwrap();
+        assert!(Jwk::new(key).is_err());
+    }
+
+    #[test]
+    fn jwk_set() {
+        let json = format!(
+            r#"{{"keys":[
+                {{"kty":"oct","k":"AAAA","kid":"symmetric"}},
+                {{"kty":"RSA","n":"{RSA_N}","e":"AQAB","kid":"rsa"}},
+                {{"kty":"OKP","crv":"Ed448","x":"AAAA","kid":"ed448"}},
+                {{"kty":"OKP","crv":"Ed25519","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo","kid":"ed25519"}}
+            ]}}"#
+        );
+        let set = JwkSet::public_keys_from_json(&json).unwrap();
+        assert_eq!(set.keys().len(), 2);
+        assert_eq!(set.find("ed25519").unwrap().key().id(), Id::ED25519);
+        assert!(set.find("symmetric").is_none());
+
+        let reparsed = JwkSet::public_keys_from_json(&set.public_keys_to_json().unwrap()).unwrap();
+        assert_eq!(
+            reparsed.public_keys_to_json().unwrap(),
+            set.public_keys_to_json().unwrap()
+        );
+
+        let mut private = JwkSet::new();
+        let mut jwk = Jwk::new(PKey::from_x25519_private_key(&[1; 32]).unwrap()).unwrap();
+        jwk.set_key_id("x25519");
+        private.push(jwk);
+        let json = private.private_keys_to_json().unwrap();
+        let reparsed = JwkSet::private_keys_from_json(std::str::from_utf8(&json).unwrap()).unwrap();
+        assert_eq!(reparsed.find("x25519").unwrap().key().id(), Id::X25519);
+
+        assert!(JwkSet::public_keys_from_json("{}").is_err());
+        assert!(JwkSet::public_keys_from_json(r#"{"keys":[{"kty":"EC"}]}"#).is_err());
+    }
+}
@@ -0,0 +1,616 @@
+//! JSON Web Signatures.
+//!
+//! [`JwsSigner`] produces [RFC 7515] compact serializations and [`JwsVerifier`] checks them, with
+//! the RSA, RSA-PSS, ECDSA and EdDSA algorithms of [RFC 7518] and [RFC 8037].
+//!
+//! A verifier is created for one key and one algorithm, and rejects tokens that name another
+//! algorithm in their header. There are no symmetric algorithms, so a public key can never be
+//! used as an HMAC secret.
+//!
+//! ```
+//! use rama_boring::ec::{EcGroup, EcKey};
+//! use rama_boring::jws::{Algorithm, JwsSigner, JwsVerifier};
+//! use rama_boring::nid::Nid;
+//! use rama_boring::pkey::PKey;
+//!
+//! let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+//! let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+//!
+//! let signer = JwsSigner::new(Algorithm::ES256, key.clone()).unwrap();
+//! let token = signer.sign(b"hello").unwrap();
+//!
+//! let verifier = JwsVerifier::new(Algorithm::ES256, &key).unwrap();
+//! assert_eq!(verifier.verify(&token).unwrap().payload(), b"hello");
+//! assert!(JwsVerifier::new(Algorithm::RS256, &key).is_err());
+//! ```
+//!
+//! [RFC 7515]: https://www.rfc-editor.org/rfc/rfc7515.html
+//! [RFC 7518]: https://www.rfc-editor.org/rfc/rfc7518.html
+//! [RFC 8037]: https://www.rfc-editor.org/rfc/rfc8037.html
+
+use std::fmt;
+
+use crate::base64;
+use crate::ecdsa::EcdsaSig;
+use crate::error::ErrorStack;
+use crate::hash::MessageDigest;
+use crate::json::{Object, Value};
+use crate::jwk::Jwk;
+use crate::nid::Nid;
+use crate::pkey::{HasPublic, Id, PKey, PKeyRef, Private, Public};
+use crate::rsa::Padding;
+use crate::sign::{RsaPssSaltlen, Signer, Verifier};
+
+// RFC 7518 requires at least 2048-bit RSA keys
+const MIN_RSA_BITS: u32 = 2048;
+
+/// A JWS signature algorithm.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Algorithm(Scheme, Digest);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Scheme {
+    Pkcs1,
+    Pss,
+    Ecdsa,
+    EdDsa,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Digest {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl Algorithm {
+    /// RSASSA-PKCS1-v1_5 with SHA-256.
+    pub const RS256: Algorithm = Algorithm(Scheme::Pkcs1, Digest::Sha256);
+    /// RSASSA-PKCS1-v1_5 with SHA-384.
+    pub const RS384: Algorithm = Algorithm(Scheme::Pkcs1, Digest::Sha384);
+    /// RSASSA-PKCS1-v1_5 with SHA-512.
+    pub const RS512: Algorithm = Algorithm(Scheme::Pkcs1, Digest::Sha512);
+    /// RSASSA-PSS with SHA-256 and MGF1 with SHA-256.
+    pub const PS256: Algorithm = Algorithm(Scheme::Pss, Digest::Sha256);
+    /// RSASSA-PSS with SHA-384 and MGF1 with SHA-384.
+    pub const PS384: Algorithm = Algorithm(Scheme::Pss, Digest::Sha384);
+    /// RSASSA-PSS with SHA-512 and MGF1 with SHA-512.
+    pub const PS512: Algorithm = Algorithm(Scheme::Pss, Digest::Sha512);
+    /// ECDSA over P-256 with SHA-256.
+    pub const ES256: Algorithm = Algorithm(Scheme::Ecdsa, Digest::Sha256);
+    /// ECDSA over P-384 with SHA-384.
+    pub const ES384: Algorithm = Algorithm(Scheme::Ecdsa, Digest::Sha384);
+    /// ECDSA over P-521 with SHA-512.
+    pub const ES512: Algorithm = Algorithm(Scheme::Ecdsa, Digest::Sha512);
+    /// Ed25519.
+    pub const EDDSA: Algorithm = Algorithm(Scheme::EdDsa, Digest::Sha512);
+
+    const ALL: [Algorithm; 10] = [
+        Algorithm::RS256,
+        Algorithm::RS384,
+        Algorithm::RS512,
+        Algorithm::PS256,
+        Algorithm::PS384,
+        Algorithm::PS512,
+        Algorithm::ES256,
+        Algorithm::ES384,
+        Algorithm::ES512,
+        Algorithm::EDDSA,
+    ];
+
+    /// Returns the `alg` header value of the algorithm, such as `ES256`.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match (self.0, self.1) {
+            (Scheme::Pkcs1, Digest::Sha256) => "RS256",
+            (Scheme::Pkcs1, Digest::Sha384) => "RS384",
+            (Scheme::Pkcs1, Digest::Sha512) => "RS512",
+            (Scheme::Pss, Digest::Sha256) => "PS256",
+            (Scheme::Pss, Digest::Sha384) => "PS384",
+            (Scheme::Pss, Digest::Sha512) => "PS512",
+            (Scheme::Ecdsa, Digest::Sha256) => "ES256",
+            (Scheme::Ecdsa, Digest::Sha384) => "ES384",
+            (Scheme::Ecdsa, Digest::Sha512) => "ES512",
+            (Scheme::EdDsa, _) => "EdDSA",
+        }
+    }
+
+    /// Returns the algorithm with the `alg` header value `name`, if it is supported.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        Algorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name)
+    }
+
+    /// Returns the only algorithm `key` can be used with, for EC and Ed25519 keys.
+    ///
+    /// RSA keys can be used with several algorithms, so `None` is returned for them.
+    pub fn for_key<T>(key: &PKeyRef<T>) -> Result<Option<Algorithm>, ErrorStack> {
+        let algorithm = match key.id() {
+            Id::EC => match key.ec_key()?.group().curve_name() {
+                Some(Nid::X9_62_PRIME256V1) => Some(Algorithm::ES256),
+                Some(Nid::SECP384R1) => Some(Algorithm::ES384),
+                Some(Nid::SECP521R1) => Some(Algorithm::ES512),
+                _ => None,
+            },
+            Id::ED25519 => Some(Algorithm::EDDSA),
+            _ => None,
+        };
+        Ok(algorithm)
+    }
+
+    fn digest(self) -> MessageDigest {
+        match self.1 {
+            Digest::Sha256 => MessageDigest::sha256(),
+            Digest::Sha384 => MessageDigest::sha384(),
+            Digest::Sha512 => MessageDigest::sha512(),
+        }
+    }
+
+    /// Fails unless `key` is of the type and size the algorithm requires.
+    fn check_key<T>(self, key: &PKeyRef<T>) -> Result<(), ErrorStack> {
+        let compatible = match self.0 {
+            Scheme::Pkcs1 | Scheme::Pss => key.id() == Id::RSA && key.bits() >= MIN_RSA_BITS,
+            Scheme::Ecdsa | Scheme::EdDsa => Algorithm::for_key(key)? == Some(self),
+        };
+        if compatible {
+            Ok(())
+        } else {
+            Err(ErrorStack::internal_error_str(
+                "key not usable with the JWS algorithm",
+            ))
+        }
+    }
+}
+
+impl fmt::Debug for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The protected header of a JWS.
+#[derive(Clone, Debug)]
+pub struct JwsHeader {
+    algorithm: Algorithm,
+    key_id: Option<String>,
+    token_type: Option<String>,
+    content_type: Option<String>,
+}
+
+impl JwsHeader {
+    /// Decodes the header of the compact JWS `token`, without verifying its signature.
+    ///
+    /// This is meant to pick the key to verify the token with, using the `kid` parameter.
+    /// Nothing in the header can be trusted before the token is verified.
+    pub fn decode_unverified(token: &str) -> Result<JwsHeader, ErrorStack> {
+        let (header, _, _) = split(token)?;
+        JwsHeader::decode(header)
+    }
+
+    /// Returns the `alg` parameter.
+    #[must_use]
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Returns the `kid` parameter.
+    #[must_use]
+    pub fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    /// Returns the `typ` parameter, such as `JWT`.
+    #[must_use]
+    pub fn token_type(&self) -> Option<&str> {
+        self.token_type.as_deref()
+    }
+
+    /// Returns the `cty` parameter.
+    #[must_use]
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    fn decode(encoded: &str) -> Result<JwsHeader, ErrorStack> {
+        let json = String::from_utf8(base64::decode_block_url(encoded)?)
+            .map_err(ErrorStack::internal_error)?;
+        let Value::Object(object) = Value::parse(&json)? else {
+            return Err(malformed());
+        };
+        // No extension is understood, so critical ones cannot be honored
+        if object.get("crit").is_some() {
+            return Err(ErrorStack::internal_error_str(
+                "unsupported critical JWS header parameter",
+            ));
+        }
+        let algorithm = object.get_str("alg")?.ok_or_else(malformed)?;
+        Ok(JwsHeader {
+            algorithm: Algorithm::from_name(algorithm)
+                .ok_or_else(|| ErrorStack::internal_error_str("unsupported JWS algorithm"))?,
+            key_id: object.get_str("kid")?.map(str::to_owned),
+            token_type: object.get_str("typ")?.map(str::to_owned),
+            content_type: object.get_str("cty")?.map(str::to_owned),
+        })
+    }
+
+    fn encode(&self) -> String {
+        let mut object = Object::new();
+        object.insert("alg", self.algorithm.name());
+        for (name, value) in [
+            ("typ", &self.token_type),
+            ("cty", &self.content_type),
+            ("kid", &self.key_id),
+        ] {
+            if let Some(value) = value {
+                object.insert(name, value.as_str());
+            }
+        }
+        base64::encode_block_url(Value::from(object).to_json().as_bytes())
+    }
+}
+
+/// Signs payloads as compact JWS.
+#[derive(Clone)]
+pub struct JwsSigner {
+    key: PKey<Private>,
+    header: JwsHeader,
+}
+
+impl JwsSigner {
+    /// Creates a signer for `algorithm` with `key`.
+    ///
+    /// Fails if the key cannot be used with the algorithm: RSA keys must be at least 2048 bits
+    /// long, and EC keys must be on the curve of the algorithm.
+    pub fn new(algorithm: Algorithm, key: PKey<Private>) -> Result<JwsSigner, ErrorStack> {
+        algorithm.check_key(&key)?;
+        Ok(JwsSigner {
+            key,
+            header: JwsHeader {
+                algorithm,
+                key_id: None,
+                token_type: None,
+                content_type: None,
+            },
+        })
+    }
+
+    /// Creates a signer with the key, the `alg` and the `kid` of `jwk`.
+    ///
+    /// The `alg` parameter is only optional for EC and Ed25519 keys.
+    pub fn from_jwk(jwk: &Jwk<Private>) -> Result<JwsSigner, ErrorStack> {
+        let mut signer = JwsSigner::new(jwk_algorithm(jwk)?, jwk.key().to_owned())?;
+        if let Some(key_id) = jwk.key_id() {
+            signer.set_key_id(key_id);
+        }
+        Ok(signer)
+    }
+
+    /// Sets the `kid` header parameter.
+    pub fn set_key_id(&mut self, key_id: &str) {
+        self.header.key_id = Some(key_id.to_owned());
+    }
+
+    /// Sets the `typ` header parameter.
+    pub fn set_token_type(&mut self, token_type: &str) {
+        self.header.token_type = Some(token_type.to_owned());
+    }
+
+    /// Sets the `cty` header parameter.
+    pub fn set_content_type(&mut self, content_type: &str) {
+        self.header.content_type = Some(content_type.to_owned());
+    }
+
+    /// Returns the algorithm of the signer.
+    #[must_use]
+    pub fn algorithm(&self) -> Algorithm {
+        self.header.algorithm
+    }
+
+    /// Signs `payload`, returning the compact serialization.
+    pub fn sign(&self, payload: &[u8]) -> Result<String, ErrorStack> {
+        self.sign_with_header(&self.header, payload)
+    }
+
+    /// Signs a JWT, with a `typ` of `JWT` unless another one was set.
+    pub(crate) fn sign_jwt(&self, payload: &[u8]) -> Result<String, ErrorStack> {
+        let mut header = self.header.clone();
+        header.token_type.get_or_insert_with(|| "JWT".to_owned());
+        self.sign_with_header(&header, payload)
+    }
+
+    fn sign_with_header(&self, header: &JwsHeader, payload: &[u8]) -> Result<String, ErrorStack> {
+        let mut token = header.encode();
+        token.push('.');
+        token.push_str(&base64::encode_block_url(payload));
+        let signature = self.sign_input(token.as_bytes())?;
+        token.push('.');
+        token.push_str(&base64::encode_block_url(&signature));
+        Ok(token)
+    }
+
+    fn sign_input(&self, input: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+        let algorithm = self.header.algorithm;
+        let mut signer = match algorithm.0 {
+            Scheme::EdDsa => Signer::new_without_digest(&self.key)?,
+            _ => Signer::new(algorithm.digest(), &self.key)?,
+        };
+        if algorithm.0 == Scheme::Pss {
+            signer.set_rsa_padding(Padding::PKCS1_PSS)?;
+            signer.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+            signer.set_rsa_mgf1_md(algorithm.digest())?;
+        }
+        let signature = signer.sign_oneshot_to_vec(input)?;
+        if algorithm.0 != Scheme::Ecdsa {
+            return Ok(signature);
+        }
+
+        // JWS uses the fixed-length R || S encoding rather than DER
+        EcdsaSig::from_der(&signature)?.to_p1363(self.key.ec_key()?.group())
+    }
+}
+
+impl fmt::Debug for JwsSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwsSigner")
+            .field("header", &self.header)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Verifies compact JWS for one key and one algorithm.
+#[derive(Clone)]
+pub struct JwsVerifier {
+    algorithm: Algorithm,
+    key: PKey<Public>,
+}
+
+impl JwsVerifier {
+    /// Creates a verifier for `algorithm` with the public part of `key`.
+    ///
+    /// Fails if the key cannot be used with the algorithm, as [`JwsSigner::new`] does.
+    pub fn new<T>(algorithm: Algorithm, key: &PKeyRef<T>) -> Result<JwsVerifier, ErrorStack>
+    where
+        T: HasPublic,
+    {
+        algorithm.check_key(key)?;
+        let key = PKey::public_key_from_der(&key.public_key_to_der()?)?;
+        Ok(JwsVerifier { algorithm, key })
+    }
+
+    /// Creates a verifier with the key and the `alg` of `jwk`.
+    ///
+    /// The `alg` parameter is only optional for EC and Ed25519 keys.
+    pub fn from_jwk<T>(jwk: &Jwk<T>) -> Result<JwsVerifier, ErrorStack>
+    where
+        T: HasPublic,
+    {
+        JwsVerifier::new(jwk_algorithm(jwk)?, jwk.key())
+    }
+
+    /// Returns the algorithm of the verifier.
+    #[must_use]
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Verifies the compact JWS `token`, returning its header and payload.
+    ///
+    /// Fails if the signature is invalid, or if the `alg` header parameter is not the algorithm
+    /// of the verifier.
+    pub fn verify(&self, token: &str) -> Result<Jws, ErrorStack> {
+        let (header, payload, signature) = split(token)?;
+        let decoded = JwsHeader::decode(header)?;
+        if decoded.algorithm != self.algorithm {
+            return Err(ErrorStack::internal_error_str("unexpected JWS algorithm"));
+        }
+        let signature = base64::decode_block_url(signature)?;
+        // The signing input is the encoded header and payload
+        let input = &token[..header.len() + 1 + payload.len()];
+        if !self.verify_input(input.as_bytes(), &signature)? {
+            return Err(ErrorStack::internal_error_str("invalid JWS signature"));
+        }
+
+        Ok(Jws {
+            header: decoded,
+            payload: base64::decode_block_url(payload)?,
+        })
+    }
+
+    fn verify_input(&self, input: &[u8], signature: &[u8]) -> Result<bool, ErrorStack> {
+        let algorithm = self.algorithm;
+        let mut verifier = match algorithm.0 {
+            Scheme::EdDsa => Verifier::new_without_digest(&self.key)?,
+            _ => Verifier::new(algorithm.digest(), &self.key)?,
+        };
+        match algorithm.0 {
+            Scheme::Pss => {
+                verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
+                verifier.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+                verifier.set_rsa_mgf1_md(algorithm.digest())?;
+            }
+            Scheme::Ecdsa => {
+                let ec_key = self.key.ec_key()?;
+                let Ok(signature) = EcdsaSig::from_p1363(signature, ec_key.group()) else {
+                    return Ok(false);
+                };
+                return verifier.verify_oneshot(&signature.to_der()?, input);
+            }
+            Scheme::Pkcs1 | Scheme::EdDsa => {}
+        }
+        verifier.verify_oneshot(signature, input)
+    }
+}
+
+impl fmt::Debug for JwsVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwsVerifier")
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A verified JWS.
+#[derive(Clone, Debug)]
+pub struct Jws {
+    header: JwsHeader,
+    payload: Vec<u8>,
+}
+
+impl Jws {
+    /// Returns the protected header.
+    #[must_use]
+    pub fn header(&self) -> &JwsHeader {
+        &self.header
+    }
+
+    /// Returns the payload.
+    #[must_use]
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Consumes `self`, returning the payload.
+    #[must_use]
+    pub fn into_payload(self) -> Vec<u8> {
+        self.payload
+    }
+}
+
+/// Returns the algorithm the `alg` of `jwk` names, or the only one its key can be used with.
+fn jwk_algorithm<T>(jwk: &Jwk<T>) -> Result<Algorithm, ErrorStack> {
+    match jwk.algorithm() {
+        Some(name) => Algorithm::from_name(name)
+            .ok_or_else(|| ErrorStack::internal_error_str("unsupported JWS algorithm")),
+        None => Algorithm::for_key(jwk.key())?
+            .ok_or_else(|| ErrorStack::internal_error_str("JWK without an algorithm")),
+    }
+}
+
+/// Splits a compact JWS into its encoded header, payload and signature.
+fn split(token: &str) -> Result<(&str, &str, &str), ErrorStack> {
+    let mut parts = token.split('.');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(header), Some(payload), Some(signature), None) => Ok((header, payload, signature)),
+        _ => Err(malformed()),
+    }
+}
+
+fn malformed() -> ErrorStack {
+    ErrorStack::internal_error_str("malformed JWS")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ec::{EcGroup, EcKey};
+    use crate::ed25519::Ed25519PrivateKey;
+    use crate::rsa::Rsa;
+
+    fn ec_key(nid: Nid) -> PKey<Private> {
+        let group = EcGroup::from_curve_name(nid).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn sign_verify() {
+        let rsa = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let ed25519 = Ed25519PrivateKey::generate().unwrap().as_pkey().clone();
+        let keys = [
+            (Algorithm::RS256, rsa.clone()),
+            (Algorithm::RS384, rsa.clone()),
+            (Algorithm::RS512, rsa.clone()),
+            (Algorithm::PS256, rsa.clone()),
+            (Algorithm::PS384, rsa.clone()),
+            (Algorithm::PS512, rsa),
+            (Algorithm::ES256, ec_key(Nid::X9_62_PRIME256V1)),
+            (Algorithm::ES384, ec_key(Nid::SECP384R1)),
+            (Algorithm::ES512, ec_key(Nid::SECP521R1)),
+            (Algorithm::EDDSA, ed25519),
+        ];
+        for (algorithm, key) in keys {
+            assert_eq!(Algorithm::from_name(algorithm.name()), Some(algorithm));
+
+            let mut signer = JwsSigner::new(algorithm, key.clone()).unwrap();
+            signer.set_key_id("key-1");
+            let token = signer.sign(b"{\"hello\":\"world\"}").unwrap();
+            let header = JwsHeader::decode_unverified(&token).unwrap();
+            assert_eq!(header.algorithm(), algorithm);
+            assert_eq!(header.key_id(), Some("key-1"));
+
+            let verifier = JwsVerifier::new(algorithm, &key).unwrap();
+            let jws = verifier.verify(&token).unwrap();
+            assert_eq!(jws.payload(), b"{\"hello\":\"world\"}");
+
+            // Tampering with the payload or the signature
+            let (signed, signature) = token.rsplit_once('.').unwrap();
+            let forged = format!("{signed}.{}", base64::encode_block_url(&[0; 64]));
+            assert!(verifier.verify(&forged).is_err());
+            let (header, _) = signed.split_once('.').unwrap();
+            let forged = format!("{header}.e30.{signature}");
+            assert!(verifier.verify(&forged).is_err());
+        }
+    }
+
+    #[test]
+    fn ecdsa_signature_encoding() {
+        let key = ec_key(Nid::SECP521R1);
+        let signer = JwsSigner::new(Algorithm::ES512, key).unwrap();
+        let token = signer.sign(b"payload").unwrap();
+        let signature = token.rsplit('.').next().unwrap();
+        assert_eq!(base64::decode_block_url(signature).unwrap().len(), 132);
+    }
+
+    #[test]
+    fn rfc7515_es256() {
+        // RFC 7515, appendix A.3
+        let jwk = Jwk::<Public>::public_key_from_json(
+            r#"{"kty":"EC","crv":"P-256","x":"f83OJ3D2xF1Bg8vub9tLe1gHMzV76e8Tus9uPHvRVEU","y":"x_FEzRu9m36HLN_tue659LNpXW6pCyStikYjKIWI5a0"}"#,
+        )
+        .unwrap();
+        let verifier = JwsVerifier::from_jwk(&jwk).unwrap();
+        assert_eq!(verifier.algorithm(), Algorithm::ES256);
+        let jws = verifier
+            .verify(concat!(
+                "eyJhbGciOiJFUzI1NiJ9",
+                ".",
+                "eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ",
+                ".",
+                "DtEhU3ljbEg8L38VWAfUAqOyKAM6-Xx-F4GawxaepmXFCgfTjDxw5djxLa8ISlSApmWQxfKTUJqPP3-Kg6NU1Q",
+            ))
+            .unwrap();
+        assert!(jws.payload().starts_with(b"{\"iss\":\"joe\""));
+    }
+
+    #[test]
+    fn algorithm_confusion() {
+        let key = ec_key(Nid::X9_62_PRIME256V1);
+        assert!(JwsVerifier::new(Algorithm::ES384, &key).is_err());
+        assert!(JwsVerifier::new(Algorithm::RS256, &key).is_err());
+        assert!(JwsSigner::new(Algorithm::EDDSA, key.clone()).is_err());
+        let small_rsa = PKey::from_rsa(Rsa::generate(1024).unwrap()).unwrap();
+        assert!(JwsSigner::new(Algorithm::RS256, small_rsa).is_err());
+
+        let verifier = JwsVerifier::new(Algorithm::ES256, &key).unwrap();
+        let signer = JwsSigner::new(Algorithm::ES256, key).unwrap();
+        let token = signer.sign(b"payload").unwrap();
+        let (_, rest) = token.split_once('.').unwrap();
+        for header in [
+            r#"{"alg":"HS256"}"#,
+            r#"{"alg":"none"}"#,
+            r#"{"alg":"ES384"}"#,
+            r#"{"alg":"ES256","crit":["exp"],"exp":0}"#,
+        ] {
+            let forged = format!("{}.{rest}", base64::encode_block_url(header.as_bytes()));
+            assert!(verifier.verify(&forged).is_err(), "{header}");
+        }
+        assert!(verifier.verify(&format!("{token}.")).is_err());
+    }
+}
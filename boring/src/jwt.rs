@@ -0,0 +1,430 @@
+//! JSON Web Tokens.
+//!
+//! [`Claims`] is the [RFC 7519] claims set of a token, signed as a JWS with a
+//! [`JwsSigner`]. [`JwtValidator`] verifies the signature of a token and then its registered
+//! time, issuer and audience claims.
+//!
+//! ```
+//! use rama_boring::ec::{EcGroup, EcKey};
+//! use rama_boring::jws::{Algorithm, JwsSigner, JwsVerifier};
+//! use rama_boring::jwt::{Claims, JwtValidator};
+//! use rama_boring::nid::Nid;
+//! use rama_boring::pkey::PKey;
+//!
+//! let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+//! let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+//!
+//! let mut claims = Claims::new();
+//! claims.set_issuer("https://issuer.example");
+//! claims.set_audience(&["api"]);
+//! claims.set_expiration(2_000_000_000);
+//! let token = claims
+//!     .sign(&JwsSigner::new(Algorithm::ES256, key.clone()).unwrap())
+//!     .unwrap();
+//!
+//! let mut validator = JwtValidator::new(JwsVerifier::new(Algorithm::ES256, &key).unwrap());
+//! validator
+//!     .issuer("https://issuer.example")
+//!     .audience("api")
+//!     .time(1_900_000_000);
+//! let claims = validator.validate(&token).unwrap();
+//! assert_eq!(claims.issuer(), Some("https://issuer.example"));
+//! ```
+//!
+//! [RFC 7519]: https://www.rfc-editor.org/rfc/rfc7519.html
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::ErrorStack;
+use crate::json::{Object, Value};
+use crate::jws::{JwsSigner, JwsVerifier};
+
+const STRING_CLAIMS: [&str; 3] = ["iss", "sub", "jti"];
+const DATE_CLAIMS: [&str; 3] = ["exp", "nbf", "iat"];
+
+/// A JWT claims set.
+///
+/// The registered claims are checked to have the types RFC 7519 gives them, so their getters
+/// cannot fail.
+#[derive(Clone, Debug, Default)]
+pub struct Claims(Object);
+
+impl Claims {
+    /// Creates an empty claims set.
+    #[must_use]
+    pub fn new() -> Claims {
+        Claims::default()
+    }
+
+    /// Parses a claims set from its JSON object.
+    pub fn from_json(json: &str) -> Result<Claims, ErrorStack> {
+        let Value::Object(object) = Value::parse(json)? else {
+            return Err(malformed());
+        };
+        for name in STRING_CLAIMS.iter().chain(&DATE_CLAIMS).chain(&["aud"]) {
+            if let Some(value) = object.get(name) {
+                check_claim(name, value)?;
+            }
+        }
+        Ok(Claims(object))
+    }
+
+    /// Returns the claims set as a JSON object.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        self.0.write(&mut json);
+        json
+    }
+
+    /// Signs the claims set, returning the compact serialization of the token.
+    ///
+    /// The `typ` header parameter is `JWT`, unless the signer sets another one.
+    pub fn sign(&self, signer: &JwsSigner) -> Result<String, ErrorStack> {
+        signer.sign_jwt(self.to_json().as_bytes())
+    }
+
+    /// Returns the `iss` claim.
+    #[must_use]
+    pub fn issuer(&self) -> Option<&str> {
+        self.string("iss")
+    }
+
+    /// Sets the `iss` claim.
+    pub fn set_issuer(&mut self, issuer: &str) {
+        self.0.insert("iss", issuer);
+    }
+
+    /// Returns the `sub` claim.
+    #[must_use]
+    pub fn subject(&self) -> Option<&str> {
+        self.string("sub")
+    }
+
+    /// Sets the `sub` claim.
+    pub fn set_subject(&mut self, subject: &str) {
+        self.0.insert("sub", subject);
+    }
+
+    /// Returns the `aud` claim, which may be a single string.
+    #[must_use]
+    pub fn audience(&self) -> Vec<&str> {
+        match self.0.get("aud") {
+            Some(Value::String(audience)) => vec![audience],
+            Some(Value::Array(audiences)) => audiences.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Sets the `aud` claim, as a single string if there is only one audience.
+    pub fn set_audience(&mut self, audience: &[&str]) {
+        match audience {
+            [audience] => self.0.insert("aud", *audience),
+            _ => self.0.insert(
+                "aud",
+                Value::Array(audience.iter().map(|&audience| audience.into()).collect()),
+            ),
+        }
+    }
+
+    /// Returns the `exp` claim, in seconds since the epoch.
+    #[must_use]
+    pub fn expiration(&self) -> Option<i64> {
+        self.date("exp")
+    }
+
+    /// Sets the `exp` claim, in seconds since the epoch.
+    pub fn set_expiration(&mut self, expiration: i64) {
+        self.0.insert("exp", expiration);
+    }
+
+    /// Returns the `nbf` claim, in seconds since the epoch.
+    #[must_use]
+    pub fn not_before(&self) -> Option<i64> {
+        self.date("nbf")
+    }
+
+    /// Sets the `nbf` claim, in seconds since the epoch.
+    pub fn set_not_before(&mut self, not_before: i64) {
+        self.0.insert("nbf", not_before);
+    }
+
+    /// Returns the `iat` claim, in seconds since the epoch.
+    #[must_use]
+    pub fn issued_at(&self) -> Option<i64> {
+        self.date("iat")
+    }
+
+    /// Sets the `iat` claim, in seconds since the epoch.
+    pub fn set_issued_at(&mut self, issued_at: i64) {
+        self.0.insert("iat", issued_at);
+    }
+
+    /// Returns the `jti` claim.
+    #[must_use]
+    pub fn jwt_id(&self) -> Option<&str> {
+        self.string("jti")
+    }
+
+    /// Sets the `jti` claim.
+    pub fn set_jwt_id(&mut self, jwt_id: &str) {
+        self.0.insert("jti", jwt_id);
+    }
+
+    /// Returns the claim `name` as JSON.
+    #[must_use]
+    pub fn claim(&self, name: &str) -> Option<String> {
+        self.0.get(name).map(Value::to_json)
+    }
+
+    /// Sets the claim `name` to the JSON value `json`.
+    ///
+    /// Fails if `json` is malformed, or if it has the wrong type for a registered claim.
+    pub fn set_claim(&mut self, name: &str, json: &str) -> Result<(), ErrorStack> {
+        let value = Value::parse(json)?;
+        check_claim(name, &value)?;
+        self.0.insert(name, value);
+        Ok(())
+    }
+
+    fn string(&self, name: &str) -> Option<&str> {
+        self.0.get(name).and_then(Value::as_str)
+    }
+
+    fn date(&self, name: &str) -> Option<i64> {
+        self.0.get(name).and_then(Value::as_i64)
+    }
+}
+
+/// Fails if `value` has the wrong type for the registered claim `name`.
+fn check_claim(name: &str, value: &Value) -> Result<(), ErrorStack> {
+    let valid = if STRING_CLAIMS.contains(&name) {
+        value.as_str().is_some()
+    } else if DATE_CLAIMS.contains(&name) {
+        value.as_i64().is_some()
+    } else if name == "aud" {
+        value.as_str().is_some()
+            || value
+                .as_array()
+                .is_some_and(|values| values.iter().all(|value| value.as_str().is_some()))
+    } else {
+        true
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(malformed())
+    }
+}
+
+/// Validates JWTs.
+///
+/// A validator is configured once and can validate any number of tokens. The signature is
+/// verified first, then:
+///
+/// * the token must have an `exp` claim and must not have expired, and must not be used before
+///   its `nbf` claim;
+/// * the `iss` claim must match, if an issuer is required;
+/// * a token with an `aud` claim must list the configured audience. Tokens with an `aud` claim
+///   are rejected if no audience is configured.
+#[derive(Clone, Debug)]
+pub struct JwtValidator {
+    verifier: JwsVerifier,
+    issuer: Option<String>,
+    audience: Option<String>,
+    leeway: i64,
+    time: Option<i64>,
+    require_expiration: bool,
+}
+
+impl JwtValidator {
+    /// Creates a validator verifying token signatures with `verifier`.
+    #[must_use]
+    pub fn new(verifier: JwsVerifier) -> JwtValidator {
+        JwtValidator {
+            verifier,
+            issuer: None,
+            audience: None,
+            leeway: 0,
+            time: None,
+            require_expiration: true,
+        }
+    }
+
+    /// Requires the `iss` claim to be `issuer`.
+    pub fn issuer(&mut self, issuer: &str) -> &mut JwtValidator {
+        self.issuer = Some(issuer.to_owned());
+        self
+    }
+
+    /// Requires the `aud` claim to list `audience`.
+    pub fn audience(&mut self, audience: &str) -> &mut JwtValidator {
+        self.audience = Some(audience.to_owned());
+        self
+    }
+
+    /// Allows `leeway` seconds of clock skew when checking the `exp` and `nbf` claims.
+    pub fn leeway(&mut self, leeway: u32) -> &mut JwtValidator {
+        self.leeway = leeway.into();
+        self
+    }
+
+    /// Validates tokens at `time`, in seconds since the epoch, instead of the current time.
+    pub fn time(&mut self, time: i64) -> &mut JwtValidator {
+        self.time = Some(time);
+        self
+    }
+
+    /// Sets whether tokens without an `exp` claim are rejected, which they are by default.
+    ///
+    /// Passing `false` accepts tokens that never expire.
+    pub fn require_expiration(&mut self, require: bool) -> &mut JwtValidator {
+        self.require_expiration = require;
+        self
+    }
+
+    /// Verifies the signature of the compact JWT `token` and validates its claims.
+    pub fn validate(&self, token: &str) -> Result<Claims, ErrorStack> {
+        let jws = self.verifier.verify(token)?;
+        let payload = std::str::from_utf8(jws.payload()).map_err(ErrorStack::internal_error)?;
+        let claims = Claims::from_json(payload)?;
+
+        let now = match self.time {
+            Some(time) => time,
+            None => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_err(ErrorStack::internal_error)?;
+                i64::try_from(now.as_secs()).map_err(ErrorStack::internal_error)?
+            }
+        };
+        match claims.expiration() {
+            Some(expiration) if now.saturating_sub(self.leeway) >= expiration => {
+                return Err(ErrorStack::internal_error_str("JWT has expired"));
+            }
+            None if self.require_expiration => {
+                return Err(ErrorStack::internal_error_str("JWT without an expiration"));
+            }
+            _ => {}
+        }
+        if let Some(not_before) = claims.not_before() {
+            if now.saturating_add(self.leeway) < not_before {
+                return Err(ErrorStack::internal_error_str("JWT is not yet valid"));
+            }
+        }
+
+        if let Some(issuer) = &self.issuer {
+            if claims.issuer() != Some(issuer.as_str()) {
+                return Err(ErrorStack::internal_error_str("unexpected JWT issuer"));
+            }
+        }
+        let audience = claims.audience();
+        let audience_matches = match &self.audience {
+            Some(expected) => audience.contains(&expected.as_str()),
+            None => claims.0.get("aud").is_none(),
+        };
+        if !audience_matches {
+            return Err(ErrorStack::internal_error_str("unexpected JWT audience"));
+        }
+
+        Ok(claims)
+    }
+}
+
+fn malformed() -> ErrorStack {
+    ErrorStack::internal_error_str("malformed JWT claims")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ec::{EcGroup, EcKey};
+    use crate::jws::Algorithm;
+    use crate::nid::Nid;
+    use crate::pkey::{PKey, Private};
+
+    fn key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn claims() {
+        let mut claims = Claims::new();
+        claims.set_issuer("issuer");
+        claims.set_subject("subject");
+        claims.set_audience(&["a", "b"]);
+        claims.set_expiration(1_000);
+        claims.set_issued_at(100);
+        claims.set_claim("admin", "true").unwrap();
+        assert!(claims.set_claim("exp", "\"soon\"").is_err());
+        assert!(claims.set_claim("aud", "[1]").is_err());
+
+        let parsed = Claims::from_json(&claims.to_json()).unwrap();
+        assert_eq!(parsed.issuer(), Some("issuer"));
+        assert_eq!(parsed.subject(), Some("subject"));
+        assert_eq!(parsed.audience(), ["a", "b"]);
+        assert_eq!(parsed.expiration(), Some(1_000));
+        assert_eq!(parsed.issued_at(), Some(100));
+        assert_eq!(parsed.not_before(), None);
+        assert_eq!(parsed.claim("admin").as_deref(), Some("true"));
+
+        assert_eq!(
+            Claims::from_json(r#"{"exp":1300819380.5}"#)
+                .unwrap()
+                .expiration(),
+            Some(1300819380)
+        );
+        assert!(Claims::from_json(r#"{"nbf":"0"}"#).is_err());
+        assert!(Claims::from_json("[]").is_err());
+    }
+
+    #[test]
+    fn validate() {
+        let key = key();
+        let signer = JwsSigner::new(Algorithm::ES256, key.clone()).unwrap();
+        let mut claims = Claims::new();
+        claims.set_issuer("issuer");
+        claims.set_audience(&["api"]);
+        claims.set_not_before(1_000);
+        claims.set_expiration(2_000);
+        let token = claims.sign(&signer).unwrap();
+        let header = crate::jws::JwsHeader::decode_unverified(&token).unwrap();
+        assert_eq!(header.token_type(), Some("JWT"));
+
+        let verifier = JwsVerifier::new(Algorithm::ES256, &key).unwrap();
+        let mut validator = JwtValidator::new(verifier);
+        validator.issuer("issuer").audience("api").time(1_500);
+        assert_eq!(validator.validate(&token).unwrap().issuer(), Some("issuer"));
+
+        // Time checks, with and without leeway
+        assert!(validator.clone().time(999).validate(&token).is_err());
+        assert!(validator.clone().time(2_000).validate(&token).is_err());
+        validator.leeway(60);
+        assert!(validator.clone().time(950).validate(&token).is_ok());
+        assert!(validator.clone().time(2_059).validate(&token).is_ok());
+        assert!(validator.clone().time(2_060).validate(&token).is_err());
+
+        assert!(validator.clone().issuer("other").validate(&token).is_err());
+        assert!(validator
+            .clone()
+            .audience("other")
+            .validate(&token)
+            .is_err());
+        let mut no_audience = validator.clone();
+        no_audience.audience = None;
+        assert!(no_audience.validate(&token).is_err());
+
+        let mut claims = Claims::new();
+        claims.set_issuer("issuer");
+        claims.set_audience(&["api"]);
+        let token = claims.sign(&signer).unwrap();
+        assert!(validator.validate(&token).is_err());
+        validator.require_expiration(false);
+        assert!(validator.validate(&token).is_ok());
+
+        // Signed by another key
+        let other = JwsSigner::new(Algorithm::ES256, self::key()).unwrap();
+        assert!(validator.validate(&claims.sign(&other).unwrap()).is_err());
+    }
+}
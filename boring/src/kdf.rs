@@ -0,0 +1,553 @@
+//! Key derivation functions.
+//!
+//! This module provides HKDF ([RFC 5869]) over any [`MessageDigest`], the TLS 1.3 `HKDF-Expand-Label`
+//! construction ([RFC 8446, section 7.1]) and the TLS 1.2 PRF ([RFC 5246, section 5]).
+//!
+//! ```
+//! use rama_boring::hash::MessageDigest;
+//! use rama_boring::kdf::Prk;
+//!
+//! let prk = Prk::extract(MessageDigest::sha256(), b"input key material", b"salt").unwrap();
+//! let mut key = [0; 32];
+//! prk.expand(b"application key", &mut key).unwrap();
+//! ```
+//!
+//! [RFC 5869]: https://www.rfc-editor.org/rfc/rfc5869.html
+//! [RFC 8446, section 7.1]: https://www.rfc-editor.org/rfc/rfc8446.html#section-7.1
+//! [RFC 5246, section 5]: https://www.rfc-editor.org/rfc/rfc5246.html#section-5
+
+use openssl_macros::corresponds;
+use std::fmt;
+
+use crate::error::ErrorStack;
+use crate::hash::MessageDigest;
+use crate::hmac::Hmac;
+use crate::{cvt, ffi};
+
+const MAX_PRK_LEN: usize = ffi::EVP_MAX_MD_SIZE as usize;
+
+/// Derives `out.len()` bytes from `secret` using HKDF, performing both the extract and the
+/// expand steps.
+#[corresponds(HKDF)]
+pub fn hkdf(
+    digest: MessageDigest,
+    secret: &[u8],
+    salt: &[u8],
+    info: &[u8],
+    out: &mut [u8],
+) -> Result<(), ErrorStack> {
+    unsafe {
+        ffi::init();
+        cvt(ffi::HKDF(
+            out.as_mut_ptr(),
+            out.len(),
+            digest.as_ptr(),
+            secret.as_ptr(),
+            secret.len(),
+            salt.as_ptr(),
+            salt.len(),
+            info.as_ptr(),
+            info.len(),
+        ))
+    }
+}
+
+/// Performs the HKDF expand step, filling `out` from the pseudorandom key `prk`.
+///
+/// `out` can be at most 255 times the digest size long.
+#[corresponds(HKDF_expand)]
+pub fn hkdf_expand(
+    digest: MessageDigest,
+    prk: &[u8],
+    info: &[u8],
+    out: &mut [u8],
+) -> Result<(), ErrorStack> {
+    unsafe {
+        ffi::init();
+        cvt(ffi::HKDF_expand(
+            out.as_mut_ptr(),
+            out.len(),
+            digest.as_ptr(),
+            prk.as_ptr(),
+            prk.len(),
+            info.as_ptr(),
+            info.len(),
+        ))
+    }
+}
+
+/// Computes the TLS 1.2 PRF, filling `out` from `secret`, `label` and the concatenation of
+/// `seed1` and `seed2`.
+#[corresponds(CRYPTO_tls1_prf)]
+pub fn tls1_prf(
+    digest: MessageDigest,
+    secret: &[u8],
+    label: &[u8],
+    seed1: &[u8],
+    seed2: &[u8],
+    out: &mut [u8],
+) -> Result<(), ErrorStack> {
+    unsafe {
+        ffi::init();
+        cvt(ffi::CRYPTO_tls1_prf(
+            digest.as_ptr(),
+            out.as_mut_ptr(),
+            out.len(),
+            secret.as_ptr(),
+            secret.len(),
+            label.as_ptr().cast(),
+            label.len(),
+            seed1.as_ptr(),
+            seed1.len(),
+            seed2.as_ptr(),
+            seed2.len(),
+        ))
+    }
+}
+
+/// A pseudorandom key, the output of the HKDF extract step.
+///
+/// The key is cleared from memory when dropped.
+#[derive(Clone)]
+pub struct Prk {
+    digest: MessageDigest,
+    bytes: [u8; MAX_PRK_LEN],
+    len: usize,
+}
+
+impl Prk {
+    /// Performs the HKDF extract step.
+    #[corresponds(HKDF_extract)]
+    pub fn extract(digest: MessageDigest, secret: &[u8], salt: &[u8]) -> Result<Self, ErrorStack> {
+        let mut bytes = [0; MAX_PRK_LEN];
+        let mut len = 0;
+        unsafe {
+            ffi::init();
+            cvt(ffi::HKDF_extract(
+                bytes.as_mut_ptr(),
+                &mut len,
+                digest.as_ptr(),
+                secret.as_ptr(),
+                secret.len(),
+                salt.as_ptr(),
+                salt.len(),
+            ))?;
+        }
+        Ok(Self { digest, bytes, len })
+    }
+
+    /// Uses `bytes` as a pseudorandom key, such as a TLS 1.3 secret that was derived elsewhere.
+    ///
+    /// `bytes` must be exactly as long as the digest output.
+    pub fn from_bytes(digest: MessageDigest, bytes: &[u8]) -> Result<Self, ErrorStack> {
+        if bytes.len() != digest.size() {
+            return Err(ErrorStack::internal_error_str(
+                "pseudorandom key length does not match the digest",
+            ));
+        }
+        let mut prk = Self {
+            digest,
+            bytes: [0; MAX_PRK_LEN],
+            len: bytes.len(),
+        };
+        prk.bytes[..bytes.len()].copy_from_slice(bytes);
+        Ok(prk)
+    }
+
+    /// The digest this key is used with.
+    #[must_use]
+    pub fn digest(&self) -> MessageDigest {
+        self.digest
+    }
+
+    /// The secret bytes of this key.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Performs the HKDF expand step, filling `out`.
+    #[corresponds(HKDF_expand)]
+    pub fn expand(&self, info: &[u8], out: &mut [u8]) -> Result<(), ErrorStack> {
+        hkdf_expand(self.digest, self.as_bytes(), info, out)
+    }
+
+    /// Performs TLS 1.3 `HKDF-Expand-Label`, filling `out`.
+    ///
+    /// `label` is given without the `"tls13 "` prefix, which is added automatically.
+    pub fn expand_label(
+        &self,
+        label: &[u8],
+        context: &[u8],
+        out: &mut [u8],
+    ) -> Result<(), ErrorStack> {
+        let info = hkdf_label(label, context, out.len())?;
+        self.expand(&info, out)
+    }
+
+    /// Returns an [`Expander`] producing the HKDF expand output for `info` incrementally.
+    #[must_use]
+    pub fn expander(&self, info: &[u8]) -> Expander<'_> {
+        Expander::new(self, info.to_vec(), 255 * self.len)
+    }
+
+    /// Returns an [`Expander`] producing the `HKDF-Expand-Label` output incrementally.
+    ///
+    /// The output length is part of the label, so it must be known upfront and the expander
+    /// produces at most `len` bytes.
+    pub fn label_expander(
+        &self,
+        label: &[u8],
+        context: &[u8],
+        len: usize,
+    ) -> Result<Expander<'_>, ErrorStack> {
+        let info = hkdf_label(label, context, len)?;
+        Ok(Expander::new(self, info, len.min(255 * self.len)))
+    }
+}
+
+impl fmt::Debug for Prk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Prk")
+            .field("digest", &self.digest.type_())
+            .field("key", &"[redacted]")
+            .finish()
+    }
+}
+
+impl Drop for Prk {
+    fn drop(&mut self) {
+        // SAFETY: pointer and length are valid
+        unsafe {
+            ffi::OPENSSL_cleanse(self.bytes.as_mut_ptr().cast(), self.bytes.len());
+        }
+    }
+}
+
+/// Encodes the `HkdfLabel` structure of RFC 8446.
+fn hkdf_label(label: &[u8], context: &[u8], len: usize) -> Result<Vec<u8>, ErrorStack> {
+    const PREFIX: &[u8] = b"tls13 ";
+
+    let len = u16::try_from(len)
+        .map_err(|_| ErrorStack::internal_error_str("output too long for HKDF-Expand-Label"))?;
+    let label_len = u8::try_from(PREFIX.len() + label.len())
+        .map_err(|_| ErrorStack::internal_error_str("label too long"))?;
+    let context_len = u8::try_from(context.len())
+        .map_err(|_| ErrorStack::internal_error_str("context too long"))?;
+
+    let mut info = Vec::with_capacity(4 + label_len as usize + context.len());
+    info.extend_from_slice(&len.to_be_bytes());
+    info.push(label_len);
+    info.extend_from_slice(PREFIX);
+    info.extend_from_slice(label);
+    info.push(context_len);
+    info.extend_from_slice(context);
+    Ok(info)
+}
+
+/// Produces HKDF expand output incrementally.
+///
+/// Output produced in several calls to [`fill`](Expander::fill) is identical to the output of a
+/// single [`Prk::expand`] call of the combined length.
+pub struct Expander<'a> {
+    prk: &'a Prk,
+    info: Vec<u8>,
+    counter: u8,
+    block: [u8; MAX_PRK_LEN],
+    block_pos: usize,
+    remaining: usize,
+}
+
+impl<'a> Expander<'a> {
+    fn new(prk: &'a Prk, info: Vec<u8>, remaining: usize) -> Self {
+        Self {
+            prk,
+            info,
+            counter: 0,
+            block: [0; MAX_PRK_LEN],
+            block_pos: prk.len,
+            remaining,
+        }
+    }
+
+    /// The number of bytes that can still be produced.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Fills `out` with the next bytes of output.
+    ///
+    /// Fails without producing output if fewer than `out.len()` bytes remain.
+    pub fn fill(&mut self, out: &mut [u8]) -> Result<(), ErrorStack> {
+        if out.len() > self.remaining {
+            return Err(ErrorStack::internal_error_str("HKDF output exhausted"));
+        }
+
+        let mut written = 0;
+        while written < out.len() {
+            if self.block_pos == self.prk.len {
+                self.next_block()?;
+            }
+            let len = (out.len() - written).min(self.prk.len - self.block_pos);
+            out[written..written + len]
+                .copy_from_slice(&self.block[self.block_pos..self.block_pos + len]);
+            self.block_pos += len;
+            written += len;
+        }
+        self.remaining -= out.len();
+        Ok(())
+    }
+
+    /// Computes `T(i) = HMAC(PRK, T(i - 1) || info || i)`.
+    fn next_block(&mut self) -> Result<(), ErrorStack> {
+        let mut hmac = Hmac::init(self.prk.as_bytes(), &self.prk.digest)?;
+        if self.counter > 0 {
+            hmac.update(&self.block[..self.prk.len])?;
+        }
+        self.counter += 1;
+        hmac.update(&self.info)?;
+        hmac.update(&[self.counter])?;
+
+        let mut block = hmac.finalize()?;
+        self.block[..block.len()].copy_from_slice(&block);
+        self.block_pos = 0;
+        unsafe {
+            ffi::OPENSSL_cleanse(block.as_mut_ptr().cast(), block.len());
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Expander<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Expander")
+            .field("remaining", &self.remaining)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Expander<'_> {
+    fn drop(&mut self) {
+        // SAFETY: pointer and length are valid
+        unsafe {
+            ffi::OPENSSL_cleanse(self.block.as_mut_ptr().cast(), self.block.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex::FromHex;
+
+    struct Rfc5869Vector {
+        digest: fn() -> MessageDigest,
+        ikm: &'static str,
+        salt: &'static str,
+        info: &'static str,
+        prk: &'static str,
+        okm: &'static str,
+    }
+
+    // RFC 5869 appendix A, test cases 1, 3 and 4
+    const RFC5869_VECTORS: &[Rfc5869Vector] = &[
+        Rfc5869Vector {
+            digest: MessageDigest::sha256,
+            ikm: "0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b",
+            salt: "000102030405060708090a0b0c",
+            info: "f0f1f2f3f4f5f6f7f8f9",
+            prk: "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5",
+            okm: "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865",
+        },
+        Rfc5869Vector {
+            digest: MessageDigest::sha256,
+            ikm: "0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b",
+            salt: "",
+            info: "",
+            prk: "19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04",
+            okm: "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8",
+        },
+        Rfc5869Vector {
+            digest: MessageDigest::sha1,
+            ikm: "0b0b0b0b0b0b0b0b0b0b0b",
+            salt: "000102030405060708090a0b0c",
+            info: "f0f1f2f3f4f5f6f7f8f9",
+            prk: "9b6c18c432a7bf8f0e71c8eb88f4b30baa2ba243",
+            okm: "085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2c22e422478d305f3f896",
+        },
+    ];
+
+    #[test]
+    fn rfc5869() {
+        for v in RFC5869_VECTORS {
+            let digest = (v.digest)();
+            let ikm = Vec::from_hex(v.ikm).unwrap();
+            let salt = Vec::from_hex(v.salt).unwrap();
+            let info = Vec::from_hex(v.info).unwrap();
+            let okm = Vec::from_hex(v.okm).unwrap();
+
+            let prk = Prk::extract(digest, &ikm, &salt).unwrap();
+            assert_eq!(hex::encode(prk.as_bytes()), v.prk);
+
+            let mut out = vec![0; okm.len()];
+            prk.expand(&info, &mut out).unwrap();
+            assert_eq!(out, okm);
+
+            let mut out = vec![0; okm.len()];
+            hkdf_expand(digest, prk.as_bytes(), &info, &mut out).unwrap();
+            assert_eq!(out, okm);
+
+            let mut out = vec![0; okm.len()];
+            hkdf(digest, &ikm, &salt, &info, &mut out).unwrap();
+            assert_eq!(out, okm);
+
+            let mut expander = prk.expander(&info);
+            let mut out = vec![0; okm.len()];
+            for chunk in out.chunks_mut(5) {
+                expander.fill(chunk).unwrap();
+            }
+            assert_eq!(out, okm);
+        }
+    }
+
+    #[test]
+    fn expand_too_long() {
+        let prk = Prk::extract(MessageDigest::sha256(), b"secret", b"").unwrap();
+        let mut out = vec![0; 255 * 32 + 1];
+        assert!(prk.expand(b"", &mut out).is_err());
+
+        let mut expander = prk.expander(b"");
+        assert_eq!(expander.remaining(), 255 * 32);
+        expander.fill(&mut out[..255 * 32]).unwrap();
+        assert!(expander.fill(&mut [0]).is_err());
+    }
+
+    #[test]
+    fn tls13_derived_secret() {
+        // The early secret and "derived" secret of a TLS 1.3 handshake without PSK, see RFC 8448
+        let digest = MessageDigest::sha256();
+        let early_secret = Prk::extract(digest, &[0; 32], &[]).unwrap();
+        assert_eq!(
+            hex::encode(early_secret.as_bytes()),
+            "33ad0a1c607ec03b09e6cd9893680ce210adf300aa1f2660e1b22e10f170f92a"
+        );
+
+        let empty_hash = crate::hash::hash(digest, b"").unwrap();
+        let mut derived = [0; 32];
+        early_secret
+            .expand_label(b"derived", &empty_hash, &mut derived)
+            .unwrap();
+        assert_eq!(
+            hex::encode(derived),
+            "6f2615a108c702c5678f54fc9dbab69716c076189c48250cebeac3576c3611ba"
+        );
+
+        let mut expander = early_secret
+            .label_expander(b"derived", &empty_hash, 32)
+            .unwrap();
+        let mut streamed = [0; 32];
+        expander.fill(&mut streamed[..7]).unwrap();
+        expander.fill(&mut streamed[7..]).unwrap();
+        assert_eq!(streamed, derived);
+        assert!(expander.fill(&mut [0]).is_err());
+    }
+
+    #[test]
+    fn label_too_long() {
+        let prk = Prk::extract(MessageDigest::sha256(), b"secret", b"").unwrap();
+        let mut out = [0; 16];
+        assert!(prk.expand_label(&[b'a'; 250], b"", &mut out).is_err());
+        assert!(prk.expand_label(b"key", &[0; 256], &mut out).is_err());
+    }
+
+    #[test]
+    fn from_bytes() {
+        let digest = MessageDigest::sha384();
+        let prk = Prk::extract(digest, b"secret", b"salt").unwrap();
+        let imported = Prk::from_bytes(digest, prk.as_bytes()).unwrap();
+
+        let (mut a, mut b) = ([0; 48], [0; 48]);
+        prk.expand_label(b"key", b"", &mut a).unwrap();
+        imported.expand_label(b"key", b"", &mut b).unwrap();
+        assert_eq!(a, b);
+
+        assert!(Prk::from_bytes(MessageDigest::sha256(), prk.as_bytes()).is_err());
+        assert!(format!("{prk:?}").contains("redacted"));
+    }
+
+    // The TLS 1.2 PRF test vector for SHA-256 posted to the IETF TLS working group list
+    #[test]
+    fn tls1_prf_sha256() {
+        let secret = Vec::from_hex("9bbe436ba940f017b17652849a71db35").unwrap();
+        let seed = Vec::from_hex("a0ba9f936cda311827a6f796ffd5198c").unwrap();
+        let expected = Vec::from_hex(concat!(
+            "e3f229ba727be17b8d122620557cd453c2aab21d07c3d495329b52d4e61edb5a",
+            "6b301791e90d35c9c9a46b4e14baf9af0fa022f7077def17abfd3797c0564bab",
+            "4fbc91666e9def9b97fce34f796789baa48082d122ee42c5a72e5a5110fff701",
+            "87347b66",
+        ))
+        .unwrap();
+
+        let mut out = [0; 100];
+        tls1_prf(
+            MessageDigest::sha256(),
+            &secret,
+            b"test label",
+            &seed[..7],
+            &seed[7..],
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(out[..], expected[..]);
+    }
+
+    #[test]
+    fn tls1_prf_deterministic() {
+        let digest = MessageDigest::sha256();
+        let mut a = [0; 48];
+        let mut b = [0; 100];
+        tls1_prf(
+            digest,
+            b"secret",
+            b"master secret",
+            b"client",
+            b"server",
+            &mut a,
+        )
+        .unwrap();
+        tls1_prf(
+            digest,
+            b"secret",
+            b"master secret",
+            b"client",
+            b"server",
+            &mut b,
+        )
+        .unwrap();
+        assert_eq!(a[..], b[..48]);
+
+        tls1_prf(
+            digest,
+            b"secret",
+            b"key expansion",
+            b"client",
+            b"server",
+            &mut b,
+        )
+        .unwrap();
+        assert_ne!(a[..], b[..48]);
+
+        // Only the concatenation of the seeds matters
+        tls1_prf(
+            digest,
+            b"secret",
+            b"master secret",
+            b"clientserver",
+            b"",
+            &mut b,
+        )
+        .unwrap();
+        assert_eq!(a[..], b[..48]);
+    }
+}
@@ -115,12 +115,16 @@ use crate::error::ErrorStack;
 mod macros;
 
 mod bio;
+mod der;
+mod json;
 pub mod libc_types;
 #[macro_use]
 mod util;
+pub mod aead;
 pub mod aes;
 pub mod asn1;
 pub mod base64;
+pub mod blind_rsa;
 pub mod bn;
 pub mod conf;
 pub mod derive;
@@ -134,21 +138,32 @@ pub mod ex_data;
 pub mod hash;
 pub mod hmac;
 pub mod hpke;
+pub mod jwk;
+pub mod jws;
+pub mod jwt;
+pub mod kdf;
+pub mod mac;
 pub mod memcmp;
-// pub mod mlkem; // TODO
+pub mod mlkem;
 pub mod nid;
 pub mod pkcs12;
 pub mod pkcs5;
+pub mod pkcs7;
 pub mod pkey;
 pub mod rand;
 pub mod rsa;
+pub mod secret;
 pub mod sha;
 pub mod sign;
+pub mod slhdsa;
+pub mod spake2;
 pub mod srtp;
+pub mod ssh;
 pub mod ssl;
 pub mod stack;
 pub mod string;
 pub mod symm;
+pub mod trust_token;
 pub mod version;
 pub mod x25519;
 pub mod x509;
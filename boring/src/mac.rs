@@ -0,0 +1,319 @@
+//! Message authentication codes.
+//!
+//! [`Hmac`], [`Cmac`] and [`Poly1305`] share the [`Mac`] trait: data is fed in with
+//! [`update`](Mac::update), and the tag is either produced with [`finalize`](Mac::finalize) or
+//! checked in constant time with [`verify`](Mac::verify). Cloning a MAC in progress copies its
+//! state, so a common prefix only has to be processed once.
+//!
+//! ```
+//! use rama_boring::hash::MessageDigest;
+//! use rama_boring::mac::{Hmac, Mac};
+//!
+//! let mut prefix = Hmac::init(b"key", &MessageDigest::sha384()).unwrap();
+//! prefix.update(b"common prefix, ").unwrap();
+//!
+//! let mut hmac = prefix.clone();
+//! hmac.update(b"first message").unwrap();
+//! let tag = hmac.finalize().unwrap();
+//!
+//! let mut hmac = prefix.clone();
+//! hmac.update(b"first message").unwrap();
+//! assert!(hmac.verify(&tag).unwrap());
+//! ```
+
+use openssl_macros::corresponds;
+use std::fmt;
+
+use crate::error::ErrorStack;
+use crate::memcmp;
+use crate::symm::Cipher;
+use crate::{cvt, cvt_p, ffi};
+
+pub use crate::hmac::Hmac;
+
+/// A message authentication code computation.
+pub trait Mac: Clone {
+    /// Feeds `data` into the MAC.
+    fn update(&mut self, data: &[u8]) -> Result<(), ErrorStack>;
+
+    /// Returns the tag over all data fed in so far.
+    fn finalize(self) -> Result<Vec<u8>, ErrorStack>;
+
+    /// Checks `tag` against the tag over all data fed in so far, in constant time.
+    ///
+    /// Truncated tags are rejected.
+    fn verify(self, tag: &[u8]) -> Result<bool, ErrorStack> {
+        let expected = self.finalize()?;
+        Ok(expected.len() == tag.len() && memcmp::eq(&expected, tag))
+    }
+}
+
+impl Mac for Hmac {
+    #[corresponds(HMAC_Update)]
+    fn update(&mut self, data: &[u8]) -> Result<(), ErrorStack> {
+        Hmac::update(self, data)
+    }
+
+    #[corresponds(HMAC_Final)]
+    fn finalize(self) -> Result<Vec<u8>, ErrorStack> {
+        Hmac::finalize(self)
+    }
+}
+
+/// CMAC ([RFC 4493]) over a block cipher, usually AES.
+///
+/// [RFC 4493]: https://www.rfc-editor.org/rfc/rfc4493.html
+pub struct Cmac(*mut ffi::CMAC_CTX);
+
+// SAFETY: the context is only accessed through `&mut self`
+unsafe impl Send for Cmac {}
+unsafe impl Sync for Cmac {}
+
+impl Cmac {
+    /// Creates an AES-CMAC computation, using AES-128 or AES-256 depending on the key length.
+    pub fn aes(key: &[u8]) -> Result<Self, ErrorStack> {
+        let cipher = match key.len() {
+            16 => Cipher::aes_128_cbc(),
+            32 => Cipher::aes_256_cbc(),
+            _ => {
+                return Err(ErrorStack::internal_error_str(
+                    "invalid AES-CMAC key length",
+                ))
+            }
+        };
+        Self::new(cipher, key)
+    }
+
+    /// Creates a CMAC computation over `cipher`, which should be the CBC mode of a block cipher
+    /// such as [`Cipher::aes_128_cbc`].
+    #[corresponds(CMAC_Init)]
+    pub fn new(cipher: Cipher, key: &[u8]) -> Result<Self, ErrorStack> {
+        unsafe {
+            ffi::init();
+            let ctx = Self(cvt_p(ffi::CMAC_CTX_new())?);
+            cvt(ffi::CMAC_Init(
+                ctx.0,
+                key.as_ptr().cast(),
+                key.len(),
+                cipher.as_ptr(),
+                // ENGINE api is deprecated
+                std::ptr::null_mut(),
+            ))?;
+            Ok(ctx)
+        }
+    }
+}
+
+impl Mac for Cmac {
+    #[corresponds(CMAC_Update)]
+    fn update(&mut self, data: &[u8]) -> Result<(), ErrorStack> {
+        unsafe { cvt(ffi::CMAC_Update(self.0, data.as_ptr(), data.len())) }
+    }
+
+    #[corresponds(CMAC_Final)]
+    fn finalize(self) -> Result<Vec<u8>, ErrorStack> {
+        let mut out = vec![0; ffi::EVP_MAX_BLOCK_LENGTH as usize];
+        let mut out_len = 0;
+        unsafe {
+            cvt(ffi::CMAC_Final(self.0, out.as_mut_ptr(), &mut out_len))?;
+        }
+        out.truncate(out_len);
+        Ok(out)
+    }
+}
+
+impl Clone for Cmac {
+    #[corresponds(CMAC_CTX_copy)]
+    fn clone(&self) -> Self {
+        unsafe {
+            let ctx = ffi::CMAC_CTX_new();
+            assert!(!ctx.is_null());
+            let clone = Self(ctx);
+            assert_eq!(ffi::CMAC_CTX_copy(clone.0, self.0), 1);
+            clone
+        }
+    }
+}
+
+impl Drop for Cmac {
+    fn drop(&mut self) {
+        unsafe { ffi::CMAC_CTX_free(self.0) }
+    }
+}
+
+impl fmt::Debug for Cmac {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cmac").finish_non_exhaustive()
+    }
+}
+
+// BoringSSL aligns the state within the buffer itself, so copies must keep the same alignment
+#[derive(Clone)]
+#[repr(C, align(64))]
+struct Poly1305State(ffi::poly1305_state);
+
+/// The Poly1305 one-time authenticator ([RFC 8439, section 2.5]).
+///
+/// A Poly1305 key must only ever be used to authenticate a single message. Cloning a computation
+/// is fine as long as only one of the clones is used to produce a tag that is revealed.
+///
+/// [RFC 8439, section 2.5]: https://www.rfc-editor.org/rfc/rfc8439.html#section-2.5
+#[derive(Clone)]
+pub struct Poly1305 {
+    state: Box<Poly1305State>,
+}
+
+impl Poly1305 {
+    /// The length of a Poly1305 key.
+    pub const KEY_LEN: usize = 32;
+
+    /// The length of a Poly1305 tag.
+    pub const TAG_LEN: usize = 16;
+
+    /// Creates a Poly1305 computation with the one-time `key`.
+    #[corresponds(CRYPTO_poly1305_init)]
+    #[must_use]
+    pub fn new(key: &[u8; Self::KEY_LEN]) -> Self {
+        let mut state = Box::new(Poly1305State(
+            [0; std::mem::size_of::<ffi::poly1305_state>()],
+        ));
+        unsafe {
+            ffi::init();
+            ffi::CRYPTO_poly1305_init(&mut state.0, key.as_ptr());
+        }
+        Self { state }
+    }
+
+    /// Computes the tag of `data` in one go.
+    #[must_use]
+    pub fn tag(key: &[u8; Self::KEY_LEN], data: &[u8]) -> [u8; Self::TAG_LEN] {
+        let mut poly1305 = Self::new(key);
+        poly1305.update_state(data);
+        poly1305.finish_state()
+    }
+
+    fn update_state(&mut self, data: &[u8]) {
+        unsafe { ffi::CRYPTO_poly1305_update(&mut self.state.0, data.as_ptr(), data.len()) }
+    }
+
+    fn finish_state(mut self) -> [u8; Self::TAG_LEN] {
+        let mut tag = [0; Self::TAG_LEN];
+        unsafe { ffi::CRYPTO_poly1305_finish(&mut self.state.0, tag.as_mut_ptr()) };
+        tag
+    }
+}
+
+impl Mac for Poly1305 {
+    #[corresponds(CRYPTO_poly1305_update)]
+    fn update(&mut self, data: &[u8]) -> Result<(), ErrorStack> {
+        self.update_state(data);
+        Ok(())
+    }
+
+    #[corresponds(CRYPTO_poly1305_finish)]
+    fn finalize(self) -> Result<Vec<u8>, ErrorStack> {
+        Ok(self.finish_state().to_vec())
+    }
+}
+
+impl Drop for Poly1305 {
+    fn drop(&mut self) {
+        // SAFETY: pointer and length are valid
+        unsafe {
+            ffi::OPENSSL_cleanse(self.state.0.as_mut_ptr().cast(), self.state.0.len());
+        }
+    }
+}
+
+impl fmt::Debug for Poly1305 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Poly1305").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::MessageDigest;
+    use hex::FromHex;
+
+    fn check<M: Mac>(mac: M, message: &[u8], expected: &str) {
+        let expected = Vec::from_hex(expected).unwrap();
+
+        let mut one_shot = mac.clone();
+        one_shot.update(message).unwrap();
+        assert_eq!(one_shot.finalize().unwrap(), expected);
+
+        let mut incremental = mac.clone();
+        for chunk in message.chunks(3) {
+            incremental.update(chunk).unwrap();
+        }
+        let mut other = incremental.clone();
+        assert!(incremental.verify(&expected).unwrap());
+
+        other.update(b"more").unwrap();
+        assert!(!other.clone().verify(&expected).unwrap());
+        assert!(!mac.clone().verify(&expected[..expected.len() - 1]).unwrap());
+    }
+
+    #[test]
+    fn hmac_rfc4231() {
+        // Test case 1
+        let key = [0x0b; 20];
+        check(
+            Hmac::init(&key, &MessageDigest::sha224()).unwrap(),
+            b"Hi There",
+            "896fb1128abbdf196832107cd49df33f47b4b1169912ba4f53684b22",
+        );
+        check(
+            Hmac::init(&key, &MessageDigest::sha256()).unwrap(),
+            b"Hi There",
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+        );
+        check(
+            Hmac::init(&key, &MessageDigest::sha384()).unwrap(),
+            b"Hi There",
+            "afd03944d84895626b0825f4ab46907f15f9dadbe4101ec682aa034c7cebc59cfaea9ea9076ede7f4af152e8b2fa9cb6",
+        );
+    }
+
+    #[test]
+    fn hmac_matches_hash_hmac() {
+        let mut hmac = Hmac::init(b"key", &MessageDigest::sha512()).unwrap();
+        hmac.update(b"message").unwrap();
+        assert_eq!(
+            hmac.finalize().unwrap(),
+            crate::hash::hmac_sha512(b"key", b"message").unwrap()
+        );
+    }
+
+    #[test]
+    fn cmac_rfc4493() {
+        let key = Vec::from_hex("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+        check(
+            Cmac::aes(&key).unwrap(),
+            b"",
+            "bb1d6929e95937287fa37d129b756746",
+        );
+        check(
+            Cmac::aes(&key).unwrap(),
+            &Vec::from_hex("6bc1bee22e409f96e93d7e117393172a").unwrap(),
+            "070a16b46b4d4144f79bdd9dd04a287c",
+        );
+        assert!(Cmac::aes(&key[..15]).is_err());
+    }
+
+    #[test]
+    fn poly1305_rfc8439() {
+        // Section 2.5.2
+        let key = <[u8; 32]>::from_hex(
+            "85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b",
+        )
+        .unwrap();
+        let message = b"Cryptographic Forum Research Group";
+        let expected = "a8061dc1305136c6c22b8baf0c0127a9";
+
+        check(Poly1305::new(&key), message, expected);
+        assert_eq!(hex::encode(Poly1305::tag(&key, message)), expected);
+    }
+}
@@ -74,6 +74,25 @@ macro_rules! private_key_to_pem {
     }
 }
 
+macro_rules! private_key_to_pem_secret {
+    ($(#[$m:meta])* $n:ident, $f:path) => {
+        $(#[$m])*
+        pub fn $n(&self) -> Result<crate::secret::SecretBytes, crate::error::ErrorStack> {
+            unsafe {
+                let bio = crate::bio::MemBio::new()?;
+                cvt($f(bio.as_ptr(),
+                        self.as_ptr(),
+                        ptr::null(),
+                        ptr::null_mut(),
+                        -1,
+                        None,
+                        ptr::null_mut()))?;
+                Ok(crate::secret::SecretBytes::from(bio.get_buf()))
+            }
+        }
+    }
+}
+
 macro_rules! to_pem {
     ($(#[$m:meta])* $n:ident, $f:path) => {
         $(#[$m])*
@@ -103,6 +122,22 @@ macro_rules! to_der {
     };
 }
 
+macro_rules! to_der_secret {
+    ($(#[$m:meta])* $n:ident, $f:path) => {
+        $(#[$m])*
+        pub fn $n(&self) -> Result<crate::secret::SecretBytes, crate::error::ErrorStack> {
+            unsafe {
+                let len = crate::cvt_nz($f(::foreign_types::ForeignTypeRef::as_ptr(self),
+                                        ptr::null_mut()))?;
+                let mut buf = crate::secret::SecretBytes::zeroed(len.get());
+                crate::cvt($f(::foreign_types::ForeignTypeRef::as_ptr(self),
+                              &mut buf.as_mut_ptr()))?;
+                Ok(buf)
+            }
+        }
+    };
+}
+
 macro_rules! from_der {
     ($(#[$m:meta])* $n:ident, $t:ty, $f:path, $len_ty:ty) => {
         $(#[$m])*
@@ -139,13 +174,13 @@ macro_rules! foreign_type_and_impl_send_sync {
         $(fn clone = $clone:expr;)*
 
         $(#[$owned_attr:meta])*
-        pub struct $owned:ident;
+        $vis:vis struct $owned:ident;
     )
         => {
             foreign_type! {
                 $(#[$impl_attr])*
                 $(#[$owned_attr])*
-                pub unsafe type $owned: Send + Sync {
+                $vis unsafe type $owned: Send + Sync {
                     type CType = $ctype;
                     fn drop = $drop;
                     $(fn clone = $clone;)*
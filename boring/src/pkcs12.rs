@@ -1,4 +1,9 @@
 //! PKCS #12 archives.
+//!
+//! [`Pkcs12Builder::build`] and [`Pkcs12Ref::parse2`] handle an archive holding one key, its
+//! certificate and a chain. [`Pkcs12Builder::build2`] and [`Pkcs12Ref::parse_bags`] handle any
+//! number of keys and certificates, with their friendly names, local key IDs and other
+//! attributes, as [`Pkcs12Bag`]s.
 
 use crate::ffi;
 use crate::libc_types::c_int;
@@ -7,15 +12,40 @@ use openssl_macros::corresponds;
 use std::ffi::CString;
 use std::ptr;
 
+use crate::asn1::Asn1Object;
+use crate::der::{self, Reader};
 use crate::error::ErrorStack;
+use crate::hash::{hash, Hasher, MessageDigest};
+use crate::hmac::Hmac;
+use crate::memcmp;
 use crate::nid::Nid;
+use crate::pkcs5::pbkdf2_hmac;
 use crate::pkey::{HasPrivate, PKey, PKeyRef, Private};
+use crate::rand::rand_bytes;
+use crate::secret::SecretBytes;
 use crate::stack::{Stack, StackRef};
+use crate::symm::{self, Cipher};
 use crate::x509::{X509Ref, X509};
 use crate::{cvt_0i, cvt_p};
 
 pub const PKCS12_DEFAULT_ITER: c_int = 2048;
 
+const DATA: &str = "1.2.840.113549.1.7.1";
+const ENCRYPTED_DATA: &str = "1.2.840.113549.1.7.6";
+const KEY_BAG: &str = "1.2.840.113549.1.12.10.1.1";
+const PKCS8_SHROUDED_KEY_BAG: &str = "1.2.840.113549.1.12.10.1.2";
+const CERT_BAG: &str = "1.2.840.113549.1.12.10.1.3";
+const SAFE_CONTENTS_BAG: &str = "1.2.840.113549.1.12.10.1.6";
+const X509_CERTIFICATE: &str = "1.2.840.113549.1.9.22.1";
+const FRIENDLY_NAME: &str = "1.2.840.113549.1.9.20";
+const LOCAL_KEY_ID: &str = "1.2.840.113549.1.9.21";
+const PBES2: &str = "1.2.840.113549.1.5.13";
+const PBKDF2: &str = "1.2.840.113549.1.5.12";
+const HMAC_WITH_SHA256: &str = "1.2.840.113549.2.9";
+
+/// How deeply `safeContentsBag`s may be nested.
+const MAX_SAFE_CONTENTS_DEPTH: usize = 4;
+
 foreign_type_and_impl_send_sync! {
     type CType = ffi::PKCS12;
     fn drop = ffi::PKCS12_free;
@@ -70,6 +100,49 @@ impl Pkcs12Ref {
             Ok(ParsedPkcs12_2 { pkey, cert, ca })
         }
     }
+
+    /// Extracts every bag of the `Pkcs12`, with its attributes.
+    ///
+    /// The MAC is verified if there is one. Bags encrypted with PBES2, with PBKDF2 and AES-CBC or
+    /// 3DES, and with the legacy PKCS #12 schemes based on 3DES and RC2, are decrypted. Nested
+    /// `safeContentsBag`s are flattened.
+    pub fn parse_bags(&self, pass: &str) -> Result<Vec<Pkcs12Bag>, ErrorStack> {
+        let der = der::from_ber(&self.to_der()?)?;
+        let mut reader = Reader::new(&der);
+        let mut pfx = reader.read_sequence()?;
+        reader.finish()?;
+        if der::parse_integer(pfx.read(der::INTEGER)?)? != 3 {
+            return Err(ErrorStack::internal_error_str(
+                "unsupported PKCS #12 version",
+            ));
+        }
+        let (content_type, content) = content_info(pfx.read_sequence()?)?;
+        if content_type != DATA {
+            return Err(ErrorStack::internal_error_str("unsupported content type"));
+        }
+        let auth_safe = data_content(content)?;
+        if let Some(mac_data) = pfx.read_optional(der::SEQUENCE)? {
+            verify_mac(Reader::new(mac_data), pass, &auth_safe)?;
+        }
+        pfx.finish()?;
+
+        let auth_safe = der::from_ber(&auth_safe)?;
+        let mut reader = Reader::new(&auth_safe);
+        let mut content_infos = reader.read_sequence()?;
+        reader.finish()?;
+
+        let mut bags = vec![];
+        while !content_infos.is_empty() {
+            let (content_type, content) = content_info(content_infos.read_sequence()?)?;
+            let safe_contents = match content_type.as_str() {
+                DATA => SecretBytes::from(data_content(content)?),
+                ENCRYPTED_DATA => encrypted_data_content(content, pass)?,
+                _ => return Err(ErrorStack::internal_error_str("unsupported content type")),
+            };
+            parse_safe_contents(&safe_contents, pass, 0, &mut bags)?;
+        }
+        Ok(bags)
+    }
 }
 
 impl Pkcs12 {
@@ -98,7 +171,10 @@ impl Pkcs12 {
             nid_cert: Nid::UNDEF, //nid::PBE_WITHSHA1AND40BITRC2_CBC,
             iter: PKCS12_DEFAULT_ITER,
             mac_iter: PKCS12_DEFAULT_ITER,
+            mac_md: MessageDigest::sha256(),
             ca: None,
+            entries: vec![],
+            bags: vec![],
         }
     }
 }
@@ -127,17 +203,33 @@ pub struct Pkcs12Builder {
     nid_cert: Nid,
     iter: c_int,
     mac_iter: c_int,
+    mac_md: MessageDigest,
     ca: Option<Stack<X509>>,
+    entries: Vec<(String, PKey<Private>, X509)>,
+    bags: Vec<Pkcs12Bag>,
 }
 
 impl Pkcs12Builder {
     /// The encryption algorithm that should be used for the key
+    ///
+    /// With [`build2`], the default is PBES2 with PBKDF2-HMAC-SHA256 and AES-256-CBC. `nid` may
+    /// be `Nid::AES_128_CBC` or `Nid::AES_256_CBC` for PBES2, or one of the legacy
+    /// `Nid::PBE_WITHSHA1AND3_KEY_TRIPLEDES_CBC`, `Nid::PBE_WITHSHA1AND128BITRC2_CBC` and
+    /// `Nid::PBE_WITHSHA1AND40BITRC2_CBC` for old implementations.
+    ///
+    /// [`build2`]: Pkcs12Builder::build2
     pub fn key_algorithm(&mut self, nid: Nid) -> &mut Self {
         self.nid_key = nid;
         self
     }
 
     /// The encryption algorithm that should be used for the cert
+    ///
+    /// The same algorithms as for [`key_algorithm`] are supported by [`build2`], which also
+    /// encrypts every bag other than keys with it.
+    ///
+    /// [`key_algorithm`]: Pkcs12Builder::key_algorithm
+    /// [`build2`]: Pkcs12Builder::build2
     pub fn cert_algorithm(&mut self, nid: Nid) -> &mut Self {
         self.nid_cert = nid;
         self
@@ -158,6 +250,17 @@ impl Pkcs12Builder {
         self
     }
 
+    /// The digest of the MAC, SHA-256 by default.
+    ///
+    /// Only used by [`build2`], [`build`] always uses SHA-1.
+    ///
+    /// [`build`]: Pkcs12Builder::build
+    /// [`build2`]: Pkcs12Builder::build2
+    pub fn mac_md(&mut self, md: MessageDigest) -> &mut Self {
+        self.mac_md = md;
+        self
+    }
+
     /// An additional set of certificates to include in the archive beyond the one provided to
     /// `build`.
     pub fn ca(&mut self, ca: Stack<X509>) -> &mut Self {
@@ -165,6 +268,117 @@ impl Pkcs12Builder {
         self
     }
 
+    /// Adds a key and its certificate to the archive built by [`build2`].
+    ///
+    /// Both bags get `friendly_name` and, to pair them, the SHA-1 digest of the certificate as
+    /// their local key ID, as OpenSSL does. May be called several times to add several pairs.
+    ///
+    /// [`build2`]: Pkcs12Builder::build2
+    pub fn add_key_and_cert(
+        &mut self,
+        friendly_name: &str,
+        pkey: &PKeyRef<Private>,
+        cert: &X509Ref,
+    ) -> &mut Self {
+        self.entries
+            .push((friendly_name.to_owned(), pkey.to_owned(), cert.to_owned()));
+        self
+    }
+
+    /// Adds a bag to the archive built by [`build2`], with its attributes.
+    ///
+    /// [`build2`]: Pkcs12Builder::build2
+    pub fn add_bag(&mut self, bag: Pkcs12Bag) -> &mut Self {
+        self.bags.push(bag);
+        self
+    }
+
+    /// Builds the PKCS #12 object from the keys, certificates and bags that were added.
+    ///
+    /// Keys are stored in shrouded key bags encrypted with the key algorithm. Every other bag,
+    /// including the certificates set with [`ca`], is stored in a safe encrypted with the cert
+    /// algorithm. The archive is authenticated with an HMAC of the MAC digest.
+    ///
+    /// [`ca`]: Pkcs12Builder::ca
+    pub fn build2(&self, password: &str) -> Result<Pkcs12, ErrorStack> {
+        let iter = iterations(self.iter)?;
+        let mac_iter = iterations(self.mac_iter)?;
+
+        let mut bags = vec![];
+        for (friendly_name, pkey, cert) in &self.entries {
+            let local_key_id = cert.digest(MessageDigest::sha1())?;
+            let mut key_bag = Pkcs12Bag::new(Pkcs12BagValue::Key(pkey.clone()));
+            key_bag
+                .set_friendly_name(friendly_name)
+                .set_local_key_id(&local_key_id);
+            let mut cert_bag = Pkcs12Bag::new(Pkcs12BagValue::Certificate(cert.clone()));
+            cert_bag
+                .set_friendly_name(friendly_name)
+                .set_local_key_id(&local_key_id);
+            bags.push(key_bag);
+            bags.push(cert_bag);
+        }
+        bags.extend(self.bags.iter().cloned());
+        for cert in self.ca.iter().flatten() {
+            bags.push(Pkcs12Bag::new(Pkcs12BagValue::Certificate(cert.to_owned())));
+        }
+
+        let mut key_bags = vec![];
+        let mut other_bags = vec![];
+        for bag in &bags {
+            let encoded = encode_bag(bag, password, self.nid_key, iter)?;
+            if matches!(bag.value, Pkcs12BagValue::Key(_)) {
+                key_bags.push(encoded);
+            } else {
+                other_bags.push(encoded);
+            }
+        }
+
+        let mut auth_safe = vec![];
+        if !other_bags.is_empty() {
+            let (algorithm, encrypted) =
+                encrypt(self.nid_cert, password, iter, &der::sequence(&other_bags))?;
+            let encrypted_data = der::sequence(&[
+                der::integer(der::INTEGER, 0),
+                der::sequence(&[
+                    der::oid(DATA)?,
+                    algorithm,
+                    der::tlv(der::context(0), &encrypted),
+                ]),
+            ]);
+            auth_safe.push(der::sequence(&[
+                der::oid(ENCRYPTED_DATA)?,
+                der::tlv(der::context_constructed(0), &encrypted_data),
+            ]));
+        }
+        if !key_bags.is_empty() {
+            auth_safe.push(data_content_info(&der::sequence(&key_bags))?);
+        }
+        let auth_safe = der::sequence(&auth_safe);
+
+        let mut salt = [0; 8];
+        rand_bytes(&mut salt)?;
+        let mac = mac(self.mac_md, password, &salt, mac_iter, &auth_safe)?;
+        let mac_data = der::sequence(&[
+            der::sequence(&[
+                der::sequence(&[
+                    der::oid(self.mac_md.type_().short_name()?)?,
+                    der::tlv(der::NULL, &[]),
+                ]),
+                der::tlv(der::OCTET_STRING, &mac),
+            ]),
+            der::tlv(der::OCTET_STRING, &salt),
+            der::integer(der::INTEGER, mac_iter as u64),
+        ]);
+
+        let pfx = der::sequence(&[
+            der::integer(der::INTEGER, 3),
+            data_content_info(&auth_safe)?,
+            mac_data,
+        ]);
+        Pkcs12::from_der(&pfx)
+    }
+
     /// Builds the PKCS #12 object
     ///
     /// # Arguments
@@ -218,12 +432,635 @@ impl Pkcs12Builder {
     }
 }
 
+/// The value of a [`Pkcs12Bag`].
+#[derive(Clone)]
+pub enum Pkcs12BagValue {
+    /// A private key, stored in a `keyBag` or a `pkcs8ShroudedKeyBag`.
+    Key(PKey<Private>),
+    /// An X.509 certificate, stored in a `certBag`.
+    Certificate(X509),
+    /// A bag of another type, such as a CRL or a secret.
+    Other {
+        /// The OID of the bag type, in dotted form.
+        bag_type: String,
+        /// The DER-encoded value of the bag.
+        value: Vec<u8>,
+    },
+}
+
+/// A bag of a PKCS #12 archive, with its attributes.
+#[derive(Clone)]
+pub struct Pkcs12Bag {
+    value: Pkcs12BagValue,
+    friendly_name: Option<String>,
+    local_key_id: Option<Vec<u8>>,
+    attributes: Vec<Pkcs12Attribute>,
+}
+
+impl Pkcs12Bag {
+    /// Creates a bag holding `value`, without attributes.
+    #[must_use]
+    pub fn new(value: Pkcs12BagValue) -> Pkcs12Bag {
+        Pkcs12Bag {
+            value,
+            friendly_name: None,
+            local_key_id: None,
+            attributes: vec![],
+        }
+    }
+
+    /// Returns the value of the bag.
+    #[must_use]
+    pub fn value(&self) -> &Pkcs12BagValue {
+        &self.value
+    }
+
+    /// Returns the value of the bag, consuming it.
+    #[must_use]
+    pub fn into_value(self) -> Pkcs12BagValue {
+        self.value
+    }
+
+    /// Returns the `friendlyName` attribute.
+    #[must_use]
+    pub fn friendly_name(&self) -> Option<&str> {
+        self.friendly_name.as_deref()
+    }
+
+    /// Sets the `friendlyName` attribute, the name applications show for the bag.
+    pub fn set_friendly_name(&mut self, friendly_name: &str) -> &mut Pkcs12Bag {
+        self.friendly_name = Some(friendly_name.to_owned());
+        self
+    }
+
+    /// Returns the `localKeyId` attribute.
+    #[must_use]
+    pub fn local_key_id(&self) -> Option<&[u8]> {
+        self.local_key_id.as_deref()
+    }
+
+    /// Sets the `localKeyId` attribute, which pairs a key with its certificate.
+    pub fn set_local_key_id(&mut self, local_key_id: &[u8]) -> &mut Pkcs12Bag {
+        self.local_key_id = Some(local_key_id.to_vec());
+        self
+    }
+
+    /// Returns the attributes other than `friendlyName` and `localKeyId`.
+    #[must_use]
+    pub fn attributes(&self) -> &[Pkcs12Attribute] {
+        &self.attributes
+    }
+
+    /// Adds an attribute other than `friendlyName` and `localKeyId`.
+    pub fn add_attribute(&mut self, attribute: Pkcs12Attribute) -> &mut Pkcs12Bag {
+        self.attributes.push(attribute);
+        self
+    }
+}
+
+/// An attribute of a [`Pkcs12Bag`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pkcs12Attribute {
+    oid: String,
+    values: Vec<Vec<u8>>,
+}
+
+impl Pkcs12Attribute {
+    /// Creates an attribute of type `oid`, given in dotted form or as a short or long name, with
+    /// DER-encoded `values`.
+    pub fn new(oid: &str, values: Vec<Vec<u8>>) -> Result<Pkcs12Attribute, ErrorStack> {
+        for value in &values {
+            let mut reader = Reader::new(value);
+            reader.read_element()?;
+            reader.finish()?;
+        }
+        let oid = der::oid(oid)?;
+        let oid = der::parse_oid(Reader::new(&oid).read(der::OBJECT_IDENTIFIER)?)?;
+        Ok(Pkcs12Attribute { oid, values })
+    }
+
+    /// Returns the OID of the attribute type, in dotted form.
+    #[must_use]
+    pub fn oid(&self) -> &str {
+        &self.oid
+    }
+
+    /// Returns the DER-encoded values of the attribute.
+    #[must_use]
+    pub fn values(&self) -> &[Vec<u8>] {
+        &self.values
+    }
+
+    fn to_der(&self) -> Result<Vec<u8>, ErrorStack> {
+        Ok(der::sequence(&[
+            der::oid(&self.oid)?,
+            der::set_of(&self.values),
+        ]))
+    }
+}
+
+/// The largest iteration count accepted, so that a file cannot make the key derivation run for a
+/// very long time.
+const MAX_ITERATIONS: usize = 100_000_000;
+
+fn iterations(iter: c_int) -> Result<usize, ErrorStack> {
+    match usize::try_from(iter) {
+        Ok(iter) if (1..=MAX_ITERATIONS).contains(&iter) => Ok(iter),
+        _ => Err(ErrorStack::internal_error_str("invalid iteration count")),
+    }
+}
+
+fn parse_iterations(contents: &[u8]) -> Result<usize, ErrorStack> {
+    match usize::try_from(der::parse_integer(contents)?) {
+        Ok(iter) if (1..=MAX_ITERATIONS).contains(&iter) => Ok(iter),
+        Ok(iter) if iter > 0 => Err(ErrorStack::internal_error_str("iteration count too large")),
+        _ => Err(der::malformed()),
+    }
+}
+
+/// Encodes a password as PKCS #12 expects it for its key derivation function: a NUL terminated
+/// big-endian UTF-16 string.
+fn bmp_password(password: &str) -> SecretBytes {
+    password
+        .encode_utf16()
+        .chain([0])
+        .flat_map(u16::to_be_bytes)
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// The key derivation function of RFC 7292, appendix B.2, with `id` 1 for keys, 2 for IVs and 3
+/// for MAC keys.
+fn pkcs12_kdf(
+    id: u8,
+    password: &[u8],
+    salt: &[u8],
+    iter: usize,
+    md: MessageDigest,
+    out: &mut [u8],
+) -> Result<(), ErrorStack> {
+    let block_size = unsafe { ffi::EVP_MD_block_size(md.as_ptr()) };
+    let fill = |data: &[u8]| {
+        let len = data.len().div_ceil(block_size) * block_size;
+        data.iter().copied().cycle().take(len).collect::<Vec<_>>()
+    };
+    let mut input = SecretBytes::from([fill(salt), fill(password)].concat());
+
+    for chunk in out.chunks_mut(md.size()) {
+        let mut hasher = Hasher::new(md)?;
+        hasher.update(&vec![id; block_size])?;
+        hasher.update(&input)?;
+        let mut digest = hasher.finish()?;
+        for _ in 1..iter {
+            digest = hash(md, &digest)?;
+        }
+        chunk.copy_from_slice(&digest[..chunk.len()]);
+
+        // Each block of the input is incremented by the digest repeated to a block, plus one
+        let increment = fill(&digest[..]);
+        for block in input.chunks_mut(block_size) {
+            let mut carry = 1;
+            for (byte, increment) in block.iter_mut().zip(&increment).rev() {
+                let sum = u16::from(*byte) + u16::from(*increment) + carry;
+                *byte = sum as u8;
+                carry = sum >> 8;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn mac(
+    md: MessageDigest,
+    password: &str,
+    salt: &[u8],
+    iter: usize,
+    data: &[u8],
+) -> Result<Vec<u8>, ErrorStack> {
+    let mut key = SecretBytes::zeroed(md.size());
+    pkcs12_kdf(3, &bmp_password(password), salt, iter, md, &mut key)?;
+    let mut hmac = Hmac::init(&key, &md)?;
+    hmac.update(data)?;
+    hmac.finalize()
+}
+
+fn verify_mac(mut mac_data: Reader<'_>, password: &str, data: &[u8]) -> Result<(), ErrorStack> {
+    let mut digest_info = mac_data.read_sequence()?;
+    let mut algorithm = digest_info.read_sequence()?;
+    let md = Asn1Object::from_str(&der::parse_oid(algorithm.read(der::OBJECT_IDENTIFIER)?)?)
+        .ok()
+        .and_then(|object| MessageDigest::from_nid(object.nid()))
+        .ok_or_else(|| ErrorStack::internal_error_str("unsupported MAC algorithm"))?;
+    let expected = digest_info.read(der::OCTET_STRING)?;
+    digest_info.finish()?;
+    let salt = mac_data.read(der::OCTET_STRING)?;
+    let iter = match mac_data.read_optional(der::INTEGER)? {
+        Some(iter) => parse_iterations(iter)?,
+        None => 1,
+    };
+    mac_data.finish()?;
+
+    let actual = mac(md, password, salt, iter, data)?;
+    if actual.len() == expected.len() && memcmp::eq(&actual, expected) {
+        Ok(())
+    } else {
+        Err(ErrorStack::internal_error_str(
+            "MAC verification failed, the password may be wrong",
+        ))
+    }
+}
+
+/// Returns the cipher of a legacy PKCS #12 encryption scheme.
+fn legacy_cipher(algorithm: Nid) -> Option<Cipher> {
+    match algorithm {
+        Nid::PBE_WITHSHA1AND3_KEY_TRIPLEDES_CBC => Some(Cipher::des_ede3_cbc()),
+        Nid::PBE_WITHSHA1AND128BITRC2_CBC => Some(Cipher::rc2_cbc()),
+        Nid::PBE_WITHSHA1AND40BITRC2_CBC => Some(Cipher::rc2_40_cbc()),
+        _ => None,
+    }
+}
+
+fn is_pbes2_cipher(cipher: Cipher) -> bool {
+    [
+        Cipher::aes_128_cbc(),
+        Cipher::aes_192_cbc(),
+        Cipher::aes_256_cbc(),
+        Cipher::des_ede3_cbc(),
+    ]
+    .contains(&cipher)
+}
+
+/// Encrypts `data` with `algorithm`, returning the encoded `AlgorithmIdentifier` and the
+/// ciphertext.
+fn encrypt(
+    algorithm: Nid,
+    password: &str,
+    iter: usize,
+    data: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), ErrorStack> {
+    if let Some(cipher) = legacy_cipher(algorithm) {
+        let mut salt = [0; 8];
+        rand_bytes(&mut salt)?;
+        let password = bmp_password(password);
+        let mut key = SecretBytes::zeroed(cipher.key_len());
+        pkcs12_kdf(1, &password, &salt, iter, MessageDigest::sha1(), &mut key)?;
+        let mut iv = vec![0; cipher.iv_len().unwrap_or_default()];
+        pkcs12_kdf(2, &password, &salt, iter, MessageDigest::sha1(), &mut iv)?;
+
+        let algorithm = der::sequence(&[
+            der::oid(algorithm.short_name()?)?,
+            der::sequence(&[
+                der::tlv(der::OCTET_STRING, &salt),
+                der::integer(der::INTEGER, iter as u64),
+            ]),
+        ]);
+        return Ok((algorithm, symm::encrypt(cipher, &key, Some(&iv), data)?));
+    }
+
+    let cipher = if algorithm == Nid::UNDEF {
+        Cipher::aes_256_cbc()
+    } else {
+        Cipher::from_nid(algorithm)
+            .filter(|&cipher| is_pbes2_cipher(cipher))
+            .ok_or_else(|| ErrorStack::internal_error_str("unsupported encryption algorithm"))?
+    };
+    let mut salt = [0; 16];
+    rand_bytes(&mut salt)?;
+    let mut key = SecretBytes::zeroed(cipher.key_len());
+    pbkdf2_hmac(
+        password.as_bytes(),
+        &salt,
+        iter,
+        MessageDigest::sha256(),
+        &mut key,
+    )?;
+    let mut iv = vec![0; cipher.iv_len().unwrap_or_default()];
+    rand_bytes(&mut iv)?;
+
+    let algorithm = der::sequence(&[
+        der::oid(PBES2)?,
+        der::sequence(&[
+            der::sequence(&[
+                der::oid(PBKDF2)?,
+                der::sequence(&[
+                    der::tlv(der::OCTET_STRING, &salt),
+                    der::integer(der::INTEGER, iter as u64),
+                    der::sequence(&[der::oid(HMAC_WITH_SHA256)?, der::tlv(der::NULL, &[])]),
+                ]),
+            ]),
+            der::sequence(&[
+                der::oid(cipher.nid().short_name()?)?,
+                der::tlv(der::OCTET_STRING, &iv),
+            ]),
+        ]),
+    ]);
+    Ok((algorithm, symm::encrypt(cipher, &key, Some(&iv), data)?))
+}
+
+/// Decrypts `data` with the `AlgorithmIdentifier` whose contents are `algorithm`.
+fn decrypt(algorithm: &[u8], password: &str, data: &[u8]) -> Result<SecretBytes, ErrorStack> {
+    let mut algorithm = Reader::new(algorithm);
+    let oid = der::parse_oid(algorithm.read(der::OBJECT_IDENTIFIER)?)?;
+    let mut params = algorithm.read_sequence()?;
+    algorithm.finish()?;
+
+    let (cipher, key, iv) = if oid == PBES2 {
+        let mut kdf = params.read_sequence()?;
+        if der::parse_oid(kdf.read(der::OBJECT_IDENTIFIER)?)? != PBKDF2 {
+            return Err(ErrorStack::internal_error_str(
+                "unsupported key derivation function",
+            ));
+        }
+        let mut kdf_params = kdf.read_sequence()?;
+        kdf.finish()?;
+        let salt = kdf_params.read(der::OCTET_STRING)?;
+        let iter = parse_iterations(kdf_params.read(der::INTEGER)?)?;
+        let key_len = kdf_params
+            .read_optional(der::INTEGER)?
+            .map(der::parse_integer)
+            .transpose()?;
+        let prf = if kdf_params.peek_tag() == Some(der::SEQUENCE) {
+            let mut prf = kdf_params.read_sequence()?;
+            hmac_digest(&der::parse_oid(prf.read(der::OBJECT_IDENTIFIER)?)?)?
+        } else {
+            MessageDigest::sha1()
+        };
+        kdf_params.finish()?;
+
+        let mut scheme = params.read_sequence()?;
+        let cipher = Asn1Object::from_str(&der::parse_oid(scheme.read(der::OBJECT_IDENTIFIER)?)?)
+            .ok()
+            .and_then(|object| Cipher::from_nid(object.nid()))
+            .filter(|&cipher| is_pbes2_cipher(cipher))
+            .ok_or_else(|| ErrorStack::internal_error_str("unsupported encryption algorithm"))?;
+        let iv = scheme.read(der::OCTET_STRING)?;
+        scheme.finish()?;
+        if key_len.is_some_and(|len| len != cipher.key_len() as u64)
+            || cipher.iv_len() != Some(iv.len())
+        {
+            return Err(der::malformed());
+        }
+
+        let mut key = SecretBytes::zeroed(cipher.key_len());
+        pbkdf2_hmac(password.as_bytes(), salt, iter, prf, &mut key)?;
+        (cipher, key, iv.to_vec())
+    } else {
+        let cipher = Asn1Object::from_str(&oid)
+            .ok()
+            .and_then(|object| legacy_cipher(object.nid()))
+            .ok_or_else(|| ErrorStack::internal_error_str("unsupported encryption algorithm"))?;
+        let salt = params.read(der::OCTET_STRING)?;
+        let iter = parse_iterations(params.read(der::INTEGER)?)?;
+
+        let password = bmp_password(password);
+        let mut key = SecretBytes::zeroed(cipher.key_len());
+        pkcs12_kdf(1, &password, salt, iter, MessageDigest::sha1(), &mut key)?;
+        let mut iv = vec![0; cipher.iv_len().unwrap_or_default()];
+        pkcs12_kdf(2, &password, salt, iter, MessageDigest::sha1(), &mut iv)?;
+        (cipher, key, iv)
+    };
+    params.finish()?;
+
+    symm::decrypt(cipher, &key, Some(&iv), data)
+        .map(SecretBytes::from)
+        .map_err(|_| ErrorStack::internal_error_str("decryption failed"))
+}
+
+fn hmac_digest(oid: &str) -> Result<MessageDigest, ErrorStack> {
+    let nid = Asn1Object::from_str(oid).map(|object| object.nid());
+    match nid {
+        Ok(Nid::HMACWITHSHA1) => Ok(MessageDigest::sha1()),
+        Ok(Nid::HMACWITHSHA224) => Ok(MessageDigest::sha224()),
+        Ok(Nid::HMACWITHSHA256) => Ok(MessageDigest::sha256()),
+        Ok(Nid::HMACWITHSHA384) => Ok(MessageDigest::sha384()),
+        Ok(Nid::HMACWITHSHA512) => Ok(MessageDigest::sha512()),
+        _ => Err(ErrorStack::internal_error_str("unsupported PBKDF2 PRF")),
+    }
+}
+
+fn data_content_info(content: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+    Ok(der::sequence(&[
+        der::oid(DATA)?,
+        der::tlv(
+            der::context_constructed(0),
+            &der::tlv(der::OCTET_STRING, content),
+        ),
+    ]))
+}
+
+/// Reads a `ContentInfo`, returning its content type and the contents of its content.
+fn content_info(mut content_info: Reader<'_>) -> Result<(String, &[u8]), ErrorStack> {
+    let content_type = der::parse_oid(content_info.read(der::OBJECT_IDENTIFIER)?)?;
+    let content = content_info.read(der::context_constructed(0))?;
+    content_info.finish()?;
+    Ok((content_type, content))
+}
+
+/// Returns the data of the content of a `data` `ContentInfo`.
+fn data_content(content: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+    let mut content = Reader::new(content);
+    let data = content.read(der::OCTET_STRING)?.to_vec();
+    content.finish()?;
+    Ok(data)
+}
+
+/// Returns the decrypted data of the content of an `encryptedData` `ContentInfo`.
+fn encrypted_data_content(content: &[u8], password: &str) -> Result<SecretBytes, ErrorStack> {
+    let mut content = Reader::new(content);
+    let mut encrypted_data = content.read_sequence()?;
+    content.finish()?;
+
+    der::parse_integer(encrypted_data.read(der::INTEGER)?)?;
+    let mut encrypted_content_info = encrypted_data.read_sequence()?;
+    encrypted_data.finish()?;
+    encrypted_content_info.read(der::OBJECT_IDENTIFIER)?;
+    let algorithm = encrypted_content_info.read(der::SEQUENCE)?;
+    let encrypted = encrypted_content_info.read_implicit_octet_string(0)?;
+    encrypted_content_info.finish()?;
+    decrypt(algorithm, password, &encrypted)
+}
+
+fn parse_safe_contents(
+    safe_contents: &[u8],
+    password: &str,
+    depth: usize,
+    bags: &mut Vec<Pkcs12Bag>,
+) -> Result<(), ErrorStack> {
+    if depth > MAX_SAFE_CONTENTS_DEPTH {
+        return Err(ErrorStack::internal_error_str(
+            "safe contents are nested too deeply",
+        ));
+    }
+    let safe_contents = der::from_ber(safe_contents)?;
+    let mut reader = Reader::new(&safe_contents);
+    let mut safe_bags = reader.read_sequence()?;
+    reader.finish()?;
+
+    while !safe_bags.is_empty() {
+        let mut safe_bag = safe_bags.read_sequence()?;
+        let bag_type = der::parse_oid(safe_bag.read(der::OBJECT_IDENTIFIER)?)?;
+        let mut explicit = Reader::new(safe_bag.read(der::context_constructed(0))?);
+        let value = explicit.read_element()?;
+        explicit.finish()?;
+        let attributes = safe_bag.read_optional(der::SET)?;
+        safe_bag.finish()?;
+
+        let value = match bag_type.as_str() {
+            KEY_BAG => Pkcs12BagValue::Key(PKey::private_key_from_pkcs8(value)?),
+            PKCS8_SHROUDED_KEY_BAG => {
+                let mut reader = Reader::new(value);
+                let mut encrypted_private_key_info = reader.read_sequence()?;
+                reader.finish()?;
+                let algorithm = encrypted_private_key_info.read(der::SEQUENCE)?;
+                let encrypted = encrypted_private_key_info.read(der::OCTET_STRING)?;
+                encrypted_private_key_info.finish()?;
+                let private_key_info = decrypt(algorithm, password, encrypted)?;
+                Pkcs12BagValue::Key(PKey::private_key_from_pkcs8(&private_key_info)?)
+            }
+            CERT_BAG => {
+                let mut reader = Reader::new(value);
+                let mut cert_bag = reader.read_sequence()?;
+                reader.finish()?;
+                let cert_type = der::parse_oid(cert_bag.read(der::OBJECT_IDENTIFIER)?)?;
+                let mut explicit = Reader::new(cert_bag.read(der::context_constructed(0))?);
+                cert_bag.finish()?;
+                if cert_type == X509_CERTIFICATE {
+                    let cert = X509::from_der(explicit.read(der::OCTET_STRING)?)?;
+                    explicit.finish()?;
+                    Pkcs12BagValue::Certificate(cert)
+                } else {
+                    Pkcs12BagValue::Other {
+                        bag_type,
+                        value: value.to_vec(),
+                    }
+                }
+            }
+            SAFE_CONTENTS_BAG => {
+                parse_safe_contents(value, password, depth + 1, bags)?;
+                continue;
+            }
+            _ => Pkcs12BagValue::Other {
+                bag_type,
+                value: value.to_vec(),
+            },
+        };
+
+        let mut bag = Pkcs12Bag::new(value);
+        let mut attributes = Reader::new(attributes.unwrap_or_default());
+        while !attributes.is_empty() {
+            let mut attribute = attributes.read_sequence()?;
+            let oid = der::parse_oid(attribute.read(der::OBJECT_IDENTIFIER)?)?;
+            let mut values = Reader::new(attribute.read(der::SET)?);
+            attribute.finish()?;
+            match oid.as_str() {
+                FRIENDLY_NAME if values.peek_tag() == Some(der::BMP_STRING) => {
+                    bag.friendly_name = Some(parse_bmp_string(values.read(der::BMP_STRING)?)?);
+                    values.finish()?;
+                }
+                LOCAL_KEY_ID if values.peek_tag() == Some(der::OCTET_STRING) => {
+                    bag.local_key_id = Some(values.read(der::OCTET_STRING)?.to_vec());
+                    values.finish()?;
+                }
+                _ => {
+                    let mut elements = vec![];
+                    while !values.is_empty() {
+                        elements.push(values.read_element()?.to_vec());
+                    }
+                    bag.attributes.push(Pkcs12Attribute {
+                        oid,
+                        values: elements,
+                    });
+                }
+            }
+        }
+        bags.push(bag);
+    }
+    Ok(())
+}
+
+fn parse_bmp_string(contents: &[u8]) -> Result<String, ErrorStack> {
+    if contents.len() % 2 != 0 {
+        return Err(der::malformed());
+    }
+    let units = contents
+        .chunks(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|_| der::malformed())
+}
+
+fn encode_bag(
+    bag: &Pkcs12Bag,
+    password: &str,
+    key_algorithm: Nid,
+    iter: usize,
+) -> Result<Vec<u8>, ErrorStack> {
+    let (bag_type, value) = match &bag.value {
+        Pkcs12BagValue::Key(key) => {
+            let private_key_info = key.private_key_to_der_pkcs8_secret()?;
+            let (algorithm, encrypted) = encrypt(key_algorithm, password, iter, &private_key_info)?;
+            (
+                PKCS8_SHROUDED_KEY_BAG,
+                der::sequence(&[algorithm, der::tlv(der::OCTET_STRING, &encrypted)]),
+            )
+        }
+        Pkcs12BagValue::Certificate(cert) => (
+            CERT_BAG,
+            der::sequence(&[
+                der::oid(X509_CERTIFICATE)?,
+                der::tlv(
+                    der::context_constructed(0),
+                    &der::tlv(der::OCTET_STRING, &cert.to_der()?),
+                ),
+            ]),
+        ),
+        Pkcs12BagValue::Other { bag_type, value } => {
+            let mut reader = Reader::new(value);
+            reader.read_element()?;
+            reader.finish()?;
+            (bag_type.as_str(), value.clone())
+        }
+    };
+
+    let mut attributes = vec![];
+    if let Some(friendly_name) = &bag.friendly_name {
+        let bmp_string = friendly_name
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect::<Vec<_>>();
+        attributes.push(der::sequence(&[
+            der::oid(FRIENDLY_NAME)?,
+            der::set_of(&[der::tlv(der::BMP_STRING, &bmp_string)]),
+        ]));
+    }
+    if let Some(local_key_id) = &bag.local_key_id {
+        attributes.push(der::sequence(&[
+            der::oid(LOCAL_KEY_ID)?,
+            der::set_of(&[der::tlv(der::OCTET_STRING, local_key_id)]),
+        ]));
+    }
+    for attribute in &bag.attributes {
+        attributes.push(attribute.to_der()?);
+    }
+
+    let mut safe_bag = vec![
+        der::oid(bag_type)?,
+        der::tlv(der::context_constructed(0), &value),
+    ];
+    if !attributes.is_empty() {
+        safe_bag.push(der::set_of(&attributes));
+    }
+    Ok(der::sequence(&safe_bag))
+}
+
 #[cfg(test)]
 mod test {
     use crate::hash::MessageDigest;
     use hex;
 
     use crate::asn1::Asn1Time;
+    use crate::ec::{EcGroup, EcKey};
     use crate::nid::Nid;
     use crate::pkey::PKey;
     use crate::rsa::Rsa;
@@ -302,4 +1139,256 @@ mod test {
         );
         assert!(parsed.pkey.public_eq(&pkey));
     }
+
+    fn self_signed(cn: &str) -> (PKey<Private>, X509) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let pkey = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+        let mut name = X509Name::builder().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, cn).unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(365).unwrap())
+            .unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&pkey).unwrap();
+        builder.sign(&pkey, MessageDigest::sha256()).unwrap();
+        (pkey, builder.build())
+    }
+
+    fn keys(bags: &[Pkcs12Bag]) -> Vec<&Pkcs12Bag> {
+        bags.iter()
+            .filter(|bag| matches!(bag.value(), Pkcs12BagValue::Key(_)))
+            .collect()
+    }
+
+    fn certs(bags: &[Pkcs12Bag]) -> Vec<&Pkcs12Bag> {
+        bags.iter()
+            .filter(|bag| matches!(bag.value(), Pkcs12BagValue::Certificate(_)))
+            .collect()
+    }
+
+    fn cert(bag: &Pkcs12Bag) -> &X509 {
+        match bag.value() {
+            Pkcs12BagValue::Certificate(cert) => cert,
+            _ => panic!("not a certificate bag"),
+        }
+    }
+
+    fn key(bag: &Pkcs12Bag) -> &PKey<Private> {
+        match bag.value() {
+            Pkcs12BagValue::Key(key) => key,
+            _ => panic!("not a key bag"),
+        }
+    }
+
+    #[test]
+    fn parse_bags_legacy() {
+        // RC2-40 encrypted certificates and a 3DES shrouded key, with a SHA-1 MAC
+        let der = include_bytes!("../test/identity.p12");
+        let pkcs12 = Pkcs12::from_der(der).unwrap();
+        let bags = pkcs12.parse_bags("mypass").unwrap();
+
+        let keys = keys(&bags);
+        let certs = certs(&bags);
+        assert_eq!(keys.len(), 1);
+        assert_eq!(certs.len(), 2);
+        let leaf = certs
+            .iter()
+            .find(|bag| bag.local_key_id() == keys[0].local_key_id())
+            .unwrap();
+        assert_eq!(
+            hex::encode(cert(leaf).digest(MessageDigest::sha1()).unwrap()),
+            "59172d9313e84459bcff27f967e79e6e9217e584"
+        );
+        assert!(key(keys[0]).public_eq(&cert(leaf).public_key().unwrap()));
+
+        assert!(pkcs12.parse_bags("wrong").is_err());
+
+        // Written by Java's keytool
+        let der = include_bytes!("../test/keystore-empty-chain.p12");
+        let bags = Pkcs12::from_der(der)
+            .unwrap()
+            .parse_bags("cassandra")
+            .unwrap();
+        assert_eq!(keys(&bags).len(), 1);
+    }
+
+    #[test]
+    fn parse_bags_pbes2() {
+        // Written by OpenSSL 3 with its defaults: PBES2 with PBKDF2-HMAC-SHA256 and
+        // AES-256-CBC, and a SHA-256 MAC
+        let der = include_bytes!("../test/pkcs12-aes.p12");
+        let bags = Pkcs12::from_der(der).unwrap().parse_bags("mypass").unwrap();
+        let expected = X509::from_pem(include_bytes!("../test/pkcs7-cert.pem")).unwrap();
+        let local_key_id = hex::decode("9b3e2a0bce1bca1717372aa7a939943cb5295e30").unwrap();
+
+        let keys = keys(&bags);
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].friendly_name(), Some("pkcs7 test"));
+        assert_eq!(keys[0].local_key_id(), Some(&*local_key_id));
+        assert!(key(keys[0]).public_eq(&expected.public_key().unwrap()));
+
+        let certs = certs(&bags);
+        assert_eq!(certs.len(), 2);
+        let leaf = certs
+            .iter()
+            .find(|bag| bag.friendly_name() == Some("pkcs7 test"))
+            .unwrap();
+        assert_eq!(leaf.local_key_id(), Some(&*local_key_id));
+        assert_eq!(cert(leaf).to_der().unwrap(), expected.to_der().unwrap());
+        assert!(certs.iter().any(|bag| bag.friendly_name().is_none()));
+    }
+
+    #[test]
+    fn build2() {
+        let (alice_key, alice) = self_signed("alice");
+        let (bob_key, bob) = self_signed("bob");
+        let (_, root) = self_signed("root");
+        let mut ca = Stack::new().unwrap();
+        ca.push(root.clone()).unwrap();
+
+        let trusted = Pkcs12Attribute::new(
+            "2.16.840.1.113894.746875.1.1",
+            vec![der::oid("anyExtendedKeyUsage").unwrap()],
+        )
+        .unwrap();
+        let mut trusted_bag = Pkcs12Bag::new(Pkcs12BagValue::Certificate(root.clone()));
+        trusted_bag
+            .set_friendly_name("trusted root")
+            .add_attribute(trusted.clone());
+
+        let mut builder = Pkcs12::builder();
+        builder
+            .add_key_and_cert("alice", &alice_key, &alice)
+            .add_key_and_cert("bøb", &bob_key, &bob)
+            .add_bag(trusted_bag)
+            .ca(ca)
+            .mac_md(MessageDigest::sha512())
+            .cert_algorithm(Nid::PBE_WITHSHA1AND3_KEY_TRIPLEDES_CBC);
+        let pkcs12 = builder.build2("mypass").unwrap();
+        let pkcs12 = Pkcs12::from_der(&pkcs12.to_der().unwrap()).unwrap();
+
+        let bags = pkcs12.parse_bags("mypass").unwrap();
+        let keys = keys(&bags);
+        let certs = certs(&bags);
+        assert_eq!(keys.len(), 2);
+        assert_eq!(certs.len(), 4);
+        for (name, key_bag) in ["alice", "bøb"].iter().zip(&keys) {
+            assert_eq!(key_bag.friendly_name(), Some(*name));
+            let cert_bag = certs
+                .iter()
+                .find(|bag| bag.local_key_id() == key_bag.local_key_id())
+                .unwrap();
+            assert_eq!(cert_bag.friendly_name(), Some(*name));
+            assert!(key(key_bag).public_eq(&cert(cert_bag).public_key().unwrap()));
+        }
+        assert_eq!(
+            keys[0].local_key_id().unwrap(),
+            &*alice.digest(MessageDigest::sha1()).unwrap()
+        );
+
+        let trusted_bag = certs
+            .iter()
+            .find(|bag| bag.friendly_name() == Some("trusted root"))
+            .unwrap();
+        assert_eq!(trusted_bag.attributes(), [trusted]);
+        assert!(certs
+            .iter()
+            .any(|bag| bag.friendly_name().is_none() && bag.attributes().is_empty()));
+
+        assert!(pkcs12.parse_bags("wrong").is_err());
+    }
+
+    #[test]
+    fn kdf() {
+        // These match the output of OpenSSL's PKCS12KDF (`openssl kdf -kdfopt digest:SHA1 ...`)
+        for (password, salt, iter, id, expected) in [
+            (
+                "smeg",
+                "0a58cf64530d823f",
+                1,
+                1,
+                "8aaae6297b6cb04642ab5b077851284eb7128f1a2a7fbca3",
+            ),
+            ("smeg", "0a58cf64530d823f", 1, 2, "79993dfe048d3b76"),
+            (
+                "queeg",
+                "05dec959acff72f7",
+                1000,
+                1,
+                "ed2034e36328830ff09df1e1a07dd357185dac0d4f9eb3d4",
+            ),
+            ("queeg", "05dec959acff72f7", 1000, 2, "11dedad7758d4860"),
+        ] {
+            let mut out = vec![0; expected.len() / 2];
+            pkcs12_kdf(
+                id,
+                &bmp_password(password),
+                &hex::decode(salt).unwrap(),
+                iter,
+                MessageDigest::sha1(),
+                &mut out,
+            )
+            .unwrap();
+            assert_eq!(hex::encode(out), expected);
+        }
+    }
+
+    #[test]
+    fn iteration_limit() {
+        assert_eq!(
+            parse_iterations(&[0x05, 0xf5, 0xe1, 0x00]).unwrap(),
+            100_000_000
+        );
+        assert!(parse_iterations(&[0x05, 0xf5, 0xe1, 0x01]).is_err());
+        assert!(parse_iterations(&[0x00]).is_err());
+
+        let (pkey, cert) = self_signed("ns.example.com");
+        let mut builder = Pkcs12::builder();
+        builder
+            .add_key_and_cert("ns.example.com", &pkey, &cert)
+            .key_iter(100_000_001);
+        assert!(builder.build2("mypass").is_err());
+    }
+
+    #[test]
+    fn build2_compatibility() {
+        let (pkey, cert) = self_signed("ns.example.com");
+
+        for (key_algorithm, cert_algorithm) in [
+            (Nid::UNDEF, Nid::UNDEF),
+            (Nid::AES_128_CBC, Nid::AES_256_CBC),
+            (
+                Nid::PBE_WITHSHA1AND3_KEY_TRIPLEDES_CBC,
+                Nid::PBE_WITHSHA1AND40BITRC2_CBC,
+            ),
+        ] {
+            let mut builder = Pkcs12::builder();
+            builder
+                .add_key_and_cert("ns.example.com", &pkey, &cert)
+                .key_algorithm(key_algorithm)
+                .cert_algorithm(cert_algorithm)
+                .mac_md(MessageDigest::sha1());
+            let pkcs12 = builder.build2("mypass").unwrap();
+
+            // Parsed by BoringSSL too
+            let parsed = pkcs12.parse("mypass").unwrap();
+            assert_eq!(parsed.cert.to_der().unwrap(), cert.to_der().unwrap());
+            assert!(parsed.pkey.public_eq(&pkey));
+        }
+
+        let mut builder = Pkcs12::builder();
+        builder
+            .add_key_and_cert("ns.example.com", &pkey, &cert)
+            .key_algorithm(Nid::AES_128_CBC_HMAC_SHA1);
+        assert!(builder.build2("mypass").is_err());
+    }
 }
@@ -0,0 +1,1215 @@
+//! PKCS #7 and CMS messages.
+//!
+//! [`SignedData`] messages are signed with a [`SignedDataBuilder`], and parsed and verified with
+//! [`SignedData::from_der`] and [`SignedData::verify`]. They are compatible with the `SignedData`
+//! of RFC 5652 (CMS), which is how PKCS #7 is used today. Certificate bundles, the degenerate
+//! `SignedData` without signers used to distribute certificate chains, are handled by
+//! [`certificates_from_der`], [`certificates_from_pem`] and [`bundle_certificates`].
+//!
+//! [`encrypt`] and [`decrypt`] handle `EnvelopedData` messages, with RSA key transport and
+//! AES-CBC content encryption.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rama_boring::hash::MessageDigest;
+//! use rama_boring::pkcs7::{SignedData, SignedDataBuilder};
+//! use rama_boring::pkey::PKey;
+//! use rama_boring::x509::store::X509StoreBuilder;
+//! use rama_boring::x509::X509;
+//!
+//! # let cert = X509::from_pem(&[]).unwrap();
+//! # let key = PKey::private_key_from_pem(&[]).unwrap();
+//! # let root = X509::from_pem(&[]).unwrap();
+//! let signed = SignedDataBuilder::new()
+//!     .add_signer(&cert, &key, MessageDigest::sha256())
+//!     .detached()
+//!     .sign(b"release.tar.gz contents")
+//!     .unwrap();
+//! let der = signed.to_der().unwrap();
+//!
+//! let mut store = X509StoreBuilder::new().unwrap();
+//! store.add_cert(&root).unwrap();
+//! let signed = SignedData::from_der(&der).unwrap();
+//! let signers = signed
+//!     .verify(Some(b"release.tar.gz contents"), &store.build())
+//!     .unwrap();
+//! ```
+
+use crate::ffi;
+use foreign_types::ForeignTypeRef;
+use openssl_macros::corresponds;
+use std::mem::MaybeUninit;
+use std::{hint, ptr, slice};
+
+use crate::asn1::Asn1Object;
+use crate::bio::MemBioSlice;
+use crate::bn::{BigNum, BigNumRef};
+use crate::cvt;
+use crate::der::{self, Reader};
+use crate::error::ErrorStack;
+use crate::hash::{hash, MessageDigest};
+use crate::nid::Nid;
+use crate::pkey::{Id, PKey, PKeyRef, Private};
+use crate::rand::rand_bytes;
+use crate::rsa::Padding;
+use crate::secret::SecretBytes;
+use crate::sign::{Signer, Verifier};
+use crate::stack::{Stack, StackRef};
+use crate::symm::{self, Cipher};
+use crate::x509::store::X509StoreRef;
+use crate::x509::{X509Name, X509NameRef, X509Ref, X509StoreContext, X509};
+
+const DATA: &str = "1.2.840.113549.1.7.1";
+const SIGNED_DATA: &str = "1.2.840.113549.1.7.2";
+const ENVELOPED_DATA: &str = "1.2.840.113549.1.7.3";
+const CONTENT_TYPE: &str = "1.2.840.113549.1.9.3";
+const MESSAGE_DIGEST: &str = "1.2.840.113549.1.9.4";
+const RSA_ENCRYPTION: &str = "1.2.840.113549.1.1.1";
+const RSASSA_PSS: &str = "1.2.840.113549.1.1.10";
+const ED25519: &str = "1.3.101.112";
+
+/// Extracts the certificates of a DER-encoded PKCS #7 message.
+///
+/// This is how certificate bundles (`.p7b` files) are read. The signers of the message, if any,
+/// are not verified.
+#[corresponds(PKCS7_get_certificates)]
+pub fn certificates_from_der(der: &[u8]) -> Result<Stack<X509>, ErrorStack> {
+    unsafe {
+        let certs = Stack::new()?;
+        let mut cbs = cbs_init(der);
+        cvt(ffi::PKCS7_get_certificates(certs.as_ptr(), &mut cbs))?;
+        Ok(certs)
+    }
+}
+
+/// Extracts the certificates of a PEM-encoded PKCS #7 message.
+///
+/// The signers of the message, if any, are not verified.
+#[corresponds(PKCS7_get_PEM_certificates)]
+pub fn certificates_from_pem(pem: &[u8]) -> Result<Stack<X509>, ErrorStack> {
+    unsafe {
+        let certs = Stack::new()?;
+        let bio = MemBioSlice::new(pem)?;
+        cvt(ffi::PKCS7_get_PEM_certificates(
+            certs.as_ptr(),
+            bio.as_ptr(),
+        ))?;
+        Ok(certs)
+    }
+}
+
+/// Encodes `certs` as a DER-encoded PKCS #7 certificate bundle.
+#[corresponds(PKCS7_bundle_certificates)]
+pub fn bundle_certificates(certs: &StackRef<X509>) -> Result<Vec<u8>, ErrorStack> {
+    unsafe {
+        let mut cbb: MaybeUninit<ffi::CBB> = MaybeUninit::uninit();
+        cvt(ffi::CBB_init(cbb.as_mut_ptr(), 0))?;
+        let mut data = ptr::null_mut();
+        let mut len = 0;
+        if ffi::PKCS7_bundle_certificates(cbb.as_mut_ptr(), certs.as_ptr()) != 1
+            || ffi::CBB_finish(cbb.as_mut_ptr(), &mut data, &mut len) != 1
+        {
+            ffi::CBB_cleanup(cbb.as_mut_ptr());
+            return Err(ErrorStack::get());
+        }
+        let der = slice::from_raw_parts(data, len).to_vec();
+        ffi::OPENSSL_free(data.cast());
+        Ok(der)
+    }
+}
+
+struct SignerParams {
+    cert: X509,
+    key: PKey<Private>,
+    digest: MessageDigest,
+}
+
+/// A builder for [`SignedData`] messages.
+#[derive(Default)]
+pub struct SignedDataBuilder {
+    signers: Vec<SignerParams>,
+    certificates: Vec<X509>,
+    detached: bool,
+}
+
+impl SignedDataBuilder {
+    /// Creates a builder for a message without signers.
+    #[must_use]
+    pub fn new() -> SignedDataBuilder {
+        SignedDataBuilder::default()
+    }
+
+    /// Adds a signer, signing with `key` and `digest`.
+    ///
+    /// `cert` is the certificate of `key` and is included in the message. RSA keys sign with
+    /// PKCS #1 v1.5 padding. Ed25519 keys must be used with SHA-512, as RFC 8419 requires.
+    pub fn add_signer(
+        &mut self,
+        cert: &X509Ref,
+        key: &PKeyRef<Private>,
+        digest: MessageDigest,
+    ) -> &mut SignedDataBuilder {
+        self.signers.push(SignerParams {
+            cert: cert.to_owned(),
+            key: key.to_owned(),
+            digest,
+        });
+        self
+    }
+
+    /// Includes `cert` in the message, typically an intermediate certificate of a signer.
+    pub fn add_certificate(&mut self, cert: &X509Ref) -> &mut SignedDataBuilder {
+        self.certificates.push(cert.to_owned());
+        self
+    }
+
+    /// Leaves the content out of the message, so that it is a detached signature.
+    pub fn detached(&mut self) -> &mut SignedDataBuilder {
+        self.detached = true;
+        self
+    }
+
+    /// Signs `data`.
+    ///
+    /// Each signer signs the `contentType` and `messageDigest` signed attributes.
+    pub fn sign(&self, data: &[u8]) -> Result<SignedData, ErrorStack> {
+        let mut digest_algorithms = vec![];
+        let mut signer_infos = vec![];
+        for signer in &self.signers {
+            let digest_algorithm = der::sequence(&[digest_oid(signer.digest)?]);
+            if !digest_algorithms.contains(&digest_algorithm) {
+                digest_algorithms.push(digest_algorithm.clone());
+            }
+            signer_infos.push(signer_info(signer, digest_algorithm, data)?);
+        }
+
+        let mut certificates = self
+            .signers
+            .iter()
+            .map(|signer| &signer.cert)
+            .chain(&self.certificates)
+            .map(|cert| cert.to_der())
+            .collect::<Result<Vec<_>, _>>()?;
+        certificates.sort();
+        certificates.dedup();
+
+        let mut encap_content_info = vec![der::oid(DATA)?];
+        if !self.detached {
+            encap_content_info.push(der::tlv(
+                der::context_constructed(0),
+                &der::tlv(der::OCTET_STRING, data),
+            ));
+        }
+        let mut signed_data = vec![
+            der::integer(der::INTEGER, 1),
+            der::set_of(&digest_algorithms),
+            der::sequence(&encap_content_info),
+        ];
+        if !certificates.is_empty() {
+            signed_data.push(implicit(
+                der::context_constructed(0),
+                der::set_of(&certificates),
+            ));
+        }
+        signed_data.push(der::set_of(&signer_infos));
+
+        let content_info = der::sequence(&[
+            der::oid(SIGNED_DATA)?,
+            der::tlv(der::context_constructed(0), &der::sequence(&signed_data)),
+        ]);
+        SignedData::from_der(&content_info)
+    }
+}
+
+fn signer_info(
+    signer: &SignerParams,
+    digest_algorithm: Vec<u8>,
+    data: &[u8],
+) -> Result<Vec<u8>, ErrorStack> {
+    let message_digest = hash(signer.digest, data)?;
+    let signed_attributes = der::set_of(&[
+        der::sequence(&[der::oid(CONTENT_TYPE)?, der::set_of(&[der::oid(DATA)?])]),
+        der::sequence(&[
+            der::oid(MESSAGE_DIGEST)?,
+            der::set_of(&[der::tlv(der::OCTET_STRING, &message_digest)]),
+        ]),
+    ]);
+
+    // The signature is over the DER encoding of the attributes as a SET OF
+    let (signature_algorithm, signature) = match signer.key.id() {
+        Id::RSA => (
+            der::sequence(&[der::oid(RSA_ENCRYPTION)?, der::tlv(der::NULL, &[])]),
+            Signer::new(signer.digest, &signer.key)?.sign_oneshot_to_vec(&signed_attributes)?,
+        ),
+        Id::EC => (
+            der::sequence(&[ecdsa_oid(signer.digest)?]),
+            Signer::new(signer.digest, &signer.key)?.sign_oneshot_to_vec(&signed_attributes)?,
+        ),
+        Id::ED25519 => {
+            if signer.digest.type_() != Nid::SHA512 {
+                return Err(ErrorStack::internal_error_str(
+                    "Ed25519 signers must use SHA-512",
+                ));
+            }
+            (
+                der::sequence(&[der::oid(ED25519)?]),
+                Signer::new_without_digest(&signer.key)?.sign_oneshot_to_vec(&signed_attributes)?,
+            )
+        }
+        _ => return Err(ErrorStack::internal_error_str("unsupported signer key")),
+    };
+
+    Ok(der::sequence(&[
+        der::integer(der::INTEGER, 1),
+        issuer_and_serial_number(&signer.cert)?,
+        digest_algorithm,
+        implicit(der::context_constructed(0), signed_attributes),
+        signature_algorithm,
+        der::tlv(der::OCTET_STRING, &signature),
+    ]))
+}
+
+/// A PKCS #7 `SignedData` message.
+pub struct SignedData {
+    der: Vec<u8>,
+    content_type: String,
+    content: Option<Vec<u8>>,
+    certificates: Vec<X509>,
+    signers: Vec<SignerInfo>,
+}
+
+impl SignedData {
+    /// Parses a DER-encoded `SignedData` message.
+    ///
+    /// BER encodings, such as the indefinite lengths of streamed messages, are accepted too.
+    pub fn from_der(der: &[u8]) -> Result<SignedData, ErrorStack> {
+        let der = der::from_ber(der)?;
+        let mut signed_data = content_info(&der, SIGNED_DATA)?;
+
+        der::parse_integer(signed_data.read(der::INTEGER)?)?;
+        signed_data.read(der::SET)?;
+
+        let mut encap_content_info = signed_data.read_sequence()?;
+        let content_type = der::parse_oid(encap_content_info.read(der::OBJECT_IDENTIFIER)?)?;
+        let content = match encap_content_info.read_optional(der::context_constructed(0))? {
+            Some(explicit) => {
+                let mut explicit = Reader::new(explicit);
+                let content = explicit.read(der::OCTET_STRING)?.to_vec();
+                explicit.finish()?;
+                Some(content)
+            }
+            None => None,
+        };
+        encap_content_info.finish()?;
+
+        let mut certificates = vec![];
+        if let Some(choices) = signed_data.read_optional(der::context_constructed(0))? {
+            let mut choices = Reader::new(choices);
+            while !choices.is_empty() {
+                // Attribute certificates and other formats are skipped
+                let choice = choices.read_element()?;
+                if choice[0] == der::SEQUENCE {
+                    certificates.push(X509::from_der(choice)?);
+                }
+            }
+        }
+        signed_data.read_optional(der::context_constructed(1))?;
+
+        let mut signers = vec![];
+        let mut signer_infos = Reader::new(signed_data.read(der::SET)?);
+        while !signer_infos.is_empty() {
+            signers.push(SignerInfo::parse(signer_infos.read_sequence()?)?);
+        }
+        signed_data.finish()?;
+
+        Ok(SignedData {
+            der,
+            content_type,
+            content,
+            certificates,
+            signers,
+        })
+    }
+
+    /// Serializes the message to DER.
+    pub fn to_der(&self) -> Result<Vec<u8>, ErrorStack> {
+        Ok(self.der.clone())
+    }
+
+    /// Returns the signed content, or `None` if the signature is detached.
+    #[must_use]
+    pub fn content(&self) -> Option<&[u8]> {
+        self.content.as_deref()
+    }
+
+    /// Returns the certificates included in the message.
+    #[must_use]
+    pub fn certificates(&self) -> &[X509] {
+        &self.certificates
+    }
+
+    /// Returns the signers of the message.
+    #[must_use]
+    pub fn signers(&self) -> &[SignerInfo] {
+        &self.signers
+    }
+
+    /// Verifies every signer of the message, returning their certificates.
+    ///
+    /// `content` is the signed content of a detached signature, and must be `None` otherwise. The
+    /// certificate of each signer must be included in the message, and must chain up to a
+    /// certificate of `store`, with the other certificates of the message as intermediates. The
+    /// verification parameters of `store` apply.
+    ///
+    /// Fails if the message has no signers or if any signer fails to verify.
+    pub fn verify(
+        &self,
+        content: Option<&[u8]>,
+        store: &X509StoreRef,
+    ) -> Result<Vec<X509>, ErrorStack> {
+        let content = match (&self.content, content) {
+            (Some(content), None) => content.as_slice(),
+            (None, Some(content)) => content,
+            (Some(_), Some(_)) => {
+                return Err(ErrorStack::internal_error_str(
+                    "content given for a message that is not detached",
+                ))
+            }
+            (None, None) => {
+                return Err(ErrorStack::internal_error_str(
+                    "no content given for a detached message",
+                ))
+            }
+        };
+        if self.signers.is_empty() {
+            return Err(ErrorStack::internal_error_str("message has no signers"));
+        }
+
+        let mut intermediates = Stack::new()?;
+        for cert in &self.certificates {
+            intermediates.push(cert.clone())?;
+        }
+
+        let mut signer_certs = vec![];
+        for signer in &self.signers {
+            let cert = self
+                .certificates
+                .iter()
+                .find(|cert| signer.id.matches(cert))
+                .ok_or_else(|| ErrorStack::internal_error_str("signer certificate not found"))?;
+            signer.verify(cert, &self.content_type, content)?;
+
+            let mut context = X509StoreContext::new()?;
+            context.init(store, cert, &intermediates, |ctx| {
+                if ctx.verify_cert()? {
+                    return Ok(());
+                }
+                match ctx.verify_result() {
+                    Err(error) => Err(ErrorStack::internal_error(error)),
+                    Ok(()) => Err(ErrorStack::internal_error_str(
+                        "certificate verification failed",
+                    )),
+                }
+            })?;
+            signer_certs.push(cert.clone());
+        }
+        Ok(signer_certs)
+    }
+}
+
+/// Identifies a certificate in a signer or a recipient.
+enum CertificateId {
+    IssuerAndSerialNumber(X509Name, BigNum),
+    SubjectKeyId(Vec<u8>),
+}
+
+impl CertificateId {
+    fn parse(reader: &mut Reader<'_>) -> Result<CertificateId, ErrorStack> {
+        if reader.peek_tag() == Some(der::SEQUENCE) {
+            let mut sequence = reader.read_sequence()?;
+            let issuer = X509Name::from_der(sequence.read_element()?)?;
+            let serial = sequence.read(der::INTEGER)?;
+            sequence.finish()?;
+            match serial.first() {
+                Some(byte) if byte & 0x80 == 0 => {}
+                _ => {
+                    return Err(ErrorStack::internal_error_str(
+                        "unsupported negative serial number",
+                    ))
+                }
+            }
+            Ok(CertificateId::IssuerAndSerialNumber(
+                issuer,
+                BigNum::from_slice(serial)?,
+            ))
+        } else {
+            let id = reader.read(der::context(0))?;
+            Ok(CertificateId::SubjectKeyId(id.to_vec()))
+        }
+    }
+
+    fn matches(&self, cert: &X509Ref) -> bool {
+        match self {
+            CertificateId::IssuerAndSerialNumber(issuer, serial) => {
+                let same_issuer = match (issuer.to_der(), cert.issuer_name().to_der()) {
+                    (Ok(issuer), Ok(cert_issuer)) => issuer == cert_issuer,
+                    _ => false,
+                };
+                same_issuer
+                    && cert
+                        .serial_number()
+                        .to_bn()
+                        .is_ok_and(|cert_serial| cert_serial == *serial)
+            }
+            CertificateId::SubjectKeyId(id) => cert
+                .subject_key_id()
+                .is_some_and(|cert_id| cert_id.as_slice() == id.as_slice()),
+        }
+    }
+}
+
+/// A signer of a [`SignedData`] message.
+pub struct SignerInfo {
+    id: CertificateId,
+    digest: MessageDigest,
+    signed_attributes: Option<SignedAttributes>,
+    signature_algorithm: String,
+    signature: Vec<u8>,
+}
+
+struct SignedAttributes {
+    /// The DER encoding of the attributes as a `SET OF`, which is what is signed.
+    der: Vec<u8>,
+    content_type: Option<String>,
+    message_digest: Option<Vec<u8>>,
+}
+
+impl SignerInfo {
+    fn parse(mut reader: Reader<'_>) -> Result<SignerInfo, ErrorStack> {
+        der::parse_integer(reader.read(der::INTEGER)?)?;
+        let id = CertificateId::parse(&mut reader)?;
+        let digest = digest_from_oid(&algorithm_oid(&mut reader)?)?;
+
+        let signed_attributes = if reader.peek_tag() == Some(der::context_constructed(0)) {
+            Some(SignedAttributes::parse(reader.read_element()?)?)
+        } else {
+            None
+        };
+
+        let signature_algorithm = algorithm_oid(&mut reader)?;
+        let signature = reader.read(der::OCTET_STRING)?.to_vec();
+        reader.read_optional(der::context_constructed(1))?;
+        reader.finish()?;
+
+        Ok(SignerInfo {
+            id,
+            digest,
+            signed_attributes,
+            signature_algorithm,
+            signature,
+        })
+    }
+
+    /// Returns the issuer of the signer certificate, if the signer is identified by it.
+    #[must_use]
+    pub fn issuer_name(&self) -> Option<&X509NameRef> {
+        match &self.id {
+            CertificateId::IssuerAndSerialNumber(issuer, _) => Some(issuer),
+            CertificateId::SubjectKeyId(_) => None,
+        }
+    }
+
+    /// Returns the serial number of the signer certificate, if the signer is identified by it.
+    #[must_use]
+    pub fn serial_number(&self) -> Option<&BigNumRef> {
+        match &self.id {
+            CertificateId::IssuerAndSerialNumber(_, serial) => Some(serial),
+            CertificateId::SubjectKeyId(_) => None,
+        }
+    }
+
+    /// Returns the subject key identifier of the signer certificate, if the signer is identified
+    /// by it.
+    #[must_use]
+    pub fn subject_key_id(&self) -> Option<&[u8]> {
+        match &self.id {
+            CertificateId::IssuerAndSerialNumber(..) => None,
+            CertificateId::SubjectKeyId(id) => Some(id),
+        }
+    }
+
+    /// Returns the digest the signer used.
+    #[must_use]
+    pub fn digest(&self) -> MessageDigest {
+        self.digest
+    }
+
+    /// Returns the signature.
+    #[must_use]
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    fn verify(&self, cert: &X509Ref, content_type: &str, content: &[u8]) -> Result<(), ErrorStack> {
+        if self.signature_algorithm == RSASSA_PSS {
+            return Err(ErrorStack::internal_error_str(
+                "unsupported signature algorithm",
+            ));
+        }
+
+        let signed = match &self.signed_attributes {
+            Some(attributes) => {
+                let digest = hash(self.digest, content)?;
+                if attributes.message_digest.as_deref() != Some(&*digest) {
+                    return Err(ErrorStack::internal_error_str("message digest mismatch"));
+                }
+                if attributes.content_type.as_deref() != Some(content_type) {
+                    return Err(ErrorStack::internal_error_str("content type mismatch"));
+                }
+                &attributes.der
+            }
+            None => content,
+        };
+
+        let key = cert.public_key()?;
+        if !is_signature_algorithm(&self.signature_algorithm, key.id(), self.digest) {
+            return Err(ErrorStack::internal_error_str(
+                "signature algorithm does not match the signer key",
+            ));
+        }
+        let mut verifier = if key.id() == Id::ED25519 {
+            Verifier::new_without_digest(&key)?
+        } else {
+            Verifier::new(self.digest, &key)?
+        };
+        if verifier.verify_oneshot(&self.signature, signed)? {
+            Ok(())
+        } else {
+            Err(ErrorStack::internal_error_str(
+                "signature verification failed",
+            ))
+        }
+    }
+}
+
+impl SignedAttributes {
+    fn parse(element: &[u8]) -> Result<SignedAttributes, ErrorStack> {
+        let mut content_type = None;
+        let mut message_digest = None;
+
+        let mut attributes = Reader::new(Reader::new(element).read(der::context_constructed(0))?);
+        while !attributes.is_empty() {
+            let mut attribute = attributes.read_sequence()?;
+            let attribute_type = der::parse_oid(attribute.read(der::OBJECT_IDENTIFIER)?)?;
+            let mut values = Reader::new(attribute.read(der::SET)?);
+            attribute.finish()?;
+            match &*attribute_type {
+                CONTENT_TYPE => {
+                    content_type = Some(der::parse_oid(values.read(der::OBJECT_IDENTIFIER)?)?);
+                    values.finish()?;
+                }
+                MESSAGE_DIGEST => {
+                    message_digest = Some(values.read(der::OCTET_STRING)?.to_vec());
+                    values.finish()?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(SignedAttributes {
+            der: implicit(der::SET, element.to_vec()),
+            content_type,
+            message_digest,
+        })
+    }
+}
+
+/// Encrypts `data` to `recipients` as a DER-encoded `EnvelopedData` message.
+///
+/// The recipients must have RSA keys, which transport the content key with PKCS #1 v1.5 padding.
+/// Only the AES-CBC ciphers are supported.
+pub fn encrypt(
+    recipients: &StackRef<X509>,
+    data: &[u8],
+    cipher: Cipher,
+) -> Result<Vec<u8>, ErrorStack> {
+    if !is_supported_cipher(cipher) {
+        return Err(ErrorStack::internal_error_str("unsupported cipher"));
+    }
+    let mut key = vec![0; cipher.key_len()];
+    rand_bytes(&mut key)?;
+    let mut iv = vec![0; cipher.iv_len().unwrap_or_default()];
+    rand_bytes(&mut iv)?;
+    let encrypted_content = symm::encrypt(cipher, &key, Some(&*iv), data)?;
+
+    let mut recipient_infos = vec![];
+    for recipient in recipients {
+        let public_key = recipient.public_key()?;
+        if public_key.id() != Id::RSA {
+            return Err(ErrorStack::internal_error_str(
+                "recipients must have RSA keys",
+            ));
+        }
+        let rsa = public_key.rsa()?;
+        let mut encrypted_key = vec![0; rsa.size() as usize];
+        let len = rsa.public_encrypt(&key, &mut encrypted_key, Padding::PKCS1)?;
+        encrypted_key.truncate(len);
+
+        recipient_infos.push(der::sequence(&[
+            der::integer(der::INTEGER, 0),
+            issuer_and_serial_number(recipient)?,
+            der::sequence(&[der::oid(RSA_ENCRYPTION)?, der::tlv(der::NULL, &[])]),
+            der::tlv(der::OCTET_STRING, &encrypted_key),
+        ]));
+    }
+    if recipient_infos.is_empty() {
+        return Err(ErrorStack::internal_error_str("no recipients"));
+    }
+
+    let enveloped_data = der::sequence(&[
+        der::integer(der::INTEGER, 0),
+        der::set_of(&recipient_infos),
+        der::sequence(&[
+            der::oid(DATA)?,
+            der::sequence(&[
+                der::oid(cipher.nid().short_name()?)?,
+                der::tlv(der::OCTET_STRING, &iv),
+            ]),
+            der::tlv(der::context(0), &encrypted_content),
+        ]),
+    ]);
+    Ok(der::sequence(&[
+        der::oid(ENVELOPED_DATA)?,
+        der::tlv(der::context_constructed(0), &enveloped_data),
+    ]))
+}
+
+/// Decrypts a DER-encoded `EnvelopedData` message with the key of the recipient `cert`.
+///
+/// BER encodings are accepted too. The recipient must use RSA key transport with PKCS #1 v1.5
+/// padding, and the content must be encrypted with AES-CBC.
+pub fn decrypt(der: &[u8], cert: &X509Ref, key: &PKeyRef<Private>) -> Result<Vec<u8>, ErrorStack> {
+    let der = der::from_ber(der)?;
+    let mut enveloped_data = content_info(&der, ENVELOPED_DATA)?;
+
+    der::parse_integer(enveloped_data.read(der::INTEGER)?)?;
+    enveloped_data.read_optional(der::context_constructed(0))?;
+
+    let mut encrypted_key = None;
+    let mut recipient_infos = Reader::new(enveloped_data.read(der::SET)?);
+    while !recipient_infos.is_empty() {
+        // Only key transport recipients are untagged, the other kinds are skipped
+        let (tag, contents) = recipient_infos.read_any()?;
+        if tag != der::SEQUENCE {
+            continue;
+        }
+        let mut recipient = Reader::new(contents);
+        der::parse_integer(recipient.read(der::INTEGER)?)?;
+        let id = CertificateId::parse(&mut recipient)?;
+        let algorithm = algorithm_oid(&mut recipient)?;
+        let recipient_key = recipient.read(der::OCTET_STRING)?;
+        recipient.finish()?;
+        if id.matches(cert) {
+            if algorithm != RSA_ENCRYPTION {
+                return Err(ErrorStack::internal_error_str(
+                    "unsupported key transport algorithm",
+                ));
+            }
+            encrypted_key = Some(recipient_key);
+            break;
+        }
+    }
+    let encrypted_key = encrypted_key
+        .ok_or_else(|| ErrorStack::internal_error_str("no recipient matches the certificate"))?;
+
+    let mut encrypted_content_info = enveloped_data.read_sequence()?;
+    encrypted_content_info.read(der::OBJECT_IDENTIFIER)?;
+    let mut algorithm = encrypted_content_info.read_sequence()?;
+    let cipher = Asn1Object::from_str(&der::parse_oid(algorithm.read(der::OBJECT_IDENTIFIER)?)?)
+        .ok()
+        .and_then(|object| Cipher::from_nid(object.nid()))
+        .filter(|&cipher| is_supported_cipher(cipher))
+        .ok_or_else(|| ErrorStack::internal_error_str("unsupported cipher"))?;
+    let iv = algorithm.read(der::OCTET_STRING)?;
+    algorithm.finish()?;
+    if cipher.iv_len() != Some(iv.len()) {
+        return Err(der::malformed());
+    }
+    let encrypted_content = encrypted_content_info.read_implicit_octet_string(0)?;
+    encrypted_content_info.finish()?;
+
+    // A failed key decryption continues with a random key, so that it cannot be told apart from
+    // a failed content decryption (RFC 3218). The padding is checked in constant time, and the
+    // random key is drawn either way.
+    let mut random_key = SecretBytes::zeroed(cipher.key_len());
+    rand_bytes(&mut random_key)?;
+    let rsa = key.rsa()?;
+    let mut block = SecretBytes::zeroed(rsa.size() as usize);
+    let content_key = match rsa.private_decrypt(encrypted_key, &mut block, Padding::NONE) {
+        Ok(len) if len == block.len() => pkcs1_unpad_or(&block, &random_key),
+        _ => {
+            // The encrypted key is too long or too large for the key, which is public
+            ErrorStack::clear();
+            random_key
+        }
+    };
+    symm::decrypt(cipher, &content_key, Some(iv), &encrypted_content)
+        .map_err(|_| ErrorStack::internal_error_str("decryption failed"))
+}
+
+/// Returns the message of the PKCS #1 v1.5 encryption block `block` if it is as long as
+/// `fallback`, and `fallback` otherwise, in constant time.
+fn pkcs1_unpad_or(block: &[u8], fallback: &[u8]) -> SecretBytes {
+    // 0x00 || 0x02 || at least 8 nonzero bytes || 0x00 || message
+    let Some(separator) = block
+        .len()
+        .checked_sub(fallback.len() + 1)
+        .filter(|&i| i >= 10)
+    else {
+        return SecretBytes::from(fallback);
+    };
+    let is_zero = |byte: u8| (u16::from(byte).wrapping_sub(1) >> 8) as u8;
+    let mut valid = is_zero(block[0]) & is_zero(block[1] ^ 2) & is_zero(block[separator]);
+    for &byte in &block[2..separator] {
+        valid &= !is_zero(byte);
+    }
+    let valid = hint::black_box(valid);
+
+    let mut message = SecretBytes::zeroed(fallback.len());
+    for ((out, &decrypted), &random) in message
+        .iter_mut()
+        .zip(&block[separator + 1..])
+        .zip(fallback)
+    {
+        *out = (decrypted & valid) | (random & !valid);
+    }
+    message
+}
+
+fn is_supported_cipher(cipher: Cipher) -> bool {
+    [
+        Cipher::aes_128_cbc(),
+        Cipher::aes_192_cbc(),
+        Cipher::aes_256_cbc(),
+    ]
+    .contains(&cipher)
+}
+
+/// Reads a `ContentInfo` of type `content_type`, returning a reader over its content.
+fn content_info<'a>(der: &'a [u8], content_type: &str) -> Result<Reader<'a>, ErrorStack> {
+    let mut reader = Reader::new(der);
+    let mut content_info = reader.read_sequence()?;
+    reader.finish()?;
+    if der::parse_oid(content_info.read(der::OBJECT_IDENTIFIER)?)? != content_type {
+        return Err(ErrorStack::internal_error_str("unexpected content type"));
+    }
+    let mut explicit = Reader::new(content_info.read(der::context_constructed(0))?);
+    content_info.finish()?;
+    let content = explicit.read_sequence()?;
+    explicit.finish()?;
+    Ok(content)
+}
+
+/// Reads an `AlgorithmIdentifier`, returning its OID. The parameters are ignored.
+fn algorithm_oid(reader: &mut Reader<'_>) -> Result<String, ErrorStack> {
+    let mut algorithm = reader.read_sequence()?;
+    der::parse_oid(algorithm.read(der::OBJECT_IDENTIFIER)?)
+}
+
+fn digest_oid(digest: MessageDigest) -> Result<Vec<u8>, ErrorStack> {
+    der::oid(digest.type_().short_name()?)
+}
+
+fn digest_from_oid(oid: &str) -> Result<MessageDigest, ErrorStack> {
+    Asn1Object::from_str(oid)
+        .ok()
+        .and_then(|object| MessageDigest::from_nid(object.nid()))
+        .ok_or_else(|| ErrorStack::internal_error_str("unsupported digest algorithm"))
+}
+
+fn ecdsa_oid(digest: MessageDigest) -> Result<Vec<u8>, ErrorStack> {
+    let nid = ecdsa_nid(digest)
+        .ok_or_else(|| ErrorStack::internal_error_str("unsupported digest algorithm"))?;
+    der::oid(nid.short_name()?)
+}
+
+fn ecdsa_nid(digest: MessageDigest) -> Option<Nid> {
+    match digest.type_() {
+        Nid::SHA1 => Some(Nid::ECDSA_WITH_SHA1),
+        Nid::SHA224 => Some(Nid::ECDSA_WITH_SHA224),
+        Nid::SHA256 => Some(Nid::ECDSA_WITH_SHA256),
+        Nid::SHA384 => Some(Nid::ECDSA_WITH_SHA384),
+        Nid::SHA512 => Some(Nid::ECDSA_WITH_SHA512),
+        _ => None,
+    }
+}
+
+fn rsa_nid(digest: MessageDigest) -> Option<Nid> {
+    match digest.type_() {
+        Nid::SHA1 => Some(Nid::SHA1WITHRSAENCRYPTION),
+        Nid::SHA224 => Some(Nid::SHA224WITHRSAENCRYPTION),
+        Nid::SHA256 => Some(Nid::SHA256WITHRSAENCRYPTION),
+        Nid::SHA384 => Some(Nid::SHA384WITHRSAENCRYPTION),
+        Nid::SHA512 => Some(Nid::SHA512WITHRSAENCRYPTION),
+        _ => None,
+    }
+}
+
+/// Returns `true` if `oid` is a signature algorithm for a key of type `key` with `digest`.
+///
+/// RSA signatures may be identified by `rsaEncryption` too, as RFC 3370 allows.
+fn is_signature_algorithm(oid: &str, key: Id, digest: MessageDigest) -> bool {
+    let nid = Asn1Object::from_str(oid).map_or(Nid::UNDEF, |object| object.nid());
+    match key {
+        Id::RSA => nid == Nid::RSAENCRYPTION || rsa_nid(digest) == Some(nid),
+        Id::EC => ecdsa_nid(digest) == Some(nid),
+        Id::ED25519 => oid == ED25519,
+        _ => false,
+    }
+}
+
+fn issuer_and_serial_number(cert: &X509Ref) -> Result<Vec<u8>, ErrorStack> {
+    Ok(der::sequence(&[
+        cert.issuer_name().to_der()?,
+        der::unsigned_integer(der::INTEGER, &cert.serial_number().to_bn()?.to_vec()),
+    ]))
+}
+
+/// Replaces the tag of an encoded value, for `IMPLICIT` tagging.
+fn implicit(tag: u8, mut encoded: Vec<u8>) -> Vec<u8> {
+    encoded[0] = tag;
+    encoded
+}
+
+fn cbs_init(data: &[u8]) -> ffi::CBS {
+    ffi::CBS {
+        data: data.as_ptr(),
+        len: data.len(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::asn1::Asn1Time;
+    use crate::base64;
+    use crate::bn::MsbOption;
+    use crate::ec::{EcGroup, EcKey};
+    use crate::ed25519::Ed25519PrivateKey;
+    use crate::rsa::Rsa;
+    use crate::x509::extension::BasicConstraints;
+    use crate::x509::store::{X509Store, X509StoreBuilder};
+
+    const MESSAGE: &[u8] = b"hello from openssl";
+
+    fn issue(
+        cn: &str,
+        key: &PKey<Private>,
+        issuer: Option<(&X509, &PKey<Private>)>,
+        ca: bool,
+    ) -> X509 {
+        let mut name = X509Name::builder().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, cn).unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        let mut serial = BigNum::new().unwrap();
+        serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+        builder
+            .set_serial_number(&serial.to_asn1_integer().unwrap())
+            .unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder
+            .set_issuer_name(issuer.map_or(&*name, |(cert, _)| cert.subject_name()))
+            .unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(30).unwrap())
+            .unwrap();
+        if ca {
+            let basic_constraints = BasicConstraints::new().critical().ca().build().unwrap();
+            builder.append_extension(&basic_constraints).unwrap();
+        }
+        builder
+            .sign(issuer.map_or(key, |(_, key)| key), MessageDigest::sha256())
+            .unwrap();
+        builder.build()
+    }
+
+    fn ec_key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    fn store(root: &X509) -> X509Store {
+        let mut store = X509StoreBuilder::new().unwrap();
+        store.add_cert(root).unwrap();
+        store.build()
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let root_key = ec_key();
+        let root = issue("root", &root_key, None, true);
+        let intermediate_key = ec_key();
+        let intermediate = issue(
+            "intermediate",
+            &intermediate_key,
+            Some((&root, &root_key)),
+            true,
+        );
+        let key = ec_key();
+        let cert = issue(
+            "signer",
+            &key,
+            Some((&intermediate, &intermediate_key)),
+            false,
+        );
+
+        let signed = SignedDataBuilder::new()
+            .add_signer(&cert, &key, MessageDigest::sha256())
+            .add_certificate(&intermediate)
+            .sign(MESSAGE)
+            .unwrap();
+        let signed = SignedData::from_der(&signed.to_der().unwrap()).unwrap();
+        assert_eq!(signed.content(), Some(MESSAGE));
+        assert_eq!(signed.certificates().len(), 2);
+        assert_eq!(signed.signers().len(), 1);
+        assert_eq!(
+            signed.signers()[0].serial_number().unwrap(),
+            &*cert.serial_number().to_bn().unwrap()
+        );
+
+        let signers = signed.verify(None, &store(&root)).unwrap();
+        assert_eq!(signers[0].to_der().unwrap(), cert.to_der().unwrap());
+        assert!(signed.verify(Some(MESSAGE), &store(&root)).is_err());
+
+        // Without the intermediate the chain cannot be built
+        let signed = SignedDataBuilder::new()
+            .add_signer(&cert, &key, MessageDigest::sha256())
+            .sign(MESSAGE)
+            .unwrap();
+        assert!(signed.verify(None, &store(&root)).is_err());
+    }
+
+    #[test]
+    fn detached() {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let cert = issue("signer", &key, None, true);
+        let ed25519_key = Ed25519PrivateKey::generate().unwrap().as_pkey().clone();
+        let ed25519_cert = issue("ed25519 signer", &ed25519_key, Some((&cert, &key)), false);
+
+        let signed = SignedDataBuilder::new()
+            .add_signer(&cert, &key, MessageDigest::sha384())
+            .add_signer(&ed25519_cert, &ed25519_key, MessageDigest::sha512())
+            .detached()
+            .sign(MESSAGE)
+            .unwrap();
+        assert_eq!(signed.content(), None);
+        assert_eq!(signed.signers().len(), 2);
+
+        let store = store(&cert);
+        assert_eq!(signed.verify(Some(MESSAGE), &store).unwrap().len(), 2);
+        assert!(signed.verify(Some(b"tampered"), &store).is_err());
+        assert!(signed.verify(None, &store).is_err());
+
+        assert!(SignedDataBuilder::new()
+            .add_signer(&ed25519_cert, &ed25519_key, MessageDigest::sha256())
+            .sign(MESSAGE)
+            .is_err());
+    }
+
+    #[test]
+    fn openssl_signed_data() {
+        let cert = X509::from_pem(include_bytes!("../test/pkcs7-cert.pem")).unwrap();
+        // Streamed by OpenSSL, with indefinite lengths
+        let signed = SignedData::from_der(include_bytes!("../test/pkcs7-signed.der")).unwrap();
+        assert_eq!(signed.content(), Some(MESSAGE));
+        assert_eq!(signed.signers()[0].digest().type_(), Nid::SHA256);
+
+        let signers = signed.verify(None, &store(&cert)).unwrap();
+        assert_eq!(signers[0].to_der().unwrap(), cert.to_der().unwrap());
+
+        let other_root = issue("other root", &ec_key(), None, true);
+        assert!(signed.verify(None, &store(&other_root)).is_err());
+    }
+
+    #[test]
+    fn signature_algorithm_mismatch() {
+        let key = ec_key();
+        let cert = issue("signer", &key, None, true);
+        let der = SignedDataBuilder::new()
+            .add_signer(&cert, &key, MessageDigest::sha256())
+            .sign(MESSAGE)
+            .unwrap()
+            .to_der()
+            .unwrap();
+        assert!(SignedData::from_der(&der)
+            .unwrap()
+            .verify(None, &store(&cert))
+            .is_ok());
+
+        // The signature algorithm is not signed, so it is replaced with ecdsa-with-SHA384
+        let ecdsa_with_sha256 = hex::decode("06082a8648ce3d040302").unwrap();
+        let position = der
+            .windows(ecdsa_with_sha256.len())
+            .rposition(|window| window == ecdsa_with_sha256)
+            .unwrap();
+        let mut tampered = der.clone();
+        tampered[position + ecdsa_with_sha256.len() - 1] = 0x03;
+        let signed = SignedData::from_der(&tampered).unwrap();
+        assert!(signed.verify(None, &store(&cert)).is_err());
+    }
+
+    #[test]
+    fn malformed_signed_data() {
+        let key = ec_key();
+        let cert = issue("signer", &key, None, true);
+        let der = SignedDataBuilder::new()
+            .add_signer(&cert, &key, MessageDigest::sha256())
+            .sign(MESSAGE)
+            .unwrap()
+            .to_der()
+            .unwrap();
+
+        assert!(SignedData::from_der(&[]).is_err());
+        assert!(SignedData::from_der(&der[..der.len() - 1]).is_err());
+        let mut trailing = der.clone();
+        trailing.push(0);
+        assert!(SignedData::from_der(&trailing).is_err());
+
+        let mut recipients = Stack::new().unwrap();
+        recipients.push(cert).unwrap();
+        let enveloped = encrypt(&recipients, MESSAGE, Cipher::aes_128_cbc()).unwrap();
+        assert!(SignedData::from_der(&enveloped).is_err());
+    }
+
+    #[test]
+    fn certificate_bundles() {
+        let root_key = ec_key();
+        let root = issue("root", &root_key, None, true);
+        let leaf = issue("leaf", &ec_key(), Some((&root, &root_key)), false);
+        let mut certs = Stack::new().unwrap();
+        certs.push(leaf.clone()).unwrap();
+        certs.push(root.clone()).unwrap();
+
+        let der = bundle_certificates(&certs).unwrap();
+        let bundled = certificates_from_der(&der).unwrap();
+        let bundled = bundled
+            .iter()
+            .map(|cert| cert.to_der().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(bundled, [leaf.to_der().unwrap(), root.to_der().unwrap()]);
+
+        let signed = SignedData::from_der(&der).unwrap();
+        assert_eq!(signed.certificates().len(), 2);
+        assert!(signed.signers().is_empty());
+        assert!(signed.verify(None, &store(&root)).is_err());
+
+        let pem = format!(
+            "-----BEGIN PKCS7-----\n{}\n-----END PKCS7-----\n",
+            base64::encode_block(&der)
+        );
+        assert_eq!(certificates_from_pem(pem.as_bytes()).unwrap().len(), 2);
+
+        // Bundles can be read from signed messages too
+        let key = ec_key();
+        let cert = issue("signer", &key, None, false);
+        let signed = SignedDataBuilder::new()
+            .add_signer(&cert, &key, MessageDigest::sha256())
+            .sign(MESSAGE)
+            .unwrap();
+        let bundled = certificates_from_der(&signed.to_der().unwrap()).unwrap();
+        assert_eq!(bundled.len(), 1);
+    }
+
+    #[test]
+    fn enveloped_data() {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let cert = issue("recipient", &key, None, false);
+        let other_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let other_cert = issue("other recipient", &other_key, None, false);
+        let mut recipients = Stack::new().unwrap();
+        recipients.push(cert.clone()).unwrap();
+        recipients.push(other_cert.clone()).unwrap();
+
+        let der = encrypt(&recipients, MESSAGE, Cipher::aes_128_cbc()).unwrap();
+        assert_eq!(decrypt(&der, &cert, &key).unwrap(), MESSAGE);
+        assert_eq!(decrypt(&der, &other_cert, &other_key).unwrap(), MESSAGE);
+        // A wrong key only fails to decrypt most of the time, as the padding may look valid
+        assert_ne!(
+            decrypt(&der, &other_cert, &key).ok().as_deref(),
+            Some(MESSAGE)
+        );
+
+        let stranger_key = ec_key();
+        let stranger = issue("stranger", &stranger_key, None, false);
+        assert!(decrypt(&der, &stranger, &key).is_err());
+        recipients.push(stranger).unwrap();
+        assert!(encrypt(&recipients, MESSAGE, Cipher::aes_128_cbc()).is_err());
+        assert!(encrypt(&Stack::new().unwrap(), MESSAGE, Cipher::aes_256_gcm()).is_err());
+    }
+
+    #[test]
+    fn malformed_enveloped_data() {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let cert = issue("recipient", &key, None, false);
+        let mut recipients = Stack::new().unwrap();
+        recipients.push(cert.clone()).unwrap();
+        let der = encrypt(&recipients, MESSAGE, Cipher::aes_256_cbc()).unwrap();
+
+        assert!(decrypt(&der[..der.len() - 1], &cert, &key).is_err());
+        let mut trailing = der.clone();
+        trailing.push(0);
+        assert!(decrypt(&trailing, &cert, &key).is_err());
+
+        // A corrupted content key fails like a wrong one, unless the padding happens to look valid
+        let header = [0x04, 0x82, 0x01, 0x00];
+        let position = der
+            .windows(header.len())
+            .position(|window| window == header)
+            .unwrap();
+        let mut tampered = der.clone();
+        tampered[position + header.len() + 10] ^= 1;
+        assert_ne!(
+            decrypt(&tampered, &cert, &key).ok().as_deref(),
+            Some(MESSAGE)
+        );
+    }
+
+    #[test]
+    fn pkcs1_unpadding() {
+        let fallback = [0xaa; 8];
+        let mut block = vec![0x00, 0x02];
+        block.extend_from_slice(&[0x11; 21]);
+        block.push(0x00);
+        block.extend_from_slice(b"content!");
+        assert_eq!(&*pkcs1_unpad_or(&block, &fallback), b"content!");
+
+        for (index, byte) in [(0, 0x01), (1, 0x01), (12, 0x00), (23, 0x11)] {
+            let mut invalid = block.clone();
+            invalid[index] = byte;
+            assert_eq!(&*pkcs1_unpad_or(&invalid, &fallback), fallback);
+        }
+        // The padding string must be at least 8 bytes long
+        let mut short = vec![0x00, 0x02];
+        short.extend_from_slice(&[0x11; 7]);
+        short.push(0x00);
+        short.extend_from_slice(b"content!");
+        assert_eq!(&*pkcs1_unpad_or(&short, &fallback), fallback);
+    }
+
+    #[test]
+    fn openssl_enveloped_data() {
+        let cert = X509::from_pem(include_bytes!("../test/pkcs7-cert.pem")).unwrap();
+        let key = PKey::private_key_from_pem(include_bytes!("../test/pkcs7-key.pem")).unwrap();
+        let der = include_bytes!("../test/pkcs7-enveloped.der");
+        assert_eq!(decrypt(der, &cert, &key).unwrap(), MESSAGE);
+    }
+}
@@ -133,6 +133,22 @@ where
         ffi::i2d_RSAPrivateKey
     }
 
+    private_key_to_pem_secret! {
+        /// Like [`private_key_to_pem`](Self::private_key_to_pem), but returns the key in a buffer
+        /// that is wiped when dropped.
+        #[corresponds(PEM_write_bio_RSAPrivateKey)]
+        private_key_to_pem_secret,
+        ffi::PEM_write_bio_RSAPrivateKey
+    }
+
+    to_der_secret! {
+        /// Like [`private_key_to_der`](Self::private_key_to_der), but returns the key in a buffer
+        /// that is wiped when dropped.
+        #[corresponds(i2d_RSAPrivateKey)]
+        private_key_to_der_secret,
+        ffi::i2d_RSAPrivateKey
+    }
+
     /// Decrypts data using the private key, returning the number of decrypted bytes.
     ///
     /// # Panics
@@ -650,6 +666,20 @@ mod test {
         assert!(Rsa::private_key_from_pem_passphrase(&pem, b"fizzbuzz").is_err());
     }
 
+    #[test]
+    fn test_private_key_secret() {
+        let key = Rsa::private_key_from_pem(include_bytes!("../test/rsa.pem")).unwrap();
+        assert!(key
+            .private_key_to_der_secret()
+            .unwrap()
+            .ct_eq(&key.private_key_to_der().unwrap()));
+        assert!(key
+            .private_key_to_pem_secret()
+            .unwrap()
+            .ct_eq(&key.private_key_to_pem().unwrap()));
+        assert!(key.d().to_vec_secret().ct_eq(&key.d().to_vec()));
+    }
+
     #[test]
     fn test_public_encrypt_private_decrypt_with_padding() {
         let key = include_bytes!("../test/rsa.pem.pub");
@@ -0,0 +1,136 @@
+//! Secret byte buffers.
+//!
+//! [`SecretBytes`] is returned by APIs that hand out key material, such as private keys, shared
+//! secrets and exported keying material. It wipes its contents with `OPENSSL_cleanse` when dropped
+//! and never prints them in its `Debug` output.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use crate::ffi;
+use crate::memcmp;
+
+/// A heap allocated buffer of secret bytes, wiped when dropped.
+///
+/// The contents are accessible through [`Deref`] to `[u8]`. Copying them out, for example with
+/// `to_vec`, creates copies that are not wiped.
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    /// Creates a zeroed buffer of `len` bytes, to be filled in place.
+    #[must_use]
+    pub fn zeroed(len: usize) -> Self {
+        Self(vec![0; len])
+    }
+
+    /// Shortens the buffer to `len` bytes, wiping the bytes that are removed.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.0.len() {
+            cleanse(&mut self.0[len..]);
+            self.0.truncate(len);
+        }
+    }
+
+    /// Compares the contents with `other` in constant time.
+    ///
+    /// Only the contents are compared in constant time, not the lengths.
+    #[must_use]
+    pub fn ct_eq(&self, other: &[u8]) -> bool {
+        self.0.len() == other.len() && memcmp::eq(&self.0, other)
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    /// Takes ownership of `bytes` without copying them.
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<&[u8]> for SecretBytes {
+    fn from(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+}
+
+impl Deref for SecretBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl DerefMut for SecretBytes {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl AsRef<[u8]> for SecretBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Clone for SecretBytes {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl PartialEq for SecretBytes {
+    /// Compares the contents in constant time, see [`SecretBytes::ct_eq`].
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other)
+    }
+}
+
+impl Eq for SecretBytes {}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBytes([redacted; {}])", self.0.len())
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        // Wipe the spare capacity as well, it may hold bytes from before a truncation
+        let capacity = self.0.capacity();
+        // SAFETY: the allocation is `capacity` bytes long
+        unsafe {
+            ffi::OPENSSL_cleanse(self.0.as_mut_ptr().cast(), capacity);
+        }
+    }
+}
+
+fn cleanse(buf: &mut [u8]) {
+    // SAFETY: pointer and length are valid
+    unsafe {
+        ffi::OPENSSL_cleanse(buf.as_mut_ptr().cast(), buf.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_is_redacted() {
+        let secret = SecretBytes::from(&b"hunter2"[..]);
+        assert_eq!(format!("{secret:?}"), "SecretBytes([redacted; 7])");
+    }
+
+    #[test]
+    fn deref_and_truncate() {
+        let mut secret = SecretBytes::zeroed(8);
+        secret.copy_from_slice(b"12345678");
+        secret.truncate(4);
+        assert_eq!(&*secret, b"1234");
+        assert!(secret.ct_eq(b"1234"));
+        assert!(!secret.ct_eq(b"1235"));
+        assert!(!secret.ct_eq(b"12345"));
+        assert_eq!(secret.clone().as_ref(), b"1234");
+    }
+}
@@ -0,0 +1,283 @@
+//! SPAKE2 password-authenticated key exchange over Curve25519.
+//!
+//! Two peers that share a low-entropy password, such as a pairing code, derive a strong shared
+//! key. An eavesdropper learns nothing about the password, and an active attacker can only test
+//! one password guess per exchange.
+//!
+//! One peer takes the [`Alice`] role and the other the [`Bob`] role. Each generates a message,
+//! sends it to the other and processes the message it receives:
+//!
+//! ```
+//! use rama_boring::spake2::{Alice, Bob, Spake2};
+//!
+//! let alice = Spake2::<Alice>::new(b"phone", b"tv").unwrap();
+//! let bob = Spake2::<Bob>::new(b"tv", b"phone").unwrap();
+//!
+//! let (alice, alice_msg) = alice.generate_msg(b"123456").unwrap();
+//! let (bob, bob_msg) = bob.generate_msg(b"123456").unwrap();
+//!
+//! let alice_key = alice.process_msg(&bob_msg).unwrap();
+//! let bob_key = bob.process_msg(&alice_msg).unwrap();
+//! assert_eq!(alice_key, bob_key);
+//! ```
+//!
+//! Each step consumes its context, so a context cannot generate two messages or process two
+//! messages. The exchange does not tell a peer whether the other used the same password: the
+//! keys just differ. The shared key must therefore be confirmed, for example by exchanging MACs
+//! of the transcript, before it is relied upon.
+//!
+//! This is the BoringSSL variant of SPAKE2, which is only compatible with other BoringSSL
+//! based implementations.
+
+use openssl_macros::corresponds;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::error::ErrorStack;
+use crate::secret::SecretBytes;
+use crate::{cvt, cvt_p, ffi};
+
+/// The length of SPAKE2 messages (32 bytes).
+pub const MESSAGE_LEN: usize = ffi::SPAKE2_MAX_MSG_SIZE as usize;
+
+/// The length of the shared key (64 bytes).
+pub const KEY_LEN: usize = ffi::SPAKE2_MAX_KEY_SIZE as usize;
+
+/// A tag type for the first role of the exchange.
+pub enum Alice {}
+
+/// A tag type for the second role of the exchange.
+pub enum Bob {}
+
+/// A SPAKE2 role, [`Alice`] or [`Bob`].
+///
+/// This trait is sealed and cannot be implemented outside of this crate.
+pub trait Spake2Role: private::Sealed {}
+
+impl Spake2Role for Alice {}
+
+impl Spake2Role for Bob {}
+
+mod private {
+    use crate::ffi;
+
+    pub trait Sealed {
+        /// The BoringSSL role.
+        const ROLE: ffi::spake2_role_t;
+    }
+
+    impl Sealed for super::Alice {
+        const ROLE: ffi::spake2_role_t = ffi::spake2_role_t::spake2_role_alice;
+    }
+
+    impl Sealed for super::Bob {
+        const ROLE: ffi::spake2_role_t = ffi::spake2_role_t::spake2_role_bob;
+    }
+}
+
+struct Context(*mut ffi::SPAKE2_CTX);
+
+// The context is only used through methods consuming its owner
+unsafe impl Send for Context {}
+unsafe impl Sync for Context {}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        unsafe { ffi::SPAKE2_CTX_free(self.0) }
+    }
+}
+
+/// A SPAKE2 context in role `R`, which has not generated its message yet.
+pub struct Spake2<R> {
+    ctx: Context,
+    role: PhantomData<R>,
+}
+
+impl<R> Spake2<R>
+where
+    R: Spake2Role,
+{
+    /// Creates a context for the peer named `my_name`, exchanging with the peer named
+    /// `their_name`.
+    ///
+    /// The names are bound into the key, so both peers must agree on them.
+    #[corresponds(SPAKE2_CTX_new)]
+    pub fn new(my_name: &[u8], their_name: &[u8]) -> Result<Spake2<R>, ErrorStack> {
+        unsafe {
+            ffi::init();
+            let ctx = cvt_p(ffi::SPAKE2_CTX_new(
+                R::ROLE,
+                my_name.as_ptr(),
+                my_name.len(),
+                their_name.as_ptr(),
+                their_name.len(),
+            ))?;
+            Ok(Spake2 {
+                ctx: Context(ctx),
+                role: PhantomData,
+            })
+        }
+    }
+
+    /// Generates the message to send to the other peer, blinded with `password`.
+    #[corresponds(SPAKE2_generate_msg)]
+    pub fn generate_msg(
+        self,
+        password: &[u8],
+    ) -> Result<(Spake2Pending<R>, [u8; MESSAGE_LEN]), ErrorStack> {
+        let mut msg = [0; MESSAGE_LEN];
+        let mut msg_len = 0;
+        unsafe {
+            cvt(ffi::SPAKE2_generate_msg(
+                self.ctx.0,
+                msg.as_mut_ptr(),
+                &mut msg_len,
+                msg.len(),
+                password.as_ptr(),
+                password.len(),
+            ))?;
+        }
+        if msg_len != MESSAGE_LEN {
+            return Err(ErrorStack::internal_error_str(
+                "unexpected SPAKE2 message length",
+            ));
+        }
+
+        let pending = Spake2Pending {
+            ctx: self.ctx,
+            role: PhantomData,
+        };
+        Ok((pending, msg))
+    }
+}
+
+impl<R> fmt::Debug for Spake2<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Spake2").finish_non_exhaustive()
+    }
+}
+
+/// A SPAKE2 context in role `R` waiting for the message of the other peer.
+pub struct Spake2Pending<R> {
+    ctx: Context,
+    role: PhantomData<R>,
+}
+
+impl<R> Spake2Pending<R>
+where
+    R: Spake2Role,
+{
+    /// Processes the message of the other peer, returning the shared key.
+    ///
+    /// The keys of both peers are equal if, and only if, they used the same password and names.
+    #[corresponds(SPAKE2_process_msg)]
+    pub fn process_msg(self, their_msg: &[u8]) -> Result<SecretBytes, ErrorStack> {
+        let mut key = SecretBytes::zeroed(KEY_LEN);
+        let mut key_len = 0;
+        unsafe {
+            cvt(ffi::SPAKE2_process_msg(
+                self.ctx.0,
+                key.as_mut_ptr(),
+                &mut key_len,
+                key.len(),
+                their_msg.as_ptr(),
+                their_msg.len(),
+            ))?;
+        }
+        key.truncate(key_len);
+        Ok(key)
+    }
+}
+
+impl<R> fmt::Debug for Spake2Pending<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Spake2Pending").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // There are no known-answer tests, as SPAKE2_generate_msg draws its private scalar from the
+    // RNG and the public API of BoringSSL has no way to fix it. BoringSSL's own spake25519_test.cc
+    // has no fixed vectors either, for the same reason.
+
+    fn exchange(
+        alice_names: (&[u8], &[u8]),
+        alice_password: &[u8],
+        bob_names: (&[u8], &[u8]),
+        bob_password: &[u8],
+    ) -> (SecretBytes, SecretBytes) {
+        let alice = Spake2::<Alice>::new(alice_names.0, alice_names.1).unwrap();
+        let bob = Spake2::<Bob>::new(bob_names.0, bob_names.1).unwrap();
+        let (alice, alice_msg) = alice.generate_msg(alice_password).unwrap();
+        let (bob, bob_msg) = bob.generate_msg(bob_password).unwrap();
+        (
+            alice.process_msg(&bob_msg).unwrap(),
+            bob.process_msg(&alice_msg).unwrap(),
+        )
+    }
+
+    #[test]
+    fn matching() {
+        let (alice, bob) = exchange(
+            (b"alice", b"bob"),
+            b"password",
+            (b"bob", b"alice"),
+            b"password",
+        );
+        assert_eq!(alice.len(), KEY_LEN);
+        assert_eq!(alice, bob);
+
+        // Every exchange yields a fresh key
+        let (again, _) = exchange(
+            (b"alice", b"bob"),
+            b"password",
+            (b"bob", b"alice"),
+            b"password",
+        );
+        assert_ne!(alice, again);
+
+        let (alice, bob) = exchange((b"", b""), b"", (b"", b""), b"");
+        assert_eq!(alice, bob);
+    }
+
+    #[test]
+    fn mismatch() {
+        let (alice, bob) = exchange(
+            (b"alice", b"bob"),
+            b"password",
+            (b"bob", b"alice"),
+            b"passw0rd",
+        );
+        assert_ne!(alice, bob);
+
+        let (alice, bob) = exchange(
+            (b"alice", b"bob"),
+            b"password",
+            (b"bob", b"eve"),
+            b"password",
+        );
+        assert_ne!(alice, bob);
+    }
+
+    #[test]
+    fn same_role() {
+        let first = Spake2::<Alice>::new(b"a", b"b").unwrap();
+        let second = Spake2::<Alice>::new(b"b", b"a").unwrap();
+        let (first, first_msg) = first.generate_msg(b"password").unwrap();
+        let (second, second_msg) = second.generate_msg(b"password").unwrap();
+        assert_ne!(
+            first.process_msg(&second_msg).unwrap(),
+            second.process_msg(&first_msg).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_message() {
+        let alice = Spake2::<Alice>::new(b"alice", b"bob").unwrap();
+        let (alice, msg) = alice.generate_msg(b"password").unwrap();
+        assert!(alice.process_msg(&msg[1..]).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::iter;
+use std::sync::{Arc, RwLock};
+
+use crate::asn1::{Asn1Object, Asn1Time};
+use crate::bn::{BigNum, MsbOption};
+use crate::ec::{EcGroup, EcKey};
+use crate::error::ErrorStack;
+use crate::hash::MessageDigest;
+use crate::nid::Nid;
+use crate::pkey::{PKey, PKeyRef, Private};
+use crate::sha::sha256;
+use crate::ssl::{
+    select_next_proto, AlpnError, ClientHello, ExtensionType, NameType, SelectCertError,
+};
+use crate::x509::extension::SubjectAlternativeName;
+use crate::x509::{X509Name, X509Ref, X509};
+
+/// The `acme-tls/1` ALPN protocol of the ACME `tls-alpn-01` challenge, in wire format.
+///
+/// It can be passed to [`SslContextBuilder::set_alpn_protos`] to offer it.
+///
+/// [`SslContextBuilder::set_alpn_protos`]: crate::ssl::SslContextBuilder::set_alpn_protos
+pub const ACME_TLS_ALPN_PROTOCOL: &[u8] = b"\x0aacme-tls/1";
+
+/// The `id-pe-acmeIdentifier` extension, from RFC 8737.
+const ACME_IDENTIFIER_OID: &str = "1.3.6.1.5.5.7.1.31";
+
+/// A certificate answering an ACME `tls-alpn-01` challenge, with its private key.
+///
+/// The certificate is self-signed, and has the domain as its only subject alternative name and
+/// the critical `acmeIdentifier` extension holding the SHA-256 digest of the key authorization,
+/// as RFC 8737 requires.
+pub struct AcmeChallengeCertificate {
+    cert: X509,
+    key: PKey<Private>,
+}
+
+impl AcmeChallengeCertificate {
+    /// Creates the challenge certificate for `domain` and `key_authorization`, with a new P-256
+    /// key.
+    pub fn new(
+        domain: &str,
+        key_authorization: &str,
+    ) -> Result<AcmeChallengeCertificate, ErrorStack> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+        AcmeChallengeCertificate::with_key(domain, key_authorization, key)
+    }
+
+    /// Creates the challenge certificate for `domain` and `key_authorization`, with `key`.
+    pub fn with_key(
+        domain: &str,
+        key_authorization: &str,
+        key: PKey<Private>,
+    ) -> Result<AcmeChallengeCertificate, ErrorStack> {
+        let mut name = X509Name::builder()?;
+        name.append_entry_by_nid(Nid::COMMONNAME, domain)?;
+        let name = name.build();
+
+        let mut builder = X509::builder()?;
+        builder.set_version(2)?;
+        let mut serial = BigNum::new()?;
+        serial.rand(159, MsbOption::MAYBE_ZERO, false)?;
+        builder.set_serial_number(&serial.to_asn1_integer()?)?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(&name)?;
+        builder.set_pubkey(&key)?;
+        builder.set_not_before(&Asn1Time::days_from_now(0)?)?;
+        builder.set_not_after(&Asn1Time::days_from_now(7)?)?;
+
+        let subject_alt_name = SubjectAlternativeName::new()
+            .dns(domain)
+            .build(&builder.x509v3_context(None, None))?;
+        builder.append_extension(&subject_alt_name)?;
+        // extnValue is an OCTET STRING holding the digest
+        let mut acme_identifier = vec![0x04, 32];
+        acme_identifier.extend_from_slice(&sha256(key_authorization.as_bytes()));
+        builder.append_extension_der_payload(
+            &Asn1Object::from_str(ACME_IDENTIFIER_OID)?,
+            true,
+            &acme_identifier,
+        )?;
+
+        builder.sign(&key, MessageDigest::sha256())?;
+        Ok(AcmeChallengeCertificate {
+            cert: builder.build(),
+            key,
+        })
+    }
+
+    /// Returns the certificate.
+    #[must_use]
+    pub fn cert(&self) -> &X509Ref {
+        &self.cert
+    }
+
+    /// Returns the private key of the certificate.
+    #[must_use]
+    pub fn key(&self) -> &PKeyRef<Private> {
+        &self.key
+    }
+}
+
+/// The pending ACME `tls-alpn-01` challenges a server answers, by domain.
+///
+/// Clones share the same challenges, so that challenges can be added while a server built with
+/// [`SslAcceptorBuilder::set_acme_challenges`] is running.
+///
+/// [`SslAcceptorBuilder::set_acme_challenges`]: crate::ssl::SslAcceptorBuilder::set_acme_challenges
+#[derive(Clone, Default)]
+pub struct AcmeChallenges(Arc<RwLock<HashMap<String, Arc<AcmeChallengeCertificate>>>>);
+
+impl AcmeChallenges {
+    /// Creates an empty set of challenges.
+    #[must_use]
+    pub fn new() -> AcmeChallenges {
+        AcmeChallenges::default()
+    }
+
+    /// Adds the challenge for `domain`, replacing any previous one.
+    pub fn insert(&self, domain: &str, key_authorization: &str) -> Result<(), ErrorStack> {
+        let cert = AcmeChallengeCertificate::new(domain, key_authorization)?;
+        self.insert_certificate(domain, cert);
+        Ok(())
+    }
+
+    /// Adds a challenge certificate for `domain`, replacing any previous one.
+    pub fn insert_certificate(&self, domain: &str, cert: AcmeChallengeCertificate) {
+        self.0
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(domain.to_ascii_lowercase(), Arc::new(cert));
+    }
+
+    /// Removes the challenge for `domain`, once it is validated.
+    pub fn remove(&self, domain: &str) {
+        self.0
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&domain.to_ascii_lowercase());
+    }
+
+    fn get(&self, domain: &str) -> Option<Arc<AcmeChallengeCertificate>> {
+        self.0
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&domain.to_ascii_lowercase())
+            .cloned()
+    }
+
+    /// Serves the challenge certificate if the client offers only the `acme-tls/1` protocol.
+    ///
+    /// This is meant to be called from a select-certificate callback. Returns `Ok(true)` if the
+    /// handshake is a challenge and the certificate was set, and `Ok(false)` if it is not a
+    /// challenge. Fails if it is a challenge for a domain without one.
+    pub fn select_certificate(
+        &self,
+        client_hello: &mut ClientHello<'_>,
+    ) -> Result<bool, SelectCertError> {
+        let alpn =
+            client_hello.get_extension(ExtensionType::APPLICATION_LAYER_PROTOCOL_NEGOTIATION);
+        if !alpn.is_some_and(is_acme_alpn) {
+            return Ok(false);
+        }
+        let cert = client_hello
+            .servername(NameType::HOST_NAME)
+            .and_then(|domain| self.get(domain))
+            .ok_or(SelectCertError::ERROR)?;
+
+        let ssl = client_hello.ssl_mut();
+        ssl.set_certificate(&cert.cert)
+            .map_err(|_| SelectCertError::ERROR)?;
+        ssl.set_private_key(&cert.key)
+            .map_err(|_| SelectCertError::ERROR)?;
+        Ok(true)
+    }
+}
+
+/// Returns `true` if the ALPN extension body `alpn` lists `acme-tls/1` and nothing else.
+fn is_acme_alpn(alpn: &[u8]) -> bool {
+    alpn.strip_prefix(&(ACME_TLS_ALPN_PROTOCOL.len() as u16).to_be_bytes())
+        == Some(ACME_TLS_ALPN_PROTOCOL)
+}
+
+/// The ALPN select callback of [`SslAcceptorBuilder::set_acme_challenges`].
+///
+/// Selects `acme-tls/1` for challenges, and otherwise the first protocol of `server` the client
+/// supports, like [`select_next_proto`].
+///
+/// [`SslAcceptorBuilder::set_acme_challenges`]: crate::ssl::SslAcceptorBuilder::set_acme_challenges
+pub(crate) fn select_alpn<'a>(server: &[u8], client: &'a [u8]) -> Result<&'a [u8], AlpnError> {
+    if client == ACME_TLS_ALPN_PROTOCOL {
+        return Ok(&client[1..]);
+    }
+    let selected = select_next_proto(server, client).ok_or(AlpnError::NOACK)?;
+    // The selected protocol must be borrowed from the client protocols, which outlive `server`
+    protocols(client)
+        .find(|protocol| *protocol == selected)
+        .ok_or(AlpnError::NOACK)
+}
+
+/// Returns the protocols of an ALPN protocol list in wire format.
+fn protocols(mut list: &[u8]) -> impl Iterator<Item = &[u8]> {
+    iter::from_fn(move || {
+        let (&len, rest) = list.split_first()?;
+        let protocol = rest.get(..usize::from(len))?;
+        list = &rest[protocol.len()..];
+        Some(protocol)
+    })
+}
@@ -1,11 +1,14 @@
-use std::io::{Read, Write};
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::{self, Read, Write};
 use std::ops::{Deref, DerefMut};
 
 use crate::dh::Dh;
 use crate::error::ErrorStack;
+use crate::ssl::acme::{self, AcmeChallenges};
 use crate::ssl::{
-    HandshakeError, Ssl, SslContext, SslContextBuilder, SslContextRef, SslMethod, SslMode,
-    SslOptions, SslRef, SslStream, SslVerifyMode,
+    EchRejected, HandshakeError, Ssl, SslContext, SslContextBuilder, SslContextRef, SslMethod,
+    SslMode, SslOptions, SslRef, SslStream, SslVerifyMode,
 };
 use crate::version;
 use std::net::IpAddr;
@@ -218,6 +221,19 @@ impl ConnectConfiguration {
         self.verify_hostname = verify_hostname;
     }
 
+    /// Creates a new configuration from the same [`SslContext`], with the same SNI and hostname
+    /// verification settings.
+    ///
+    /// Settings made directly on the `Ssl` of this configuration are not carried over. This is
+    /// useful to reconnect, e.g. when retrying after ECH was rejected.
+    pub fn fresh(&self) -> Result<ConnectConfiguration, ErrorStack> {
+        Ssl::new(self.ssl.ssl_context()).map(|ssl| ConnectConfiguration {
+            ssl,
+            sni: self.sni,
+            verify_hostname: self.verify_hostname,
+        })
+    }
+
     /// Returns an [`Ssl`] configured to connect to the provided domain.
     ///
     /// The domain, if given, is used for SNI (if it is not an IP address)
@@ -271,6 +287,100 @@ impl ConnectConfiguration {
             .map_err(HandshakeError::SetupFailure)?
             .handshake()
     }
+
+    /// Attempts a client-side TLS session offering Encrypted Client Hello with `ech_config_list`.
+    ///
+    /// If the server rejects ECH, its certificate is verified against the ECH public name instead
+    /// of `domain`, and [`EchConnectError::Rejected`] reports the configs to retry with. The
+    /// connection itself can't be used anymore.
+    pub fn connect_ech<S>(
+        mut self,
+        domain: Option<&str>,
+        ech_config_list: &[u8],
+        stream: S,
+    ) -> Result<SslStream<S>, EchConnectError<HandshakeError<S>>>
+    where
+        S: Read + Write,
+    {
+        self.ssl
+            .set_ech_config_list(ech_config_list)
+            .map_err(|e| EchConnectError::Handshake(HandshakeError::SetupFailure(e)))?;
+
+        self.connect(domain, stream)
+            .map_err(|e| match e.ech_rejected() {
+                Some(rejected) => EchConnectError::Rejected(rejected),
+                None => EchConnectError::Handshake(e),
+            })
+    }
+
+    /// Like [`Self::connect_ech`], but retries once with the server's retry configs if ECH is
+    /// rejected.
+    ///
+    /// `reconnect` is called to open a fresh stream for the second attempt. The new session is
+    /// created with [`Self::fresh`], so settings made directly on the `Ssl` of this configuration
+    /// are not carried over.
+    ///
+    /// If the server securely disabled ECH, no retry is attempted, as that would send the
+    /// ClientHello in the clear. It's up to the caller to decide whether to reconnect without ECH.
+    pub fn connect_ech_with_retry<S, F>(
+        self,
+        domain: Option<&str>,
+        ech_config_list: &[u8],
+        stream: S,
+        reconnect: F,
+    ) -> Result<SslStream<S>, EchConnectError<HandshakeError<S>>>
+    where
+        S: Read + Write,
+        F: FnOnce() -> io::Result<S>,
+    {
+        let retry_config = self
+            .fresh()
+            .map_err(|e| EchConnectError::Handshake(HandshakeError::SetupFailure(e)))?;
+
+        match self.connect_ech(domain, ech_config_list, stream) {
+            Err(EchConnectError::Rejected(rejected)) => {
+                let Some(retry_configs) = rejected.retry_configs() else {
+                    return Err(EchConnectError::Rejected(rejected));
+                };
+                let stream = reconnect().map_err(EchConnectError::Io)?;
+                retry_config.connect_ech(domain, retry_configs, stream)
+            }
+            result => result,
+        }
+    }
+}
+
+/// An error from an ECH-aware connection attempt.
+///
+/// `E` is the error type of the handshake, [`HandshakeError`] for blocking streams.
+#[derive(Debug)]
+pub enum EchConnectError<E> {
+    /// The server rejected ECH.
+    Rejected(EchRejected),
+    /// The handshake failed for another reason.
+    Handshake(E),
+    /// Opening a fresh stream for the retry failed.
+    Io(io::Error),
+}
+
+impl<E: StdError + 'static> StdError for EchConnectError<E> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            EchConnectError::Rejected(e) => Some(e),
+            EchConnectError::Handshake(e) => Some(e),
+            EchConnectError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for EchConnectError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EchConnectError::Rejected(e) => e.fmt(f),
+            EchConnectError::Handshake(e) => e.fmt(f),
+            EchConnectError::Io(e) => write!(f, "failed to reconnect for ECH retry: {e}"),
+        }
+    }
 }
 
 impl Deref for ConnectConfiguration {
@@ -405,6 +515,30 @@ impl SslAcceptor {
 pub struct SslAcceptorBuilder(SslContextBuilder);
 
 impl SslAcceptorBuilder {
+    /// Answers the ACME `tls-alpn-01` challenges of `challenges`.
+    ///
+    /// When a client offers only the `acme-tls/1` ALPN protocol, the challenge certificate for
+    /// its server name is served and `acme-tls/1` is negotiated. The handshake fails if there is
+    /// no challenge for the server name. Other handshakes use the configured certificate, and
+    /// negotiate the first protocol of `protocols` the client supports, as
+    /// [`select_next_proto`] does. `protocols` is in wire format, like the argument of
+    /// [`SslContextBuilder::set_alpn_protos`], and may be empty to negotiate no protocol.
+    ///
+    /// This replaces the select-certificate and ALPN select callbacks. To keep other callbacks,
+    /// call [`AcmeChallenges::select_certificate`] from a custom select-certificate callback,
+    /// and select `acme-tls/1` in the ALPN select callback when the client protocols are
+    /// [`ACME_TLS_ALPN_PROTOCOL`].
+    ///
+    /// [`select_next_proto`]: crate::ssl::select_next_proto
+    /// [`ACME_TLS_ALPN_PROTOCOL`]: crate::ssl::ACME_TLS_ALPN_PROTOCOL
+    pub fn set_acme_challenges(&mut self, challenges: AcmeChallenges, protocols: &[u8]) {
+        self.set_select_certificate_callback(move |mut client_hello| {
+            challenges.select_certificate(&mut client_hello).map(|_| ())
+        });
+        let protocols = protocols.to_vec();
+        self.set_alpn_select_callback(move |_, client| acme::select_alpn(&protocols, client));
+    }
+
     /// Consumes the builder, returning a `SslAcceptor`.
     #[must_use]
     pub fn build(self) -> SslAcceptor {
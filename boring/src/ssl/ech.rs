@@ -1,10 +1,14 @@
 use crate::ffi;
 use crate::libc_types::c_int;
 use foreign_types::ForeignType;
+use openssl_macros::corresponds;
+use std::collections::VecDeque;
+use std::ffi::CString;
+use std::{fmt, ptr, slice};
 
 use crate::error::ErrorStack;
-use crate::hpke::HpkeKey;
-use crate::{cvt_0i, cvt_p};
+use crate::hpke::{Aead, HpkeKey, HpkeKeyRef, Kdf};
+use crate::{cvt_0i, cvt_p, try_int};
 
 pub struct SslEchKeysBuilder {
     keys: SslEchKeys,
@@ -61,3 +65,227 @@ impl SslEchKeys {
         SslEchKeysBuilder::new()
     }
 }
+
+/// The `ECHConfig` version implemented by BoringSSL (draft-ietf-tls-esni-13 and later).
+const ECH_CONFIG_VERSION: u16 = 0xfe0d;
+
+/// Builds an encoded `ECHConfig` for an [`HpkeKey`].
+///
+/// The HPKE KEM and public key are taken from the key. By default the config offers the cipher
+/// suites BoringSSL picks (HKDF-SHA256 with AES-128-GCM and ChaCha20-Poly1305); use
+/// [`add_cipher_suite`](Self::add_cipher_suite) to advertise a different set.
+pub struct EchConfigBuilder {
+    config_id: u8,
+    public_name: String,
+    max_name_len: u8,
+    cipher_suites: Vec<(Kdf, Aead)>,
+}
+
+impl EchConfigBuilder {
+    /// Creates a builder for a config identified by `config_id`, using `public_name` as the
+    /// name sent in the outer ClientHello.
+    #[must_use]
+    pub fn new(config_id: u8, public_name: &str) -> EchConfigBuilder {
+        EchConfigBuilder {
+            config_id,
+            public_name: public_name.to_owned(),
+            max_name_len: 0,
+            cipher_suites: Vec::new(),
+        }
+    }
+
+    /// Sets the longest server name clients are expected to send, used to pad the inner
+    /// ClientHello. Defaults to 0, meaning clients use their own padding policy.
+    pub fn set_max_name_len(&mut self, max_name_len: u8) {
+        self.max_name_len = max_name_len;
+    }
+
+    /// Advertises an HPKE cipher suite. Suites are offered in the order they are added.
+    pub fn add_cipher_suite(&mut self, kdf: Kdf, aead: Aead) {
+        self.cipher_suites.push((kdf, aead));
+    }
+
+    /// Encodes the config for `key`.
+    #[corresponds(SSL_marshal_ech_config)]
+    pub fn build(self, key: HpkeKey) -> Result<EchConfig, ErrorStack> {
+        let mut config = unsafe {
+            ffi::init();
+            let mut out = ptr::null_mut();
+            let mut out_len = 0;
+            cvt_0i(ffi::SSL_marshal_ech_config(
+                &mut out,
+                &mut out_len,
+                self.config_id,
+                key.as_ptr(),
+                CString::new(self.public_name)
+                    .map_err(ErrorStack::internal_error)?
+                    .as_ptr(),
+                usize::from(self.max_name_len),
+            ))?;
+            let config = slice::from_raw_parts(out, out_len).to_vec();
+            ffi::OPENSSL_free(out.cast());
+            config
+        };
+
+        if !self.cipher_suites.is_empty() {
+            config = replace_cipher_suites(&config, &self.cipher_suites)?;
+        }
+
+        Ok(EchConfig {
+            config_id: self.config_id,
+            config,
+            key,
+        })
+    }
+}
+
+/// Rewrites the `cipher_suites` of an encoded `ECHConfig`.
+fn replace_cipher_suites(config: &[u8], suites: &[(Kdf, Aead)]) -> Result<Vec<u8>, ErrorStack> {
+    let malformed = || ErrorStack::internal_error_str("malformed ECHConfig");
+
+    // version(2) || length(2) || config_id(1) || kem_id(2) || public_key<2> || cipher_suites<2>
+    let read_u16 = |at: usize| -> Result<usize, ErrorStack> {
+        let bytes = config.get(at..at + 2).ok_or_else(malformed)?;
+        Ok(usize::from(u16::from_be_bytes([bytes[0], bytes[1]])))
+    };
+    let public_key_len = read_u16(7)?;
+    let suites_at = 9 + public_key_len;
+    let suites_end = suites_at + 2 + read_u16(suites_at)?;
+    if suites_end > config.len() {
+        return Err(malformed());
+    }
+
+    let mut contents = config[4..suites_at].to_vec();
+    let suites_len = try_int::<usize, u16>(suites.len() * 4)?;
+    contents.extend_from_slice(&suites_len.to_be_bytes());
+    for (kdf, aead) in suites {
+        contents.extend_from_slice(&kdf.id().to_be_bytes());
+        contents.extend_from_slice(&aead.id().to_be_bytes());
+    }
+    contents.extend_from_slice(&config[suites_end..]);
+
+    let mut out = Vec::with_capacity(contents.len() + 4);
+    out.extend_from_slice(&ECH_CONFIG_VERSION.to_be_bytes());
+    out.extend_from_slice(&try_int::<usize, u16>(contents.len())?.to_be_bytes());
+    out.extend_from_slice(&contents);
+    Ok(out)
+}
+
+/// An encoded `ECHConfig` along with its private key.
+#[derive(Clone)]
+pub struct EchConfig {
+    config_id: u8,
+    config: Vec<u8>,
+    key: HpkeKey,
+}
+
+impl EchConfig {
+    /// Returns a builder for a config identified by `config_id`.
+    #[must_use]
+    pub fn builder(config_id: u8, public_name: &str) -> EchConfigBuilder {
+        EchConfigBuilder::new(config_id, public_name)
+    }
+
+    /// The config identifier.
+    #[must_use]
+    pub fn config_id(&self) -> u8 {
+        self.config_id
+    }
+
+    /// The encoded `ECHConfig`, as expected by [`SslEchKeysBuilder::add_key`].
+    #[must_use]
+    pub fn ech_config(&self) -> &[u8] {
+        &self.config
+    }
+
+    /// The HPKE key of this config.
+    #[must_use]
+    pub fn key(&self) -> &HpkeKeyRef {
+        &self.key
+    }
+
+    /// Encodes an `ECHConfigList` for clients, holding only this config.
+    pub fn ech_config_list(&self) -> Result<Vec<u8>, ErrorStack> {
+        ech_config_list([self])
+    }
+}
+
+impl fmt::Debug for EchConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EchConfig")
+            .field("config_id", &self.config_id)
+            .field("config", &format_args!("[{}]", self.config.len()))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Encodes an `ECHConfigList` for clients from `configs`, in order of preference.
+pub fn ech_config_list<'a>(
+    configs: impl IntoIterator<Item = &'a EchConfig>,
+) -> Result<Vec<u8>, ErrorStack> {
+    let mut list = vec![0; 2];
+    for config in configs {
+        list.extend_from_slice(&config.config);
+    }
+    let len = try_int::<usize, u16>(list.len() - 2)?;
+    list[..2].copy_from_slice(&len.to_be_bytes());
+    Ok(list)
+}
+
+/// Keeps track of several generations of ECH keys.
+///
+/// Clients may hold on to an `ECHConfigList` for a while (e.g. from a cached DNS HTTPS record), so
+/// a server should keep accepting older keys for some time after rotating. Only the current
+/// generation is published and sent as retry configs though, so clients that got rejected retry
+/// with a fresh config.
+pub struct EchKeyRotation {
+    generations: VecDeque<Vec<EchConfig>>,
+    max_generations: usize,
+}
+
+impl EchKeyRotation {
+    /// Creates an empty rotation keeping at most `max_generations` generations of keys, including
+    /// the current one.
+    #[must_use]
+    pub fn new(max_generations: usize) -> EchKeyRotation {
+        EchKeyRotation {
+            generations: VecDeque::new(),
+            max_generations: max_generations.max(1),
+        }
+    }
+
+    /// Makes `configs` the current generation, dropping the oldest generation if there are too
+    /// many.
+    pub fn rotate(&mut self, configs: Vec<EchConfig>) {
+        self.generations.push_front(configs);
+        self.generations.truncate(self.max_generations);
+    }
+
+    /// The configs of the current generation.
+    #[must_use]
+    pub fn current(&self) -> &[EchConfig] {
+        self.generations
+            .front()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Encodes the `ECHConfigList` to publish, holding the current generation only.
+    pub fn ech_config_list(&self) -> Result<Vec<u8>, ErrorStack> {
+        ech_config_list(self.current())
+    }
+
+    /// Builds the server side keys.
+    ///
+    /// All retained generations can be decrypted, but only the current generation is sent as
+    /// retry configs.
+    pub fn build_keys(&self) -> Result<SslEchKeys, ErrorStack> {
+        let mut builder = SslEchKeys::builder()?;
+        for (generation, configs) in self.generations.iter().enumerate() {
+            for config in configs {
+                builder.add_key(generation == 0, config.ech_config(), config.key.clone())?;
+            }
+        }
+        Ok(builder.build())
+    }
+}
@@ -265,6 +265,82 @@ fn fmt_mid_handshake_error(
     write!(f, " {}", s.error())
 }
 
+impl<S> HandshakeError<S> {
+    /// Returns the details of the server rejecting Encrypted Client Hello, if that is why the
+    /// handshake failed.
+    ///
+    /// BoringSSL only reports the rejection after the server's certificate has been verified
+    /// against the ECH public name, so the retry configs can be trusted.
+    #[must_use]
+    pub fn ech_rejected(&self) -> Option<EchRejected> {
+        match self {
+            HandshakeError::Failure(s) => EchRejected::from_mid_handshake(s),
+            _ => None,
+        }
+    }
+}
+
+/// The server rejected Encrypted Client Hello.
+///
+/// The connection can't be used anymore. Reconnect using the
+/// [retry configs](Self::retry_configs), or without ECH if there are none.
+#[derive(Debug, Clone)]
+pub struct EchRejected {
+    public_name: Vec<u8>,
+    retry_configs: Option<Vec<u8>>,
+}
+
+impl EchRejected {
+    pub(crate) fn from_mid_handshake<S>(s: &MidHandshakeSslStream<S>) -> Option<EchRejected> {
+        let rejected = s.error().ssl_error().is_some_and(|stack| {
+            stack
+                .errors()
+                .iter()
+                .any(|e| e.library_reason(ffi::ERR_LIB_SSL) == Some(ffi::SSL_R_ECH_REJECTED))
+        });
+        if !rejected {
+            return None;
+        }
+
+        Some(EchRejected {
+            public_name: s.ssl().get_ech_name_override()?.to_vec(),
+            retry_configs: s.ssl().get_ech_retry_configs().map(<[u8]>::to_vec),
+        })
+    }
+
+    /// The ECH public name the server's certificate was verified against.
+    #[must_use]
+    pub fn public_name(&self) -> &[u8] {
+        &self.public_name
+    }
+
+    /// The `ECHConfigList` the server asks to retry with.
+    ///
+    /// `None` means the server has securely disabled ECH, and the connection may be retried
+    /// without ECH.
+    #[must_use]
+    pub fn retry_configs(&self) -> Option<&[u8]> {
+        self.retry_configs.as_deref()
+    }
+}
+
+impl fmt::Display for EchRejected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ECH rejected by {}",
+            String::from_utf8_lossy(&self.public_name)
+        )?;
+        if self.retry_configs.is_some() {
+            f.write_str(", retry with the provided configs")
+        } else {
+            f.write_str(", ECH disabled by the server")
+        }
+    }
+}
+
+impl error::Error for EchRejected {}
+
 impl<S> From<ErrorStack> for HandshakeError<S> {
     fn from(e: ErrorStack) -> HandshakeError<S> {
         HandshakeError::SetupFailure(e)
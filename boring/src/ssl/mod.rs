@@ -84,6 +84,7 @@ use crate::ex_data::Index;
 use crate::hmac::HmacCtxRef;
 use crate::nid::Nid;
 use crate::pkey::{HasPrivate, PKeyRef, Params, Private};
+use crate::secret::SecretBytes;
 use crate::srtp::{SrtpProtectionProfile, SrtpProtectionProfileRef};
 use crate::ssl::bio::BioMethod;
 use crate::ssl::callbacks::*;
@@ -98,18 +99,24 @@ use crate::x509::{
 use crate::{cvt, cvt_0i, cvt_n, cvt_p, init, try_int};
 use crate::{ffi, free_data_box};
 
+pub use self::acme::{AcmeChallengeCertificate, AcmeChallenges, ACME_TLS_ALPN_PROTOCOL};
 pub use self::async_callbacks::{
     AsyncPrivateKeyMethod, AsyncPrivateKeyMethodError, AsyncSelectCertError, BoxCustomVerifyFinish,
     BoxCustomVerifyFuture, BoxGetSessionFinish, BoxGetSessionFuture, BoxPrivateKeyMethodFinish,
     BoxPrivateKeyMethodFuture, BoxSelectCertFinish, BoxSelectCertFuture, ExDataFuture,
 };
 pub use self::connector::{
-    ConnectConfiguration, SslAcceptor, SslAcceptorBuilder, SslConnector, SslConnectorBuilder,
+    ConnectConfiguration, EchConnectError, SslAcceptor, SslAcceptorBuilder, SslConnector,
+    SslConnectorBuilder,
 };
 pub use self::credential::{SslCredential, SslCredentialBuilder, SslCredentialRef};
-pub use self::ech::{SslEchKeys, SslEchKeysRef};
-pub use self::error::{Error, ErrorCode, HandshakeError};
+pub use self::ech::{
+    ech_config_list, EchConfig, EchConfigBuilder, EchKeyRotation, SslEchKeys, SslEchKeysBuilder,
+    SslEchKeysRef,
+};
+pub use self::error::{EchRejected, Error, ErrorCode, HandshakeError};
 
+mod acme;
 mod async_callbacks;
 mod bio;
 mod callbacks;
@@ -2769,6 +2776,16 @@ impl SslSessionRef {
         unsafe { SSL_SESSION_get_master_key(self.as_ptr(), buf.as_mut_ptr(), buf.len()) }
     }
 
+    /// Returns a copy of the master key, in a buffer that is wiped when dropped.
+    #[corresponds(SSL_SESSION_get_master_key)]
+    #[must_use]
+    pub fn master_key_secret(&self) -> SecretBytes {
+        let mut buf = SecretBytes::zeroed(self.master_key_len());
+        let len = self.master_key(&mut buf);
+        buf.truncate(len);
+        buf
+    }
+
     /// Returns the time at which the session was established, in seconds since the Unix epoch.
     #[corresponds(SSL_SESSION_get_time)]
     #[allow(clippy::useless_conversion)]
@@ -3601,6 +3618,20 @@ impl SslRef {
         }
     }
 
+    /// Like [`export_keying_material`](Self::export_keying_material), but returns `len` bytes of
+    /// keying material in a new buffer that is wiped when dropped.
+    #[corresponds(SSL_export_keying_material)]
+    pub fn export_keying_material_secret(
+        &self,
+        len: usize,
+        label: &str,
+        context: Option<&[u8]>,
+    ) -> Result<SecretBytes, ErrorStack> {
+        let mut out = SecretBytes::zeroed(len);
+        self.export_keying_material(&mut out, label, context)?;
+        Ok(out)
+    }
+
     /// Sets the session to be used.
     ///
     /// This should be called before the handshake to attempt to reuse a previously established
@@ -0,0 +1,114 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use crate::asn1::Asn1Object;
+use crate::pkey::PKey;
+use crate::sha::sha256;
+use crate::ssl::{
+    AcmeChallenges, HandshakeError, SslAcceptor, SslConnector, SslMethod, SslStream, SslVerifyMode,
+    ACME_TLS_ALPN_PROTOCOL,
+};
+use crate::x509::X509;
+
+use super::{CERT, KEY};
+
+const KEY_AUTHORIZATION: &str = "token.thumbprint";
+
+/// Connects like an ACME server validating a `tls-alpn-01` challenge for `domain`.
+fn validate(
+    port: u16,
+    domain: &str,
+    alpn: &[u8],
+) -> Result<SslStream<TcpStream>, HandshakeError<TcpStream>> {
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    // The challenge certificate is self-signed
+    connector.set_verify(SslVerifyMode::NONE);
+    if !alpn.is_empty() {
+        connector.set_alpn_protos(alpn).unwrap();
+    }
+    let connector = connector.build();
+
+    let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    let mut config = connector.configure().unwrap();
+    config.set_verify_hostname(false);
+    config.connect(Some(domain), stream)
+}
+
+#[test]
+fn tls_alpn_01() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let challenges = AcmeChallenges::new();
+    challenges.insert("Example.com", KEY_AUTHORIZATION).unwrap();
+
+    let server_challenges = challenges.clone();
+    let t = thread::spawn(move || {
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        acceptor
+            .set_private_key(&PKey::private_key_from_pem(KEY).unwrap())
+            .unwrap();
+        acceptor
+            .set_certificate(&X509::from_pem(CERT).unwrap())
+            .unwrap();
+        acceptor.set_acme_challenges(server_challenges, b"\x02h2\x08http/1.1");
+        let acceptor = acceptor.build();
+
+        for expect_success in [true, true, true, false] {
+            let stream = listener.accept().unwrap().0;
+            match acceptor.accept(stream) {
+                Ok(mut stream) => {
+                    assert!(expect_success);
+                    stream.write_all(&[0]).unwrap();
+                }
+                Err(_) => assert!(!expect_success),
+            }
+        }
+    });
+
+    // The challenge certificate is served for acme-tls/1
+    let mut stream = validate(port, "example.com", ACME_TLS_ALPN_PROTOCOL).unwrap();
+    stream.read_exact(&mut [0]).unwrap();
+    assert_eq!(
+        stream.ssl().selected_alpn_protocol(),
+        Some(&b"acme-tls/1"[..])
+    );
+    let cert = stream.ssl().peer_certificate().unwrap();
+    assert_eq!(
+        cert.subject_alt_names().unwrap()[0].dnsname(),
+        Some("example.com")
+    );
+    let acme_identifier = Asn1Object::from_str("1.3.6.1.5.5.7.1.31").unwrap();
+    let extension = cert
+        .extensions()
+        .find(|extension| extension.object().to_string() == acme_identifier.to_string())
+        .unwrap();
+    assert!(extension.critical());
+    let mut expected = vec![0x04, 32];
+    expected.extend_from_slice(&sha256(KEY_AUTHORIZATION.as_bytes()));
+    assert_eq!(extension.data().as_slice(), expected);
+
+    // Other handshakes get the configured certificate
+    let mut stream = validate(port, "example.com", b"").unwrap();
+    stream.read_exact(&mut [0]).unwrap();
+    assert_eq!(
+        stream.ssl().peer_certificate().unwrap().to_der().unwrap(),
+        X509::from_pem(CERT).unwrap().to_der().unwrap()
+    );
+    assert_eq!(stream.ssl().selected_alpn_protocol(), None);
+
+    // Other clients negotiate the server protocols
+    let mut stream = validate(port, "example.com", b"\x08http/1.1\x02h2").unwrap();
+    stream.read_exact(&mut [0]).unwrap();
+    assert_eq!(stream.ssl().selected_alpn_protocol(), Some(&b"h2"[..]));
+    assert_eq!(
+        stream.ssl().peer_certificate().unwrap().to_der().unwrap(),
+        X509::from_pem(CERT).unwrap().to_der().unwrap()
+    );
+
+    // A challenge for an unknown domain is rejected
+    assert!(validate(port, "example.org", ACME_TLS_ALPN_PROTOCOL).is_err());
+
+    t.join().unwrap();
+}
@@ -1,7 +1,14 @@
-use crate::hpke::HpkeKey;
-use crate::ssl::ech::SslEchKeys;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+
+use crate::hpke::{Aead, HpkeKey, Kdf, Kem};
+use crate::ssl::ech::{EchConfig, EchKeyRotation, SslEchKeys};
 use crate::ssl::test::server::{ClientSslBuilder, Server};
-use crate::ssl::HandshakeError;
+use crate::ssl::{
+    EchConnectError, HandshakeError, Ssl, SslConnector, SslContext, SslFiletype, SslMethod,
+};
+use crate::x509::X509VerifyError;
 
 // For future reference, these configs are generated by building the bssl tool (the binary is built
 // alongside boringssl) and running the following command:
@@ -17,7 +24,7 @@ static ECH_KEY_2: &[u8] = include_bytes!("../../../test/echkey-2");
 
 fn bootstrap_ech(config: &[u8], key: &[u8], list: &[u8]) -> (Server, ClientSslBuilder) {
     let server = {
-        let key = HpkeKey::dhkem_p256_sha256(key).unwrap();
+        let key = HpkeKey::new(Kem::x25519_hkdf_sha256(), key).unwrap();
         let mut ech_keys_builder = SslEchKeys::builder().unwrap();
         ech_keys_builder.add_key(true, config, key).unwrap();
         let ech_keys = ech_keys_builder.build();
@@ -45,7 +52,7 @@ fn ech() {
 
 #[test]
 fn ech_with_raw_cipher_list() {
-    let key = HpkeKey::dhkem_p256_sha256(ECH_KEY).unwrap();
+    let key = HpkeKey::new(Kem::x25519_hkdf_sha256(), ECH_KEY).unwrap();
     let mut ech_keys_builder = SslEchKeys::builder().unwrap();
     ech_keys_builder.add_key(true, ECH_CONFIG, key).unwrap();
     let ech_keys = ech_keys_builder.build();
@@ -94,3 +101,296 @@ fn ech_grease() {
     let ssl_stream = client.connect();
     assert!(!ssl_stream.ssl().ech_accepted());
 }
+
+fn bootstrap_ech_keys(ech_keys: &SslEchKeys, list: &[u8]) -> (Server, ClientSslBuilder) {
+    let mut builder = Server::builder();
+    builder.ctx().set_ech_keys(ech_keys).unwrap();
+    let server = builder.build();
+
+    let mut client = server.client_with_root_ca().build().builder();
+    client.ssl().set_ech_config_list(list).unwrap();
+    client.ssl().set_hostname("foobar.com").unwrap();
+
+    (server, client)
+}
+
+fn generate_config(config_id: u8) -> EchConfig {
+    generate_config_with_public_name(config_id, "ech.com")
+}
+
+fn generate_config_with_public_name(config_id: u8, public_name: &str) -> EchConfig {
+    let key = HpkeKey::generate(Kem::x25519_hkdf_sha256()).unwrap();
+    EchConfig::builder(config_id, public_name)
+        .build(key)
+        .unwrap()
+}
+
+#[test]
+fn ech_generated_config() {
+    let config = generate_config(7);
+    assert_eq!(config.config_id(), 7);
+
+    let mut ech_keys_builder = SslEchKeys::builder().unwrap();
+    ech_keys_builder
+        .add_key(true, config.ech_config(), config.key().to_owned())
+        .unwrap();
+    let ech_keys = ech_keys_builder.build();
+
+    let (_server, client) = bootstrap_ech_keys(&ech_keys, &config.ech_config_list().unwrap());
+    let ssl_stream = client.connect();
+    assert!(ssl_stream.ssl().ech_accepted());
+}
+
+#[test]
+fn ech_config_matches_bssl_tool() {
+    // `ECH_CONFIG` holds config id 0 and the public name ech.com
+    let key = HpkeKey::new(Kem::x25519_hkdf_sha256(), ECH_KEY).unwrap();
+    let config = EchConfig::builder(0, "ech.com").build(key).unwrap();
+    assert_eq!(config.ech_config(), ECH_CONFIG);
+    assert_eq!(config.ech_config_list().unwrap(), ECH_CONFIG_LIST);
+}
+
+#[test]
+fn ech_config_custom_suites() {
+    let key = HpkeKey::generate(Kem::x25519_hkdf_sha256()).unwrap();
+    let mut builder = EchConfig::builder(3, "ech.com");
+    builder.set_max_name_len(64);
+    builder.add_cipher_suite(Kdf::hkdf_sha256(), Aead::chacha20_poly1305());
+    let config = builder.build(key).unwrap();
+
+    let mut rotation = EchKeyRotation::new(1);
+    rotation.rotate(vec![config]);
+    let ech_keys = rotation.build_keys().unwrap();
+
+    let (_server, client) = bootstrap_ech_keys(&ech_keys, &rotation.ech_config_list().unwrap());
+    let ssl_stream = client.connect();
+    assert!(ssl_stream.ssl().ech_accepted());
+}
+
+#[test]
+fn ech_rotation_accepts_previous_generation() {
+    let old = generate_config(1);
+    let old_list = old.ech_config_list().unwrap();
+
+    let mut rotation = EchKeyRotation::new(2);
+    rotation.rotate(vec![old]);
+    rotation.rotate(vec![generate_config(2)]);
+    assert_eq!(rotation.current().len(), 1);
+    assert_eq!(rotation.current()[0].config_id(), 2);
+
+    let ech_keys = rotation.build_keys().unwrap();
+    let (_server, client) = bootstrap_ech_keys(&ech_keys, &old_list);
+    let ssl_stream = client.connect();
+    assert!(ssl_stream.ssl().ech_accepted());
+}
+
+#[test]
+fn ech_rotation_retry_configs_are_current() {
+    let old = generate_config(1);
+    let old_list = old.ech_config_list().unwrap();
+
+    let mut rotation = EchKeyRotation::new(1);
+    rotation.rotate(vec![old]);
+    rotation.rotate(vec![generate_config(2)]);
+
+    let ech_keys = rotation.build_keys().unwrap();
+    let (_server, client) = bootstrap_ech_keys(&ech_keys, &old_list);
+
+    let HandshakeError::Failure(failed_ssl_stream) = client.connect_err() else {
+        panic!("wrong HandshakeError failure variant!");
+    };
+    assert!(!failed_ssl_stream.ssl().ech_accepted());
+    assert_eq!(
+        failed_ssl_stream.ssl().get_ech_retry_configs(),
+        Some(rotation.ech_config_list().unwrap().as_ref())
+    );
+}
+
+/// Accepts `connections` connections, tolerating handshakes aborted by the client.
+fn spawn_ech_server(ech_keys: &SslEchKeys, connections: usize) -> (SocketAddr, JoinHandle<()>) {
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.set_certificate_chain_file("test/cert.pem").unwrap();
+    ctx.set_private_key_file("test/key.pem", SslFiletype::PEM)
+        .unwrap();
+    ctx.set_ech_keys(ech_keys).unwrap();
+    let ctx = ctx.build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = thread::spawn(move || {
+        for _ in 0..connections {
+            let socket = listener.accept().unwrap().0;
+            if let Ok(mut stream) = Ssl::new(&ctx).unwrap().accept(socket) {
+                stream.write_all(&[0]).unwrap();
+            }
+        }
+    });
+
+    (addr, handle)
+}
+
+fn ech_connector() -> SslConnector {
+    let mut connector = SslConnector::no_default_verify_builder(SslMethod::tls()).unwrap();
+    connector.set_ca_file("test/root-ca.pem").unwrap();
+    connector.build()
+}
+
+/// The public name must be valid for the server certificate, which is only valid for foobar.com.
+fn stale_rotation() -> (EchKeyRotation, Vec<u8>) {
+    let stale_list = generate_config_with_public_name(1, "foobar.com")
+        .ech_config_list()
+        .unwrap();
+    let mut rotation = EchKeyRotation::new(1);
+    rotation.rotate(vec![generate_config_with_public_name(2, "foobar.com")]);
+    (rotation, stale_list)
+}
+
+#[test]
+fn ech_connect_reports_rejection() {
+    let (rotation, stale_list) = stale_rotation();
+    let (addr, handle) = spawn_ech_server(&rotation.build_keys().unwrap(), 1);
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let result =
+        ech_connector()
+            .configure()
+            .unwrap()
+            .connect_ech(Some("foobar.com"), &stale_list, stream);
+
+    let Err(EchConnectError::Rejected(rejected)) = result else {
+        panic!("expected ECH rejection");
+    };
+    assert_eq!(rejected.public_name(), b"foobar.com");
+    assert_eq!(
+        rejected.retry_configs(),
+        Some(rotation.ech_config_list().unwrap().as_ref())
+    );
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn ech_connect_with_retry() {
+    let (rotation, stale_list) = stale_rotation();
+    let (addr, handle) = spawn_ech_server(&rotation.build_keys().unwrap(), 2);
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut ssl_stream = ech_connector()
+        .configure()
+        .unwrap()
+        .connect_ech_with_retry(Some("foobar.com"), &stale_list, stream, || {
+            TcpStream::connect(addr)
+        })
+        .unwrap();
+    assert!(ssl_stream.ssl().ech_accepted());
+
+    let mut buf = [1];
+    ssl_stream.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, [0]);
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn ech_connect_accepted_first_time() {
+    let (rotation, _) = stale_rotation();
+    let (addr, handle) = spawn_ech_server(&rotation.build_keys().unwrap(), 1);
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let ssl_stream = ech_connector()
+        .configure()
+        .unwrap()
+        .connect_ech_with_retry(
+            Some("foobar.com"),
+            &rotation.ech_config_list().unwrap(),
+            stream,
+            || -> std::io::Result<TcpStream> { panic!("no retry expected") },
+        )
+        .unwrap();
+    assert!(ssl_stream.ssl().ech_accepted());
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn ech_connect_rejected_verifies_public_name() {
+    // The server certificate is only valid for the public name, not for the name the client
+    // wanted to reach, so the handshake only gets to the rejection through the public name
+    let (rotation, stale_list) = stale_rotation();
+    let (addr, handle) = spawn_ech_server(&rotation.build_keys().unwrap(), 1);
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let result = ech_connector().configure().unwrap().connect_ech(
+        Some("secret.example"),
+        &stale_list,
+        stream,
+    );
+
+    let Err(EchConnectError::Rejected(rejected)) = result else {
+        panic!("expected ECH rejection");
+    };
+    assert_eq!(rejected.public_name(), b"foobar.com");
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn ech_connect_rejected_checks_public_name() {
+    // The server certificate is valid for the name the client wanted to reach, but not for the
+    // public name, which is what a rejected ECH handshake is verified against
+    let stale_list = generate_config_with_public_name(1, "ech.com")
+        .ech_config_list()
+        .unwrap();
+    let mut rotation = EchKeyRotation::new(1);
+    rotation.rotate(vec![generate_config_with_public_name(2, "ech.com")]);
+    let (addr, handle) = spawn_ech_server(&rotation.build_keys().unwrap(), 1);
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let result =
+        ech_connector()
+            .configure()
+            .unwrap()
+            .connect_ech(Some("foobar.com"), &stale_list, stream);
+
+    let Err(EchConnectError::Handshake(HandshakeError::Failure(failed))) = result else {
+        panic!("expected a certificate verification failure");
+    };
+    assert!(!failed.ssl().ech_accepted());
+    assert_eq!(
+        failed.ssl().verify_result(),
+        Err(X509VerifyError::HOSTNAME_MISMATCH)
+    );
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn ech_connect_with_retry_checks_public_name() {
+    // The public name of the stale and the retry configs is not valid for the server certificate,
+    // so the rejection cannot be authenticated and its retry configs must not be used
+    let stale_list = generate_config_with_public_name(1, "ech.com")
+        .ech_config_list()
+        .unwrap();
+    let mut rotation = EchKeyRotation::new(1);
+    rotation.rotate(vec![generate_config_with_public_name(2, "ech.com")]);
+    let (addr, handle) = spawn_ech_server(&rotation.build_keys().unwrap(), 1);
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let result = ech_connector().configure().unwrap().connect_ech_with_retry(
+        Some("foobar.com"),
+        &stale_list,
+        stream,
+        || -> std::io::Result<TcpStream> { panic!("no retry expected") },
+    );
+
+    let Err(EchConnectError::Handshake(HandshakeError::Failure(failed))) = result else {
+        panic!("expected a certificate verification failure");
+    };
+    assert!(!failed.ssl().ech_accepted());
+    assert_eq!(
+        failed.ssl().verify_result(),
+        Err(X509VerifyError::HOSTNAME_MISMATCH)
+    );
+
+    handle.join().unwrap();
+}
@@ -25,6 +25,7 @@ use crate::x509::{X509Name, X509};
 
 use super::CompliancePolicy;
 
+mod acme;
 mod cert_compressor;
 mod cert_verify;
 mod custom_verify;
@@ -1358,6 +1359,12 @@ fn keying_export() {
         .export_keying_material(&mut buf, label, Some(context))
         .unwrap();
 
+    let secret = stream
+        .ssl()
+        .export_keying_material_secret(32, label, Some(context))
+        .unwrap();
+    assert_eq!(&*secret, &buf);
+
     stream.read_exact(&mut [0]).unwrap();
 
     let buf2 = guard.join().unwrap();
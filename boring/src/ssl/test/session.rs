@@ -29,6 +29,7 @@ fn active_session() {
     let mut buf = vec![0; len + 1];
     let copied = session.master_key(&mut buf);
     assert_eq!(copied, len);
+    assert_eq!(&*session.master_key_secret(), &buf[..len]);
 }
 
 #[test]
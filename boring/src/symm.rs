@@ -255,6 +255,22 @@ impl Cipher {
         unsafe { Cipher(ffi::EVP_rc4()) }
     }
 
+    /// RC2 in CBC mode with a 128-bit key.
+    ///
+    /// This is only meant to decrypt legacy data, such as old PKCS #12 archives.
+    #[must_use]
+    pub fn rc2_cbc() -> Cipher {
+        unsafe { Cipher(ffi::EVP_rc2_cbc()) }
+    }
+
+    /// RC2 in CBC mode with a 40-bit key.
+    ///
+    /// This is only meant to decrypt legacy data, such as old PKCS #12 archives.
+    #[must_use]
+    pub fn rc2_40_cbc() -> Cipher {
+        unsafe { Cipher(ffi::EVP_rc2_40_cbc()) }
+    }
+
     /// Creates a `Cipher` from a raw pointer to its OpenSSL type.
     ///
     /// # Safety
@@ -0,0 +1,654 @@
+//! Trust Tokens, the Privacy Pass issuance protocols of BoringSSL.
+//!
+//! An issuer signs blinded tokens for a client, which can later redeem them. The issuer cannot
+//! link a redemption to the issuance that produced the token, but can attach a public metadata
+//! value (the key ID) and, with the PMB methods, one bit of private metadata that only it can
+//! read back.
+//!
+//! ```
+//! use rama_boring::trust_token::{
+//!     TrustTokenClient, TrustTokenIssuerBuilder, TrustTokenKey, TrustTokenMethod,
+//! };
+//!
+//! let method = TrustTokenMethod::pst_v1_voprf();
+//! let key = TrustTokenKey::generate(method, 1).unwrap();
+//!
+//! let mut builder = TrustTokenIssuerBuilder::new(method, 10).unwrap();
+//! builder.add_key(&key).unwrap();
+//! let issuer = builder.build();
+//!
+//! let mut client = TrustTokenClient::new(method, 10).unwrap();
+//! client.add_key(&key).unwrap();
+//!
+//! let request = client.begin_issuance(2).unwrap();
+//! let (response, issued) = issuer.issue(&request, 1, false, 10).unwrap();
+//! assert_eq!(issued, 2);
+//! let (_, tokens) = client.finish_issuance(&response).unwrap();
+//!
+//! let request = client.begin_redemption(&tokens[0], b"client data", 0).unwrap();
+//! let redemption = issuer.redeem(&request).unwrap();
+//! assert_eq!(redemption.public_metadata(), 1);
+//! assert_eq!(redemption.client_data(), b"client data");
+//! ```
+
+use std::fmt;
+use std::ptr;
+use std::slice;
+
+use foreign_types::{ForeignType, ForeignTypeRef};
+use openssl_macros::corresponds;
+
+use crate::error::ErrorStack;
+use crate::secret::SecretBytes;
+use crate::stack::{Stack, Stackable};
+use crate::{cvt, cvt_p, ffi};
+
+// TRUST_TOKEN_MAX_PRIVATE_KEY_SIZE and TRUST_TOKEN_MAX_PUBLIC_KEY_SIZE
+const MAX_KEY_SIZE: usize = 512;
+
+/// A Trust Token protocol.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct TrustTokenMethod(*const ffi::TRUST_TOKEN_METHOD);
+
+unsafe impl Send for TrustTokenMethod {}
+unsafe impl Sync for TrustTokenMethod {}
+
+impl TrustTokenMethod {
+    /// The Privacy Pass VOPRF protocol over P-384, without private metadata.
+    #[corresponds(TRUST_TOKEN_pst_v1_voprf)]
+    #[must_use]
+    pub fn pst_v1_voprf() -> TrustTokenMethod {
+        unsafe { TrustTokenMethod(ffi::TRUST_TOKEN_pst_v1_voprf()) }
+    }
+
+    /// The private metadata bit (PMB) protocol over P-384.
+    #[corresponds(TRUST_TOKEN_pst_v1_pmb)]
+    #[must_use]
+    pub fn pst_v1_pmb() -> TrustTokenMethod {
+        unsafe { TrustTokenMethod(ffi::TRUST_TOKEN_pst_v1_pmb()) }
+    }
+
+    /// The experimental VOPRF protocol used by the first Chrome origin trial of Trust Tokens.
+    #[corresponds(TRUST_TOKEN_experiment_v2_voprf)]
+    #[must_use]
+    pub fn experiment_v2_voprf() -> TrustTokenMethod {
+        unsafe { TrustTokenMethod(ffi::TRUST_TOKEN_experiment_v2_voprf()) }
+    }
+
+    /// The experimental PMB protocol used by the first Chrome origin trial of Trust Tokens.
+    #[corresponds(TRUST_TOKEN_experiment_v2_pmb)]
+    #[must_use]
+    pub fn experiment_v2_pmb() -> TrustTokenMethod {
+        unsafe { TrustTokenMethod(ffi::TRUST_TOKEN_experiment_v2_pmb()) }
+    }
+
+    /// Returns a pointer to the underlying method.
+    #[must_use]
+    pub fn as_ptr(&self) -> *const ffi::TRUST_TOKEN_METHOD {
+        self.0
+    }
+}
+
+impl fmt::Debug for TrustTokenMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = if *self == TrustTokenMethod::pst_v1_voprf() {
+            "pst_v1_voprf"
+        } else if *self == TrustTokenMethod::pst_v1_pmb() {
+            "pst_v1_pmb"
+        } else if *self == TrustTokenMethod::experiment_v2_voprf() {
+            "experiment_v2_voprf"
+        } else {
+            "experiment_v2_pmb"
+        };
+        f.write_str(name)
+    }
+}
+
+/// An issuer key pair, identified by a key ID.
+///
+/// The key ID is the public metadata of the tokens issued with the key.
+#[derive(Clone)]
+pub struct TrustTokenKey {
+    id: u32,
+    private_key: SecretBytes,
+    public_key: Vec<u8>,
+}
+
+impl TrustTokenKey {
+    /// Generates a key pair with ID `id`.
+    #[corresponds(TRUST_TOKEN_generate_key)]
+    pub fn generate(method: TrustTokenMethod, id: u32) -> Result<TrustTokenKey, ErrorStack> {
+        TrustTokenKey::new(
+            id,
+            |private_key, private_key_len, public_key, public_key_len| unsafe {
+                ffi::TRUST_TOKEN_generate_key(
+                    method.0,
+                    private_key,
+                    private_key_len,
+                    MAX_KEY_SIZE,
+                    public_key,
+                    public_key_len,
+                    MAX_KEY_SIZE,
+                    id,
+                )
+            },
+        )
+    }
+
+    /// Deterministically derives a key pair with ID `id` from `secret`.
+    ///
+    /// The secret must be uniformly random and kept as secret as the private key.
+    #[corresponds(TRUST_TOKEN_derive_key_from_secret)]
+    pub fn derive_from_secret(
+        method: TrustTokenMethod,
+        id: u32,
+        secret: &[u8],
+    ) -> Result<TrustTokenKey, ErrorStack> {
+        TrustTokenKey::new(
+            id,
+            |private_key, private_key_len, public_key, public_key_len| unsafe {
+                ffi::TRUST_TOKEN_derive_key_from_secret(
+                    method.0,
+                    private_key,
+                    private_key_len,
+                    MAX_KEY_SIZE,
+                    public_key,
+                    public_key_len,
+                    MAX_KEY_SIZE,
+                    id,
+                    secret.as_ptr(),
+                    secret.len(),
+                )
+            },
+        )
+    }
+
+    /// Creates a key pair from its serialized halves, as returned by [`Self::private_key`] and
+    /// [`Self::public_key`].
+    ///
+    /// The keys are only checked when they are added to an issuer or a client.
+    #[must_use]
+    pub fn from_parts(id: u32, private_key: &[u8], public_key: &[u8]) -> TrustTokenKey {
+        TrustTokenKey {
+            id,
+            private_key: private_key.into(),
+            public_key: public_key.to_vec(),
+        }
+    }
+
+    /// Returns the key ID.
+    #[must_use]
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the serialized private key, which the issuer needs.
+    #[must_use]
+    pub fn private_key(&self) -> &[u8] {
+        &self.private_key
+    }
+
+    /// Returns the serialized public key, which is distributed to clients.
+    #[must_use]
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    fn new(
+        id: u32,
+        generate: impl FnOnce(*mut u8, *mut usize, *mut u8, *mut usize) -> i32,
+    ) -> Result<TrustTokenKey, ErrorStack> {
+        ffi::init();
+
+        let mut private_key = SecretBytes::zeroed(MAX_KEY_SIZE);
+        let mut public_key = vec![0; MAX_KEY_SIZE];
+        let mut private_key_len = 0;
+        let mut public_key_len = 0;
+        cvt(generate(
+            private_key.as_mut_ptr(),
+            &mut private_key_len,
+            public_key.as_mut_ptr(),
+            &mut public_key_len,
+        ))?;
+        private_key.truncate(private_key_len);
+        public_key.truncate(public_key_len);
+        Ok(TrustTokenKey {
+            id,
+            private_key,
+            public_key,
+        })
+    }
+}
+
+impl fmt::Debug for TrustTokenKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrustTokenKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::TRUST_TOKEN;
+    fn drop = ffi::TRUST_TOKEN_free;
+
+    /// A token issued to a client, which it can redeem once.
+    pub struct TrustToken;
+}
+
+impl Stackable for TrustToken {
+    type StackType = ffi::stack_st_TRUST_TOKEN;
+}
+
+impl TrustToken {
+    /// Restores a token from its serialization, as returned by [`TrustTokenRef::as_bytes`].
+    #[corresponds(TRUST_TOKEN_new)]
+    pub fn from_bytes(data: &[u8]) -> Result<TrustToken, ErrorStack> {
+        unsafe {
+            ffi::init();
+            cvt_p(ffi::TRUST_TOKEN_new(data.as_ptr(), data.len())).map(|p| TrustToken::from_ptr(p))
+        }
+    }
+}
+
+impl TrustTokenRef {
+    /// Returns the serialized token, which clients store until they redeem it.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            let token = &*self.as_ptr();
+            if token.len == 0 {
+                return &[];
+            }
+            slice::from_raw_parts(token.data, token.len)
+        }
+    }
+}
+
+impl fmt::Debug for TrustToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrustToken").finish_non_exhaustive()
+    }
+}
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::TRUST_TOKEN_ISSUER;
+    fn drop = ffi::TRUST_TOKEN_ISSUER_free;
+
+    /// A Trust Token issuer, which issues and redeems tokens.
+    ///
+    /// Issuers are created with a [`TrustTokenIssuerBuilder`], and can then be shared between
+    /// threads.
+    pub struct TrustTokenIssuer;
+}
+
+/// A builder for [`TrustTokenIssuer`].
+pub struct TrustTokenIssuerBuilder(TrustTokenIssuer);
+
+impl TrustTokenIssuerBuilder {
+    /// Creates an issuer for `method`, issuing up to `max_batch_size` tokens per request.
+    #[corresponds(TRUST_TOKEN_ISSUER_new)]
+    pub fn new(
+        method: TrustTokenMethod,
+        max_batch_size: u16,
+    ) -> Result<TrustTokenIssuerBuilder, ErrorStack> {
+        unsafe {
+            ffi::init();
+            cvt_p(ffi::TRUST_TOKEN_ISSUER_new(method.0, max_batch_size.into()))
+                .map(|p| TrustTokenIssuerBuilder(TrustTokenIssuer::from_ptr(p)))
+        }
+    }
+
+    /// Adds a key to issue and redeem tokens with.
+    ///
+    /// The key ID of the key is used as the public metadata of the tokens it issues. Up to six
+    /// keys may be added, depending on the method.
+    #[corresponds(TRUST_TOKEN_ISSUER_add_key)]
+    pub fn add_key(&mut self, key: &TrustTokenKey) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::TRUST_TOKEN_ISSUER_add_key(
+                self.0.as_ptr(),
+                key.private_key.as_ptr(),
+                key.private_key.len(),
+            ))
+        }
+    }
+
+    /// Sets the key encrypting the private metadata bit of the tokens.
+    ///
+    /// This is only needed with the PMB methods, to let the issuer read back the private metadata
+    /// of redeemed tokens.
+    #[corresponds(TRUST_TOKEN_ISSUER_set_metadata_key)]
+    pub fn set_metadata_key(&mut self, key: &[u8]) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::TRUST_TOKEN_ISSUER_set_metadata_key(
+                self.0.as_ptr(),
+                key.as_ptr(),
+                key.len(),
+            ))
+        }
+    }
+
+    /// Consumes the builder, returning the issuer.
+    #[must_use]
+    pub fn build(self) -> TrustTokenIssuer {
+        self.0
+    }
+}
+
+impl TrustTokenIssuerRef {
+    /// Issues tokens for the client `request`, returning the response for the client and the
+    /// number of tokens issued.
+    ///
+    /// The tokens are issued with the key whose ID is `public_metadata`, and carry the
+    /// `private_metadata` bit. At most `max_issuance` tokens are issued.
+    #[corresponds(TRUST_TOKEN_ISSUER_issue)]
+    pub fn issue(
+        &self,
+        request: &[u8],
+        public_metadata: u32,
+        private_metadata: bool,
+        max_issuance: usize,
+    ) -> Result<(Vec<u8>, usize), ErrorStack> {
+        unsafe {
+            let mut out = ptr::null_mut();
+            let mut out_len = 0;
+            let mut issued = 0;
+            cvt(ffi::TRUST_TOKEN_ISSUER_issue(
+                self.as_ptr(),
+                &mut out,
+                &mut out_len,
+                &mut issued,
+                request.as_ptr(),
+                request.len(),
+                public_metadata,
+                private_metadata.into(),
+                max_issuance,
+            ))?;
+            Ok((take_buffer(out, out_len), issued))
+        }
+    }
+
+    /// Redeems the token of the client `request`.
+    ///
+    /// The issuer must keep track of redeemed tokens, as this does not prevent a token from being
+    /// redeemed twice.
+    #[corresponds(TRUST_TOKEN_ISSUER_redeem)]
+    pub fn redeem(&self, request: &[u8]) -> Result<TrustTokenRedemption, ErrorStack> {
+        unsafe {
+            let mut public_metadata = 0;
+            let mut private_metadata = 0;
+            let mut token = ptr::null_mut();
+            let mut client_data = ptr::null_mut();
+            let mut client_data_len = 0;
+            cvt(ffi::TRUST_TOKEN_ISSUER_redeem(
+                self.as_ptr(),
+                &mut public_metadata,
+                &mut private_metadata,
+                &mut token,
+                &mut client_data,
+                &mut client_data_len,
+                request.as_ptr(),
+                request.len(),
+            ))?;
+            Ok(TrustTokenRedemption {
+                public_metadata,
+                private_metadata: private_metadata != 0,
+                token: TrustToken::from_ptr(token),
+                client_data: take_buffer(client_data, client_data_len),
+            })
+        }
+    }
+}
+
+impl fmt::Debug for TrustTokenIssuer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrustTokenIssuer").finish_non_exhaustive()
+    }
+}
+
+/// A token redeemed by an issuer.
+#[derive(Debug)]
+pub struct TrustTokenRedemption {
+    public_metadata: u32,
+    private_metadata: bool,
+    token: TrustToken,
+    client_data: Vec<u8>,
+}
+
+impl TrustTokenRedemption {
+    /// Returns the public metadata of the token, the ID of the key it was issued with.
+    #[must_use]
+    pub fn public_metadata(&self) -> u32 {
+        self.public_metadata
+    }
+
+    /// Returns the private metadata bit of the token.
+    ///
+    /// This is always false with the VOPRF methods.
+    #[must_use]
+    pub fn private_metadata(&self) -> bool {
+        self.private_metadata
+    }
+
+    /// Returns the redeemed token, to detect tokens redeemed twice.
+    #[must_use]
+    pub fn token(&self) -> &TrustTokenRef {
+        &self.token
+    }
+
+    /// Returns the data the client attached to the redemption.
+    #[must_use]
+    pub fn client_data(&self) -> &[u8] {
+        &self.client_data
+    }
+}
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::TRUST_TOKEN_CLIENT;
+    fn drop = ffi::TRUST_TOKEN_CLIENT_free;
+
+    /// A Trust Token client, which requests tokens from an issuer and redeems them.
+    pub struct TrustTokenClient;
+}
+
+impl TrustTokenClient {
+    /// Creates a client for `method`, requesting up to `max_batch_size` tokens at once.
+    #[corresponds(TRUST_TOKEN_CLIENT_new)]
+    pub fn new(
+        method: TrustTokenMethod,
+        max_batch_size: u16,
+    ) -> Result<TrustTokenClient, ErrorStack> {
+        unsafe {
+            ffi::init();
+            cvt_p(ffi::TRUST_TOKEN_CLIENT_new(method.0, max_batch_size.into()))
+                .map(|p| TrustTokenClient::from_ptr(p))
+        }
+    }
+}
+
+impl TrustTokenClientRef {
+    /// Adds the public key of an issuer key, returning its index.
+    ///
+    /// The index identifies the key tokens were issued with in [`Self::finish_issuance`].
+    #[corresponds(TRUST_TOKEN_CLIENT_add_key)]
+    pub fn add_key(&mut self, key: &TrustTokenKey) -> Result<usize, ErrorStack> {
+        self.add_public_key(key.public_key())
+    }
+
+    /// Adds a serialized issuer public key, returning its index.
+    #[corresponds(TRUST_TOKEN_CLIENT_add_key)]
+    pub fn add_public_key(&mut self, public_key: &[u8]) -> Result<usize, ErrorStack> {
+        unsafe {
+            let mut index = 0;
+            cvt(ffi::TRUST_TOKEN_CLIENT_add_key(
+                self.as_ptr(),
+                &mut index,
+                public_key.as_ptr(),
+                public_key.len(),
+            ))?;
+            Ok(index)
+        }
+    }
+
+    /// Starts the issuance of `count` tokens, returning the request for the issuer.
+    #[corresponds(TRUST_TOKEN_CLIENT_begin_issuance)]
+    pub fn begin_issuance(&mut self, count: usize) -> Result<Vec<u8>, ErrorStack> {
+        unsafe {
+            let mut out = ptr::null_mut();
+            let mut out_len = 0;
+            cvt(ffi::TRUST_TOKEN_CLIENT_begin_issuance(
+                self.as_ptr(),
+                &mut out,
+                &mut out_len,
+                count,
+            ))?;
+            Ok(take_buffer(out, out_len))
+        }
+    }
+
+    /// Completes the issuance with the issuer `response`, returning the index of the key the
+    /// tokens were issued with, and the tokens.
+    #[corresponds(TRUST_TOKEN_CLIENT_finish_issuance)]
+    pub fn finish_issuance(
+        &mut self,
+        response: &[u8],
+    ) -> Result<(usize, Vec<TrustToken>), ErrorStack> {
+        unsafe {
+            let mut index = 0;
+            let tokens = cvt_p(ffi::TRUST_TOKEN_CLIENT_finish_issuance(
+                self.as_ptr(),
+                &mut index,
+                response.as_ptr(),
+                response.len(),
+            ))?;
+            let tokens = Stack::<TrustToken>::from_ptr(tokens);
+            Ok((index, tokens.into_iter().collect()))
+        }
+    }
+
+    /// Creates the request redeeming `token`, attaching `data` and the time of the redemption in
+    /// seconds since the epoch.
+    ///
+    /// The token must not be redeemed again.
+    #[corresponds(TRUST_TOKEN_CLIENT_begin_redemption)]
+    pub fn begin_redemption(
+        &mut self,
+        token: &TrustTokenRef,
+        data: &[u8],
+        time: u64,
+    ) -> Result<Vec<u8>, ErrorStack> {
+        unsafe {
+            let mut out = ptr::null_mut();
+            let mut out_len = 0;
+            cvt(ffi::TRUST_TOKEN_CLIENT_begin_redemption(
+                self.as_ptr(),
+                &mut out,
+                &mut out_len,
+                token.as_ptr(),
+                data.as_ptr(),
+                data.len(),
+                time,
+            ))?;
+            Ok(take_buffer(out, out_len))
+        }
+    }
+}
+
+impl fmt::Debug for TrustTokenClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrustTokenClient").finish_non_exhaustive()
+    }
+}
+
+/// Copies and frees a buffer allocated by BoringSSL.
+unsafe fn take_buffer(buf: *mut u8, len: usize) -> Vec<u8> {
+    if buf.is_null() {
+        return vec![];
+    }
+    let data = slice::from_raw_parts(buf, len).to_vec();
+    ffi::OPENSSL_free(buf.cast());
+    data
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn issue(
+        method: TrustTokenMethod,
+        private_metadata: bool,
+    ) -> (TrustTokenIssuer, TrustTokenClient, Vec<TrustToken>) {
+        let keys = [
+            TrustTokenKey::generate(method, 7).unwrap(),
+            TrustTokenKey::generate(method, 8).unwrap(),
+        ];
+        let mut builder = TrustTokenIssuerBuilder::new(method, 10).unwrap();
+        let mut client = TrustTokenClient::new(method, 10).unwrap();
+        for key in &keys {
+            builder.add_key(key).unwrap();
+            client.add_key(key).unwrap();
+        }
+        builder.set_metadata_key(&[0x42; 32]).unwrap();
+        let issuer = builder.build();
+
+        let request = client.begin_issuance(3).unwrap();
+        let (response, issued) = issuer.issue(&request, 8, private_metadata, 2).unwrap();
+        assert_eq!(issued, 2);
+        let (index, tokens) = client.finish_issuance(&response).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(tokens.len(), 2);
+        (issuer, client, tokens)
+    }
+
+    #[test]
+    fn voprf() {
+        let (issuer, mut client, tokens) = issue(TrustTokenMethod::pst_v1_voprf(), false);
+        let token = TrustToken::from_bytes(tokens[1].as_bytes()).unwrap();
+        let request = client
+            .begin_redemption(&token, b"data", 1_700_000_000)
+            .unwrap();
+        let redemption = issuer.redeem(&request).unwrap();
+        assert_eq!(redemption.public_metadata(), 8);
+        assert!(!redemption.private_metadata());
+        assert_eq!(redemption.client_data(), b"data");
+        assert_eq!(redemption.token().as_bytes(), token.as_bytes());
+
+        assert!(issuer.redeem(&request[1..]).is_err());
+    }
+
+    #[test]
+    fn pmb() {
+        for private_metadata in [false, true] {
+            let (issuer, mut client, tokens) =
+                issue(TrustTokenMethod::pst_v1_pmb(), private_metadata);
+            let request = client.begin_redemption(&tokens[0], b"", 0).unwrap();
+            let redemption = issuer.redeem(&request).unwrap();
+            assert_eq!(redemption.public_metadata(), 8);
+            assert_eq!(redemption.private_metadata(), private_metadata);
+        }
+    }
+
+    #[test]
+    fn derive_from_secret() {
+        let method = TrustTokenMethod::pst_v1_voprf();
+        let a = TrustTokenKey::derive_from_secret(method, 1, &[1; 32]).unwrap();
+        let b = TrustTokenKey::derive_from_secret(method, 1, &[1; 32]).unwrap();
+        assert_eq!(a.private_key(), b.private_key());
+        assert_eq!(a.public_key(), b.public_key());
+
+        let c = TrustTokenKey::from_parts(a.id(), a.private_key(), a.public_key());
+        let mut builder = TrustTokenIssuerBuilder::new(method, 1).unwrap();
+        builder.add_key(&c).unwrap();
+        assert!(builder
+            .add_key(&TrustTokenKey::from_parts(2, &[1, 2, 3], &[]))
+            .is_err());
+    }
+
+    #[test]
+    fn wrong_method() {
+        let key = TrustTokenKey::generate(TrustTokenMethod::pst_v1_pmb(), 1).unwrap();
+        let mut client = TrustTokenClient::new(TrustTokenMethod::pst_v1_voprf(), 1).unwrap();
+        assert!(client.add_key(&key).is_err());
+    }
+}
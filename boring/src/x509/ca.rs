@@ -0,0 +1,404 @@
+//! A minimal certificate authority.
+//!
+//! [`CertificateAuthority`] issues certificates from certificate requests, following a
+//! [`CertificateTemplate`] for the validity period and extensions. It also creates the roots and
+//! intermediates of a small PKI, such as the one of a test suite.
+//!
+//! # Example
+//!
+//! ```
+//! use rama_boring::ec::{EcGroup, EcKey};
+//! use rama_boring::hash::MessageDigest;
+//! use rama_boring::nid::Nid;
+//! use rama_boring::pkey::PKey;
+//! use rama_boring::x509::ca::{CertificateAuthority, CertificateTemplate, SanPolicy};
+//! use rama_boring::x509::extension::{ExtendedKeyUsage, KeyUsage};
+//! use rama_boring::x509::{X509Name, X509Req};
+//!
+//! let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+//! let name = |cn| {
+//!     let mut name = X509Name::builder().unwrap();
+//!     name.append_entry_by_nid(Nid::COMMONNAME, cn).unwrap();
+//!     name.build()
+//! };
+//!
+//! let root_key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+//! let root =
+//!     CertificateAuthority::self_signed(&name("root"), root_key, &CertificateTemplate::ca(None))
+//!         .unwrap();
+//!
+//! let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+//! let mut req = X509Req::builder().unwrap();
+//! req.set_subject_name(&name("example.com")).unwrap();
+//! req.set_pubkey(&key).unwrap();
+//! req.sign(&key, MessageDigest::sha256()).unwrap();
+//! let req = req.build();
+//!
+//! let mut template = CertificateTemplate::new();
+//! template
+//!     .validity_days(30)
+//!     .key_usage(KeyUsage::new().critical().digital_signature().clone())
+//!     .extended_key_usage(ExtendedKeyUsage::new().server_auth().clone())
+//!     .san_policy(SanPolicy::Copy);
+//! let cert = root.issue(&req, &template).unwrap();
+//! assert_eq!(root.cert().issued(&cert), Ok(()));
+//! ```
+
+use foreign_types::{ForeignType, ForeignTypeRef};
+
+use crate::asn1::Asn1Time;
+use crate::bn::{BigNum, MsbOption};
+use crate::error::ErrorStack;
+use crate::ffi;
+use crate::hash::MessageDigest;
+use crate::libc_types::time_t;
+use crate::nid::Nid;
+use crate::pkey::{HasPublic, PKey, PKeyRef, Private};
+use crate::stack::Stack;
+use crate::x509::extension::{
+    AuthorityKeyIdentifier, BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectKeyIdentifier,
+};
+use crate::x509::{
+    GeneralName, GeneralNameRef, X509Builder, X509Extension, X509NameRef, X509Ref, X509ReqRef, X509,
+};
+
+/// How the subject alternative names of a certificate request are handled.
+#[derive(Debug, Clone, Copy)]
+pub enum SanPolicy {
+    /// The subject alternative names of the request are not copied to the certificate.
+    Ignore,
+    /// The subject alternative name extension of the request is copied to the certificate.
+    Copy,
+    /// Like `Copy`, but the request is rejected if the function returns `false` for one of its
+    /// names.
+    Restrict(fn(&GeneralNameRef) -> bool),
+}
+
+/// The validity period and extensions of the certificates issued by a [`CertificateAuthority`].
+///
+/// The serial number, subject key identifier and authority key identifier are always set by
+/// the certificate authority.
+#[derive(Debug, Clone)]
+pub struct CertificateTemplate {
+    not_before: Option<time_t>,
+    validity_days: u32,
+    basic_constraints: Option<BasicConstraints>,
+    key_usage: Option<KeyUsage>,
+    extended_key_usage: Option<ExtendedKeyUsage>,
+    san_policy: SanPolicy,
+}
+
+impl Default for CertificateTemplate {
+    fn default() -> CertificateTemplate {
+        CertificateTemplate::new()
+    }
+}
+
+impl CertificateTemplate {
+    /// Creates a template for end-entity certificates.
+    ///
+    /// Certificates are valid for 90 days from the time they are issued, have no extensions
+    /// other than the key identifiers, and subject alternative names are ignored.
+    #[must_use]
+    pub fn new() -> CertificateTemplate {
+        CertificateTemplate {
+            not_before: None,
+            validity_days: 90,
+            basic_constraints: None,
+            key_usage: None,
+            extended_key_usage: None,
+            san_policy: SanPolicy::Ignore,
+        }
+    }
+
+    /// Creates a template for CA certificates, with an optional pathlen.
+    ///
+    /// Certificates are valid for 10 years, and have critical basic constraints and a critical
+    /// key usage allowing to sign certificates and CRLs.
+    #[must_use]
+    pub fn ca(pathlen: Option<u32>) -> CertificateTemplate {
+        let mut basic_constraints = BasicConstraints::new();
+        basic_constraints.critical().ca();
+        if let Some(pathlen) = pathlen {
+            basic_constraints.pathlen(pathlen);
+        }
+        let mut key_usage = KeyUsage::new();
+        key_usage.critical().key_cert_sign().crl_sign();
+
+        CertificateTemplate {
+            validity_days: 3650,
+            basic_constraints: Some(basic_constraints),
+            key_usage: Some(key_usage),
+            ..CertificateTemplate::new()
+        }
+    }
+
+    /// Sets the start of the validity period, in seconds since the epoch.
+    ///
+    /// Defaults to the time the certificate is issued.
+    pub fn not_before(&mut self, not_before: time_t) -> &mut CertificateTemplate {
+        self.not_before = Some(not_before);
+        self
+    }
+
+    /// Sets the length of the validity period in days.
+    pub fn validity_days(&mut self, days: u32) -> &mut CertificateTemplate {
+        self.validity_days = days;
+        self
+    }
+
+    /// Sets the basic constraints extension.
+    pub fn basic_constraints(
+        &mut self,
+        basic_constraints: BasicConstraints,
+    ) -> &mut CertificateTemplate {
+        self.basic_constraints = Some(basic_constraints);
+        self
+    }
+
+    /// Sets the key usage extension.
+    pub fn key_usage(&mut self, key_usage: KeyUsage) -> &mut CertificateTemplate {
+        self.key_usage = Some(key_usage);
+        self
+    }
+
+    /// Sets the extended key usage extension.
+    pub fn extended_key_usage(
+        &mut self,
+        extended_key_usage: ExtendedKeyUsage,
+    ) -> &mut CertificateTemplate {
+        self.extended_key_usage = Some(extended_key_usage);
+        self
+    }
+
+    /// Sets how the subject alternative names of certificate requests are handled.
+    pub fn san_policy(&mut self, san_policy: SanPolicy) -> &mut CertificateTemplate {
+        self.san_policy = san_policy;
+        self
+    }
+}
+
+/// A certificate authority, made of a CA certificate and its private key.
+pub struct CertificateAuthority {
+    cert: X509,
+    key: PKey<Private>,
+    digest: MessageDigest,
+}
+
+impl CertificateAuthority {
+    /// Creates a certificate authority from its certificate and private key.
+    ///
+    /// Fails if `key` does not match the public key of `cert`.
+    pub fn new(cert: X509, key: PKey<Private>) -> Result<CertificateAuthority, ErrorStack> {
+        if !cert.public_key()?.public_eq(&key) {
+            return Err(ErrorStack::internal_error_str(
+                "private key does not match the certificate",
+            ));
+        }
+        Ok(CertificateAuthority {
+            cert,
+            key,
+            digest: MessageDigest::sha256(),
+        })
+    }
+
+    /// Creates a root certificate authority with a self-signed certificate.
+    ///
+    /// The certificate is signed with SHA-256.
+    pub fn self_signed(
+        subject: &X509NameRef,
+        key: PKey<Private>,
+        template: &CertificateTemplate,
+    ) -> Result<CertificateAuthority, ErrorStack> {
+        let cert = build(
+            subject,
+            &key,
+            None,
+            template,
+            |_| Ok(None),
+            |builder| builder.sign(&key, MessageDigest::sha256()),
+        )?;
+        CertificateAuthority::new(cert, key)
+    }
+
+    /// Sets the digest certificates are signed with. Defaults to SHA-256.
+    ///
+    /// Ed25519 keys must use [`MessageDigest::null`].
+    pub fn set_digest(&mut self, digest: MessageDigest) {
+        self.digest = digest;
+    }
+
+    /// Returns the certificate of the certificate authority.
+    #[must_use]
+    pub fn cert(&self) -> &X509Ref {
+        &self.cert
+    }
+
+    /// Returns the private key of the certificate authority.
+    #[must_use]
+    pub fn key(&self) -> &PKeyRef<Private> {
+        &self.key
+    }
+
+    /// Issues a certificate for the subject and public key of `req`.
+    ///
+    /// Fails if the signature of `req` is invalid, or if its subject alternative names are
+    /// rejected by the policy of `template`. Other extensions of `req` are ignored.
+    pub fn issue(
+        &self,
+        req: &X509ReqRef,
+        template: &CertificateTemplate,
+    ) -> Result<X509, ErrorStack> {
+        let public_key = req.public_key()?;
+        if !req.verify(&public_key)? {
+            return Err(ErrorStack::internal_error_str(
+                "certificate request signature is invalid",
+            ));
+        }
+        let subject_alt_name = |policy: SanPolicy| request_subject_alt_name(req, policy);
+        build(
+            req.subject_name(),
+            &public_key,
+            Some(&self.cert),
+            template,
+            subject_alt_name,
+            |builder| builder.sign(&self.key, self.digest),
+        )
+    }
+
+    /// Issues a certificate for `subject` and `public_key`, without a certificate request.
+    pub fn issue_for_key<T>(
+        &self,
+        subject: &X509NameRef,
+        public_key: &PKeyRef<T>,
+        template: &CertificateTemplate,
+    ) -> Result<X509, ErrorStack>
+    where
+        T: HasPublic,
+    {
+        build(
+            subject,
+            public_key,
+            Some(&self.cert),
+            template,
+            |_| Ok(None),
+            |builder| builder.sign(&self.key, self.digest),
+        )
+    }
+
+    /// Creates an intermediate certificate authority for `subject` and `key`, whose certificate
+    /// is issued by this one.
+    ///
+    /// `template` would usually be created with [`CertificateTemplate::ca`].
+    pub fn issue_intermediate(
+        &self,
+        subject: &X509NameRef,
+        key: PKey<Private>,
+        template: &CertificateTemplate,
+    ) -> Result<CertificateAuthority, ErrorStack> {
+        let cert = self.issue_for_key(subject, &key, template)?;
+        let mut intermediate = CertificateAuthority::new(cert, key)?;
+        intermediate.digest = self.digest;
+        Ok(intermediate)
+    }
+}
+
+fn build<T>(
+    subject: &X509NameRef,
+    public_key: &PKeyRef<T>,
+    issuer: Option<&X509Ref>,
+    template: &CertificateTemplate,
+    subject_alt_name: impl FnOnce(SanPolicy) -> Result<Option<X509Extension>, ErrorStack>,
+    sign: impl FnOnce(&mut X509Builder) -> Result<(), ErrorStack>,
+) -> Result<X509, ErrorStack>
+where
+    T: HasPublic,
+{
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+
+    // 159 bits, so that the serial number is positive and at most 20 bytes long
+    let mut serial = BigNum::new()?;
+    serial.rand(159, MsbOption::MAYBE_ZERO, false)?;
+    builder.set_serial_number(&serial.to_asn1_integer()?)?;
+
+    builder.set_subject_name(subject)?;
+    builder.set_issuer_name(issuer.map_or(subject, |issuer| issuer.subject_name()))?;
+    builder.set_pubkey(public_key)?;
+
+    let days = time_t::from(template.validity_days) * 24 * 60 * 60;
+    let (not_before, not_after) = match template.not_before {
+        Some(not_before) => (
+            Asn1Time::from_unix(not_before)?,
+            Asn1Time::from_unix(not_before + days)?,
+        ),
+        None => (
+            Asn1Time::days_from_now(0)?,
+            Asn1Time::days_from_now(template.validity_days)?,
+        ),
+    };
+    builder.set_not_before(&not_before)?;
+    builder.set_not_after(&not_after)?;
+
+    if let Some(basic_constraints) = &template.basic_constraints {
+        builder.append_extension(&basic_constraints.build()?)?;
+    }
+    if let Some(key_usage) = &template.key_usage {
+        builder.append_extension(&key_usage.build()?)?;
+    }
+    if let Some(extended_key_usage) = &template.extended_key_usage {
+        builder.append_extension(&extended_key_usage.build()?)?;
+    }
+    let subject_key_identifier =
+        SubjectKeyIdentifier::new().build(&builder.x509v3_context(issuer, None))?;
+    builder.append_extension(&subject_key_identifier)?;
+    // A self-signed certificate is its own issuer, so this uses the identifier added above
+    let authority_key_identifier = AuthorityKeyIdentifier::new()
+        .keyid(false)
+        .build(&builder.x509v3_context(issuer, None))?;
+    builder.append_extension(&authority_key_identifier)?;
+    if let Some(subject_alt_name) = subject_alt_name(template.san_policy)? {
+        builder.append_extension(&subject_alt_name)?;
+    }
+
+    sign(&mut builder)?;
+    Ok(builder.build())
+}
+
+/// Returns the subject alternative name extension of `req` to copy, following `policy`.
+fn request_subject_alt_name(
+    req: &X509ReqRef,
+    policy: SanPolicy,
+) -> Result<Option<X509Extension>, ErrorStack> {
+    if let SanPolicy::Ignore = policy {
+        return Ok(None);
+    }
+    let extensions = match req.extensions() {
+        Ok(extensions) => extensions,
+        // The request has no extensions
+        Err(e) if e.errors().is_empty() => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let Some(extension) = extensions
+        .into_iter()
+        .find(|extension| extension.object().nid() == Nid::SUBJECT_ALT_NAME)
+    else {
+        return Ok(None);
+    };
+
+    if let SanPolicy::Restrict(allowed) = policy {
+        let names: Stack<GeneralName> = unsafe {
+            let names = ffi::X509V3_EXT_d2i(extension.as_ptr());
+            if names.is_null() {
+                return Err(ErrorStack::get());
+            }
+            Stack::from_ptr(names.cast())
+        };
+        if !names.iter().all(allowed) {
+            return Err(ErrorStack::internal_error_str(
+                "subject alternative name rejected by policy",
+            ));
+        }
+    }
+    Ok(Some(extension))
+}
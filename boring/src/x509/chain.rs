@@ -0,0 +1,350 @@
+//! Certificate chain verification outside of a TLS handshake.
+//!
+//! [`ChainVerifier`] verifies a leaf certificate against a trust store under a policy, and
+//! returns a [`ChainReport`] describing the chain that was built and every error that was found,
+//! rather than only the first one. This is useful to verify certificates that are not presented
+//! in a handshake, such as code signing certificates.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rama_boring::asn1::Asn1Object;
+//! use rama_boring::stack::Stack;
+//! use rama_boring::x509::chain::ChainVerifier;
+//! use rama_boring::x509::store::X509StoreBuilder;
+//! use rama_boring::x509::X509;
+//!
+//! # let root = X509::from_pem(&[]).unwrap();
+//! # let leaf = X509::from_pem(&[]).unwrap();
+//! let mut store = X509StoreBuilder::new().unwrap();
+//! store.add_cert(&root).unwrap();
+//!
+//! let mut verifier = ChainVerifier::new(store.build());
+//! verifier.extended_key_usage(Asn1Object::from_str("codeSigning").unwrap());
+//!
+//! let report = verifier.verify(&leaf, &Stack::new().unwrap()).unwrap();
+//! for error in report.errors() {
+//!     println!("depth {}: {}", error.depth(), error.error());
+//! }
+//! ```
+
+use crate::libc_types::{c_int, time_t};
+use foreign_types::{ForeignType, ForeignTypeRef};
+use std::mem;
+use std::net::IpAddr;
+use std::ptr;
+use std::sync::LazyLock;
+
+use crate::asn1::Asn1Object;
+use crate::error::ErrorStack;
+use crate::ex_data::Index;
+use crate::ffi;
+use crate::stack::{Stack, StackRef};
+use crate::x509::store::X509Store;
+use crate::x509::verify::{X509Purpose, X509VerifyFlags, X509VerifyParamRef};
+use crate::x509::{X509Ref, X509StoreContext, X509StoreContextRef, X509VerifyError, X509};
+
+static ERRORS_INDEX: LazyLock<Index<X509StoreContext, Vec<ChainError>>> =
+    LazyLock::new(|| X509StoreContext::new_ex_index().unwrap());
+
+/// Errors meaning that the chain does not end in a certificate of the trust store.
+const UNTRUSTED_ERRORS: [X509VerifyError; 5] = [
+    X509VerifyError::UNABLE_TO_GET_ISSUER_CERT,
+    X509VerifyError::UNABLE_TO_GET_ISSUER_CERT_LOCALLY,
+    X509VerifyError::DEPTH_ZERO_SELF_SIGNED_CERT,
+    X509VerifyError::SELF_SIGNED_CERT_IN_CHAIN,
+    X509VerifyError::CERT_UNTRUSTED,
+];
+
+/// Verifies certificate chains against a trust store and a policy.
+///
+/// A verifier is configured once and can verify any number of chains.
+pub struct ChainVerifier {
+    store: X509Store,
+    flags: X509VerifyFlags,
+    purpose: Option<X509Purpose>,
+    extended_key_usages: Vec<Asn1Object>,
+    host: Option<String>,
+    email: Option<String>,
+    ip: Option<IpAddr>,
+    time: Option<time_t>,
+    max_depth: Option<u32>,
+}
+
+impl ChainVerifier {
+    /// Creates a verifier that trusts the certificates of `store`.
+    ///
+    /// The flags, CRLs and parameters of the store apply, and the settings of the verifier are
+    /// added to them.
+    #[must_use]
+    pub fn new(store: X509Store) -> ChainVerifier {
+        ChainVerifier {
+            store,
+            flags: X509VerifyFlags::empty(),
+            purpose: None,
+            extended_key_usages: vec![],
+            host: None,
+            email: None,
+            ip: None,
+            time: None,
+            max_depth: None,
+        }
+    }
+
+    /// Sets additional verification flags.
+    pub fn flags(&mut self, flags: X509VerifyFlags) -> &mut ChainVerifier {
+        self.flags |= flags;
+        self
+    }
+
+    /// Sets the purpose the chain must be valid for.
+    pub fn purpose(&mut self, purpose: X509Purpose) -> &mut ChainVerifier {
+        self.purpose = Some(purpose);
+        self
+    }
+
+    /// Requires the leaf to list `usage` in its extended key usage extension.
+    ///
+    /// Objects can be created from an OID or a short name, e.g. `codeSigning`. A leaf without the
+    /// extension is rejected, as is a leaf that only lists `anyExtendedKeyUsage`. May be called
+    /// several times to require several usages.
+    pub fn extended_key_usage(&mut self, usage: Asn1Object) -> &mut ChainVerifier {
+        self.extended_key_usages.push(usage);
+        self
+    }
+
+    /// Requires the leaf to be valid for the DNS name `host`.
+    pub fn host(&mut self, host: &str) -> &mut ChainVerifier {
+        self.host = Some(host.to_owned());
+        self
+    }
+
+    /// Requires the leaf to be valid for the email address `email`.
+    pub fn email(&mut self, email: &str) -> &mut ChainVerifier {
+        self.email = Some(email.to_owned());
+        self
+    }
+
+    /// Requires the leaf to be valid for the IP address `ip`.
+    pub fn ip(&mut self, ip: IpAddr) -> &mut ChainVerifier {
+        self.ip = Some(ip);
+        self
+    }
+
+    /// Verifies the chain at `time`, in seconds since the epoch, instead of the current time.
+    pub fn time(&mut self, time: time_t) -> &mut ChainVerifier {
+        self.time = Some(time);
+        self
+    }
+
+    /// Sets the maximum number of intermediate certificates in the chain.
+    pub fn max_depth(&mut self, max_depth: u32) -> &mut ChainVerifier {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Verifies `leaf`, building the chain from `intermediates` and the trust store.
+    ///
+    /// Verification errors are reported in the returned [`ChainReport`]. An `Err` is only
+    /// returned if verification could not be run at all.
+    pub fn verify(
+        &self,
+        leaf: &X509Ref,
+        intermediates: &StackRef<X509>,
+    ) -> Result<ChainReport, ErrorStack> {
+        let mut context = X509StoreContext::new()?;
+        let mut report = context.init(&self.store, leaf, intermediates, |ctx| {
+            self.configure(ctx.verify_param_mut())?;
+            ctx.set_ex_data(*ERRORS_INDEX, Vec::new());
+            unsafe {
+                ffi::X509_STORE_CTX_set_verify_cb(ctx.as_ptr(), Some(record_error));
+            }
+
+            let verified = ctx.verify_cert()?;
+
+            let mut errors = ctx
+                .ex_data_mut(*ERRORS_INDEX)
+                .map(mem::take)
+                .unwrap_or_default();
+            if !verified && errors.is_empty() {
+                // Not every failure goes through the callback
+                if let Err(error) = ctx.verify_result() {
+                    errors.push(ChainError {
+                        error,
+                        depth: ctx.error_depth(),
+                        cert: ctx.current_cert().map(ToOwned::to_owned),
+                    });
+                }
+            }
+            let chain = ctx
+                .chain()
+                .map(|chain| chain.iter().map(ToOwned::to_owned).collect())
+                .unwrap_or_default();
+
+            Ok(ChainReport { chain, errors })
+        })?;
+
+        if !self.extended_key_usages.is_empty() && !self.has_extended_key_usages(leaf) {
+            report.errors.push(ChainError {
+                error: X509VerifyError::INVALID_PURPOSE,
+                depth: 0,
+                cert: Some(leaf.to_owned()),
+            });
+        }
+
+        Ok(report)
+    }
+
+    fn configure(&self, param: &mut X509VerifyParamRef) -> Result<(), ErrorStack> {
+        let mut flags = self.flags;
+        if let Some(purpose) = self.purpose {
+            param.set_purpose(purpose)?;
+        }
+        if let Some(host) = &self.host {
+            param.set_host(host)?;
+        }
+        if let Some(email) = &self.email {
+            param.set_email(email)?;
+        }
+        if let Some(ip) = self.ip {
+            param.set_ip(ip)?;
+        }
+        if let Some(time) = self.time {
+            param.set_time(time);
+            flags |= X509VerifyFlags::USE_CHECK_TIME;
+        }
+        if let Some(max_depth) = self.max_depth {
+            param.set_depth(max_depth.try_into().unwrap_or(c_int::MAX));
+        }
+        param.set_flags(flags);
+        Ok(())
+    }
+
+    fn has_extended_key_usages(&self, leaf: &X509Ref) -> bool {
+        let usages: Option<Stack<Asn1Object>> = unsafe {
+            let stack = ffi::X509_get_ext_d2i(
+                leaf.as_ptr(),
+                ffi::NID_ext_key_usage,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+            if stack.is_null() {
+                None
+            } else {
+                Some(Stack::from_ptr(stack.cast()))
+            }
+        };
+        let Some(usages) = usages else {
+            return false;
+        };
+
+        self.extended_key_usages.iter().all(|required| {
+            usages
+                .iter()
+                .any(|usage| unsafe { ffi::OBJ_cmp(usage.as_ptr(), required.as_ptr()) == 0 })
+        })
+    }
+}
+
+/// Called for every verification step. Failures are recorded and overridden, so that
+/// verification continues and finds every error.
+unsafe extern "C" fn record_error(ok: c_int, ctx: *mut ffi::X509_STORE_CTX) -> c_int {
+    if ok == 0 {
+        let ctx = X509StoreContextRef::from_ptr_mut(ctx);
+        if let Err(error) = ctx.verify_result() {
+            let error = ChainError {
+                error,
+                depth: ctx.error_depth(),
+                cert: ctx.current_cert().map(ToOwned::to_owned),
+            };
+            if let Some(errors) = ctx.ex_data_mut(*ERRORS_INDEX) {
+                errors.push(error);
+            }
+        }
+    }
+    1
+}
+
+/// The outcome of verifying a certificate chain with a [`ChainVerifier`].
+#[derive(Debug)]
+pub struct ChainReport {
+    chain: Vec<X509>,
+    errors: Vec<ChainError>,
+}
+
+impl ChainReport {
+    /// Returns `true` if no error was found.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns the chain that was built, starting with the leaf.
+    ///
+    /// If verification failed, the chain may be incomplete.
+    #[must_use]
+    pub fn chain(&self) -> &[X509] {
+        &self.chain
+    }
+
+    /// Returns every error found, in the order verification found them.
+    #[must_use]
+    pub fn errors(&self) -> &[ChainError] {
+        &self.errors
+    }
+
+    /// Returns the depth of the first error found, or `None` if the chain is valid.
+    ///
+    /// Depth 0 is the leaf, 1 its issuer, and so on.
+    #[must_use]
+    pub fn error_depth(&self) -> Option<u32> {
+        self.errors.first().map(ChainError::depth)
+    }
+
+    /// Returns the certificate of the trust store the chain ends in.
+    ///
+    /// Returns `None` if the chain does not end in a trusted certificate. The chain may have
+    /// other errors even if a trust anchor was found.
+    #[must_use]
+    pub fn trust_anchor(&self) -> Option<&X509> {
+        let untrusted = self
+            .errors
+            .iter()
+            .any(|error| UNTRUSTED_ERRORS.contains(&error.error));
+        if untrusted {
+            None
+        } else {
+            self.chain.last()
+        }
+    }
+}
+
+/// An error found while verifying a certificate chain.
+#[derive(Debug, Clone)]
+pub struct ChainError {
+    error: X509VerifyError,
+    depth: u32,
+    cert: Option<X509>,
+}
+
+impl ChainError {
+    /// Returns the error.
+    #[must_use]
+    pub fn error(&self) -> X509VerifyError {
+        self.error
+    }
+
+    /// Returns the depth in the chain of the certificate the error is about.
+    ///
+    /// Depth 0 is the leaf, 1 its issuer, and so on.
+    #[must_use]
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Returns the certificate the error is about, if the error is about a certificate.
+    #[must_use]
+    pub fn cert(&self) -> Option<&X509> {
+        self.cert.as_ref()
+    }
+}
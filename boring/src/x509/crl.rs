@@ -0,0 +1,622 @@
+//! Certificate revocation lists.
+//!
+//! An `X509Crl` lists the serial numbers of certificates that an issuer has revoked before their
+//! expiry. CRLs are checked during verification once they are added to the store with
+//! [`X509StoreBuilderRef::add_crl`] and [`X509VerifyFlags::CRL_CHECK`] or
+//! [`X509VerifyFlags::CRL_CHECK_ALL`] is set.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rama_boring::asn1::Asn1Time;
+//! use rama_boring::bn::BigNum;
+//! use rama_boring::ec::{EcGroup, EcKey};
+//! use rama_boring::hash::MessageDigest;
+//! use rama_boring::nid::Nid;
+//! use rama_boring::pkey::PKey;
+//! use rama_boring::x509::crl::{CrlReason, X509Crl};
+//! use rama_boring::x509::X509Name;
+//!
+//! let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+//! let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+//! let mut name = X509Name::builder().unwrap();
+//! name.append_entry_by_nid(Nid::COMMONNAME, "Example CA").unwrap();
+//! let name = name.build();
+//!
+//! let serial = BigNum::from_u32(1234).unwrap().to_asn1_integer().unwrap();
+//! let now = Asn1Time::days_from_now(0).unwrap();
+//!
+//! let mut builder = X509Crl::builder().unwrap();
+//! builder.set_issuer_name(&name).unwrap();
+//! builder.set_last_update(&now).unwrap();
+//! builder.set_next_update(&Asn1Time::days_from_now(7).unwrap()).unwrap();
+//! builder
+//!     .revoke(&serial, &now, Some(CrlReason::KEY_COMPROMISE))
+//!     .unwrap();
+//! builder.sign(&key, MessageDigest::sha256()).unwrap();
+//! let crl = builder.build();
+//!
+//! assert!(crl.verify(&key).unwrap());
+//! assert_eq!(crl.revoked().len(), 1);
+//! ```
+//!
+//! [`X509StoreBuilderRef::add_crl`]: crate::x509::store::X509StoreBuilderRef::add_crl
+//! [`X509VerifyFlags::CRL_CHECK`]: crate::x509::verify::X509VerifyFlags::CRL_CHECK
+//! [`X509VerifyFlags::CRL_CHECK_ALL`]: crate::x509::verify::X509VerifyFlags::CRL_CHECK_ALL
+
+use crate::libc_types::{c_int, c_long};
+use foreign_types::{ForeignType, ForeignTypeRef};
+use openssl_macros::corresponds;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
+
+use crate::asn1::{Asn1Integer, Asn1IntegerRef, Asn1TimeRef};
+use crate::bn::{BigNum, BigNumRef};
+use crate::conf::ConfRef;
+use crate::error::ErrorStack;
+use crate::ffi;
+use crate::hash::MessageDigest;
+use crate::pkey::{HasPrivate, HasPublic, PKeyRef};
+use crate::stack::{self, StackRef, Stackable};
+use crate::util::ForeignTypeRefExt;
+use crate::x509::{X509ExtensionRef, X509NameRef, X509Ref, X509v3Context};
+use crate::{cvt, cvt_n, cvt_p};
+
+/// The reason a certificate was revoked, from the `reasonCode` CRL entry extension.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct CrlReason(c_int);
+
+#[allow(missing_docs)] // no need to document the constants
+impl CrlReason {
+    pub const UNSPECIFIED: CrlReason = CrlReason(ffi::CRL_REASON_UNSPECIFIED);
+    pub const KEY_COMPROMISE: CrlReason = CrlReason(ffi::CRL_REASON_KEY_COMPROMISE);
+    pub const CA_COMPROMISE: CrlReason = CrlReason(ffi::CRL_REASON_CA_COMPROMISE);
+    pub const AFFILIATION_CHANGED: CrlReason = CrlReason(ffi::CRL_REASON_AFFILIATION_CHANGED);
+    pub const SUPERSEDED: CrlReason = CrlReason(ffi::CRL_REASON_SUPERSEDED);
+    pub const CESSATION_OF_OPERATION: CrlReason = CrlReason(ffi::CRL_REASON_CESSATION_OF_OPERATION);
+    pub const CERTIFICATE_HOLD: CrlReason = CrlReason(ffi::CRL_REASON_CERTIFICATE_HOLD);
+    pub const REMOVE_FROM_CRL: CrlReason = CrlReason(ffi::CRL_REASON_REMOVE_FROM_CRL);
+    pub const PRIVILEGE_WITHDRAWN: CrlReason = CrlReason(ffi::CRL_REASON_PRIVILEGE_WITHDRAWN);
+    pub const AA_COMPROMISE: CrlReason = CrlReason(ffi::CRL_REASON_AA_COMPROMISE);
+
+    /// Constructs a `CrlReason` from a raw `CRLReason` value.
+    #[must_use]
+    pub fn from_raw(value: c_int) -> Self {
+        CrlReason(value)
+    }
+
+    /// Returns the raw `CRLReason` value.
+    #[must_use]
+    pub fn as_raw(&self) -> c_int {
+        self.0
+    }
+}
+
+impl fmt::Debug for CrlReason {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Self::UNSPECIFIED => "UNSPECIFIED",
+            Self::KEY_COMPROMISE => "KEY_COMPROMISE",
+            Self::CA_COMPROMISE => "CA_COMPROMISE",
+            Self::AFFILIATION_CHANGED => "AFFILIATION_CHANGED",
+            Self::SUPERSEDED => "SUPERSEDED",
+            Self::CESSATION_OF_OPERATION => "CESSATION_OF_OPERATION",
+            Self::CERTIFICATE_HOLD => "CERTIFICATE_HOLD",
+            Self::REMOVE_FROM_CRL => "REMOVE_FROM_CRL",
+            Self::PRIVILEGE_WITHDRAWN => "PRIVILEGE_WITHDRAWN",
+            Self::AA_COMPROMISE => "AA_COMPROMISE",
+            _ => return fmt.debug_tuple("CrlReason").field(&self.0).finish(),
+        };
+        fmt.write_str(name)
+    }
+}
+
+/// The revocation status of a certificate according to a CRL.
+#[derive(Debug)]
+pub enum CrlStatus<'a> {
+    /// The certificate is not listed.
+    NotRevoked,
+    /// The certificate is listed as revoked.
+    Revoked(&'a X509RevokedRef),
+    /// The certificate is listed with the `removeFromCRL` reason, which only appears in delta
+    /// CRLs and undoes an earlier `certificateHold`.
+    RemoveFromCrl(&'a X509RevokedRef),
+}
+
+impl<'a> CrlStatus<'a> {
+    unsafe fn from_raw(status: c_int, revoked: *mut ffi::X509_REVOKED) -> CrlStatus<'a> {
+        match status {
+            0 => CrlStatus::NotRevoked,
+            1 => CrlStatus::Revoked(X509RevokedRef::from_ptr(revoked)),
+            2 => CrlStatus::RemoveFromCrl(X509RevokedRef::from_ptr(revoked)),
+            _ => unreachable!("X509_CRL_get0_by_{{serial,cert}} returned {status}"),
+        }
+    }
+}
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::X509_REVOKED;
+    fn drop = ffi::X509_REVOKED_free;
+
+    /// An entry of a CRL, identifying a revoked certificate.
+    pub struct X509Revoked;
+}
+
+impl Stackable for X509Revoked {
+    type StackType = ffi::stack_st_X509_REVOKED;
+}
+
+impl X509RevokedRef {
+    /// Returns the serial number of the revoked certificate.
+    #[corresponds(X509_REVOKED_get0_serialNumber)]
+    #[must_use]
+    pub fn serial_number(&self) -> &Asn1IntegerRef {
+        unsafe {
+            let r = ffi::X509_REVOKED_get0_serialNumber(self.as_ptr());
+            assert!(!r.is_null());
+            Asn1IntegerRef::from_const_ptr(r)
+        }
+    }
+
+    /// Returns the time at which the certificate was revoked.
+    #[corresponds(X509_REVOKED_get0_revocationDate)]
+    #[must_use]
+    pub fn revocation_date(&self) -> &Asn1TimeRef {
+        unsafe {
+            let r = ffi::X509_REVOKED_get0_revocationDate(self.as_ptr());
+            assert!(!r.is_null());
+            Asn1TimeRef::from_const_ptr(r)
+        }
+    }
+
+    /// Returns the reason the certificate was revoked, or `None` if the entry has no
+    /// `reasonCode` extension.
+    #[corresponds(X509_REVOKED_get_ext_d2i)]
+    pub fn reason(&self) -> Result<Option<CrlReason>, ErrorStack> {
+        unsafe {
+            let mut critical = 0;
+            let r = ffi::X509_REVOKED_get_ext_d2i(
+                self.as_ptr(),
+                ffi::NID_crl_reason,
+                &mut critical,
+                ptr::null_mut(),
+            )
+            .cast::<ffi::ASN1_ENUMERATED>();
+            if r.is_null() {
+                // -1 means the extension is absent, anything else that it is malformed or
+                // repeated
+                return if critical == -1 {
+                    Ok(None)
+                } else {
+                    Err(ErrorStack::get())
+                };
+            }
+            let reason = ffi::ASN1_ENUMERATED_get(r);
+            ffi::ASN1_ENUMERATED_free(r);
+            Ok(Some(CrlReason(reason as c_int)))
+        }
+    }
+}
+
+impl fmt::Debug for X509RevokedRef {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let serial = self
+            .serial_number()
+            .to_bn()
+            .ok()
+            .and_then(|bn| bn.to_hex_str().ok())
+            .map(|hex| hex.to_string());
+        fmt.debug_struct("X509Revoked")
+            .field("serial_number", &serial)
+            .field("revocation_date", self.revocation_date())
+            .field("reason", &self.reason().ok().flatten())
+            .finish()
+    }
+}
+
+/// A builder used to construct an `X509Crl`.
+///
+/// The CRL is version 2, so that it can carry extensions.
+pub struct X509CrlBuilder(X509Crl);
+
+impl X509CrlBuilder {
+    /// Creates a new builder.
+    #[corresponds(X509_CRL_new)]
+    pub fn new() -> Result<X509CrlBuilder, ErrorStack> {
+        unsafe {
+            ffi::init();
+            let crl = X509Crl::from_ptr(cvt_p(ffi::X509_CRL_new())?);
+            cvt(ffi::X509_CRL_set_version(
+                crl.as_ptr(),
+                ffi::X509_CRL_VERSION_2 as c_long,
+            ))?;
+            Ok(X509CrlBuilder(crl))
+        }
+    }
+
+    /// Sets the issuer name of the CRL.
+    ///
+    /// This must be the subject name of the certificate whose key signs the CRL.
+    #[corresponds(X509_CRL_set_issuer_name)]
+    pub fn set_issuer_name(&mut self, issuer_name: &X509NameRef) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::X509_CRL_set_issuer_name(
+                self.0.as_ptr(),
+                issuer_name.as_ptr(),
+            ))
+        }
+    }
+
+    /// Sets the `thisUpdate` time, at which the CRL was issued.
+    #[corresponds(X509_CRL_set1_lastUpdate)]
+    pub fn set_last_update(&mut self, last_update: &Asn1TimeRef) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::X509_CRL_set1_lastUpdate(
+                self.0.as_ptr(),
+                last_update.as_ptr(),
+            ))
+        }
+    }
+
+    /// Sets the `nextUpdate` time, by which the next CRL will be issued.
+    ///
+    /// Verification rejects the CRL as expired after this time.
+    #[corresponds(X509_CRL_set1_nextUpdate)]
+    pub fn set_next_update(&mut self, next_update: &Asn1TimeRef) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::X509_CRL_set1_nextUpdate(
+                self.0.as_ptr(),
+                next_update.as_ptr(),
+            ))
+        }
+    }
+
+    /// Adds an entry revoking the certificate with serial number `serial_number`.
+    ///
+    /// If `reason` is `Some`, the entry carries a `reasonCode` extension. RFC 5280 recommends
+    /// omitting it rather than using [`CrlReason::UNSPECIFIED`].
+    #[corresponds(X509_CRL_add0_revoked)]
+    pub fn revoke(
+        &mut self,
+        serial_number: &Asn1IntegerRef,
+        revocation_date: &Asn1TimeRef,
+        reason: Option<CrlReason>,
+    ) -> Result<(), ErrorStack> {
+        unsafe {
+            let revoked = X509Revoked::from_ptr(cvt_p(ffi::X509_REVOKED_new())?);
+            cvt(ffi::X509_REVOKED_set_serialNumber(
+                revoked.as_ptr(),
+                serial_number.as_ptr(),
+            ))?;
+            cvt(ffi::X509_REVOKED_set_revocationDate(
+                revoked.as_ptr(),
+                revocation_date.as_ptr(),
+            ))?;
+            if let Some(reason) = reason {
+                let value = cvt_p(ffi::ASN1_ENUMERATED_new())?;
+                let r = cvt(ffi::ASN1_ENUMERATED_set(value, reason.0.into())).and_then(|()| {
+                    cvt(ffi::X509_REVOKED_add1_ext_i2d(
+                        revoked.as_ptr(),
+                        ffi::NID_crl_reason,
+                        value.cast(),
+                        0,
+                        0,
+                    ))
+                });
+                ffi::ASN1_ENUMERATED_free(value);
+                r?;
+            }
+            cvt(ffi::X509_CRL_add0_revoked(
+                self.0.as_ptr(),
+                revoked.as_ptr(),
+            ))?;
+            mem::forget(revoked);
+            Ok(())
+        }
+    }
+
+    /// Sets the `cRLNumber` extension, a number that increases with each CRL the issuer
+    /// publishes.
+    #[corresponds(X509_CRL_add1_ext_i2d)]
+    pub fn set_crl_number(&mut self, crl_number: &BigNumRef) -> Result<(), ErrorStack> {
+        let crl_number = Asn1Integer::from_bn(crl_number)?;
+        unsafe {
+            cvt(ffi::X509_CRL_add1_ext_i2d(
+                self.0.as_ptr(),
+                ffi::NID_crl_number,
+                crl_number.as_ptr().cast(),
+                0,
+                ffi::X509V3_ADD_REPLACE as _,
+            ))
+        }
+    }
+
+    /// Returns a context object which is needed to create extension values for the CRL, such as
+    /// an [`AuthorityKeyIdentifier`].
+    ///
+    /// [`AuthorityKeyIdentifier`]: crate::x509::extension::AuthorityKeyIdentifier
+    #[corresponds(X509V3_set_ctx)]
+    #[must_use]
+    pub fn x509v3_context<'a>(
+        &'a self,
+        issuer: &'a X509Ref,
+        conf: Option<&'a ConfRef>,
+    ) -> X509v3Context<'a> {
+        unsafe {
+            let mut ctx = mem::zeroed();
+
+            ffi::X509V3_set_ctx(
+                &mut ctx,
+                issuer.as_ptr(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                self.0.as_ptr(),
+                0,
+            );
+
+            // nodb case taken care of since we zeroed ctx above
+            if let Some(conf) = conf {
+                ffi::X509V3_set_nconf(&mut ctx, conf.as_ptr());
+            }
+
+            X509v3Context(ctx, PhantomData)
+        }
+    }
+
+    /// Adds an X509 extension value to the CRL.
+    #[corresponds(X509_CRL_add_ext)]
+    pub fn append_extension(&mut self, extension: &X509ExtensionRef) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::X509_CRL_add_ext(
+                self.0.as_ptr(),
+                extension.as_ptr(),
+                -1,
+            ))
+        }
+    }
+
+    /// Sorts the entries by serial number and signs the CRL with a private key.
+    #[corresponds(X509_CRL_sign)]
+    pub fn sign<T>(&mut self, key: &PKeyRef<T>, hash: MessageDigest) -> Result<(), ErrorStack>
+    where
+        T: HasPrivate,
+    {
+        unsafe {
+            cvt(ffi::X509_CRL_sort(self.0.as_ptr()))?;
+            cvt(ffi::X509_CRL_sign(
+                self.0.as_ptr(),
+                key.as_ptr(),
+                hash.as_ptr(),
+            ))
+        }
+    }
+
+    /// Consumes the builder, returning the CRL.
+    #[must_use]
+    pub fn build(self) -> X509Crl {
+        self.0
+    }
+}
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::X509_CRL;
+    fn drop = ffi::X509_CRL_free;
+
+    /// A certificate revocation list.
+    pub struct X509Crl;
+}
+
+impl X509Crl {
+    /// Returns a new builder.
+    pub fn builder() -> Result<X509CrlBuilder, ErrorStack> {
+        X509CrlBuilder::new()
+    }
+
+    from_pem! {
+        /// Deserializes a PEM-encoded CRL.
+        ///
+        /// The input should have a header of `-----BEGIN X509 CRL-----`.
+        #[corresponds(PEM_read_bio_X509_CRL)]
+        from_pem,
+        X509Crl,
+        ffi::PEM_read_bio_X509_CRL
+    }
+
+    from_der! {
+        /// Deserializes a DER-encoded CRL.
+        #[corresponds(d2i_X509_CRL)]
+        from_der,
+        X509Crl,
+        ffi::d2i_X509_CRL,
+        crate::libc_types::c_long
+    }
+}
+
+impl X509CrlRef {
+    /// Returns the version of the CRL.
+    ///
+    /// The version is zero-indexed, so a version 2 CRL returns `1`.
+    #[corresponds(X509_CRL_get_version)]
+    #[must_use]
+    pub fn version(&self) -> i32 {
+        unsafe { ffi::X509_CRL_get_version(self.as_ptr()) as i32 }
+    }
+
+    /// Returns the name of the issuer of the CRL.
+    #[corresponds(X509_CRL_get_issuer)]
+    #[must_use]
+    pub fn issuer_name(&self) -> &X509NameRef {
+        unsafe {
+            let name = ffi::X509_CRL_get_issuer(self.as_ptr());
+            assert!(!name.is_null());
+            X509NameRef::from_ptr(name)
+        }
+    }
+
+    /// Returns the `thisUpdate` time, at which the CRL was issued.
+    #[corresponds(X509_CRL_get0_lastUpdate)]
+    #[must_use]
+    pub fn last_update(&self) -> &Asn1TimeRef {
+        unsafe {
+            let date = ffi::X509_CRL_get0_lastUpdate(self.as_ptr());
+            assert!(!date.is_null());
+            Asn1TimeRef::from_const_ptr(date)
+        }
+    }
+
+    /// Returns the `nextUpdate` time, if the CRL has one.
+    #[corresponds(X509_CRL_get0_nextUpdate)]
+    #[must_use]
+    pub fn next_update(&self) -> Option<&Asn1TimeRef> {
+        unsafe { Asn1TimeRef::from_const_ptr_opt(ffi::X509_CRL_get0_nextUpdate(self.as_ptr())) }
+    }
+
+    /// Returns an iterator over the entries of the CRL.
+    #[corresponds(X509_CRL_get_REVOKED)]
+    #[must_use]
+    pub fn revoked(&self) -> X509RevokedIter<'_> {
+        unsafe {
+            let revoked = ffi::X509_CRL_get_REVOKED(self.as_ptr());
+            X509RevokedIter {
+                inner: StackRef::<X509Revoked>::from_const_ptr_opt(revoked).map(StackRef::iter),
+            }
+        }
+    }
+
+    /// Returns the `cRLNumber` extension, if the CRL has one.
+    #[corresponds(X509_CRL_get_ext_d2i)]
+    pub fn crl_number(&self) -> Result<Option<BigNum>, ErrorStack> {
+        unsafe {
+            let mut critical = 0;
+            let r = ffi::X509_CRL_get_ext_d2i(
+                self.as_ptr(),
+                ffi::NID_crl_number,
+                &mut critical,
+                ptr::null_mut(),
+            )
+            .cast::<ffi::ASN1_INTEGER>();
+            if r.is_null() {
+                return if critical == -1 {
+                    Ok(None)
+                } else {
+                    Err(ErrorStack::get())
+                };
+            }
+            Asn1Integer::from_ptr(r).to_bn().map(Some)
+        }
+    }
+
+    /// Looks up the entry for the certificate with serial number `serial_number`.
+    ///
+    /// Only the serial number is compared. Use [`X509CrlRef::get_by_cert`] to also check that the
+    /// certificate was issued by the issuer of the CRL.
+    #[corresponds(X509_CRL_get0_by_serial)]
+    pub fn get_by_serial(&self, serial_number: &Asn1IntegerRef) -> CrlStatus<'_> {
+        unsafe {
+            let mut revoked = ptr::null_mut();
+            let status =
+                ffi::X509_CRL_get0_by_serial(self.as_ptr(), &mut revoked, serial_number.as_ptr());
+            CrlStatus::from_raw(status, revoked)
+        }
+    }
+
+    /// Looks up the entry for `cert`.
+    #[corresponds(X509_CRL_get0_by_cert)]
+    pub fn get_by_cert(&self, cert: &X509Ref) -> CrlStatus<'_> {
+        unsafe {
+            let mut revoked = ptr::null_mut();
+            let status = ffi::X509_CRL_get0_by_cert(self.as_ptr(), &mut revoked, cert.as_ptr());
+            CrlStatus::from_raw(status, revoked)
+        }
+    }
+
+    /// Checks if the CRL is signed using the given public key.
+    ///
+    /// Only the signature is checked: the update times and the issuer are not.
+    ///
+    /// Returns `true` if verification succeeds.
+    #[corresponds(X509_CRL_verify)]
+    pub fn verify<T>(&self, key: &PKeyRef<T>) -> Result<bool, ErrorStack>
+    where
+        T: HasPublic,
+    {
+        unsafe { cvt_n(ffi::X509_CRL_verify(self.as_ptr(), key.as_ptr())).map(|n| n != 0) }
+    }
+
+    to_pem! {
+        /// Serializes the CRL into a PEM-encoded structure.
+        ///
+        /// The output will have a header of `-----BEGIN X509 CRL-----`.
+        #[corresponds(PEM_write_bio_X509_CRL)]
+        to_pem,
+        ffi::PEM_write_bio_X509_CRL
+    }
+
+    to_der! {
+        /// Serializes the CRL into a DER-encoded structure.
+        #[corresponds(i2d_X509_CRL)]
+        to_der,
+        ffi::i2d_X509_CRL
+    }
+}
+
+impl ToOwned for X509CrlRef {
+    type Owned = X509Crl;
+
+    fn to_owned(&self) -> X509Crl {
+        unsafe {
+            ffi::X509_CRL_up_ref(self.as_ptr());
+            X509Crl::from_ptr(self.as_ptr())
+        }
+    }
+}
+
+impl Clone for X509Crl {
+    fn clone(&self) -> X509Crl {
+        X509CrlRef::to_owned(self)
+    }
+}
+
+impl AsRef<X509CrlRef> for X509CrlRef {
+    fn as_ref(&self) -> &X509CrlRef {
+        self
+    }
+}
+
+impl fmt::Debug for X509Crl {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("X509Crl")
+            .field("issuer", self.issuer_name())
+            .field("last_update", self.last_update())
+            .field("next_update", &self.next_update())
+            .field("revoked", &self.revoked().len())
+            .finish()
+    }
+}
+
+impl Stackable for X509Crl {
+    type StackType = ffi::stack_st_X509_CRL;
+}
+
+/// Iterator over the entries of a CRL.
+pub struct X509RevokedIter<'a> {
+    inner: Option<stack::Iter<'a, X509Revoked>>,
+}
+
+impl<'a> Iterator for X509RevokedIter<'a> {
+    type Item = &'a X509RevokedRef;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.as_mut()?.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner
+            .as_ref()
+            .map_or((0, Some(0)), Iterator::size_hint)
+    }
+}
+
+impl ExactSizeIterator for X509RevokedIter<'_> {}
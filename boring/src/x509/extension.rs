@@ -3,7 +3,14 @@
 //! The extensions defined for X.509 v3 certificates provide methods for
 //! associating additional attributes with users or public keys and for
 //! managing relationships between CAs. The extensions created using this
-//! module can be used with `X509v3Context` objects.
+//! module can be used with `X509v3Context` objects. Only the builders whose value depends on the
+//! issuer or the subject, such as [`SubjectKeyIdentifier`] and [`AuthorityKeyIdentifier`], take
+//! the context; the others encode their value on their own and don't need one.
+//!
+//! The same types are returned when reading extensions back from a certificate, e.g. with
+//! [`X509Ref::basic_constraints`], so that a built extension round-trips.
+//!
+//! [`X509Ref::basic_constraints`]: crate::x509::X509Ref::basic_constraints
 //!
 //! # Example
 //!
@@ -16,14 +23,19 @@
 //! let extension: X509Extension = bc.build().unwrap();
 //! ```
 use std::fmt::Write;
+use std::net::IpAddr;
 
-use crate::asn1::Asn1Object;
+use crate::asn1::{Asn1Object, Asn1ObjectRef};
+use crate::cvt_p;
+use crate::der::{self, Reader};
 use crate::error::ErrorStack;
+use crate::ffi;
 use crate::nid::Nid;
 use crate::x509::{GeneralName, Stack, X509Extension, X509v3Context};
-use foreign_types::ForeignType;
+use foreign_types::{ForeignType, ForeignTypeRef};
 
 /// An extension which indicates whether a certificate is a CA certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BasicConstraints {
     critical: bool,
     ca: bool,
@@ -83,9 +95,52 @@ impl BasicConstraints {
         }
         X509Extension::new_nid(None, None, Nid::BASIC_CONSTRAINTS, &value)
     }
+
+    /// Returns `true` if the extension is critical.
+    #[must_use]
+    pub fn is_critical(&self) -> bool {
+        self.critical
+    }
+
+    /// Returns `true` if the certificate is a CA certificate.
+    #[must_use]
+    pub fn is_ca(&self) -> bool {
+        self.ca
+    }
+
+    /// Returns the pathlen, if one is set.
+    #[must_use]
+    pub fn path_len(&self) -> Option<u32> {
+        self.pathlen
+    }
+
+    pub(crate) fn from_der(critical: bool, der: &[u8]) -> Result<BasicConstraints, ErrorStack> {
+        let mut reader = Reader::new(der);
+        let mut value = reader.read_sequence()?;
+        reader.finish()?;
+        let ca = match value.read_optional(der::BOOLEAN)? {
+            None | Some([0]) => false,
+            Some([0xff]) => true,
+            Some(_) => return Err(der::malformed()),
+        };
+        let pathlen = value
+            .read_optional(der::INTEGER)?
+            .map(|pathlen| {
+                u32::try_from(der::parse_integer(pathlen)?)
+                    .map_err(|_| ErrorStack::internal_error_str("pathlen too large"))
+            })
+            .transpose()?;
+        value.finish()?;
+        Ok(BasicConstraints {
+            critical,
+            ca,
+            pathlen,
+        })
+    }
 }
 
 /// An extension consisting of a list of names of the permitted key usages.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct KeyUsage {
     critical: bool,
     digital_signature: bool,
@@ -219,10 +274,95 @@ impl KeyUsage {
         append(&mut value, &mut first, self.decipher_only, "decipherOnly");
         X509Extension::new_nid(None, None, Nid::KEY_USAGE, &value)
     }
+
+    /// Returns `true` if the extension is critical.
+    #[must_use]
+    pub fn is_critical(&self) -> bool {
+        self.critical
+    }
+
+    /// Returns `true` if the `digitalSignature` flag is set.
+    #[must_use]
+    pub fn has_digital_signature(&self) -> bool {
+        self.digital_signature
+    }
+
+    /// Returns `true` if the `nonRepudiation` flag is set.
+    #[must_use]
+    pub fn has_non_repudiation(&self) -> bool {
+        self.non_repudiation
+    }
+
+    /// Returns `true` if the `keyEncipherment` flag is set.
+    #[must_use]
+    pub fn has_key_encipherment(&self) -> bool {
+        self.key_encipherment
+    }
+
+    /// Returns `true` if the `dataEncipherment` flag is set.
+    #[must_use]
+    pub fn has_data_encipherment(&self) -> bool {
+        self.data_encipherment
+    }
+
+    /// Returns `true` if the `keyAgreement` flag is set.
+    #[must_use]
+    pub fn has_key_agreement(&self) -> bool {
+        self.key_agreement
+    }
+
+    /// Returns `true` if the `keyCertSign` flag is set.
+    #[must_use]
+    pub fn has_key_cert_sign(&self) -> bool {
+        self.key_cert_sign
+    }
+
+    /// Returns `true` if the `cRLSign` flag is set.
+    #[must_use]
+    pub fn has_crl_sign(&self) -> bool {
+        self.crl_sign
+    }
+
+    /// Returns `true` if the `encipherOnly` flag is set.
+    #[must_use]
+    pub fn has_encipher_only(&self) -> bool {
+        self.encipher_only
+    }
+
+    /// Returns `true` if the `decipherOnly` flag is set.
+    #[must_use]
+    pub fn has_decipher_only(&self) -> bool {
+        self.decipher_only
+    }
+
+    pub(crate) fn from_der(critical: bool, der: &[u8]) -> Result<KeyUsage, ErrorStack> {
+        let mut reader = Reader::new(der);
+        let value = reader.read(der::BIT_STRING)?;
+        reader.finish()?;
+        let bits = match value {
+            [unused, bits @ ..] if *unused < 8 && (*unused == 0 || !bits.is_empty()) => bits,
+            _ => return Err(der::malformed()),
+        };
+        // Bit 0 is the most significant bit of the first byte
+        let bit = |n: usize| bits.get(n / 8).is_some_and(|b| b & (0x80 >> (n % 8)) != 0);
+        Ok(KeyUsage {
+            critical,
+            digital_signature: bit(0),
+            non_repudiation: bit(1),
+            key_encipherment: bit(2),
+            data_encipherment: bit(3),
+            key_agreement: bit(4),
+            key_cert_sign: bit(5),
+            crl_sign: bit(6),
+            encipher_only: bit(7),
+            decipher_only: bit(8),
+        })
+    }
 }
 
 /// An extension consisting of a list of usages indicating purposes
 /// for which the certificate public key can be used for.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ExtendedKeyUsage {
     critical: bool,
     items: Vec<String>,
@@ -316,6 +456,38 @@ impl ExtendedKeyUsage {
             X509Extension::new_internal(Nid::EXT_KEY_USAGE, self.critical, stack.as_ptr().cast())
         }
     }
+
+    /// Returns `true` if the extension is critical.
+    #[must_use]
+    pub fn is_critical(&self) -> bool {
+        self.critical
+    }
+
+    /// Returns the usages, in the order they were added.
+    ///
+    /// Usages read from a certificate are given by their short name, e.g. `serverAuth`, or by
+    /// their OID if they have none.
+    #[must_use]
+    pub fn usages(&self) -> &[String] {
+        &self.items
+    }
+
+    pub(crate) fn from_der(critical: bool, der: &[u8]) -> Result<ExtendedKeyUsage, ErrorStack> {
+        let mut reader = Reader::new(der);
+        let mut value = reader.read_sequence()?;
+        reader.finish()?;
+        let mut items = vec![];
+        while !value.is_empty() {
+            let oid = der::parse_oid(value.read(der::OBJECT_IDENTIFIER)?)?;
+            let nid = Asn1Object::from_str(&oid)?.nid();
+            if nid == Nid::UNDEF {
+                items.push(oid);
+            } else {
+                items.push(nid.short_name()?.to_string());
+            }
+        }
+        Ok(ExtendedKeyUsage { critical, items })
+    }
 }
 
 /// An extension that provides a means of identifying certificates that contain a
@@ -524,6 +696,751 @@ impl SubjectAlternativeName {
     }
 }
 
+/// A subtree of names permitted or excluded by a [`NameConstraints`] extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeneralSubtree {
+    /// DNS names equal to, or subdomains of, the name.
+    Dns(String),
+    /// Email addresses: a full address, a host, or a domain starting with `.`.
+    Email(String),
+    /// URIs whose host is the name, or a subdomain if it starts with `.`.
+    Uri(String),
+    /// IP addresses in the network with this address and prefix length.
+    Ip(IpAddr, u8),
+    /// Directory names under this DER-encoded `Name`, see [`X509NameRef::to_der`].
+    ///
+    /// [`X509NameRef::to_der`]: crate::x509::X509NameRef::to_der
+    DirectoryName(Vec<u8>),
+}
+
+impl GeneralSubtree {
+    fn to_der(&self) -> Result<Vec<u8>, ErrorStack> {
+        let base = match self {
+            GeneralSubtree::Dns(name) => der::tlv(der::context(2), name.as_bytes()),
+            GeneralSubtree::Email(name) => der::tlv(der::context(1), name.as_bytes()),
+            GeneralSubtree::Uri(name) => der::tlv(der::context(6), name.as_bytes()),
+            GeneralSubtree::Ip(address, prefix_len) => {
+                let (address, mask) = match address {
+                    IpAddr::V4(address) if *prefix_len <= 32 => (
+                        address.octets().to_vec(),
+                        u32::MAX
+                            .checked_shl(32 - u32::from(*prefix_len))
+                            .unwrap_or(0)
+                            .to_be_bytes()
+                            .to_vec(),
+                    ),
+                    IpAddr::V6(address) if *prefix_len <= 128 => (
+                        address.octets().to_vec(),
+                        u128::MAX
+                            .checked_shl(128 - u32::from(*prefix_len))
+                            .unwrap_or(0)
+                            .to_be_bytes()
+                            .to_vec(),
+                    ),
+                    _ => return Err(ErrorStack::internal_error_str("invalid prefix length")),
+                };
+                der::tlv(der::context(7), &[address, mask].concat())
+            }
+            GeneralSubtree::DirectoryName(name) => der::tlv(der::context_constructed(4), name),
+        };
+        // The minimum and maximum fields are omitted, as RFC 5280 requires
+        Ok(der::sequence(&[base]))
+    }
+
+    fn from_der(reader: &mut Reader<'_>) -> Result<GeneralSubtree, ErrorStack> {
+        let mut subtree = reader.read_sequence()?;
+        let base = match subtree.read_any()? {
+            (tag, name) if tag == der::context(1) => GeneralSubtree::Email(parse_string(name)?),
+            (tag, name) if tag == der::context(2) => GeneralSubtree::Dns(parse_string(name)?),
+            (tag, name) if tag == der::context(6) => GeneralSubtree::Uri(parse_string(name)?),
+            (tag, name) if tag == der::context(7) => {
+                // The address is followed by the mask
+                let (address, mask) = name.split_at(name.len() / 2);
+                let (address, mask) = match name.len() {
+                    8 => (
+                        IpAddr::from(<[u8; 4]>::try_from(address).unwrap()),
+                        u128::from(u32::from_be_bytes(mask.try_into().unwrap())) << 96,
+                    ),
+                    32 => (
+                        IpAddr::from(<[u8; 16]>::try_from(address).unwrap()),
+                        u128::from_be_bytes(mask.try_into().unwrap()),
+                    ),
+                    _ => return Err(der::malformed()),
+                };
+                let prefix_len = mask.leading_ones();
+                if prefix_len != mask.count_ones() {
+                    return Err(ErrorStack::internal_error_str(
+                        "IP address mask is not a prefix",
+                    ));
+                }
+                GeneralSubtree::Ip(address, prefix_len as u8)
+            }
+            (tag, name) if tag == der::context_constructed(4) => {
+                GeneralSubtree::DirectoryName(name.to_vec())
+            }
+            _ => {
+                return Err(ErrorStack::internal_error_str(
+                    "unsupported name constraint type",
+                ))
+            }
+        };
+        // DER omits the default minimum of 0 and RFC 5280 forbids a maximum, so either field
+        // being present means bounds that the subtree cannot represent
+        if subtree.read_optional(der::context(0))?.is_some()
+            || subtree.read_optional(der::context(1))?.is_some()
+        {
+            return Err(ErrorStack::internal_error_str(
+                "unsupported name constraint bounds",
+            ));
+        }
+        subtree.finish()?;
+        Ok(base)
+    }
+}
+
+/// An extension that restricts the names of the certificates issued below a CA certificate.
+///
+/// RFC 5280 requires the extension to be critical.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameConstraints {
+    critical: bool,
+    permitted: Vec<GeneralSubtree>,
+    excluded: Vec<GeneralSubtree>,
+}
+
+impl Default for NameConstraints {
+    fn default() -> NameConstraints {
+        NameConstraints::new()
+    }
+}
+
+impl NameConstraints {
+    /// Construct a new `NameConstraints` extension.
+    #[must_use]
+    pub fn new() -> NameConstraints {
+        NameConstraints {
+            critical: false,
+            permitted: vec![],
+            excluded: vec![],
+        }
+    }
+
+    /// Sets the `critical` flag to `true`. The extension will be critical.
+    pub fn critical(&mut self) -> &mut NameConstraints {
+        self.critical = true;
+        self
+    }
+
+    /// Adds a permitted subtree. Names of a type with permitted subtrees must be in one of them.
+    pub fn permitted(&mut self, subtree: GeneralSubtree) -> &mut NameConstraints {
+        self.permitted.push(subtree);
+        self
+    }
+
+    /// Adds an excluded subtree. Names must not be in any excluded subtree.
+    pub fn excluded(&mut self, subtree: GeneralSubtree) -> &mut NameConstraints {
+        self.excluded.push(subtree);
+        self
+    }
+
+    /// Return the `NameConstraints` extension as an `X509Extension`.
+    pub fn build(&self) -> Result<X509Extension, ErrorStack> {
+        if self.permitted.is_empty() && self.excluded.is_empty() {
+            return Err(ErrorStack::internal_error_str(
+                "name constraints need a permitted or excluded subtree",
+            ));
+        }
+        let mut value = vec![];
+        for (tag, subtrees) in [(0, &self.permitted), (1, &self.excluded)] {
+            if !subtrees.is_empty() {
+                let subtrees = subtrees
+                    .iter()
+                    .map(GeneralSubtree::to_der)
+                    .collect::<Result<Vec<_>, _>>()?;
+                value.push(der::tlv(der::context_constructed(tag), &subtrees.concat()));
+            }
+        }
+        new_der(Nid::NAME_CONSTRAINTS, self.critical, &der::sequence(&value))
+    }
+
+    /// Returns `true` if the extension is critical.
+    #[must_use]
+    pub fn is_critical(&self) -> bool {
+        self.critical
+    }
+
+    /// Returns the permitted subtrees.
+    #[must_use]
+    pub fn permitted_subtrees(&self) -> &[GeneralSubtree] {
+        &self.permitted
+    }
+
+    /// Returns the excluded subtrees.
+    #[must_use]
+    pub fn excluded_subtrees(&self) -> &[GeneralSubtree] {
+        &self.excluded
+    }
+
+    pub(crate) fn from_der(critical: bool, der: &[u8]) -> Result<NameConstraints, ErrorStack> {
+        let mut reader = Reader::new(der);
+        let mut value = reader.read_sequence()?;
+        reader.finish()?;
+        let mut subtrees = |tag| -> Result<Vec<GeneralSubtree>, ErrorStack> {
+            let mut parsed = vec![];
+            if let Some(contents) = value.read_optional(der::context_constructed(tag))? {
+                let mut reader = Reader::new(contents);
+                while !reader.is_empty() {
+                    parsed.push(GeneralSubtree::from_der(&mut reader)?);
+                }
+            }
+            Ok(parsed)
+        };
+        let permitted = subtrees(0)?;
+        let excluded = subtrees(1)?;
+        value.finish()?;
+        Ok(NameConstraints {
+            critical,
+            permitted,
+            excluded,
+        })
+    }
+}
+
+/// A qualifier of a policy in a [`CertificatePolicies`] extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyQualifier {
+    /// The URI of the certification practice statement.
+    Cps(String),
+    /// A user notice, with the text to display to relying parties.
+    UserNotice(String),
+    /// A qualifier of another type, or a user notice with a notice reference or without
+    /// explicit text, given by the dotted OID of its type and its DER encoding.
+    Other(String, Vec<u8>),
+}
+
+impl PolicyQualifier {
+    fn to_der(&self) -> Result<Vec<u8>, ErrorStack> {
+        let (id, qualifier) = match self {
+            PolicyQualifier::Cps(uri) => (
+                der::oid(Nid::ID_QT_CPS.short_name()?)?,
+                der::tlv(der::IA5_STRING, uri.as_bytes()),
+            ),
+            PolicyQualifier::UserNotice(text) => (
+                der::oid(Nid::ID_QT_UNOTICE.short_name()?)?,
+                // explicitText only, as RFC 5280 recommends against noticeRef
+                der::sequence(&[der::tlv(der::UTF8_STRING, text.as_bytes())]),
+            ),
+            PolicyQualifier::Other(id, qualifier) => {
+                let mut reader = Reader::new(qualifier);
+                reader.read_element()?;
+                reader.finish()?;
+                (der::oid(id)?, qualifier.clone())
+            }
+        };
+        Ok(der::sequence(&[id, qualifier]))
+    }
+
+    fn from_der(reader: &mut Reader<'_>) -> Result<PolicyQualifier, ErrorStack> {
+        let mut info = reader.read_sequence()?;
+        let id = der::parse_oid(info.read(der::OBJECT_IDENTIFIER)?)?;
+        let qualifier = info.read_element()?;
+        info.finish()?;
+        let parsed = match Asn1Object::from_str(&id)?.nid() {
+            Nid::ID_QT_CPS => Some(PolicyQualifier::Cps(parse_string(
+                Reader::new(qualifier).read(der::IA5_STRING)?,
+            )?)),
+            Nid::ID_QT_UNOTICE => PolicyQualifier::user_notice(qualifier)?,
+            _ => None,
+        };
+        Ok(parsed.unwrap_or_else(|| PolicyQualifier::Other(id, qualifier.to_vec())))
+    }
+
+    /// Returns `None` for user notices with a notice reference or without explicit text.
+    fn user_notice(qualifier: &[u8]) -> Result<Option<PolicyQualifier>, ErrorStack> {
+        let mut notice = Reader::new(qualifier).read_sequence()?;
+        if notice.is_empty() || notice.peek_tag() == Some(der::SEQUENCE) {
+            return Ok(None);
+        }
+        let text = match notice.read_any()? {
+            (der::BMP_STRING, text) => {
+                if text.len() % 2 != 0 {
+                    return Err(der::malformed());
+                }
+                let text = text
+                    .chunks(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect::<Vec<_>>();
+                String::from_utf16(&text).map_err(|_| der::malformed())?
+            }
+            (der::UTF8_STRING | der::IA5_STRING | der::VISIBLE_STRING, text) => parse_string(text)?,
+            _ => return Err(der::malformed()),
+        };
+        notice.finish()?;
+        Ok(Some(PolicyQualifier::UserNotice(text)))
+    }
+}
+
+/// An extension that lists the policies a certificate was issued under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificatePolicies {
+    critical: bool,
+    policies: Vec<(String, Vec<PolicyQualifier>)>,
+}
+
+impl Default for CertificatePolicies {
+    fn default() -> CertificatePolicies {
+        CertificatePolicies::new()
+    }
+}
+
+impl CertificatePolicies {
+    /// Construct a new `CertificatePolicies` extension.
+    #[must_use]
+    pub fn new() -> CertificatePolicies {
+        CertificatePolicies {
+            critical: false,
+            policies: vec![],
+        }
+    }
+
+    /// Sets the `critical` flag to `true`. The extension will be critical.
+    pub fn critical(&mut self) -> &mut CertificatePolicies {
+        self.critical = true;
+        self
+    }
+
+    /// Adds the policy with the OID `policy`, e.g. `2.23.140.1.2.1`, with optional qualifiers.
+    ///
+    /// `anyPolicy` is `2.5.29.32.0`.
+    pub fn policy(
+        &mut self,
+        policy: &str,
+        qualifiers: &[PolicyQualifier],
+    ) -> &mut CertificatePolicies {
+        self.policies
+            .push((policy.to_string(), qualifiers.to_vec()));
+        self
+    }
+
+    /// Return the `CertificatePolicies` extension as an `X509Extension`.
+    pub fn build(&self) -> Result<X509Extension, ErrorStack> {
+        if self.policies.is_empty() {
+            return Err(ErrorStack::internal_error_str(
+                "certificate policies need a policy",
+            ));
+        }
+        let mut value = vec![];
+        for (policy, qualifiers) in &self.policies {
+            let mut info = vec![der::oid(policy)?];
+            if !qualifiers.is_empty() {
+                let qualifiers = qualifiers
+                    .iter()
+                    .map(PolicyQualifier::to_der)
+                    .collect::<Result<Vec<_>, _>>()?;
+                info.push(der::sequence(&qualifiers));
+            }
+            value.push(der::sequence(&info));
+        }
+        new_der(
+            Nid::CERTIFICATE_POLICIES,
+            self.critical,
+            &der::sequence(&value),
+        )
+    }
+
+    /// Returns `true` if the extension is critical.
+    #[must_use]
+    pub fn is_critical(&self) -> bool {
+        self.critical
+    }
+
+    /// Returns the OIDs of the policies with their qualifiers, in the order they were added.
+    ///
+    /// Policies read from a certificate are given by their dotted OID.
+    pub fn policies(&self) -> impl ExactSizeIterator<Item = (&str, &[PolicyQualifier])> {
+        self.policies
+            .iter()
+            .map(|(policy, qualifiers)| (policy.as_str(), qualifiers.as_slice()))
+    }
+
+    pub(crate) fn from_der(critical: bool, der: &[u8]) -> Result<CertificatePolicies, ErrorStack> {
+        let mut reader = Reader::new(der);
+        let mut value = reader.read_sequence()?;
+        reader.finish()?;
+        let mut policies = vec![];
+        while !value.is_empty() {
+            let mut info = value.read_sequence()?;
+            let policy = der::parse_oid(info.read(der::OBJECT_IDENTIFIER)?)?;
+            let mut qualifiers = vec![];
+            if let Some(contents) = info.read_optional(der::SEQUENCE)? {
+                let mut reader = Reader::new(contents);
+                while !reader.is_empty() {
+                    qualifiers.push(PolicyQualifier::from_der(&mut reader)?);
+                }
+            }
+            info.finish()?;
+            policies.push((policy, qualifiers));
+        }
+        Ok(CertificatePolicies { critical, policies })
+    }
+}
+
+/// An extension that constrains policy validation of the paths through a CA certificate.
+///
+/// RFC 5280 requires the extension to be critical.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyConstraints {
+    critical: bool,
+    require_explicit_policy: Option<u32>,
+    inhibit_policy_mapping: Option<u32>,
+}
+
+impl Default for PolicyConstraints {
+    fn default() -> PolicyConstraints {
+        PolicyConstraints::new()
+    }
+}
+
+impl PolicyConstraints {
+    /// Construct a new `PolicyConstraints` extension.
+    #[must_use]
+    pub fn new() -> PolicyConstraints {
+        PolicyConstraints {
+            critical: false,
+            require_explicit_policy: None,
+            inhibit_policy_mapping: None,
+        }
+    }
+
+    /// Sets the `critical` flag to `true`. The extension will be critical.
+    pub fn critical(&mut self) -> &mut PolicyConstraints {
+        self.critical = true;
+        self
+    }
+
+    /// Requires an acceptable policy in every certificate after `skip_certs` more certificates
+    /// in the path.
+    pub fn require_explicit_policy(&mut self, skip_certs: u32) -> &mut PolicyConstraints {
+        self.require_explicit_policy = Some(skip_certs);
+        self
+    }
+
+    /// Forbids policy mapping after `skip_certs` more certificates in the path.
+    pub fn inhibit_policy_mapping(&mut self, skip_certs: u32) -> &mut PolicyConstraints {
+        self.inhibit_policy_mapping = Some(skip_certs);
+        self
+    }
+
+    /// Return the `PolicyConstraints` extension as an `X509Extension`.
+    pub fn build(&self) -> Result<X509Extension, ErrorStack> {
+        let mut value = vec![];
+        if let Some(skip_certs) = self.require_explicit_policy {
+            value.push(der::integer(der::context(0), skip_certs.into()));
+        }
+        if let Some(skip_certs) = self.inhibit_policy_mapping {
+            value.push(der::integer(der::context(1), skip_certs.into()));
+        }
+        if value.is_empty() {
+            return Err(ErrorStack::internal_error_str(
+                "policy constraints need requireExplicitPolicy or inhibitPolicyMapping",
+            ));
+        }
+        new_der(
+            Nid::POLICY_CONSTRAINTS,
+            self.critical,
+            &der::sequence(&value),
+        )
+    }
+
+    /// Returns `true` if the extension is critical.
+    #[must_use]
+    pub fn is_critical(&self) -> bool {
+        self.critical
+    }
+}
+
+/// An extension that tells how to access information about the issuer of the certificate, such
+/// as its OCSP responder and its certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorityInfoAccess {
+    critical: bool,
+    items: Vec<(Nid, String)>,
+}
+
+impl Default for AuthorityInfoAccess {
+    fn default() -> AuthorityInfoAccess {
+        AuthorityInfoAccess::new()
+    }
+}
+
+impl AuthorityInfoAccess {
+    /// Construct a new `AuthorityInfoAccess` extension.
+    #[must_use]
+    pub fn new() -> AuthorityInfoAccess {
+        AuthorityInfoAccess {
+            critical: false,
+            items: vec![],
+        }
+    }
+
+    /// Sets the `critical` flag to `true`. The extension will be critical.
+    ///
+    /// RFC 5280 requires the extension to be non-critical.
+    pub fn critical(&mut self) -> &mut AuthorityInfoAccess {
+        self.critical = true;
+        self
+    }
+
+    /// Adds the URI of an OCSP responder for the certificate.
+    pub fn ocsp(&mut self, uri: &str) -> &mut AuthorityInfoAccess {
+        self.items.push((Nid::AD_OCSP, uri.to_string()));
+        self
+    }
+
+    /// Adds a URI where the certificate of the issuer can be downloaded.
+    pub fn ca_issuers(&mut self, uri: &str) -> &mut AuthorityInfoAccess {
+        self.items.push((Nid::AD_CA_ISSUERS, uri.to_string()));
+        self
+    }
+
+    /// Return the `AuthorityInfoAccess` extension as an `X509Extension`.
+    pub fn build(&self) -> Result<X509Extension, ErrorStack> {
+        if self.items.is_empty() {
+            return Err(ErrorStack::internal_error_str(
+                "authority info access needs an access description",
+            ));
+        }
+        let mut value = vec![];
+        for (method, uri) in &self.items {
+            value.push(der::sequence(&[
+                der::oid(method.short_name()?)?,
+                der::tlv(der::context(6), uri.as_bytes()),
+            ]));
+        }
+        new_der(Nid::INFO_ACCESS, self.critical, &der::sequence(&value))
+    }
+
+    /// Returns `true` if the extension is critical.
+    #[must_use]
+    pub fn is_critical(&self) -> bool {
+        self.critical
+    }
+}
+
+/// An extension that tells where to download the CRLs that cover the certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrlDistributionPoints {
+    critical: bool,
+    uris: Vec<String>,
+}
+
+impl Default for CrlDistributionPoints {
+    fn default() -> CrlDistributionPoints {
+        CrlDistributionPoints::new()
+    }
+}
+
+impl CrlDistributionPoints {
+    /// Construct a new `CrlDistributionPoints` extension.
+    #[must_use]
+    pub fn new() -> CrlDistributionPoints {
+        CrlDistributionPoints {
+            critical: false,
+            uris: vec![],
+        }
+    }
+
+    /// Sets the `critical` flag to `true`. The extension will be critical.
+    pub fn critical(&mut self) -> &mut CrlDistributionPoints {
+        self.critical = true;
+        self
+    }
+
+    /// Adds a distribution point with the URI of a CRL.
+    ///
+    /// The distribution point covers all reasons and the CRL is issued by the issuer of the
+    /// certificate.
+    pub fn uri(&mut self, uri: &str) -> &mut CrlDistributionPoints {
+        self.uris.push(uri.to_string());
+        self
+    }
+
+    /// Return the `CrlDistributionPoints` extension as an `X509Extension`.
+    pub fn build(&self) -> Result<X509Extension, ErrorStack> {
+        if self.uris.is_empty() {
+            return Err(ErrorStack::internal_error_str(
+                "CRL distribution points need a distribution point",
+            ));
+        }
+        let mut value = vec![];
+        for uri in &self.uris {
+            // distributionPoint [0] { fullName [0] { uniformResourceIdentifier [6] } }
+            let full_name = der::tlv(
+                der::context_constructed(0),
+                &der::tlv(der::context(6), uri.as_bytes()),
+            );
+            value.push(der::sequence(&[der::tlv(
+                der::context_constructed(0),
+                &full_name,
+            )]));
+        }
+        new_der(
+            Nid::CRL_DISTRIBUTION_POINTS,
+            self.critical,
+            &der::sequence(&value),
+        )
+    }
+
+    /// Returns `true` if the extension is critical.
+    #[must_use]
+    pub fn is_critical(&self) -> bool {
+        self.critical
+    }
+
+    /// Returns the URIs of the distribution points.
+    ///
+    /// Reading the extension from a certificate fails if a distribution point has a name
+    /// other than a URI, reasons or a CRL issuer.
+    #[must_use]
+    pub fn uris(&self) -> &[String] {
+        &self.uris
+    }
+
+    pub(crate) fn from_der(
+        critical: bool,
+        der: &[u8],
+    ) -> Result<CrlDistributionPoints, ErrorStack> {
+        let mut reader = Reader::new(der);
+        let mut value = reader.read_sequence()?;
+        reader.finish()?;
+        let unsupported = || ErrorStack::internal_error_str("unsupported CRL distribution point");
+        let mut uris = vec![];
+        while !value.is_empty() {
+            // Only a distributionPoint with a fullName is supported, without reasons or a
+            // cRLIssuer, as `uris` could not represent them
+            let mut point = value.read_sequence()?;
+            let name = point
+                .read_optional(der::context_constructed(0))?
+                .ok_or_else(unsupported)?;
+            point.finish().map_err(|_| unsupported())?;
+            let mut name = Reader::new(name);
+            let full_name = name
+                .read_optional(der::context_constructed(0))?
+                .ok_or_else(unsupported)?;
+            name.finish()?;
+            let mut full_name = Reader::new(full_name);
+            while !full_name.is_empty() {
+                match full_name.read_any()? {
+                    (tag, uri) if tag == der::context(6) => uris.push(parse_string(uri)?),
+                    _ => return Err(unsupported()),
+                }
+            }
+        }
+        Ok(CrlDistributionPoints { critical, uris })
+    }
+}
+
+/// An extension that lists the TLS features a server using the certificate must support.
+///
+/// A certificate with the `status_request` feature is also known as OCSP Must-Staple.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsFeature {
+    critical: bool,
+    features: Vec<u16>,
+}
+
+impl Default for TlsFeature {
+    fn default() -> TlsFeature {
+        TlsFeature::new()
+    }
+}
+
+impl TlsFeature {
+    /// Construct a new `TlsFeature` extension.
+    #[must_use]
+    pub fn new() -> TlsFeature {
+        TlsFeature {
+            critical: false,
+            features: vec![],
+        }
+    }
+
+    /// Sets the `critical` flag to `true`. The extension will be critical.
+    pub fn critical(&mut self) -> &mut TlsFeature {
+        self.critical = true;
+        self
+    }
+
+    /// Requires the `status_request` feature, i.e. a stapled OCSP response.
+    pub fn status_request(&mut self) -> &mut TlsFeature {
+        self.other(5)
+    }
+
+    /// Requires the `status_request_v2` feature.
+    pub fn status_request_v2(&mut self) -> &mut TlsFeature {
+        self.other(17)
+    }
+
+    /// Requires the feature with the TLS extension number `feature`.
+    pub fn other(&mut self, feature: u16) -> &mut TlsFeature {
+        self.features.push(feature);
+        self
+    }
+
+    /// Return the `TlsFeature` extension as an `X509Extension`.
+    pub fn build(&self) -> Result<X509Extension, ErrorStack> {
+        if self.features.is_empty() {
+            return Err(ErrorStack::internal_error_str(
+                "TLS feature needs a feature",
+            ));
+        }
+        let value = self
+            .features
+            .iter()
+            .map(|&feature| der::integer(der::INTEGER, feature.into()))
+            .collect::<Vec<_>>();
+        new_der(Nid::TLS_FEATURE, self.critical, &der::sequence(&value))
+    }
+
+    /// Returns `true` if the extension is critical.
+    #[must_use]
+    pub fn is_critical(&self) -> bool {
+        self.critical
+    }
+
+    /// Returns the TLS extension numbers of the features.
+    #[must_use]
+    pub fn features(&self) -> &[u16] {
+        &self.features
+    }
+
+    pub(crate) fn from_der(critical: bool, der: &[u8]) -> Result<TlsFeature, ErrorStack> {
+        let mut reader = Reader::new(der);
+        let mut value = reader.read_sequence()?;
+        reader.finish()?;
+        let mut features = vec![];
+        while !value.is_empty() {
+            let feature = der::parse_integer(value.read(der::INTEGER)?)?;
+            features.push(
+                u16::try_from(feature)
+                    .map_err(|_| ErrorStack::internal_error_str("TLS feature too large"))?,
+            );
+        }
+        Ok(TlsFeature { critical, features })
+    }
+}
+
+fn parse_string(bytes: &[u8]) -> Result<String, ErrorStack> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| der::malformed())
+}
+
+fn new_der(nid: Nid, critical: bool, der: &[u8]) -> Result<X509Extension, ErrorStack> {
+    unsafe {
+        let object = Asn1ObjectRef::from_ptr(cvt_p(ffi::OBJ_nid2obj(nid.as_raw()))?);
+        X509Extension::from_der_payload(object, critical, der)
+    }
+}
+
 fn append(value: &mut String, first: &mut bool, should: bool, element: &str) {
     if !should {
         return;
@@ -37,10 +37,17 @@ use crate::ssl::SslRef;
 use crate::stack::{Stack, StackRef, Stackable};
 use crate::string::OpensslString;
 use crate::util::ForeignTypeRefExt;
+use crate::x509::extension::{
+    BasicConstraints, CertificatePolicies, CrlDistributionPoints, ExtendedKeyUsage, KeyUsage,
+    NameConstraints, TlsFeature,
+};
 use crate::x509::verify::{X509VerifyParam, X509VerifyParamRef};
 use crate::{cvt, cvt_n, cvt_p, try_int};
 use crate::{ffi, free_data_box};
 
+pub mod ca;
+pub mod chain;
+pub mod crl;
 pub mod extension;
 pub mod store;
 pub mod verify;
@@ -583,6 +590,75 @@ impl X509Ref {
         }
     }
 
+    /// Returns the extension with the given `nid`, if it exists.
+    ///
+    /// Fails if the certificate has the extension more than once.
+    fn unique_extension(&self, nid: Nid) -> Result<Option<&X509ExtensionRef>, ErrorStack> {
+        unsafe {
+            let index = ffi::X509_get_ext_by_NID(self.as_ptr(), nid.as_raw(), -1);
+            if index < 0 {
+                return Ok(None);
+            }
+            if ffi::X509_get_ext_by_NID(self.as_ptr(), nid.as_raw(), index) >= 0 {
+                return Err(ErrorStack::internal_error_str("duplicate extension"));
+            }
+            let ext = ffi::X509_get_ext(self.as_ptr(), index);
+            Ok(X509ExtensionRef::from_const_ptr_opt(ext.cast_const()))
+        }
+    }
+
+    fn parse_extension<T>(
+        &self,
+        nid: Nid,
+        parse: fn(bool, &[u8]) -> Result<T, ErrorStack>,
+    ) -> Result<Option<T>, ErrorStack> {
+        self.unique_extension(nid)?
+            .map(|ext| parse(ext.critical(), ext.data().as_slice()))
+            .transpose()
+    }
+
+    /// Returns this certificate's basic constraints, if they exist.
+    pub fn basic_constraints(&self) -> Result<Option<BasicConstraints>, ErrorStack> {
+        self.parse_extension(Nid::BASIC_CONSTRAINTS, BasicConstraints::from_der)
+    }
+
+    /// Returns this certificate's key usage, if it exists.
+    pub fn key_usage(&self) -> Result<Option<KeyUsage>, ErrorStack> {
+        self.parse_extension(Nid::KEY_USAGE, KeyUsage::from_der)
+    }
+
+    /// Returns this certificate's extended key usage, if it exists.
+    pub fn extended_key_usage(&self) -> Result<Option<ExtendedKeyUsage>, ErrorStack> {
+        self.parse_extension(Nid::EXT_KEY_USAGE, ExtendedKeyUsage::from_der)
+    }
+
+    /// Returns this certificate's name constraints, if they exist.
+    ///
+    /// Fails if a subtree is of a name type not supported by [`GeneralSubtree`].
+    ///
+    /// [`GeneralSubtree`]: extension::GeneralSubtree
+    pub fn name_constraints(&self) -> Result<Option<NameConstraints>, ErrorStack> {
+        self.parse_extension(Nid::NAME_CONSTRAINTS, NameConstraints::from_der)
+    }
+
+    /// Returns this certificate's policies, if they exist.
+    pub fn certificate_policies(&self) -> Result<Option<CertificatePolicies>, ErrorStack> {
+        self.parse_extension(Nid::CERTIFICATE_POLICIES, CertificatePolicies::from_der)
+    }
+
+    /// Returns this certificate's CRL distribution points, if they exist.
+    pub fn crl_distribution_points(&self) -> Result<Option<CrlDistributionPoints>, ErrorStack> {
+        self.parse_extension(
+            Nid::CRL_DISTRIBUTION_POINTS,
+            CrlDistributionPoints::from_der,
+        )
+    }
+
+    /// Returns this certificate's TLS feature extension, if it exists.
+    pub fn tls_feature(&self) -> Result<Option<TlsFeature>, ErrorStack> {
+        self.parse_extension(Nid::TLS_FEATURE, TlsFeature::from_der)
+    }
+
     /// Returns this certificate's subject alternative name entries, if they exist.
     #[corresponds(X509_get_ext_d2i)]
     #[must_use]
@@ -43,6 +43,7 @@
 use crate::error::ErrorStack;
 use crate::ffi;
 use crate::stack::StackRef;
+use crate::x509::crl::X509CrlRef;
 use crate::x509::verify::{X509VerifyFlags, X509VerifyParamRef};
 use crate::x509::{X509Object, X509Ref};
 use crate::{cvt, cvt_p};
@@ -86,6 +87,18 @@ impl X509StoreBuilderRef {
         unsafe { cvt(ffi::X509_STORE_add_cert(self.as_ptr(), cert.as_ptr())) }
     }
 
+    /// Adds a certificate revocation list to the certificate store.
+    ///
+    /// CRLs are only consulted during verification if [`X509VerifyFlags::CRL_CHECK`] or
+    /// [`X509VerifyFlags::CRL_CHECK_ALL`] is set, for example with [`Self::set_flags`]. With
+    /// either flag, verification fails with `UNABLE_TO_GET_CRL` if the store has no CRL for an
+    /// issuer that is checked.
+    #[corresponds(X509_STORE_add_crl)]
+    pub fn add_crl(&mut self, crl: impl AsRef<X509CrlRef>) -> Result<(), ErrorStack> {
+        let crl = crl.as_ref();
+        unsafe { cvt(ffi::X509_STORE_add_crl(self.as_ptr(), crl.as_ptr())) }
+    }
+
     /// Load certificates from their default locations.
     ///
     /// These locations are read from the `SSL_CERT_FILE` and `SSL_CERT_DIR`
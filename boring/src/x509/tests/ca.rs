@@ -0,0 +1,152 @@
+use crate::asn1::Asn1Time;
+use crate::hash::MessageDigest;
+use crate::pkey::{PKey, Private};
+use crate::stack::Stack;
+use crate::x509::ca::{CertificateAuthority, CertificateTemplate, SanPolicy};
+use crate::x509::chain::ChainVerifier;
+use crate::x509::extension::{ExtendedKeyUsage, KeyUsage, SubjectAlternativeName};
+use crate::x509::store::X509StoreBuilder;
+use crate::x509::{GeneralNameRef, X509Req};
+
+use super::{ec_key, name};
+
+fn request(key: &PKey<Private>, dns: &[&str]) -> X509Req {
+    let mut builder = X509Req::builder().unwrap();
+    builder.set_subject_name(&name("leaf")).unwrap();
+    builder.set_pubkey(key).unwrap();
+    if !dns.is_empty() {
+        let mut subject_alt_name = SubjectAlternativeName::new();
+        for dns in dns {
+            subject_alt_name.dns(dns);
+        }
+        let subject_alt_name = subject_alt_name
+            .build(&builder.x509v3_context(None))
+            .unwrap();
+        let mut extensions = Stack::new().unwrap();
+        extensions.push(subject_alt_name).unwrap();
+        builder.add_extensions(&extensions).unwrap();
+    }
+    builder.sign(key, MessageDigest::sha256()).unwrap();
+    builder.build()
+}
+
+fn root() -> CertificateAuthority {
+    CertificateAuthority::self_signed(&name("root"), ec_key(), &CertificateTemplate::ca(Some(1)))
+        .unwrap()
+}
+
+fn leaf_template(san_policy: SanPolicy) -> CertificateTemplate {
+    let mut template = CertificateTemplate::new();
+    template
+        .validity_days(7)
+        .key_usage(KeyUsage::new().critical().digital_signature().clone())
+        .extended_key_usage(ExtendedKeyUsage::new().server_auth().clone())
+        .san_policy(san_policy);
+    template
+}
+
+#[test]
+fn issue_chain() {
+    let root = root();
+    let intermediate = root
+        .issue_intermediate(
+            &name("intermediate"),
+            ec_key(),
+            &CertificateTemplate::ca(Some(0)),
+        )
+        .unwrap();
+    let leaf_key = ec_key();
+    let cert = intermediate
+        .issue(
+            &request(&leaf_key, &["example.com"]),
+            &leaf_template(SanPolicy::Copy),
+        )
+        .unwrap();
+
+    assert!(cert.public_key().unwrap().public_eq(&leaf_key));
+    assert_eq!(
+        cert.issuer_name().to_der().unwrap(),
+        intermediate.cert().subject_name().to_der().unwrap()
+    );
+    assert!(cert.serial_number().to_bn().unwrap().num_bits() <= 159);
+    assert_eq!(
+        cert.authority_key_id().unwrap().as_slice(),
+        intermediate.cert().subject_key_id().unwrap().as_slice()
+    );
+    assert!(cert.subject_key_id().is_some());
+    assert_eq!(
+        cert.subject_alt_names().unwrap()[0].dnsname(),
+        Some("example.com")
+    );
+    assert!(cert.key_usage().unwrap().unwrap().has_digital_signature());
+    assert_eq!(cert.basic_constraints().unwrap(), None);
+    let days = Asn1Time::days_from_now(7).unwrap();
+    assert!(cert.not_after() <= days);
+
+    let basic_constraints = intermediate.cert().basic_constraints().unwrap().unwrap();
+    assert!(basic_constraints.is_ca());
+    assert_eq!(basic_constraints.path_len(), Some(0));
+    assert_eq!(
+        root.cert().authority_key_id().unwrap().as_slice(),
+        root.cert().subject_key_id().unwrap().as_slice()
+    );
+
+    let mut store = X509StoreBuilder::new().unwrap();
+    store.add_cert(root.cert()).unwrap();
+    let mut verifier = ChainVerifier::new(store.build());
+    verifier.host("example.com");
+    let mut intermediates = Stack::new().unwrap();
+    intermediates.push(intermediate.cert().to_owned()).unwrap();
+    let report = verifier.verify(&cert, &intermediates).unwrap();
+    assert!(report.is_valid(), "{report:?}");
+}
+
+#[test]
+fn san_policy() {
+    let root = root();
+    let req = request(&ec_key(), &["example.com", "evil.test"]);
+
+    let cert = root.issue(&req, &leaf_template(SanPolicy::Ignore)).unwrap();
+    assert!(cert.subject_alt_names().is_none());
+
+    fn only_example(name: &GeneralNameRef) -> bool {
+        name.dnsname()
+            .is_some_and(|dns| dns.ends_with("example.com"))
+    }
+    assert!(root
+        .issue(&req, &leaf_template(SanPolicy::Restrict(only_example)))
+        .is_err());
+    let req = request(&ec_key(), &["www.example.com"]);
+    let cert = root
+        .issue(&req, &leaf_template(SanPolicy::Restrict(only_example)))
+        .unwrap();
+    assert_eq!(cert.subject_alt_names().unwrap().len(), 1);
+
+    // A request without extensions is accepted
+    let req = request(&ec_key(), &[]);
+    let cert = root.issue(&req, &leaf_template(SanPolicy::Copy)).unwrap();
+    assert!(cert.subject_alt_names().is_none());
+}
+
+#[test]
+fn rejects_mismatched_key() {
+    let root = root();
+    assert!(CertificateAuthority::new(root.cert().to_owned(), ec_key()).is_err());
+    assert!(CertificateAuthority::new(root.cert().to_owned(), root.key().to_owned()).is_ok());
+}
+
+#[test]
+fn validity_period() {
+    let root = root();
+    let mut template = leaf_template(SanPolicy::Ignore);
+    template.not_before(1_000_000_000).validity_days(1);
+    let cert = root.issue(&request(&ec_key(), &[]), &template).unwrap();
+    assert_eq!(
+        cert.not_before(),
+        Asn1Time::from_unix(1_000_000_000).unwrap()
+    );
+    assert_eq!(
+        cert.not_after(),
+        Asn1Time::from_unix(1_000_000_000 + 86_400).unwrap()
+    );
+}
@@ -0,0 +1,220 @@
+use crate::asn1::{Asn1Object, Asn1Time};
+use crate::libc_types::time_t;
+use crate::pkey::{PKey, Private};
+use crate::stack::Stack;
+use crate::x509::chain::ChainVerifier;
+use crate::x509::extension::{
+    BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName,
+};
+use crate::x509::store::X509StoreBuilder;
+use crate::x509::verify::X509Purpose;
+use crate::x509::{X509Builder, X509VerifyError, X509};
+
+use super::{ec_key, issue_cert};
+
+// 2001-09-09, before the validity periods below start
+const LONG_AGO: time_t = 1_000_000_000;
+
+struct Issued {
+    cert: X509,
+    key: PKey<Private>,
+}
+
+fn issue(
+    cn: &str,
+    issuer: Option<&Issued>,
+    not_after: &Asn1Time,
+    configure: impl FnOnce(&mut X509Builder),
+) -> Issued {
+    let key = ec_key();
+    let cert = issue_cert(
+        cn,
+        &key,
+        issuer.map(|issuer| (&*issuer.cert, &*issuer.key)),
+        |builder| {
+            builder
+                .set_not_before(&Asn1Time::from_unix(LONG_AGO + 1).unwrap())
+                .unwrap();
+            builder.set_not_after(not_after).unwrap();
+            configure(builder);
+        },
+    );
+    Issued { cert, key }
+}
+
+fn ca(cn: &str, issuer: Option<&Issued>) -> Issued {
+    issue(
+        cn,
+        issuer,
+        &Asn1Time::days_from_now(30).unwrap(),
+        |builder| {
+            let basic_constraints = BasicConstraints::new().critical().ca().build().unwrap();
+            builder.append_extension(&basic_constraints).unwrap();
+            let key_usage = KeyUsage::new().critical().key_cert_sign().build().unwrap();
+            builder.append_extension(&key_usage).unwrap();
+        },
+    )
+}
+
+fn leaf(issuer: &Issued, not_after: &Asn1Time, extended_key_usage: &ExtendedKeyUsage) -> Issued {
+    issue("leaf", Some(issuer), not_after, |builder| {
+        let key_usage = KeyUsage::new().digital_signature().build().unwrap();
+        builder.append_extension(&key_usage).unwrap();
+        let extended_key_usage = extended_key_usage.build().unwrap();
+        builder.append_extension(&extended_key_usage).unwrap();
+        let subject_alternative_name = SubjectAlternativeName::new()
+            .dns("example.com")
+            .build(&builder.x509v3_context(None, None))
+            .unwrap();
+        builder.append_extension(&subject_alternative_name).unwrap();
+    })
+}
+
+fn verifier(root: &Issued) -> ChainVerifier {
+    let mut store = X509StoreBuilder::new().unwrap();
+    store.add_cert(&root.cert).unwrap();
+    ChainVerifier::new(store.build())
+}
+
+fn intermediates(certs: &[&Issued]) -> Stack<X509> {
+    let mut stack = Stack::new().unwrap();
+    for issued in certs {
+        stack.push(issued.cert.clone()).unwrap();
+    }
+    stack
+}
+
+fn verify_errors(
+    verifier: &ChainVerifier,
+    leaf: &Issued,
+    chain: &[&Issued],
+) -> Vec<(X509VerifyError, u32)> {
+    let report = verifier.verify(&leaf.cert, &intermediates(chain)).unwrap();
+    report
+        .errors()
+        .iter()
+        .map(|error| (error.error(), error.depth()))
+        .collect()
+}
+
+#[test]
+fn valid_chain() {
+    let root = ca("root", None);
+    let intermediate = ca("intermediate", Some(&root));
+    let leaf = leaf(
+        &intermediate,
+        &Asn1Time::days_from_now(30).unwrap(),
+        ExtendedKeyUsage::new().code_signing(),
+    );
+
+    let mut verifier = verifier(&root);
+    verifier
+        .host("example.com")
+        .extended_key_usage(Asn1Object::from_str("codeSigning").unwrap());
+    let report = verifier
+        .verify(&leaf.cert, &intermediates(&[&intermediate]))
+        .unwrap();
+
+    assert!(report.is_valid(), "{report:?}");
+    assert_eq!(report.error_depth(), None);
+    let chain = report
+        .chain()
+        .iter()
+        .map(|cert| cert.to_der().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        chain,
+        [&leaf.cert, &intermediate.cert, &root.cert].map(|cert| cert.to_der().unwrap())
+    );
+    assert_eq!(
+        report.trust_anchor().unwrap().to_der().unwrap(),
+        root.cert.to_der().unwrap()
+    );
+}
+
+#[test]
+fn reports_every_error() {
+    let root = ca("root", None);
+    let expired = leaf(
+        &root,
+        &Asn1Time::from_unix(LONG_AGO + 3600).unwrap(),
+        ExtendedKeyUsage::new().client_auth(),
+    );
+
+    let mut verifier = verifier(&root);
+    verifier.host("example.org");
+    let errors = verify_errors(&verifier, &expired, &[]);
+    assert!(
+        errors.contains(&(X509VerifyError::CERT_HAS_EXPIRED, 0)),
+        "{errors:?}"
+    );
+    assert!(
+        errors.contains(&(X509VerifyError::HOSTNAME_MISMATCH, 0)),
+        "{errors:?}"
+    );
+
+    // The validity period is checked against the verification time
+    verifier.time(LONG_AGO + 60);
+    let errors = verify_errors(&verifier, &expired, &[]);
+    assert!(
+        !errors.contains(&(X509VerifyError::CERT_HAS_EXPIRED, 0)),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn untrusted_root() {
+    let root = ca("root", None);
+    let leaf = leaf(
+        &root,
+        &Asn1Time::days_from_now(30).unwrap(),
+        ExtendedKeyUsage::new().client_auth(),
+    );
+
+    let other_root = ca("other root", None);
+    let report = verifier(&other_root)
+        .verify(&leaf.cert, &Stack::new().unwrap())
+        .unwrap();
+    assert!(!report.is_valid());
+    assert_eq!(report.error_depth(), Some(0));
+    assert!(report.trust_anchor().is_none());
+    assert_eq!(
+        report.errors()[0].error(),
+        X509VerifyError::UNABLE_TO_GET_ISSUER_CERT_LOCALLY
+    );
+    assert_eq!(
+        report.errors()[0].cert().unwrap().to_der().unwrap(),
+        leaf.cert.to_der().unwrap()
+    );
+}
+
+#[test]
+fn policy() {
+    let root = ca("root", None);
+    let intermediate = ca("intermediate", Some(&root));
+    let leaf = leaf(
+        &intermediate,
+        &Asn1Time::days_from_now(30).unwrap(),
+        ExtendedKeyUsage::new().client_auth(),
+    );
+
+    let mut verifier = verifier(&root);
+    verifier.extended_key_usage(Asn1Object::from_str("1.3.6.1.5.5.7.3.3").unwrap());
+    assert_eq!(
+        verify_errors(&verifier, &leaf, &[&intermediate]),
+        [(X509VerifyError::INVALID_PURPOSE, 0)]
+    );
+
+    let mut verifier = self::verifier(&root);
+    verifier.purpose(X509Purpose::SSL_CLIENT);
+    assert!(verify_errors(&verifier, &leaf, &[&intermediate]).is_empty());
+    verifier.purpose(X509Purpose::SSL_SERVER);
+    assert!(verify_errors(&verifier, &leaf, &[&intermediate])
+        .contains(&(X509VerifyError::INVALID_PURPOSE, 0)));
+
+    let mut verifier = self::verifier(&root);
+    verifier.max_depth(0);
+    assert!(verify_errors(&verifier, &leaf, &[&intermediate])
+        .iter()
+        .any(|(error, _)| *error == X509VerifyError::CERT_CHAIN_TOO_LONG));
+}
@@ -0,0 +1,257 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use crate::asn1::Asn1Time;
+use crate::bn::BigNum;
+use crate::hash::MessageDigest;
+use crate::pkey::{PKeyRef, Private};
+use crate::ssl::{HandshakeError, SslAcceptor, SslConnector, SslMethod};
+use crate::stack::Stack;
+use crate::x509::crl::{CrlReason, CrlStatus, X509Crl};
+use crate::x509::extension::{
+    AuthorityKeyIdentifier, BasicConstraints, KeyUsage, SubjectAlternativeName,
+};
+use crate::x509::store::X509StoreBuilder;
+use crate::x509::verify::X509VerifyFlags;
+use crate::x509::{X509Ref, X509StoreContext, X509VerifyError, X509};
+
+use super::{ec_key, issue_cert};
+
+fn ca(key: &PKeyRef<Private>) -> X509 {
+    issue_cert("CA", key, None, |builder| {
+        let basic_constraints = BasicConstraints::new().critical().ca().build().unwrap();
+        builder.append_extension(&basic_constraints).unwrap();
+        let key_usage = KeyUsage::new()
+            .critical()
+            .key_cert_sign()
+            .crl_sign()
+            .build()
+            .unwrap();
+        builder.append_extension(&key_usage).unwrap();
+    })
+}
+
+fn leaf(
+    cn: &str,
+    serial: u32,
+    key: &PKeyRef<Private>,
+    ca: &X509Ref,
+    ca_key: &PKeyRef<Private>,
+) -> X509 {
+    issue_cert(cn, key, Some((ca, ca_key)), |builder| {
+        builder
+            .set_serial_number(&BigNum::from_u32(serial).unwrap().to_asn1_integer().unwrap())
+            .unwrap();
+        let subject_alt_name = SubjectAlternativeName::new()
+            .dns("example.com")
+            .build(&builder.x509v3_context(None, None))
+            .unwrap();
+        builder.append_extension(&subject_alt_name).unwrap();
+    })
+}
+
+fn issue_crl(ca: &X509, ca_key: &PKey<Private>, revoked: &[(u32, Option<CrlReason>)]) -> X509Crl {
+    let now = Asn1Time::days_from_now(0).unwrap();
+    let mut builder = X509Crl::builder().unwrap();
+    builder.set_issuer_name(ca.subject_name()).unwrap();
+    builder.set_last_update(&now).unwrap();
+    builder
+        .set_next_update(&Asn1Time::days_from_now(7).unwrap())
+        .unwrap();
+    builder
+        .set_crl_number(&BigNum::from_u32(42).unwrap())
+        .unwrap();
+    let authority_key_identifier = AuthorityKeyIdentifier::new()
+        .keyid(false)
+        .build(&builder.x509v3_context(ca, None))
+        .unwrap();
+    builder.append_extension(&authority_key_identifier).unwrap();
+    for &(serial, reason) in revoked {
+        let serial = BigNum::from_u32(serial).unwrap().to_asn1_integer().unwrap();
+        builder.revoke(&serial, &now, reason).unwrap();
+    }
+    builder.sign(ca_key, MessageDigest::sha256()).unwrap();
+    builder.build()
+}
+
+fn verify(ca: &X509, leaf: &X509, crl: Option<&X509Crl>) -> Result<(), X509VerifyError> {
+    let mut store = X509StoreBuilder::new().unwrap();
+    store.add_cert(ca).unwrap();
+    if let Some(crl) = crl {
+        store.add_crl(crl).unwrap();
+    }
+    store.set_flags(X509VerifyFlags::CRL_CHECK);
+    let store = store.build();
+
+    let mut context = X509StoreContext::new().unwrap();
+    context
+        .init(&store, leaf, &Stack::new().unwrap(), |c| {
+            c.verify_cert()?;
+            Ok(c.verify_result())
+        })
+        .unwrap()
+}
+
+#[test]
+fn crl_builder_and_accessors() {
+    let ca_key = ec_key();
+    let ca = ca(&ca_key);
+    let crl = issue_crl(
+        &ca,
+        &ca_key,
+        &[(7, Some(CrlReason::KEY_COMPROMISE)), (3, None)],
+    );
+
+    assert_eq!(crl.version(), 1);
+    assert_eq!(
+        crl.issuer_name().to_der().unwrap(),
+        ca.subject_name().to_der().unwrap()
+    );
+    assert!(crl.next_update().is_some());
+    assert_eq!(
+        crl.crl_number().unwrap(),
+        Some(BigNum::from_u32(42).unwrap())
+    );
+    assert!(crl.verify(&ca_key).unwrap());
+    assert!(!crl.verify(&ec_key()).unwrap());
+
+    // Entries are sorted by serial number when signing
+    let revoked = crl.revoked().collect::<Vec<_>>();
+    assert_eq!(revoked.len(), 2);
+    assert_eq!(
+        revoked[0].serial_number().to_bn().unwrap(),
+        BigNum::from_u32(3).unwrap()
+    );
+    assert_eq!(revoked[0].reason().unwrap(), None);
+    assert_eq!(
+        revoked[1].serial_number().to_bn().unwrap(),
+        BigNum::from_u32(7).unwrap()
+    );
+    assert_eq!(
+        revoked[1].reason().unwrap(),
+        Some(CrlReason::KEY_COMPROMISE)
+    );
+}
+
+#[test]
+fn crl_lookup() {
+    let ca_key = ec_key();
+    let ca = ca(&ca_key);
+    let leaf_key = ec_key();
+    let revoked = leaf("revoked", 7, &leaf_key, &ca, &ca_key);
+    let valid = leaf("valid", 8, &leaf_key, &ca, &ca_key);
+    let crl = issue_crl(&ca, &ca_key, &[(7, Some(CrlReason::SUPERSEDED))]);
+
+    match crl.get_by_cert(&revoked) {
+        CrlStatus::Revoked(entry) => {
+            assert_eq!(entry.reason().unwrap(), Some(CrlReason::SUPERSEDED));
+        }
+        status => panic!("unexpected status {status:?}"),
+    }
+    assert!(matches!(
+        crl.get_by_serial(revoked.serial_number()),
+        CrlStatus::Revoked(_)
+    ));
+    assert!(matches!(crl.get_by_cert(&valid), CrlStatus::NotRevoked));
+}
+
+#[test]
+fn crl_der_pem_roundtrip() {
+    let ca_key = ec_key();
+    let ca = ca(&ca_key);
+    let crl = issue_crl(&ca, &ca_key, &[(7, Some(CrlReason::CA_COMPROMISE))]);
+
+    let der = crl.to_der().unwrap();
+    assert_eq!(X509Crl::from_der(&der).unwrap().to_der().unwrap(), der);
+
+    let pem = crl.to_pem().unwrap();
+    assert!(pem.starts_with(b"-----BEGIN X509 CRL-----"));
+    let parsed = X509Crl::from_pem(&pem).unwrap();
+    assert!(parsed.verify(&ca_key).unwrap());
+    assert_eq!(parsed.revoked().len(), 1);
+}
+
+#[test]
+fn verify_with_crl() {
+    let ca_key = ec_key();
+    let ca = ca(&ca_key);
+    let leaf_key = ec_key();
+    let revoked = leaf("revoked", 7, &leaf_key, &ca, &ca_key);
+    let valid = leaf("valid", 8, &leaf_key, &ca, &ca_key);
+    let crl = issue_crl(&ca, &ca_key, &[(7, Some(CrlReason::KEY_COMPROMISE))]);
+
+    assert_eq!(verify(&ca, &valid, Some(&crl)), Ok(()));
+    assert_eq!(
+        verify(&ca, &revoked, Some(&crl)),
+        Err(X509VerifyError::CERT_REVOKED)
+    );
+    assert_eq!(
+        verify(&ca, &valid, None),
+        Err(X509VerifyError::UNABLE_TO_GET_CRL)
+    );
+
+    // A CRL signed by another key is rejected
+    let forged = issue_crl(&ca, &ec_key(), &[]);
+    assert_eq!(
+        verify(&ca, &valid, Some(&forged)),
+        Err(X509VerifyError::CRL_SIGNATURE_FAILURE)
+    );
+}
+
+/// Connects to a server presenting `cert` with a connector that checks `crl`.
+fn connect_with_crl(
+    ca: &X509,
+    cert: &X509,
+    key: &PKeyRef<Private>,
+    crl: &X509Crl,
+) -> Result<(), X509VerifyError> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor.set_private_key(key).unwrap();
+    acceptor.set_certificate(cert).unwrap();
+    let acceptor = acceptor.build();
+    let server = thread::spawn(move || {
+        let stream = listener.accept().unwrap().0;
+        // The client aborts the handshake when the certificate is revoked
+        if let Ok(mut stream) = acceptor.accept(stream) {
+            stream.write_all(&[0]).unwrap();
+        }
+    });
+
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    let store = connector.cert_store_mut();
+    store.add_cert(ca).unwrap();
+    store.add_crl(crl).unwrap();
+    store.set_flags(X509VerifyFlags::CRL_CHECK);
+    let connector = connector.build();
+
+    let result = match connector.connect(Some("example.com"), TcpStream::connect(addr).unwrap()) {
+        Ok(mut stream) => {
+            stream.read_exact(&mut [0]).unwrap();
+            Ok(())
+        }
+        Err(HandshakeError::Failure(stream)) => stream.ssl().verify_result(),
+        Err(e) => panic!("unexpected error {e}"),
+    };
+    server.join().unwrap();
+    result
+}
+
+#[test]
+fn tls_rejects_revoked_certificate() {
+    let ca_key = ec_key();
+    let ca = ca(&ca_key);
+    let leaf_key = ec_key();
+    let revoked = leaf("revoked", 7, &leaf_key, &ca, &ca_key);
+    let valid = leaf("valid", 8, &leaf_key, &ca, &ca_key);
+    let crl = issue_crl(&ca, &ca_key, &[(7, Some(CrlReason::KEY_COMPROMISE))]);
+
+    assert_eq!(connect_with_crl(&ca, &valid, &leaf_key, &crl), Ok(()));
+    assert_eq!(
+        connect_with_crl(&ca, &revoked, &leaf_key, &crl),
+        Err(X509VerifyError::CERT_REVOKED)
+    );
+}
@@ -0,0 +1,342 @@
+use std::net::IpAddr;
+
+use crate::asn1::Asn1Object;
+use crate::nid::Nid;
+use crate::x509::extension::{
+    AuthorityInfoAccess, BasicConstraints, CertificatePolicies, CrlDistributionPoints,
+    ExtendedKeyUsage, GeneralSubtree, KeyUsage, NameConstraints, PolicyConstraints,
+    PolicyQualifier, TlsFeature,
+};
+use crate::x509::{X509Builder, X509Extension, X509};
+
+use super::{ec_key, issue_cert, name};
+
+fn assert_extension(extension: &X509Extension, nid: Nid, critical: bool, expected: &str) {
+    assert_eq!(extension.object().nid(), nid);
+    assert_eq!(extension.critical(), critical);
+    assert_eq!(hex::encode(extension.data().as_slice()), expected);
+}
+
+#[test]
+fn name_constraints() {
+    let name = name("a");
+
+    let extension = NameConstraints::new()
+        .critical()
+        .permitted(GeneralSubtree::Dns("example.com".into()))
+        .permitted(GeneralSubtree::Ip("10.0.0.0".parse().unwrap(), 8))
+        .excluded(GeneralSubtree::Email(".example.org".into()))
+        .excluded(GeneralSubtree::DirectoryName(name.to_der().unwrap()))
+        .build()
+        .unwrap();
+    assert_extension(
+        &extension,
+        Nid::NAME_CONSTRAINTS,
+        true,
+        "303f\
+         a01b\
+         300d820b6578616d706c652e636f6d\
+         300a87080a000000ff000000\
+         a120\
+         300e810c2e6578616d706c652e6f7267\
+         300ea40c300a3108300606035504030c0161",
+    );
+
+    let ip: IpAddr = "2001:db8::".parse().unwrap();
+    let extension = NameConstraints::new()
+        .permitted(GeneralSubtree::Ip(ip, 32))
+        .build()
+        .unwrap();
+    assert!(hex::encode(extension.data().as_slice())
+        .ends_with("20010db8000000000000000000000000ffffffff000000000000000000000000"));
+
+    assert!(NameConstraints::new()
+        .permitted(GeneralSubtree::Ip("10.0.0.0".parse().unwrap(), 33))
+        .build()
+        .is_err());
+    assert!(NameConstraints::new().build().is_err());
+}
+
+#[test]
+fn certificate_policies() {
+    let extension = CertificatePolicies::new()
+        .policy("2.23.140.1.2.1", &[])
+        .policy(
+            "2.5.29.32.0",
+            &[
+                PolicyQualifier::Cps("http://a".into()),
+                PolicyQualifier::UserNotice("hi".into()),
+            ],
+        )
+        .build()
+        .unwrap();
+    assert_extension(
+        &extension,
+        Nid::CERTIFICATE_POLICIES,
+        false,
+        "303c\
+         3008060667810c010201\
+         3030\
+         0604551d2000\
+         3028\
+         301406082b060105050702011608687474703a2f2f61\
+         301006082b0601050507020230040c026869",
+    );
+
+    assert!(CertificatePolicies::new().build().is_err());
+    assert!(CertificatePolicies::new()
+        .policy("not an oid", &[])
+        .build()
+        .is_err());
+    assert!(CertificatePolicies::new()
+        .policy(
+            "2.5.29.32.0",
+            &[PolicyQualifier::Other("1.2.3".into(), vec![0x05])]
+        )
+        .build()
+        .is_err());
+}
+
+#[test]
+fn policy_constraints() {
+    let extension = PolicyConstraints::new()
+        .critical()
+        .require_explicit_policy(0)
+        .inhibit_policy_mapping(200)
+        .build()
+        .unwrap();
+    assert_extension(
+        &extension,
+        Nid::POLICY_CONSTRAINTS,
+        true,
+        "3007800100810200c8",
+    );
+
+    assert!(PolicyConstraints::new().build().is_err());
+    assert!(PolicyConstraints::new().critical().is_critical());
+}
+
+#[test]
+fn authority_info_access() {
+    let extension = AuthorityInfoAccess::new()
+        .ocsp("http://o")
+        .ca_issuers("http://c")
+        .build()
+        .unwrap();
+    assert_extension(
+        &extension,
+        Nid::INFO_ACCESS,
+        false,
+        "302c\
+         301406082b060105050730018608687474703a2f2f6f\
+         301406082b060105050730028608687474703a2f2f63",
+    );
+
+    assert!(AuthorityInfoAccess::new().build().is_err());
+    assert!(!AuthorityInfoAccess::new().is_critical());
+}
+
+#[test]
+fn crl_distribution_points() {
+    let extension = CrlDistributionPoints::new()
+        .uri("http://c/a.crl")
+        .build()
+        .unwrap();
+    assert_extension(
+        &extension,
+        Nid::CRL_DISTRIBUTION_POINTS,
+        false,
+        "30163014a012a010860e687474703a2f2f632f612e63726c",
+    );
+
+    assert!(CrlDistributionPoints::new().build().is_err());
+}
+
+#[test]
+fn extensions_in_certificate() {
+    let mut builder = X509::builder().unwrap();
+    let extensions = [
+        NameConstraints::new()
+            .critical()
+            .permitted(GeneralSubtree::Dns("example.com".into()))
+            .build()
+            .unwrap(),
+        AuthorityInfoAccess::new().ocsp("http://o").build().unwrap(),
+        CrlDistributionPoints::new()
+            .uri("http://c/a.crl")
+            .build()
+            .unwrap(),
+    ];
+    for extension in &extensions {
+        builder.append_extension(extension).unwrap();
+    }
+    let cert = builder.build();
+
+    let nids = cert
+        .extensions()
+        .map(|extension| extension.object().nid())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        nids,
+        [
+            Nid::NAME_CONSTRAINTS,
+            Nid::INFO_ACCESS,
+            Nid::CRL_DISTRIBUTION_POINTS
+        ]
+    );
+    assert!(cert.extension(0).unwrap().critical());
+}
+
+#[test]
+fn tls_feature() {
+    let extension = TlsFeature::new().status_request().build().unwrap();
+    assert_extension(&extension, Nid::TLS_FEATURE, false, "3003020105");
+
+    assert!(TlsFeature::new().build().is_err());
+}
+
+fn signed(configure: impl FnOnce(&mut X509Builder)) -> X509 {
+    let cert = issue_cert("extensions", &ec_key(), None, configure);
+    X509::from_der(&cert.to_der().unwrap()).unwrap()
+}
+
+#[test]
+fn parsed_extensions_round_trip() {
+    let mut basic_constraints = BasicConstraints::new();
+    basic_constraints.critical().ca().pathlen(2);
+    let mut key_usage = KeyUsage::new();
+    key_usage
+        .critical()
+        .key_cert_sign()
+        .crl_sign()
+        .decipher_only();
+    let mut extended_key_usage = ExtendedKeyUsage::new();
+    extended_key_usage
+        .server_auth()
+        .code_signing()
+        .other("1.2.3.4");
+    let mut name_constraints = NameConstraints::new();
+    name_constraints
+        .critical()
+        .permitted(GeneralSubtree::Dns("example.com".into()))
+        .permitted(GeneralSubtree::Ip("2001:db8::".parse().unwrap(), 32))
+        .excluded(GeneralSubtree::Uri(".example.org".into()))
+        .excluded(GeneralSubtree::Ip("192.168.0.0".parse().unwrap(), 16));
+    let mut certificate_policies = CertificatePolicies::new();
+    certificate_policies.policy("2.23.140.1.2.1", &[]).policy(
+        "1.2.3.4.5",
+        &[
+            PolicyQualifier::Cps("https://example.com/cps".into()),
+            PolicyQualifier::UserNotice("notice".into()),
+            // A user notice with a notice reference
+            PolicyQualifier::Other(
+                "1.3.6.1.5.5.7.2.2".into(),
+                hex::decode("300a30080c016f3003020101").unwrap(),
+            ),
+            PolicyQualifier::Other("1.2.3.4.6".into(), hex::decode("0500").unwrap()),
+        ],
+    );
+    let mut crl_distribution_points = CrlDistributionPoints::new();
+    crl_distribution_points
+        .uri("http://example.com/a.crl")
+        .uri("http://example.com/b.crl");
+    let mut tls_feature = TlsFeature::new();
+    tls_feature.status_request().other(1000);
+
+    let cert = signed(|builder| {
+        let extensions = [
+            basic_constraints.build().unwrap(),
+            key_usage.build().unwrap(),
+            extended_key_usage.build().unwrap(),
+            name_constraints.build().unwrap(),
+            certificate_policies.build().unwrap(),
+            crl_distribution_points.build().unwrap(),
+            tls_feature.build().unwrap(),
+        ];
+        for extension in &extensions {
+            builder.append_extension(extension).unwrap();
+        }
+    });
+
+    let parsed = cert.basic_constraints().unwrap().unwrap();
+    assert_eq!(parsed, basic_constraints);
+    assert!(parsed.is_critical() && parsed.is_ca());
+    assert_eq!(parsed.path_len(), Some(2));
+
+    let parsed = cert.key_usage().unwrap().unwrap();
+    assert_eq!(parsed, key_usage);
+    assert!(parsed.has_key_cert_sign() && parsed.has_decipher_only());
+    assert!(!parsed.has_digital_signature());
+
+    let parsed = cert.extended_key_usage().unwrap().unwrap();
+    assert_eq!(parsed, extended_key_usage);
+    assert_eq!(parsed.usages(), ["serverAuth", "codeSigning", "1.2.3.4"]);
+
+    assert_eq!(cert.name_constraints().unwrap().unwrap(), name_constraints);
+    assert_eq!(
+        cert.certificate_policies().unwrap().unwrap(),
+        certificate_policies
+    );
+    assert_eq!(
+        cert.crl_distribution_points().unwrap().unwrap(),
+        crl_distribution_points
+    );
+    assert_eq!(cert.tls_feature().unwrap().unwrap().features(), [5, 1000]);
+}
+
+#[test]
+fn parsed_extensions_absent() {
+    let cert = signed(|_| {});
+    assert_eq!(cert.basic_constraints().unwrap(), None);
+    assert_eq!(cert.key_usage().unwrap(), None);
+    assert_eq!(cert.extended_key_usage().unwrap(), None);
+    assert_eq!(cert.name_constraints().unwrap(), None);
+    assert_eq!(cert.certificate_policies().unwrap(), None);
+    assert_eq!(cert.crl_distribution_points().unwrap(), None);
+    assert_eq!(cert.tls_feature().unwrap(), None);
+}
+
+#[test]
+fn parsed_extensions_malformed() {
+    let mut builder = X509::builder().unwrap();
+    let basic_constraints = BasicConstraints::new().build().unwrap();
+    builder.append_extension(&basic_constraints).unwrap();
+    builder.append_extension(&basic_constraints).unwrap();
+    // A BIT STRING with unused bits but no contents
+    let key_usage = X509Extension::from_der_payload(
+        &Asn1Object::from_str("keyUsage").unwrap(),
+        false,
+        &[0x03, 0x01, 0x07],
+    )
+    .unwrap();
+    builder.append_extension(&key_usage).unwrap();
+    let cert = builder.build();
+
+    assert!(cert.basic_constraints().is_err());
+    assert!(cert.key_usage().is_err());
+}
+
+#[test]
+fn parsed_extensions_unsupported() {
+    let mut builder = X509::builder().unwrap();
+    // A distribution point with reasons
+    let crl_distribution_points = X509Extension::from_der_payload(
+        &Asn1Object::from_str("crlDistributionPoints").unwrap(),
+        false,
+        &hex::decode("30143012a00ca00a8608687474703a2f2f6381020640").unwrap(),
+    )
+    .unwrap();
+    builder.append_extension(&crl_distribution_points).unwrap();
+    // A permitted subtree with a minimum of 1
+    let name_constraints = X509Extension::from_der_payload(
+        &Asn1Object::from_str("nameConstraints").unwrap(),
+        true,
+        &hex::decode("3014a0123010820b6578616d706c652e636f6d800101").unwrap(),
+    )
+    .unwrap();
+    builder.append_extension(&name_constraints).unwrap();
+    let cert = builder.build();
+
+    assert!(cert.crl_distribution_points().is_err());
+    assert!(cert.name_constraints().is_err());
+}
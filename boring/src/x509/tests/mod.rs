@@ -2,9 +2,10 @@ use hex::{self, FromHex};
 
 use crate::asn1::Asn1Time;
 use crate::bn::{BigNum, MsbOption};
+use crate::ec::{EcGroup, EcKey};
 use crate::hash::MessageDigest;
 use crate::nid::Nid;
-use crate::pkey::{PKey, Private};
+use crate::pkey::{PKey, PKeyRef, Private};
 use crate::rsa::Rsa;
 use crate::stack::Stack;
 use crate::x509::extension::{
@@ -12,8 +13,12 @@ use crate::x509::extension::{
     SubjectKeyIdentifier,
 };
 use crate::x509::store::X509StoreBuilder;
-use crate::x509::{X509Extension, X509Name, X509Req, X509StoreContext, X509};
+use crate::x509::{X509Builder, X509Extension, X509Name, X509Ref, X509Req, X509StoreContext, X509};
 
+mod ca;
+mod chain;
+mod crl;
+mod extension;
 mod trusted_first;
 
 fn pkey() -> PKey<Private> {
@@ -21,6 +26,55 @@ fn pkey() -> PKey<Private> {
     PKey::from_rsa(rsa).unwrap()
 }
 
+/// Generates a P-256 key, which is much faster than generating an RSA key.
+fn ec_key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+}
+
+fn name(cn: &str) -> X509Name {
+    let mut name = X509Name::builder().unwrap();
+    name.append_entry_by_nid(Nid::COMMONNAME, cn).unwrap();
+    name.build()
+}
+
+/// Issues a certificate for `key` with the common name `cn`, valid for 30 days from now and with a
+/// random serial number.
+///
+/// It is signed by `issuer` if given and self-signed otherwise. `configure` can add extensions or
+/// override the defaults before the certificate is signed.
+fn issue_cert(
+    cn: &str,
+    key: &PKeyRef<Private>,
+    issuer: Option<(&X509Ref, &PKeyRef<Private>)>,
+    configure: impl FnOnce(&mut X509Builder),
+) -> X509 {
+    let name = name(cn);
+    let mut builder = X509::builder().unwrap();
+    builder.set_version(2).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder
+        .set_issuer_name(issuer.map_or(&*name, |(issuer, _)| issuer.subject_name()))
+        .unwrap();
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::days_from_now(30).unwrap())
+        .unwrap();
+    builder.set_pubkey(key).unwrap();
+    let mut serial = BigNum::new().unwrap();
+    serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+    builder
+        .set_serial_number(&serial.to_asn1_integer().unwrap())
+        .unwrap();
+    configure(&mut builder);
+    builder
+        .sign(issuer.map_or(key, |(_, key)| key), MessageDigest::sha256())
+        .unwrap();
+    builder.build()
+}
+
 #[test]
 fn test_cert_loading() {
     let cert = include_bytes!("../../../test/cert.pem");
@@ -47,6 +47,38 @@ bitflags! {
     }
 }
 
+/// The purpose a certificate chain is verified for.
+///
+/// The purpose restricts the key usage and extended key usage of the leaf, and the purposes the
+/// issuers are trusted for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct X509Purpose(c_int);
+
+#[allow(missing_docs)] // no need to document the constants
+impl X509Purpose {
+    pub const SSL_CLIENT: X509Purpose = X509Purpose(ffi::X509_PURPOSE_SSL_CLIENT);
+    pub const SSL_SERVER: X509Purpose = X509Purpose(ffi::X509_PURPOSE_SSL_SERVER);
+    pub const NS_SSL_SERVER: X509Purpose = X509Purpose(ffi::X509_PURPOSE_NS_SSL_SERVER);
+    pub const SMIME_SIGN: X509Purpose = X509Purpose(ffi::X509_PURPOSE_SMIME_SIGN);
+    pub const SMIME_ENCRYPT: X509Purpose = X509Purpose(ffi::X509_PURPOSE_SMIME_ENCRYPT);
+    pub const CRL_SIGN: X509Purpose = X509Purpose(ffi::X509_PURPOSE_CRL_SIGN);
+    pub const ANY: X509Purpose = X509Purpose(ffi::X509_PURPOSE_ANY);
+    pub const OCSP_HELPER: X509Purpose = X509Purpose(ffi::X509_PURPOSE_OCSP_HELPER);
+    pub const TIMESTAMP_SIGN: X509Purpose = X509Purpose(ffi::X509_PURPOSE_TIMESTAMP_SIGN);
+
+    /// Constructs an `X509Purpose` from a raw `X509_PURPOSE_*` value.
+    #[must_use]
+    pub fn from_raw(value: c_int) -> Self {
+        X509Purpose(value)
+    }
+
+    /// Returns the raw `X509_PURPOSE_*` value.
+    #[must_use]
+    pub fn as_raw(&self) -> c_int {
+        self.0
+    }
+}
+
 foreign_type_and_impl_send_sync! {
     type CType = ffi::X509_VERIFY_PARAM;
     fn drop = ffi::X509_VERIFY_PARAM_free;
@@ -166,6 +198,12 @@ impl X509VerifyParamRef {
         unsafe { ffi::X509_VERIFY_PARAM_set_time(self.as_ptr(), time) }
     }
 
+    /// Set the purpose the certificate chain is verified for.
+    #[corresponds(X509_VERIFY_PARAM_set_purpose)]
+    pub fn set_purpose(&mut self, purpose: X509Purpose) -> Result<(), ErrorStack> {
+        unsafe { cvt(ffi::X509_VERIFY_PARAM_set_purpose(self.as_ptr(), purpose.0)) }
+    }
+
     /// Set the verification depth
     #[corresponds(X509_VERIFY_PARAM_set_depth)]
     pub fn set_depth(&mut self, depth: c_int) {
@@ -13,8 +13,8 @@
 #![warn(missing_docs)]
 
 use rama_boring::ssl::{
-    self, ConnectConfiguration, ErrorCode, MidHandshakeSslStream, ShutdownResult, SslAcceptor,
-    SslRef,
+    self, ConnectConfiguration, EchRejected, ErrorCode, MidHandshakeSslStream, ShutdownResult,
+    SslAcceptor, SslRef,
 };
 use rama_boring_sys as ffi;
 use std::error::Error;
@@ -34,7 +34,7 @@ pub use crate::async_callbacks::SslContextBuilderExt;
 pub use rama_boring::ssl::{
     AsyncPrivateKeyMethod, AsyncPrivateKeyMethodError, AsyncSelectCertError, BoxGetSessionFinish,
     BoxGetSessionFuture, BoxPrivateKeyMethodFinish, BoxPrivateKeyMethodFuture, BoxSelectCertFinish,
-    BoxSelectCertFuture, ExDataFuture,
+    BoxSelectCertFuture, EchConnectError, ExDataFuture,
 };
 
 /// Asynchronously performs a client-side TLS handshake over the provided stream.
@@ -56,6 +56,65 @@ where
     HandshakeFuture(Some(mid_handshake)).await
 }
 
+/// Asynchronously performs a client-side TLS handshake offering Encrypted Client Hello with
+/// `ech_config_list`.
+///
+/// If the server rejects ECH, its certificate is verified against the ECH public name instead of
+/// `domain`, and [`EchConnectError::Rejected`] reports the configs to retry with.
+pub async fn connect_ech<S>(
+    mut config: ConnectConfiguration,
+    domain: Option<&str>,
+    ech_config_list: &[u8],
+    stream: S,
+) -> Result<SslStream<S>, EchConnectError<HandshakeError<S>>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    config.set_ech_config_list(ech_config_list).map_err(|err| {
+        EchConnectError::Handshake(HandshakeError(ssl::HandshakeError::SetupFailure(err)))
+    })?;
+
+    connect(config, domain, stream)
+        .await
+        .map_err(|err| match err.ech_rejected() {
+            Some(rejected) => EchConnectError::Rejected(rejected),
+            None => EchConnectError::Handshake(err),
+        })
+}
+
+/// Like [`connect_ech`], but retries once with the server's retry configs if ECH is rejected.
+///
+/// `reconnect` is called to open a fresh stream for the second attempt. The new session is
+/// created with [`ConnectConfiguration::fresh`], so settings made directly on the `Ssl` of
+/// `config` are not carried over. No retry is attempted if the server securely disabled ECH.
+pub async fn connect_ech_with_retry<S, F, Fut>(
+    config: ConnectConfiguration,
+    domain: Option<&str>,
+    ech_config_list: &[u8],
+    stream: S,
+    reconnect: F,
+) -> Result<SslStream<S>, EchConnectError<HandshakeError<S>>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = io::Result<S>>,
+{
+    let retry_config = config.fresh().map_err(|err| {
+        EchConnectError::Handshake(HandshakeError(ssl::HandshakeError::SetupFailure(err)))
+    })?;
+
+    match connect_ech(config, domain, ech_config_list, stream).await {
+        Err(EchConnectError::Rejected(rejected)) => {
+            let Some(retry_configs) = rejected.retry_configs() else {
+                return Err(EchConnectError::Rejected(rejected));
+            };
+            let stream = reconnect().await.map_err(EchConnectError::Io)?;
+            connect_ech(retry_config, domain, retry_configs, stream).await
+        }
+        result => result,
+    }
+}
+
 /// Asynchronously performs a server-side TLS handshake over the provided stream.
 ///
 /// This function automatically sets the task waker on the `Ssl` from `config` to
@@ -378,6 +437,13 @@ impl<S> HandshakeError<S> {
         }
     }
 
+    /// Returns the details of the server rejecting Encrypted Client Hello, if that is why the
+    /// handshake failed.
+    #[must_use]
+    pub fn ech_rejected(&self) -> Option<EchRejected> {
+        self.0.ech_rejected()
+    }
+
     /// Returns the error code, if any.
     #[must_use]
     pub fn code(&self) -> Option<ErrorCode> {
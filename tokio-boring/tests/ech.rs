@@ -0,0 +1,181 @@
+use futures::future;
+use rama_boring::hpke::{HpkeKey, Kem};
+use rama_boring::ssl::{EchConfig, EchKeyRotation};
+use rama_boring::x509::X509VerifyError;
+use rama_boring_tokio::EchConnectError;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+mod common;
+
+use self::common::{create_acceptor, create_connector, create_listener};
+
+// The public name must be valid for the server certificate, which is only valid for localhost.
+fn generate_config(config_id: u8) -> EchConfig {
+    generate_config_with_public_name(config_id, "localhost")
+}
+
+fn generate_config_with_public_name(config_id: u8, public_name: &str) -> EchConfig {
+    let key = HpkeKey::generate(Kem::x25519_hkdf_sha256()).unwrap();
+    EchConfig::builder(config_id, public_name)
+        .build(key)
+        .unwrap()
+}
+
+fn stale_rotation() -> (EchKeyRotation, Vec<u8>) {
+    let stale_list = generate_config(1).ech_config_list().unwrap();
+    let mut rotation = EchKeyRotation::new(1);
+    rotation.rotate(vec![generate_config(2)]);
+    (rotation, stale_list)
+}
+
+#[tokio::test]
+async fn ech_rejected() {
+    let (rotation, stale_list) = stale_rotation();
+    let ech_keys = rotation.build_keys().unwrap();
+    let (listener, addr) = create_listener();
+    let acceptor = create_acceptor(move |builder| builder.set_ech_keys(&ech_keys).unwrap());
+
+    let server = async {
+        let stream = listener.accept().await.unwrap().0;
+        // The client aborts the handshake after the rejection
+        assert!(rama_boring_tokio::accept(&acceptor, stream).await.is_err());
+    };
+
+    let client = async {
+        let config = create_connector(|builder| builder.set_ca_file("tests/cert.pem"))
+            .configure()
+            .unwrap();
+        let stream = TcpStream::connect(&addr).await.unwrap();
+
+        let Err(EchConnectError::Rejected(rejected)) =
+            rama_boring_tokio::connect_ech(config, Some("localhost"), &stale_list, stream).await
+        else {
+            panic!("expected ECH rejection");
+        };
+        assert_eq!(rejected.public_name(), b"localhost");
+        assert_eq!(
+            rejected.retry_configs(),
+            Some(rotation.ech_config_list().unwrap().as_ref())
+        );
+    };
+
+    future::join(server, client).await;
+}
+
+#[tokio::test]
+async fn ech_rejected_verifies_public_name() {
+    // The certificate is only valid for the public name, not for the name the client wanted to
+    // reach, so the handshake only gets to the rejection through the public name
+    let (rotation, stale_list) = stale_rotation();
+    let ech_keys = rotation.build_keys().unwrap();
+    let (listener, addr) = create_listener();
+    let acceptor = create_acceptor(move |builder| builder.set_ech_keys(&ech_keys).unwrap());
+
+    let server = async {
+        let stream = listener.accept().await.unwrap().0;
+        assert!(rama_boring_tokio::accept(&acceptor, stream).await.is_err());
+    };
+
+    let client = async {
+        let config = create_connector(|builder| builder.set_ca_file("tests/cert.pem"))
+            .configure()
+            .unwrap();
+        let stream = TcpStream::connect(&addr).await.unwrap();
+
+        let Err(EchConnectError::Rejected(rejected)) =
+            rama_boring_tokio::connect_ech(config, Some("secret.example"), &stale_list, stream)
+                .await
+        else {
+            panic!("expected ECH rejection");
+        };
+        assert_eq!(rejected.public_name(), b"localhost");
+    };
+
+    future::join(server, client).await;
+}
+
+#[tokio::test]
+async fn ech_retry() {
+    let (rotation, stale_list) = stale_rotation();
+    let ech_keys = rotation.build_keys().unwrap();
+    let (listener, addr) = create_listener();
+    let acceptor = create_acceptor(move |builder| builder.set_ech_keys(&ech_keys).unwrap());
+
+    let server = async {
+        let stream = listener.accept().await.unwrap().0;
+        assert!(rama_boring_tokio::accept(&acceptor, stream).await.is_err());
+
+        let stream = listener.accept().await.unwrap().0;
+        let mut stream = rama_boring_tokio::accept(&acceptor, stream).await.unwrap();
+        assert!(stream.ssl().ech_accepted());
+        stream.write_all(b"jkl;").await.unwrap();
+    };
+
+    let client = async {
+        let config = create_connector(|builder| builder.set_ca_file("tests/cert.pem"))
+            .configure()
+            .unwrap();
+        let stream = TcpStream::connect(&addr).await.unwrap();
+
+        let mut stream = rama_boring_tokio::connect_ech_with_retry(
+            config,
+            Some("localhost"),
+            &stale_list,
+            stream,
+            || TcpStream::connect(addr),
+        )
+        .await
+        .unwrap();
+        assert!(stream.ssl().ech_accepted());
+
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"jkl;");
+    };
+
+    future::join(server, client).await;
+}
+
+#[tokio::test]
+async fn ech_retry_checks_public_name() {
+    // The public name of the stale and the retry configs is not valid for the certificate, so the
+    // rejection cannot be authenticated and its retry configs must not be used
+    let stale_list = generate_config_with_public_name(1, "ech.com")
+        .ech_config_list()
+        .unwrap();
+    let mut rotation = EchKeyRotation::new(1);
+    rotation.rotate(vec![generate_config_with_public_name(2, "ech.com")]);
+    let ech_keys = rotation.build_keys().unwrap();
+    let (listener, addr) = create_listener();
+    let acceptor = create_acceptor(move |builder| builder.set_ech_keys(&ech_keys).unwrap());
+
+    let server = async {
+        let stream = listener.accept().await.unwrap().0;
+        assert!(rama_boring_tokio::accept(&acceptor, stream).await.is_err());
+    };
+
+    let client = async {
+        let config = create_connector(|builder| builder.set_ca_file("tests/cert.pem"))
+            .configure()
+            .unwrap();
+        let stream = TcpStream::connect(&addr).await.unwrap();
+
+        let result = rama_boring_tokio::connect_ech_with_retry(
+            config,
+            Some("localhost"),
+            &stale_list,
+            stream,
+            || future::ready(Err(std::io::Error::other("no retry expected"))),
+        )
+        .await;
+        let Err(EchConnectError::Handshake(err)) = result else {
+            panic!("expected a certificate verification failure");
+        };
+        let ssl = err.ssl().unwrap();
+        assert!(!ssl.ech_accepted());
+        assert_eq!(ssl.verify_result(), Err(X509VerifyError::HOSTNAME_MISMATCH));
+    };
+
+    future::join(server, client).await;
+}